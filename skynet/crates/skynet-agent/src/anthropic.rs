@@ -70,6 +70,7 @@ impl LlmProvider for AnthropicProvider {
                 .get("retry-after")
                 .and_then(|v| v.to_str().ok())
                 .and_then(|v| v.parse::<u64>().ok())
+                .map(|s| s * 1000) // Retry-After is in seconds
                 .unwrap_or(5000);
             return Err(ProviderError::RateLimited {
                 retry_after_ms: retry,
@@ -121,6 +122,7 @@ impl LlmProvider for AnthropicProvider {
                 .get("retry-after")
                 .and_then(|v| v.to_str().ok())
                 .and_then(|v| v.parse::<u64>().ok())
+                .map(|s| s * 1000) // Retry-After is in seconds
                 .unwrap_or(5000);
             return Err(ProviderError::RateLimited {
                 retry_after_ms: retry,
@@ -229,6 +231,8 @@ fn parse_response(resp: ApiResponse) -> ChatResponse {
         model: resp.model,
         tokens_in: resp.usage.input_tokens,
         tokens_out: resp.usage.output_tokens,
+        cache_write_tokens: resp.usage.cache_creation_input_tokens,
+        cache_read_tokens: resp.usage.cache_read_input_tokens,
         stop_reason: resp.stop_reason.unwrap_or_default(),
         tool_calls,
     }
@@ -270,4 +274,8 @@ enum ContentBlock {
 struct Usage {
     input_tokens: u32,
     output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: u32,
 }
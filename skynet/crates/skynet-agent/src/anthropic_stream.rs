@@ -2,6 +2,7 @@ use serde::Deserialize;
 use tokio::sync::mpsc;
 use tracing::{debug, warn};
 
+use crate::pricing::TokenUsage;
 use crate::stream::{parse_sse_line, SseParsed, StreamEvent};
 
 /// Parse Anthropic streaming SSE response and emit StreamEvents.
@@ -18,8 +19,7 @@ pub async fn process_stream(resp: reqwest::Response, tx: mpsc::Sender<StreamEven
     let mut tool_use_name = String::new();
     let mut tool_use_input_json = String::new();
     let mut model = String::new();
-    let mut tokens_in: u32 = 0;
-    let mut tokens_out: u32 = 0;
+    let mut usage = TokenUsage::default();
     let mut stop_reason = String::new();
     let mut line_buf = String::new();
 
@@ -69,8 +69,7 @@ pub async fn process_stream(resp: reqwest::Response, tx: mpsc::Sender<StreamEven
                             &mut tool_use_name,
                             &mut tool_use_input_json,
                             &mut model,
-                            &mut tokens_in,
-                            &mut tokens_out,
+                            &mut usage,
                             &mut stop_reason,
                         ) {
                             if tx.send(event).await.is_err() {
@@ -89,8 +88,10 @@ pub async fn process_stream(resp: reqwest::Response, tx: mpsc::Sender<StreamEven
     let _ = tx
         .send(StreamEvent::Done {
             model,
-            tokens_in,
-            tokens_out,
+            tokens_in: usage.tokens_in,
+            tokens_out: usage.tokens_out,
+            cache_write_tokens: usage.cache_write_tokens,
+            cache_read_tokens: usage.cache_read_tokens,
             stop_reason,
         })
         .await;
@@ -106,16 +107,18 @@ fn parse_data_block(
     tool_use_name: &mut String,
     tool_use_input_json: &mut String,
     model: &mut String,
-    tokens_in: &mut u32,
-    tokens_out: &mut u32,
+    usage: &mut TokenUsage,
     stop_reason: &mut String,
 ) -> Option<StreamEvent> {
     match event_type {
         "message_start" => {
-            // Extract model name and input token count.
+            // Extract model name and input token counts (cache writes and
+            // reads are reported separately from uncached input).
             if let Ok(msg) = serde_json::from_str::<MessageStart>(data) {
                 *model = msg.message.model;
-                *tokens_in = msg.message.usage.input_tokens;
+                usage.tokens_in = msg.message.usage.input_tokens;
+                usage.cache_write_tokens = msg.message.usage.cache_creation_input_tokens;
+                usage.cache_read_tokens = msg.message.usage.cache_read_input_tokens;
             }
             None
         }
@@ -187,7 +190,7 @@ fn parse_data_block(
         "message_delta" => {
             // Extract final usage and stop reason.
             if let Ok(delta) = serde_json::from_str::<MessageDelta>(data) {
-                *tokens_out = delta.usage.output_tokens;
+                usage.tokens_out = delta.usage.output_tokens;
                 if let Some(reason) = delta.delta.stop_reason {
                     *stop_reason = reason;
                 }
@@ -223,6 +226,10 @@ struct MessageStartInner {
 #[derive(Deserialize)]
 struct InputUsage {
     input_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: u32,
 }
 
 /// Carries the opening metadata for a content block.
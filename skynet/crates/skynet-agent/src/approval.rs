@@ -0,0 +1,359 @@
+//! Per-tool approval policies (`[agent.approval]`).
+//!
+//! Tools with policy `deny` are never offered to the model. Tools with policy
+//! `ask` are wrapped in `ApprovalGate` by `build_tools`; a call to one is
+//! parked until an admin decides:
+//!
+//! 1. an `approval_queue` row is written with `expires_at = now + timeout_secs`;
+//! 2. `ApprovalEvent::Requested` is published — the gateway forwards it to WS
+//!    clients (`approval.requested`) and Discord posts it with buttons;
+//! 3. the call waits for `ApprovalBroker::decide` (`approval.decide`, a
+//!    Discord button), the expiry time, or the turn being aborted;
+//! 4. an approved call runs normally; anything else returns an error result
+//!    to the model explaining why the tool did not run.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::{broadcast, oneshot};
+use tracing::{info, warn};
+
+use skynet_core::config::ApprovalConfig;
+pub use skynet_core::config::ToolPolicy;
+use skynet_users::approvals::ApprovalQueue;
+use skynet_users::error::UserError;
+pub use skynet_users::types::{ApprovalRequest, ApprovalStatus};
+
+use crate::cancel::CancellationToken;
+use crate::pipeline::context::MessageContext;
+use crate::tools::{Tool, ToolResult};
+
+const EVENT_CAPACITY: usize = 64;
+
+/// Who a gated tool call is made on behalf of.
+#[derive(Debug, Clone)]
+pub struct Requester {
+    pub session_key: String,
+    pub channel: String,
+    /// Resolved user id; `None` for anonymous senders.
+    pub user_id: Option<String>,
+}
+
+/// Published whenever a request is queued or resolved.
+#[derive(Debug, Clone)]
+pub enum ApprovalEvent {
+    Requested(ApprovalRequest),
+    Resolved(ApprovalRequest),
+}
+
+/// Approval policies plus the calls currently waiting for a decision.
+pub struct ApprovalBroker {
+    config: ApprovalConfig,
+    queue: ApprovalQueue,
+    /// Request id → the parked call waiting on it.
+    waiters: Mutex<HashMap<String, oneshot::Sender<ApprovalRequest>>>,
+    events: broadcast::Sender<ApprovalEvent>,
+}
+
+impl ApprovalBroker {
+    /// Requests still pending from a previous run can never resume, so they
+    /// are expired here.
+    pub fn new(queue: ApprovalQueue, config: ApprovalConfig) -> Self {
+        match queue.expire_all_pending("gateway restarted") {
+            Ok(0) => {}
+            Ok(n) => info!(
+                expired = n,
+                "approval: expired requests left over from last run"
+            ),
+            Err(e) => warn!(error = %e, "approval: failed to expire leftover requests"),
+        }
+        Self::shared(queue, config)
+    }
+
+    /// A broker for a second process on the same database
+    /// (`--mcp-stdio`): pending requests belong to the running gateway and
+    /// are left alone.
+    pub fn shared(queue: ApprovalQueue, config: ApprovalConfig) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            config,
+            queue,
+            waiters: Mutex::new(HashMap::new()),
+            events,
+        }
+    }
+
+    /// Effective policy for `tool`.
+    pub fn policy(&self, tool: &str) -> ToolPolicy {
+        self.config.policy(tool)
+    }
+
+    /// Receive every `ApprovalEvent` published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<ApprovalEvent> {
+        self.events.subscribe()
+    }
+
+    /// Requests waiting for a decision, oldest first.
+    pub fn pending(&self) -> Result<Vec<ApprovalRequest>, UserError> {
+        self.queue.list_pending()
+    }
+
+    /// Approve or reject a pending request and wake the call waiting on it.
+    ///
+    /// Returns the updated request, or `None` if it does not exist or was
+    /// already resolved. A request past its expiry comes back `Expired`.
+    pub fn decide(
+        &self,
+        id: &str,
+        approved: bool,
+        decided_by: &str,
+        reason: Option<&str>,
+    ) -> Result<Option<ApprovalRequest>, UserError> {
+        let status = if approved {
+            ApprovalStatus::Approved
+        } else {
+            ApprovalStatus::Rejected
+        };
+        let resolved = self.queue.resolve(id, status, Some(decided_by), reason)?;
+        if let Some(ref req) = resolved {
+            info!(id, status = %req.status, decided_by, "approval: request resolved");
+            self.wake(req);
+        }
+        Ok(resolved)
+    }
+
+    /// Park a `tool` call until it is approved. `Ok` means run it; `Err`
+    /// carries the message returned to the model instead.
+    pub async fn request(
+        &self,
+        requester: &Requester,
+        tool: &str,
+        input: &serde_json::Value,
+        cancel: &CancellationToken,
+    ) -> Result<(), String> {
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let expires_at = chrono::Utc::now()
+            + chrono::Duration::seconds(self.config.timeout_secs.min(i64::MAX as u64) as i64);
+        let req = self
+            .queue
+            .enqueue(
+                requester.user_id.as_deref(),
+                &format!("tool:{tool}"),
+                serde_json::json!({ "tool": tool, "input": input }),
+                serde_json::json!({
+                    "session_key": requester.session_key,
+                    "channel": requester.channel,
+                }),
+                Some(expires_at.to_rfc3339()),
+            )
+            .map_err(|e| format!("could not queue approval for `{tool}`: {e}"))?;
+
+        let (tx, rx) = oneshot::channel();
+        self.waiters.lock().unwrap().insert(req.id.clone(), tx);
+        info!(id = %req.id, tool, session = %requester.session_key, "approval: waiting for decision");
+        let _ = self.events.send(ApprovalEvent::Requested(req.clone()));
+
+        // Whatever ends the wait, the stored row is the source of truth —
+        // a decision racing the timer still wins if it landed first.
+        let resolved = tokio::select! {
+            r = rx => r.ok(),
+            _ = cancel.cancelled() => {
+                self.close(&req.id, ApprovalStatus::Cancelled, "turn aborted")
+            }
+            _ = tokio::time::sleep(timeout) => {
+                self.close(&req.id, ApprovalStatus::Expired, "no decision before expires_at")
+            }
+        };
+
+        let Some(resolved) = resolved else {
+            return Err(format!("approval for `{tool}` failed"));
+        };
+        let reason = resolved
+            .reason
+            .as_deref()
+            .map(|r| format!(": {r}"))
+            .unwrap_or_default();
+        match resolved.status {
+            ApprovalStatus::Approved => Ok(()),
+            ApprovalStatus::Rejected => Err(format!(
+                "An admin rejected this `{tool}` call{reason}. Do not retry it; \
+                 tell the user or take a different approach."
+            )),
+            ApprovalStatus::Expired => Err(format!(
+                "The `{tool}` call needed admin approval and nobody approved it in time."
+            )),
+            ApprovalStatus::Cancelled => Err(format!("`{tool}` call cancelled{reason}")),
+            ApprovalStatus::Pending => Err(format!("approval for `{tool}` failed")),
+        }
+    }
+
+    /// End a wait without an admin decision. Returns the row as stored.
+    fn close(&self, id: &str, status: ApprovalStatus, reason: &str) -> Option<ApprovalRequest> {
+        self.waiters.lock().unwrap().remove(id);
+        match self.queue.resolve(id, status, None, Some(reason)) {
+            Ok(Some(req)) => {
+                info!(id, status = %req.status, "approval: request closed");
+                let _ = self.events.send(ApprovalEvent::Resolved(req.clone()));
+                Some(req)
+            }
+            // Already decided — fall back to whatever was decided.
+            Ok(None) => self.queue.get(id).ok().flatten(),
+            Err(e) => {
+                warn!(id, error = %e, "approval: failed to close request");
+                None
+            }
+        }
+    }
+
+    fn wake(&self, req: &ApprovalRequest) {
+        if let Some(tx) = self.waiters.lock().unwrap().remove(&req.id) {
+            let _ = tx.send(req.clone());
+        }
+        let _ = self.events.send(ApprovalEvent::Resolved(req.clone()));
+    }
+}
+
+/// Wraps a tool whose policy is `ask`: every call waits for approval first.
+pub struct ApprovalGate<C: MessageContext + 'static> {
+    inner: Box<dyn Tool>,
+    ctx: Arc<C>,
+    requester: Requester,
+    cancel: CancellationToken,
+}
+
+impl<C: MessageContext + 'static> ApprovalGate<C> {
+    pub fn new(
+        inner: Box<dyn Tool>,
+        ctx: Arc<C>,
+        requester: Requester,
+        cancel: CancellationToken,
+    ) -> Self {
+        Self {
+            inner,
+            ctx,
+            requester,
+            cancel,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: MessageContext + 'static> Tool for ApprovalGate<C> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        self.inner.input_schema()
+    }
+
+    fn is_concurrency_safe(&self) -> bool {
+        self.inner.is_concurrency_safe()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let approval = self
+            .ctx
+            .approvals()
+            .request(&self.requester, self.inner.name(), &input, &self.cancel)
+            .await;
+        match approval {
+            Ok(()) => self.inner.execute(input).await,
+            Err(message) => ToolResult::error(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn broker(timeout_secs: u64) -> Arc<ApprovalBroker> {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        skynet_users::db::init_db(&conn).unwrap();
+        let config = ApprovalConfig {
+            timeout_secs,
+            ..ApprovalConfig::default()
+        };
+        Arc::new(ApprovalBroker::new(ApprovalQueue::new(conn), config))
+    }
+
+    fn requester() -> Requester {
+        Requester {
+            session_key: "web:default".to_string(),
+            channel: "web".to_string(),
+            user_id: None,
+        }
+    }
+
+    /// Start a request in the background and return it once it is queued.
+    async fn park(
+        broker: &Arc<ApprovalBroker>,
+        cancel: &CancellationToken,
+    ) -> (String, tokio::task::JoinHandle<Result<(), String>>) {
+        let mut events = broker.subscribe();
+        let b = Arc::clone(broker);
+        let c = cancel.clone();
+        let handle = tokio::spawn(async move {
+            b.request(&requester(), "execute_command", &serde_json::json!({}), &c)
+                .await
+        });
+        let ApprovalEvent::Requested(req) = events.recv().await.unwrap() else {
+            panic!("expected a Requested event");
+        };
+        (req.id, handle)
+    }
+
+    #[tokio::test]
+    async fn approved_call_runs_and_rejected_call_does_not() {
+        let broker = broker(60);
+        let cancel = CancellationToken::new();
+
+        let (id, handle) = park(&broker, &cancel).await;
+        assert_eq!(broker.pending().unwrap().len(), 1);
+        let decided = broker.decide(&id, true, "admin", None).unwrap().unwrap();
+        assert_eq!(decided.status, ApprovalStatus::Approved);
+        assert!(handle.await.unwrap().is_ok());
+        assert!(broker.pending().unwrap().is_empty());
+
+        // The first decision wins.
+        assert!(broker.decide(&id, false, "admin", None).unwrap().is_none());
+
+        let (id, handle) = park(&broker, &cancel).await;
+        broker
+            .decide(&id, false, "admin", Some("too risky"))
+            .unwrap();
+        let err = handle.await.unwrap().unwrap_err();
+        assert!(err.contains("too risky"));
+    }
+
+    #[tokio::test]
+    async fn unanswered_request_expires() {
+        let broker = broker(0);
+        let cancel = CancellationToken::new();
+        let (id, handle) = park(&broker, &cancel).await;
+        assert!(handle.await.unwrap().unwrap_err().contains("in time"));
+        let expired = broker.decide(&id, true, "admin", None).unwrap().unwrap();
+        assert_eq!(expired.status, ApprovalStatus::Expired);
+    }
+
+    #[tokio::test]
+    async fn aborting_the_turn_cancels_the_request() {
+        let broker = broker(60);
+        let cancel = CancellationToken::new();
+        let (id, handle) = park(&broker, &cancel).await;
+        cancel.cancel();
+        assert!(handle.await.unwrap().is_err());
+        assert!(broker.decide(&id, true, "admin", None).unwrap().is_none());
+    }
+}
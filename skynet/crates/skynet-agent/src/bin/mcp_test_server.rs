@@ -0,0 +1,99 @@
+//! Minimal MCP server over stdio, used by `tests/mcp_stdio.rs`.
+//!
+//! Tools (listed over two pages to exercise pagination):
+//! - `echo {text}` — returns `text`
+//! - `add {a, b}` — returns `a + b`
+//! - `fail` — returns an `isError` result
+//! - `exit` — exits the process without replying (simulates a crash)
+
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+
+fn main() {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let Ok(msg) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let Some(id) = msg.get("id").cloned() else {
+            // Notifications need no reply.
+            continue;
+        };
+        let params = &msg["params"];
+        let result = match msg["method"].as_str().unwrap_or_default() {
+            "initialize" => Ok(json!({
+                "protocolVersion": params["protocolVersion"],
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "mcp-test-server", "version": "0.1.0" },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(list_tools(params["cursor"].as_str())),
+            "tools/call" => call_tool(
+                params["name"].as_str().unwrap_or_default(),
+                &params["arguments"],
+            ),
+            other => Err(format!("method not found: {other}")),
+        };
+        let reply = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(message) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": message },
+            }),
+        };
+        let _ = writeln!(stdout, "{reply}");
+        let _ = stdout.flush();
+    }
+}
+
+fn list_tools(cursor: Option<&str>) -> Value {
+    let object = |props: Value| json!({ "type": "object", "properties": props });
+    match cursor {
+        None => json!({
+            "tools": [
+                {
+                    "name": "echo",
+                    "description": "Echo the text back.",
+                    "inputSchema": object(json!({ "text": { "type": "string" } })),
+                    "annotations": { "readOnlyHint": true },
+                },
+                {
+                    "name": "add",
+                    "description": "Add two numbers.",
+                    "inputSchema": object(json!({
+                        "a": { "type": "number" },
+                        "b": { "type": "number" },
+                    })),
+                },
+            ],
+            "nextCursor": "page-2",
+        }),
+        Some(_) => json!({
+            "tools": [
+                { "name": "fail", "inputSchema": object(json!({})) },
+                { "name": "exit", "inputSchema": object(json!({})) },
+            ],
+        }),
+    }
+}
+
+fn call_tool(name: &str, args: &Value) -> Result<Value, String> {
+    let text = |t: String| json!({ "content": [{ "type": "text", "text": t }] });
+    match name {
+        "echo" => Ok(text(args["text"].as_str().unwrap_or_default().to_string())),
+        "add" => {
+            let sum = args["a"].as_f64().unwrap_or(0.0) + args["b"].as_f64().unwrap_or(0.0);
+            Ok(text(sum.to_string()))
+        }
+        "fail" => Ok(json!({
+            "content": [{ "type": "text", "text": "boom" }],
+            "isError": true,
+        })),
+        "exit" => std::process::exit(0),
+        other => Err(format!("unknown tool: {other}")),
+    }
+}
@@ -0,0 +1,148 @@
+//! Cooperative cancellation of in-flight chat turns (`chat.abort`).
+//!
+//! Every running turn registers a `CancellationToken` keyed by its connection,
+//! request id and session. Request ids are chosen by the client, so a turn is
+//! only found by request id together with the connection that started it. Cancelling the token stops the provider stream, makes the tool
+//! loop exit before its next iteration and kills any `execute_command` child.
+//! The turn then persists whatever the assistant had produced, marked as
+//! aborted.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Stop reason reported for a turn that was aborted.
+pub const ABORTED_STOP_REASON: &str = "aborted";
+
+/// Appended to the partial assistant turn saved for an aborted turn.
+pub const ABORTED_MARKER: &str = "[aborted by user]";
+
+/// Partial assistant text as persisted for an aborted turn.
+pub fn mark_aborted(partial: &str) -> String {
+    if partial.is_empty() {
+        ABORTED_MARKER.to_string()
+    } else {
+        format!("{partial}\n\n{ABORTED_MARKER}")
+    }
+}
+
+struct Run {
+    conn_id: String,
+    req_id: String,
+    session_key: String,
+    token: CancellationToken,
+}
+
+/// In-flight turns that `chat.abort` can cancel.
+#[derive(Default)]
+pub struct CancelRegistry {
+    next_id: AtomicU64,
+    runs: Mutex<HashMap<u64, Run>>,
+}
+
+impl CancelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a turn. It stays cancellable until the returned guard drops.
+    pub fn register(&self, conn_id: &str, req_id: &str, session_key: &str) -> RunGuard<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let token = CancellationToken::new();
+        self.runs.lock().unwrap().insert(
+            id,
+            Run {
+                conn_id: conn_id.to_string(),
+                req_id: req_id.to_string(),
+                session_key: session_key.to_string(),
+                token: token.clone(),
+            },
+        );
+        RunGuard {
+            registry: self,
+            id,
+            token,
+        }
+    }
+
+    /// Cancel the turn started by request `req_id` on connection `conn_id`.
+    /// Returns the number of turns cancelled.
+    pub fn cancel_request(&self, conn_id: &str, req_id: &str) -> usize {
+        self.cancel_where(|run| run.conn_id == conn_id && run.req_id == req_id)
+    }
+
+    /// Cancel the turns running in `session_key` that were started on
+    /// connection `conn_id`.
+    pub fn cancel_session(&self, session_key: &str, conn_id: &str) -> usize {
+        self.cancel_where(|run| run.session_key == session_key && run.conn_id == conn_id)
+    }
+
+    fn cancel_where(&self, pred: impl Fn(&Run) -> bool) -> usize {
+        let runs = self.runs.lock().unwrap();
+        let mut cancelled = 0;
+        for run in runs.values().filter(|r| pred(r)) {
+            if !run.token.is_cancelled() {
+                run.token.cancel();
+                cancelled += 1;
+            }
+        }
+        cancelled
+    }
+}
+
+/// Registration of one running turn; unregisters on drop.
+pub struct RunGuard<'a> {
+    registry: &'a CancelRegistry,
+    id: u64,
+    token: CancellationToken,
+}
+
+impl RunGuard<'_> {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        self.registry.runs.lock().unwrap().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancels_by_request_and_session() {
+        let registry = CancelRegistry::new();
+        let a = registry.register("conn-1", "req-1", "web:default");
+        let b = registry.register("conn-1", "req-2", "web:default");
+        let c = registry.register("conn-1", "req-3", "discord:dm:42");
+        let other = registry.register("conn-2", "req-1", "web:default");
+
+        assert_eq!(registry.cancel_request("conn-1", "req-1"), 1);
+        assert!(a.token().is_cancelled());
+        assert!(!b.token().is_cancelled());
+        assert!(!other.token().is_cancelled());
+
+        // Already-cancelled turns are not counted again, and other
+        // connections' turns are left alone.
+        assert_eq!(registry.cancel_session("web:default", "conn-1"), 1);
+        assert!(b.token().is_cancelled());
+        assert!(!c.token().is_cancelled());
+        assert!(!other.token().is_cancelled());
+
+        assert_eq!(registry.cancel_session("web:default", "conn-3"), 0);
+        assert!(!other.token().is_cancelled());
+    }
+
+    #[test]
+    fn finished_turns_are_unregistered() {
+        let registry = CancelRegistry::new();
+        drop(registry.register("conn-1", "req-1", "web:default"));
+        assert_eq!(registry.cancel_request("conn-1", "req-1"), 0);
+    }
+}
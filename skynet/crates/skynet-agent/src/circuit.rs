@@ -0,0 +1,253 @@
+//! Per-provider circuit breaker used by `ProviderRouter`.
+//!
+//! Each slot tracks consecutive failures. After `failure_threshold` failures
+//! (or a 429 with `Retry-After`) the circuit opens and the slot is skipped until
+//! the cooldown elapses. The first request after that is a half-open probe:
+//! success closes the circuit, failure re-opens it with a doubled cooldown.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::provider::ProviderError;
+
+/// Longest cooldown an open circuit can reach through repeated failed probes.
+const MAX_COOLDOWN: Duration = Duration::from_secs(600);
+
+/// Breaker state as reported by `agent.status` and `/health`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Healthy — requests flow normally.
+    Closed,
+    /// Unhealthy — requests skip this provider until the cooldown elapses.
+    Open,
+    /// Cooldown elapsed — a single probe request is allowed through.
+    HalfOpen,
+}
+
+/// Point-in-time health of one provider slot.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    /// Milliseconds until an open circuit allows a probe (0 when not open).
+    pub retry_in_ms: u64,
+    pub last_error: Option<String>,
+}
+
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// Cooldown applied the next time the circuit opens.
+    cooldown: Duration,
+    probe_in_flight: bool,
+    last_error: Option<String>,
+}
+
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    base_cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            base_cooldown: cooldown,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                open_until: None,
+                cooldown,
+                probe_in_flight: false,
+                last_error: None,
+            }),
+        }
+    }
+
+    /// Whether a request may be sent to this provider right now.
+    /// Moves an expired open circuit to half-open and claims the probe.
+    pub fn allow(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                if inner.open_until.is_some_and(|t| Instant::now() < t) {
+                    return false;
+                }
+                inner.state = CircuitState::HalfOpen;
+                inner.probe_in_flight = true;
+                true
+            }
+            CircuitState::HalfOpen => {
+                if inner.probe_in_flight {
+                    false
+                } else {
+                    inner.probe_in_flight = true;
+                    true
+                }
+            }
+        }
+    }
+
+    /// Whether `allow` would currently let a request through, without
+    /// claiming the half-open probe.
+    pub fn would_allow(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        match inner.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => inner.open_until.is_none_or(|t| Instant::now() >= t),
+            CircuitState::HalfOpen => !inner.probe_in_flight,
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = CircuitState::Closed;
+        inner.consecutive_failures = 0;
+        inner.open_until = None;
+        inner.cooldown = self.base_cooldown;
+        inner.probe_in_flight = false;
+    }
+
+    /// Record a failed attempt. `retry_after` (from a 429) opens the circuit
+    /// immediately for at least that long.
+    pub fn record_failure(&self, err: &ProviderError, retry_after: Option<Duration>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        inner.last_error = Some(err.to_string());
+        inner.probe_in_flight = false;
+
+        let trip = inner.state == CircuitState::HalfOpen
+            || retry_after.is_some()
+            || inner.consecutive_failures >= self.failure_threshold;
+        if !trip {
+            return;
+        }
+
+        let cooldown = retry_after.map_or(inner.cooldown, |r| r.max(inner.cooldown));
+        inner.state = CircuitState::Open;
+        inner.open_until = Some(Instant::now() + cooldown);
+        inner.cooldown = (inner.cooldown * 2).min(MAX_COOLDOWN);
+    }
+
+    /// Release a claimed half-open probe without judging the provider
+    /// (e.g. the request failed for reasons that say nothing about its health).
+    pub fn release_probe(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == CircuitState::HalfOpen {
+            inner.probe_in_flight = false;
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().unwrap().state
+    }
+
+    pub fn snapshot(&self, provider: &str) -> ProviderHealth {
+        let inner = self.inner.lock().unwrap();
+        let retry_in_ms = match (inner.state, inner.open_until) {
+            (CircuitState::Open, Some(t)) => {
+                t.saturating_duration_since(Instant::now()).as_millis() as u64
+            }
+            _ => 0,
+        };
+        ProviderHealth {
+            provider: provider.to_string(),
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            retry_in_ms,
+            last_error: inner.last_error.clone(),
+        }
+    }
+}
+
+/// Whether an error says something about provider health (outage, overload,
+/// rate limit) rather than about the request itself.
+pub fn is_health_failure(err: &ProviderError) -> bool {
+    match err {
+        ProviderError::Http(_) | ProviderError::Unavailable(_) => true,
+        ProviderError::RateLimited { .. } => true,
+        ProviderError::Api { status, .. } => *status >= 500 || *status == 408,
+        ProviderError::Parse(_) => false,
+    }
+}
+
+/// Exponential back-off with jitter: `base * 2^attempt`, capped at `max`,
+/// then randomised to 50–100% so concurrent retries do not line up.
+pub fn backoff(attempt: u32, base: Duration, max: Duration) -> Duration {
+    let exp = base.saturating_mul(1u32 << attempt.min(16)).min(max);
+    let factor = rand::thread_rng().gen_range(0.5..=1.0);
+    exp.mul_f64(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unavailable() -> ProviderError {
+        ProviderError::Unavailable("down".to_string())
+    }
+
+    #[test]
+    fn opens_after_threshold_and_probes_after_cooldown() {
+        let cb = CircuitBreaker::new(2, Duration::from_millis(20));
+        assert!(cb.allow());
+        cb.record_failure(&unavailable(), None);
+        assert_eq!(cb.snapshot("p").state, CircuitState::Closed);
+        cb.record_failure(&unavailable(), None);
+        assert_eq!(cb.snapshot("p").state, CircuitState::Open);
+        assert!(!cb.allow());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cb.allow(), "cooldown elapsed — probe allowed");
+        assert_eq!(cb.snapshot("p").state, CircuitState::HalfOpen);
+        assert!(!cb.allow(), "only one probe at a time");
+
+        cb.record_success();
+        assert_eq!(cb.snapshot("p").state, CircuitState::Closed);
+        assert!(cb.allow());
+    }
+
+    #[test]
+    fn failed_probe_reopens_with_longer_cooldown() {
+        let cb = CircuitBreaker::new(1, Duration::from_millis(10));
+        cb.record_failure(&unavailable(), None);
+        std::thread::sleep(Duration::from_millis(15));
+        assert!(cb.allow());
+        cb.record_failure(&unavailable(), None);
+        let snap = cb.snapshot("p");
+        assert_eq!(snap.state, CircuitState::Open);
+        assert!(snap.retry_in_ms > 10);
+    }
+
+    #[test]
+    fn retry_after_opens_immediately() {
+        let cb = CircuitBreaker::new(5, Duration::from_millis(10));
+        let err = ProviderError::RateLimited {
+            retry_after_ms: 60_000,
+        };
+        cb.record_failure(&err, Some(Duration::from_secs(60)));
+        let snap = cb.snapshot("p");
+        assert_eq!(snap.state, CircuitState::Open);
+        assert!(snap.retry_in_ms > 50_000);
+        assert!(!cb.allow());
+    }
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_secs(1);
+        for attempt in 0..8 {
+            let d = backoff(attempt, base, max);
+            let ceiling = (base * 2u32.pow(attempt)).min(max);
+            assert!(d <= ceiling && d >= ceiling / 2, "attempt {attempt}: {d:?}");
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! Token-aware context window budgeting.
+//!
+//! History is loaded by row count and tool results can be arbitrarily large,
+//! so a request can outgrow the model's context window. `fit_request` runs
+//! right before `provider.send` and shrinks `raw_messages` until the estimated
+//! prompt leaves room for `max_tokens` and the thinking budget:
+//!
+//! 1. Cap any single tool result at a quarter of the available budget.
+//! 2. Drop the oldest history turns (never the current user message or the
+//!    tool exchanges that follow it).
+//! 3. Truncate the remaining tool results, largest first.
+//!
+//! Token counts are estimated (~4 characters per token), not exact, so a
+//! safety margin is held back from every window.
+
+use std::collections::HashMap;
+
+use tracing::{debug, warn};
+
+use crate::provider::ChatRequest;
+use crate::thinking::ThinkingLevel;
+
+/// Built-in context sizes, matched by longest model-ID prefix.
+const DEFAULT_WINDOWS: &[(&str, u32)] = &[
+    ("claude-", 200_000),
+    ("gpt-4o", 128_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4-turbo", 128_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    ("llama3.1", 128_000),
+    ("llama3.2", 128_000),
+    ("llama3.3", 128_000),
+    ("llama3", 8_192),
+    ("qwen2.5", 32_768),
+    ("mistral", 32_768),
+];
+
+/// Window assumed for models missing from the registry.
+const FALLBACK_WINDOW: u32 = 32_768;
+
+/// Fixed per-message cost (role marker, separators).
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+
+/// Flat estimate for an image block (Anthropic bills ~1.6k tokens for a
+/// typical screenshot; the base64 payload says little about that).
+const IMAGE_TOKENS: u32 = 1_600;
+
+/// Minimum estimate for a PDF block; larger files scale with their size.
+const DOCUMENT_MIN_TOKENS: u32 = 1_500;
+
+/// Marker left where tool output was cut.
+const TRIM_MARKER: &str = "\n\n[... output trimmed to fit the context window ...]\n\n";
+
+/// Model → context window size (in tokens).
+#[derive(Debug, Clone)]
+pub struct ContextWindows {
+    windows: HashMap<String, u32>,
+}
+
+impl Default for ContextWindows {
+    fn default() -> Self {
+        Self {
+            windows: DEFAULT_WINDOWS
+                .iter()
+                .map(|&(model, size)| (model.to_string(), size))
+                .collect(),
+        }
+    }
+}
+
+impl ContextWindows {
+    /// Built-in sizes with `overrides` (from `[agent.context_windows]`) on top.
+    pub fn new(overrides: &HashMap<String, u32>) -> Self {
+        let mut registry = Self::default();
+        for (model, size) in overrides {
+            registry.windows.insert(model.clone(), *size);
+        }
+        registry
+    }
+
+    /// Context size for `model`: exact entry, else longest matching prefix,
+    /// else `FALLBACK_WINDOW`.
+    pub fn get(&self, model: &str) -> u32 {
+        if let Some(&size) = self.windows.get(model) {
+            return size;
+        }
+        self.windows
+            .iter()
+            .filter(|(known, _)| model.starts_with(known.as_str()))
+            .max_by_key(|(known, _)| known.len())
+            .map(|(_, &size)| size)
+            .unwrap_or(FALLBACK_WINDOW)
+    }
+}
+
+/// Rough token estimate for a piece of text (~4 characters per token).
+pub fn estimate_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(4)
+}
+
+/// Estimate one API message (`{"role", "content"}`), where `content` is a
+/// string or an array of content blocks.
+pub fn estimate_message_tokens(message: &serde_json::Value) -> u32 {
+    MESSAGE_OVERHEAD_TOKENS + estimate_content_tokens(&message["content"])
+}
+
+fn estimate_content_tokens(content: &serde_json::Value) -> u32 {
+    match content {
+        serde_json::Value::String(s) => estimate_tokens(s),
+        serde_json::Value::Array(blocks) => blocks.iter().map(estimate_block_tokens).sum(),
+        serde_json::Value::Null => 0,
+        other => estimate_tokens(&other.to_string()),
+    }
+}
+
+fn estimate_block_tokens(block: &serde_json::Value) -> u32 {
+    match block["type"].as_str() {
+        Some("text") => estimate_tokens(block["text"].as_str().unwrap_or_default()),
+        Some("tool_use") => {
+            estimate_tokens(block["name"].as_str().unwrap_or_default())
+                + estimate_tokens(&block["input"].to_string())
+        }
+        Some("tool_result") => estimate_content_tokens(&block["content"]),
+        Some("image") => IMAGE_TOKENS,
+        Some("document") => {
+            let data = block["source"]["data"].as_str().unwrap_or_default();
+            (data.len() as u32 / 32).max(DOCUMENT_MIN_TOKENS)
+        }
+        _ => estimate_tokens(&block.to_string()),
+    }
+}
+
+/// Shrink `req.raw_messages` so the request fits `window` tokens.
+///
+/// Requests without `raw_messages` are left untouched. Returns `false` when
+/// the request is still over budget after every reduction step — it is sent
+/// anyway and the provider decides.
+pub fn fit_request(req: &mut ChatRequest, window: u32) -> bool {
+    let Some(messages) = req.raw_messages.as_mut() else {
+        return true;
+    };
+
+    let thinking = match req.thinking {
+        Some(level) if level != ThinkingLevel::Off => level.budget_tokens(),
+        _ => 0,
+    };
+    let tools: u32 = req
+        .tools
+        .iter()
+        .map(|t| {
+            estimate_tokens(&t.name)
+                + estimate_tokens(&t.description)
+                + estimate_tokens(&t.input_schema.to_string())
+        })
+        .sum();
+    let reserved = req.max_tokens + thinking + estimate_tokens(&req.system) + tools + window / 20;
+    let available = window.saturating_sub(reserved);
+
+    fit_messages(messages, available)
+}
+
+/// Reduce `messages` to at most `available` estimated tokens.
+pub fn fit_messages(messages: &mut Vec<serde_json::Value>, available: u32) -> bool {
+    let mut total: u32 = messages.iter().map(estimate_message_tokens).sum();
+    if total <= available {
+        return true;
+    }
+    debug!(total, available, "request over context budget, trimming");
+
+    // 1. No single tool result may take more than a quarter of the budget.
+    let per_result_cap = available / 4;
+    for message in messages.iter_mut() {
+        truncate_tool_results(message, per_result_cap);
+    }
+    total = messages.iter().map(estimate_message_tokens).sum();
+
+    // 2. Drop the oldest history, keeping the current turn intact.
+    let protected = current_turn_start(messages);
+    let mut dropped = 0;
+    while total > available && dropped < protected {
+        total -= estimate_message_tokens(&messages[dropped]);
+        dropped += 1;
+    }
+    // The conversation must still open with a plain user message.
+    while dropped < protected && !is_plain_user(&messages[dropped]) {
+        total -= estimate_message_tokens(&messages[dropped]);
+        dropped += 1;
+    }
+    if dropped > 0 {
+        messages.drain(..dropped);
+        debug!(dropped, "dropped oldest history messages");
+    }
+
+    // 3. Truncate what is left of the tool output, largest result first.
+    while total > available {
+        let Some((mi, bi, size)) = largest_tool_result(messages) else {
+            break;
+        };
+        let excess = total - available;
+        let target = size.saturating_sub(excess);
+        if target + estimate_tokens(TRIM_MARKER) >= size {
+            break;
+        }
+        truncate_block(&mut messages[mi]["content"][bi], target);
+        total = messages.iter().map(estimate_message_tokens).sum();
+    }
+
+    if total > available {
+        warn!(
+            total,
+            available, "request still over context budget after trimming"
+        );
+        return false;
+    }
+    true
+}
+
+/// Index of the last plain user message — the prompt of the current turn.
+/// Everything from here on (tool_use / tool_result exchanges) is kept.
+fn current_turn_start(messages: &[serde_json::Value]) -> usize {
+    messages
+        .iter()
+        .rposition(is_plain_user)
+        .unwrap_or(messages.len().saturating_sub(1))
+}
+
+/// A user message that is not a tool_result carrier.
+fn is_plain_user(message: &serde_json::Value) -> bool {
+    if message["role"] != "user" {
+        return false;
+    }
+    match &message["content"] {
+        serde_json::Value::Array(blocks) => !blocks.iter().any(|b| b["type"] == "tool_result"),
+        _ => true,
+    }
+}
+
+fn truncate_tool_results(message: &mut serde_json::Value, cap: u32) {
+    if let Some(blocks) = message["content"].as_array_mut() {
+        for block in blocks.iter_mut().filter(|b| b["type"] == "tool_result") {
+            if estimate_content_tokens(&block["content"]) > cap {
+                truncate_block(block, cap);
+            }
+        }
+    }
+}
+
+/// Location and size of the biggest string tool_result.
+fn largest_tool_result(messages: &[serde_json::Value]) -> Option<(usize, usize, u32)> {
+    let mut best: Option<(usize, usize, u32)> = None;
+    for (mi, message) in messages.iter().enumerate() {
+        let Some(blocks) = message["content"].as_array() else {
+            continue;
+        };
+        for (bi, block) in blocks.iter().enumerate() {
+            if block["type"] != "tool_result" || !block["content"].is_string() {
+                continue;
+            }
+            let size = estimate_content_tokens(&block["content"]);
+            if best.is_none_or(|(_, _, s)| size > s) {
+                best = Some((mi, bi, size));
+            }
+        }
+    }
+    best
+}
+
+/// Cut a tool_result's string content to about `target` tokens, keeping the
+/// head and tail (errors and summaries usually sit at the end).
+fn truncate_block(block: &mut serde_json::Value, target: u32) {
+    let Some(text) = block["content"].as_str() else {
+        return;
+    };
+    let keep = (target.saturating_sub(estimate_tokens(TRIM_MARKER)) as usize) * 4;
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= keep {
+        return;
+    }
+    let head = keep * 2 / 3;
+    let tail = keep - head;
+    let mut out: String = chars[..head].iter().collect();
+    out.push_str(TRIM_MARKER);
+    out.extend(&chars[chars.len() - tail..]);
+    block["content"] = serde_json::Value::String(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(role: &str, chars: usize) -> serde_json::Value {
+        json!({ "role": role, "content": "x".repeat(chars) })
+    }
+
+    fn tool_exchange(id: &str, output_chars: usize) -> Vec<serde_json::Value> {
+        vec![
+            json!({ "role": "assistant", "content": [
+                { "type": "tool_use", "id": id, "name": "read_file", "input": {} }
+            ]}),
+            json!({ "role": "user", "content": [
+                { "type": "tool_result", "tool_use_id": id, "content": "y".repeat(output_chars) }
+            ]}),
+        ]
+    }
+
+    #[test]
+    fn registry_matches_longest_prefix() {
+        let w = ContextWindows::default();
+        assert_eq!(w.get("claude-sonnet-4-6"), 200_000);
+        assert_eq!(w.get("llama3.2:3b"), 128_000);
+        assert_eq!(w.get("llama3:8b"), 8_192);
+        assert_eq!(w.get("something-new"), FALLBACK_WINDOW);
+
+        let mut overrides = HashMap::new();
+        overrides.insert("llama3:8b".to_string(), 16_384);
+        assert_eq!(ContextWindows::new(&overrides).get("llama3:8b"), 16_384);
+    }
+
+    #[test]
+    fn attachments_use_flat_estimates_not_payload_size() {
+        let msg = serde_json::json!({
+            "role": "user",
+            "content": [
+                { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "A".repeat(400_000) } },
+                { "type": "document", "source": { "type": "url", "url": "https://example.com/a.pdf" } },
+                { "type": "text", "text": "what is this?" }
+            ]
+        });
+        assert_eq!(
+            estimate_message_tokens(&msg),
+            MESSAGE_OVERHEAD_TOKENS + IMAGE_TOKENS + DOCUMENT_MIN_TOKENS + 4
+        );
+    }
+
+    #[test]
+    fn fitting_request_is_untouched() {
+        let mut msgs = vec![text("user", 400), text("assistant", 400), text("user", 40)];
+        let before = msgs.clone();
+        assert!(fit_messages(&mut msgs, 10_000));
+        assert_eq!(msgs, before);
+    }
+
+    #[test]
+    fn drops_oldest_history_but_keeps_current_turn() {
+        let mut msgs = vec![
+            text("user", 4_000),
+            text("assistant", 4_000),
+            text("user", 4_000),
+            text("assistant", 4_000),
+            text("user", 40),
+        ];
+        msgs.extend(tool_exchange("t1", 400));
+        assert!(fit_messages(&mut msgs, 1_500));
+        // Starts on a plain user turn and still ends with the tool exchange.
+        assert!(is_plain_user(&msgs[0]));
+        assert_eq!(msgs.last().unwrap()["content"][0]["tool_use_id"], "t1");
+        assert!(msgs.len() < 8);
+    }
+
+    #[test]
+    fn oversized_tool_result_is_truncated_head_and_tail() {
+        let mut msgs = vec![text("user", 40)];
+        msgs.extend(tool_exchange("t1", 100_000));
+        assert!(fit_messages(&mut msgs, 4_000));
+        let out = msgs[2]["content"][0]["content"].as_str().unwrap();
+        assert!(out.contains("output trimmed"));
+        assert!(out.starts_with('y') && out.ends_with('y'));
+        assert!(msgs.iter().map(estimate_message_tokens).sum::<u32>() <= 4_000);
+    }
+
+    #[test]
+    fn fit_request_reserves_output_and_thinking() {
+        let mut req = ChatRequest {
+            model: "llama3".to_string(),
+            system: String::new(),
+            system_prompt: None,
+            messages: Vec::new(),
+            max_tokens: 4_096,
+            stream: false,
+            thinking: Some(ThinkingLevel::Low),
+            tools: Vec::new(),
+            raw_messages: Some(vec![
+                text("user", 8_000),
+                text("assistant", 8_000),
+                text("user", 40),
+            ]),
+        };
+        // 8192 - 4096 - 4096 - margin leaves nothing for history.
+        fit_request(&mut req, 8_192);
+        assert_eq!(req.raw_messages.unwrap().len(), 1);
+    }
+}
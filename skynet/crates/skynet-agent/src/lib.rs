@@ -1,12 +1,21 @@
 pub mod anthropic;
 pub mod anthropic_stream;
+pub mod approval;
+pub mod cancel;
+pub mod circuit;
+pub mod context_budget;
+pub mod mcp;
 pub mod ollama;
 pub mod openai;
 pub mod pipeline;
+pub mod plugins;
+pub mod pricing;
 pub mod prompt;
 pub mod provider;
 pub mod router;
+pub mod routing;
 pub mod runtime;
+pub mod search;
 pub mod stream;
 pub mod thinking;
 pub mod tools;
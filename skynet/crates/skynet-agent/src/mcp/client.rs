@@ -0,0 +1,319 @@
+//! JSON-RPC client for one MCP server: handshake, tool discovery and calls.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use skynet_core::config::McpServerConfig;
+
+use super::transport::Transport;
+use super::McpError;
+
+/// Protocol revision requested in `initialize`.
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// A tool advertised by `tools/list`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "empty_object_schema")]
+    pub input_schema: Value,
+    #[serde(default)]
+    pub annotations: McpToolAnnotations,
+}
+
+/// Behaviour hints from the server. Only `readOnlyHint` is used.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolAnnotations {
+    #[serde(default)]
+    pub read_only_hint: bool,
+}
+
+fn empty_object_schema() -> Value {
+    serde_json::json!({ "type": "object" })
+}
+
+/// Result of `tools/call`, flattened to text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McpCallOutput {
+    pub text: String,
+    pub is_error: bool,
+}
+
+/// An initialized connection to one MCP server.
+pub struct McpClient {
+    transport: Transport,
+    next_id: AtomicU64,
+    timeout: Duration,
+    server_name: String,
+}
+
+impl McpClient {
+    /// Open the configured transport and run the `initialize` handshake.
+    pub async fn connect(server: &str, config: &McpServerConfig) -> Result<Self, McpError> {
+        let client = Self {
+            transport: Transport::open(server, config)?,
+            next_id: AtomicU64::new(1),
+            timeout: Duration::from_secs(config.timeout_secs),
+            server_name: String::new(),
+        };
+        client.initialize().await
+    }
+
+    async fn initialize(mut self) -> Result<Self, McpError> {
+        let result = self
+            .request(
+                "initialize",
+                serde_json::json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "skynet", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            )
+            .await?;
+        let version = result["protocolVersion"]
+            .as_str()
+            .unwrap_or(PROTOCOL_VERSION);
+        self.transport.set_protocol_version(version);
+        self.server_name = result["serverInfo"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        self.transport
+            .notify(serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/initialized",
+            }))
+            .await?;
+        Ok(self)
+    }
+
+    /// Name the server reported in `serverInfo` (may be empty).
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    /// Every tool the server offers, following `nextCursor` pagination.
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>, McpError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match cursor {
+                Some(ref c) => serde_json::json!({ "cursor": c }),
+                None => serde_json::json!({}),
+            };
+            let mut result = self.request("tools/list", params).await?;
+            let page: Vec<McpToolInfo> = serde_json::from_value(result["tools"].take())
+                .map_err(|e| McpError::Protocol(format!("invalid tools/list result: {e}")))?;
+            tools.extend(page);
+            cursor = result["nextCursor"].as_str().map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Call `tool` with `arguments`. A tool that reports `isError` is still
+    /// `Ok`; `Err` means the call itself failed.
+    pub async fn call_tool(&self, tool: &str, arguments: Value) -> Result<McpCallOutput, McpError> {
+        let result = self
+            .request(
+                "tools/call",
+                serde_json::json!({ "name": tool, "arguments": arguments }),
+            )
+            .await?;
+        Ok(McpCallOutput {
+            text: render_content(&result),
+            is_error: result["isError"].as_bool().unwrap_or(false),
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.transport.is_closed()
+    }
+
+    /// Resolves once the connection is gone.
+    pub async fn closed(&self) {
+        self.transport.closed().await
+    }
+
+    /// Resolves when the server says its tool list changed.
+    pub async fn tools_changed(&self) {
+        self.transport.tools_changed().await
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        let mut response = tokio::time::timeout(self.timeout, self.transport.request(id, message))
+            .await
+            .map_err(|_| McpError::Timeout(self.timeout.as_secs()))??;
+        if let Some(error) = response.get("error") {
+            return Err(McpError::Rpc {
+                code: error["code"].as_i64().unwrap_or(0),
+                message: error["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        Ok(response["result"].take())
+    }
+}
+
+/// Flatten a `tools/call` result's content blocks to text for the model.
+fn render_content(result: &Value) -> String {
+    let blocks = result["content"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+    let mut parts: Vec<String> = blocks
+        .iter()
+        .map(|block| match block["type"].as_str() {
+            Some("text") => block["text"].as_str().unwrap_or_default().to_string(),
+            Some("image") | Some("audio") => format!(
+                "[{} content: {}]",
+                block["type"].as_str().unwrap_or_default(),
+                block["mimeType"].as_str().unwrap_or("unknown type")
+            ),
+            Some("resource") => match block["resource"]["text"].as_str() {
+                Some(text) => text.to_string(),
+                None => format!(
+                    "[resource: {}]",
+                    block["resource"]["uri"].as_str().unwrap_or("?")
+                ),
+            },
+            _ => block.to_string(),
+        })
+        .collect();
+    if parts.is_empty() {
+        if let Some(structured) = result.get("structuredContent") {
+            parts.push(structured.to_string());
+        }
+    }
+    parts.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::post;
+    use axum::{Json, Router};
+
+    use super::*;
+
+    #[test]
+    fn renders_content_blocks() {
+        let result = serde_json::json!({
+            "content": [
+                { "type": "text", "text": "hello" },
+                { "type": "image", "data": "…", "mimeType": "image/png" },
+                { "type": "resource", "resource": { "uri": "file:///a", "text": "body" } },
+                { "type": "resource", "resource": { "uri": "file:///b", "blob": "…" } },
+            ]
+        });
+        assert_eq!(
+            render_content(&result),
+            "hello\n[image content: image/png]\nbody\n[resource: file:///b]"
+        );
+        let structured = serde_json::json!({ "content": [], "structuredContent": { "n": 1 } });
+        assert_eq!(render_content(&structured), r#"{"n":1}"#);
+    }
+
+    /// Minimal streamable HTTP server: JSON for `initialize` and `tools/call`,
+    /// SSE for `tools/list`, and a session id that must be echoed back.
+    async fn handle(headers: HeaderMap, Json(msg): Json<Value>) -> Response {
+        let method = msg["method"].as_str().unwrap_or_default();
+        let session = headers.get("mcp-session-id").and_then(|v| v.to_str().ok());
+        if method != "initialize" && session != Some("s-1") {
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+        let id = msg["id"].clone();
+        let result = match method {
+            "initialize" => serde_json::json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "http-test" },
+            }),
+            "notifications/initialized" => return StatusCode::ACCEPTED.into_response(),
+            "tools/list" => {
+                let body = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": { "tools": [{ "name": "upper", "annotations": { "readOnlyHint": true } }] },
+                });
+                return (
+                    [("content-type", "text/event-stream")],
+                    format!("event: message\ndata: {body}\n\n"),
+                )
+                    .into_response();
+            }
+            "tools/call" => serde_json::json!({
+                "content": [{
+                    "type": "text",
+                    "text": msg["params"]["arguments"]["text"].as_str().unwrap_or_default().to_uppercase(),
+                }],
+            }),
+            _ => unreachable!(),
+        };
+        (
+            [("mcp-session-id", "s-1")],
+            Json(serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+        )
+            .into_response()
+    }
+
+    #[tokio::test]
+    async fn streamable_http_round_trip() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/mcp", post(handle)))
+                .await
+                .unwrap();
+        });
+
+        let config = McpServerConfig {
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            url: Some(format!("http://{addr}/mcp")),
+            headers: HashMap::new(),
+            enabled: true,
+            timeout_secs: 5,
+        };
+        let client = McpClient::connect("http", &config).await.unwrap();
+        assert_eq!(client.server_name(), "http-test");
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "upper");
+        assert!(tools[0].annotations.read_only_hint);
+        assert_eq!(tools[0].input_schema, empty_object_schema());
+
+        let out = client
+            .call_tool("upper", serde_json::json!({ "text": "abc" }))
+            .await
+            .unwrap();
+        assert_eq!(
+            out,
+            McpCallOutput {
+                text: "ABC".to_string(),
+                is_error: false
+            }
+        );
+        assert!(!client.is_closed());
+    }
+}
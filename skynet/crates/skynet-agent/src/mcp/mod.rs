@@ -0,0 +1,307 @@
+//! Model Context Protocol client hub (`[mcp.servers.<name>]`).
+//!
+//! `McpHub` keeps one connection per configured server. Each server has a
+//! supervisor task that connects (stdio or streamable HTTP), runs the
+//! `initialize` handshake, lists the server's tools and then waits for the
+//! connection to drop. On failure it reconnects with exponential back-off
+//! (`INITIAL_BACKOFF` doubling up to `MAX_BACKOFF`).
+//!
+//! `McpHub::tools()` returns every tool of every connected server as an
+//! `McpTool` named `mcp_{server}_{tool}`; `build_tools` adds them to each
+//! turn's tool list, so approval policies apply to them like any other tool.
+//! `McpHub::status()` backs the `mcp.status` WS method.
+//!
+//! The other direction — Skynet answering MCP clients — is `server`.
+
+pub mod client;
+pub mod server;
+pub mod tool;
+mod transport;
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use skynet_core::config::{McpConfig, McpServerConfig};
+
+use crate::cancel::CancellationToken;
+use crate::tools::Tool;
+
+pub use client::{McpCallOutput, McpClient, McpToolInfo};
+pub use tool::{tool_name, McpTool};
+
+/// Delay before the first reconnect attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay between reconnect attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum McpError {
+    #[error("invalid MCP server config: {0}")]
+    Config(String),
+
+    #[error("transport error: {0}")]
+    Transport(String),
+
+    #[error("connection closed")]
+    Closed,
+
+    #[error("request timed out after {0}s")]
+    Timeout(u64),
+
+    #[error("server error ({code}): {message}")]
+    Rpc { code: i64, message: String },
+
+    #[error("protocol error: {0}")]
+    Protocol(String),
+}
+
+/// Connection state of one server, as reported by `mcp.status`.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum McpConnectionState {
+    /// `enabled = false` in config.
+    Disabled,
+    Connecting,
+    Connected,
+    /// Connection lost or failed; waiting for the next attempt.
+    Disconnected,
+}
+
+/// One server's entry in `mcp.status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    /// `"stdio"` or `"http"`.
+    pub transport: &'static str,
+    pub state: McpConnectionState,
+    /// Exposed tool names (`mcp_{server}_{tool}`).
+    pub tools: Vec<String>,
+    pub last_error: Option<String>,
+    /// Failed or dropped connections since the last successful connect.
+    pub reconnect_attempts: u32,
+    pub connected_at: Option<String>,
+}
+
+struct ServerState {
+    state: McpConnectionState,
+    client: Option<Arc<McpClient>>,
+    tools: Vec<McpToolInfo>,
+    last_error: Option<String>,
+    reconnect_attempts: u32,
+    connected_at: Option<String>,
+}
+
+/// A configured server and its current connection.
+pub struct McpServer {
+    name: String,
+    config: McpServerConfig,
+    state: RwLock<ServerState>,
+}
+
+impl McpServer {
+    fn new(name: &str, config: McpServerConfig) -> Self {
+        let state = if config.enabled {
+            McpConnectionState::Connecting
+        } else {
+            McpConnectionState::Disabled
+        };
+        Self {
+            name: name.to_string(),
+            config,
+            state: RwLock::new(ServerState {
+                state,
+                client: None,
+                tools: Vec::new(),
+                last_error: None,
+                reconnect_attempts: 0,
+                connected_at: None,
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The live connection, if any.
+    pub fn client(&self) -> Option<Arc<McpClient>> {
+        self.state.read().unwrap().client.clone()
+    }
+
+    fn transport(&self) -> &'static str {
+        if self.config.command.is_some() {
+            "stdio"
+        } else {
+            "http"
+        }
+    }
+
+    fn status(&self) -> McpServerStatus {
+        let s = self.state.read().unwrap();
+        McpServerStatus {
+            name: self.name.clone(),
+            transport: self.transport(),
+            state: s.state,
+            tools: s
+                .tools
+                .iter()
+                .map(|t| tool_name(&self.name, &t.name))
+                .collect(),
+            last_error: s.last_error.clone(),
+            reconnect_attempts: s.reconnect_attempts,
+            connected_at: s.connected_at.clone(),
+        }
+    }
+
+    fn set_connected(&self, client: Arc<McpClient>, tools: Vec<McpToolInfo>) {
+        let mut s = self.state.write().unwrap();
+        s.state = McpConnectionState::Connected;
+        s.client = Some(client);
+        s.tools = tools;
+        s.last_error = None;
+        s.reconnect_attempts = 0;
+        s.connected_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+
+    fn set_disconnected(&self, error: String) {
+        let mut s = self.state.write().unwrap();
+        s.state = McpConnectionState::Disconnected;
+        s.client = None;
+        s.tools.clear();
+        s.last_error = Some(error);
+        s.reconnect_attempts += 1;
+        s.connected_at = None;
+    }
+
+    /// Connect, then stay connected until the connection drops or `shutdown`
+    /// fires; reconnect with back-off in between.
+    async fn supervise(self: Arc<Self>, shutdown: CancellationToken) {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            self.state.write().unwrap().state = McpConnectionState::Connecting;
+            let (was_connected, error) = tokio::select! {
+                ended = self.run_connection() => ended,
+                _ = shutdown.cancelled() => break,
+            };
+            if was_connected {
+                backoff = INITIAL_BACKOFF;
+            }
+            warn!(server = %self.name, %error, retry_in = ?backoff, "mcp: server disconnected");
+            self.set_disconnected(error);
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = shutdown.cancelled() => break,
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+        self.state.write().unwrap().client = None;
+    }
+
+    /// One connection's lifetime. Returns whether the handshake succeeded and
+    /// why the connection ended.
+    async fn run_connection(&self) -> (bool, String) {
+        let client = match McpClient::connect(&self.name, &self.config).await {
+            Ok(c) => Arc::new(c),
+            Err(e) => return (false, e.to_string()),
+        };
+        let tools = match client.list_tools().await {
+            Ok(t) => t,
+            Err(e) => return (false, format!("tools/list failed: {e}")),
+        };
+        info!(
+            server = %self.name,
+            remote = client.server_name(),
+            transport = self.transport(),
+            tools = tools.len(),
+            "mcp: server connected"
+        );
+        self.set_connected(Arc::clone(&client), tools);
+
+        loop {
+            tokio::select! {
+                _ = client.closed() => return (true, "connection closed".to_string()),
+                _ = client.tools_changed() => match client.list_tools().await {
+                    Ok(tools) => {
+                        info!(server = %self.name, tools = tools.len(), "mcp: tool list changed");
+                        self.state.write().unwrap().tools = tools;
+                    }
+                    Err(e) => warn!(server = %self.name, error = %e, "mcp: re-listing tools failed"),
+                },
+            }
+        }
+    }
+}
+
+/// Every configured MCP server.
+pub struct McpHub {
+    servers: Vec<Arc<McpServer>>,
+    shutdown: CancellationToken,
+}
+
+impl McpHub {
+    /// Servers are sorted by name; nothing connects until `start`.
+    pub fn new(config: &McpConfig) -> Self {
+        let mut servers: Vec<Arc<McpServer>> = config
+            .servers
+            .iter()
+            .map(|(name, cfg)| Arc::new(McpServer::new(name, cfg.clone())))
+            .collect();
+        servers.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            servers,
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Spawn a supervisor for every enabled server.
+    pub fn start(&self) {
+        for server in &self.servers {
+            if !server.config.enabled {
+                info!(server = %server.name, "mcp: server disabled");
+                continue;
+            }
+            tokio::spawn(Arc::clone(server).supervise(self.shutdown.clone()));
+        }
+    }
+
+    /// Stop reconnecting and drop every connection (killing stdio servers).
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    pub fn status(&self) -> Vec<McpServerStatus> {
+        self.servers.iter().map(|s| s.status()).collect()
+    }
+
+    /// Tools of every connected server. A name that collides with an earlier
+    /// one is skipped.
+    pub fn tools(&self) -> Vec<Box<dyn Tool>> {
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        let mut tools: Vec<Box<dyn Tool>> = Vec::new();
+        for server in &self.servers {
+            let infos = server.state.read().unwrap().tools.clone();
+            for info in infos {
+                let tool = McpTool::new(Arc::clone(server), info);
+                if let Some(other) = seen.get(tool.name()) {
+                    warn!(tool = tool.name(), server = %server.name, other, "mcp: duplicate tool name skipped");
+                    continue;
+                }
+                seen.insert(tool.name().to_string(), &server.name);
+                tools.push(Box::new(tool));
+            }
+        }
+        tools
+    }
+}
+
+impl Default for McpHub {
+    /// A hub with no servers.
+    fn default() -> Self {
+        Self::new(&McpConfig::default())
+    }
+}
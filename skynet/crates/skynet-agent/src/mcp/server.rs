@@ -0,0 +1,750 @@
+//! Skynet as an MCP server (`[mcp.serve]`).
+//!
+//! `McpEndpoint` answers JSON-RPC messages from MCP clients (editors, other
+//! agents). The gateway carries them over streamable HTTP (`POST /mcp`) or
+//! stdio (`skynet-gateway --mcp-stdio`); this module is transport-agnostic.
+//!
+//! Published:
+//! - tools — the agent tools named in `[mcp.serve] tools`, built by
+//!   `build_tools` so approval policies apply, plus `memory_search` over the
+//!   memory of the endpoint's user (`[mcp.serve] user_id`), when one is set.
+//!   The list is built once per endpoint, matching the `listChanged: false`
+//!   capability. Each call builds its tool afresh with its own cancellation
+//!   token, so it is one checkpoint turn for `/undo`; a call is aborted by
+//!   `notifications/cancelled` for its id, and dropping the endpoint cancels
+//!   whatever is still running;
+//! - resources — `skynet://knowledge/{topic}` for each knowledge entry and
+//!   `skynet://reminders` for the scheduled jobs.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::{debug, info};
+
+use skynet_core::config::ToolPolicy;
+
+use crate::cancel::CancellationToken;
+use crate::pipeline::context::MessageContext;
+use crate::tools::build::build_tools;
+use crate::tools::{Tool, ToolResult};
+
+/// Protocol revisions this server speaks, newest first.
+pub const SUPPORTED_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const KNOWLEDGE_PREFIX: &str = "skynet://knowledge/";
+const REMINDERS_URI: &str = "skynet://reminders";
+
+/// Knowledge entries listed by `resources/list`.
+const MAX_LISTED_RESOURCES: usize = 100;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+pub const RESOURCE_NOT_FOUND: i64 = -32002;
+
+/// A JSON-RPC error object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// A JSON-RPC response to request `id`.
+pub fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    }
+}
+
+/// Serves one MCP session; shared by every request of that session.
+pub struct McpEndpoint<C: MessageContext + 'static> {
+    ctx: Arc<C>,
+    /// Session key tool calls are logged and approvals are recorded under.
+    session_key: String,
+    /// Agent tools to publish, by name.
+    tools: Vec<String>,
+    offer_gated: bool,
+    /// User whose memories `memory_search` reads.
+    user_id: Option<String>,
+    /// Published tools, built on first use for `tools/list`.
+    published: OnceLock<Vec<Box<dyn Tool>>>,
+    /// Fires when the endpoint is dropped; each call's token is its child.
+    cancel: CancellationToken,
+    /// Running `tools/call` requests by JSON-RPC id.
+    in_flight: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl<C: MessageContext + 'static> McpEndpoint<C> {
+    pub fn new(ctx: Arc<C>, session_key: &str, tools: &[String]) -> Self {
+        Self {
+            ctx,
+            session_key: session_key.to_string(),
+            tools: tools.to_vec(),
+            offer_gated: true,
+            user_id: None,
+            published: OnceLock::new(),
+            cancel: CancellationToken::new(),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Do not publish tools whose approval policy is `ask`. Used when no admin
+    /// can reach this process to decide (`--mcp-stdio`).
+    pub fn without_gated_tools(mut self) -> Self {
+        self.offer_gated = false;
+        self
+    }
+
+    /// Publish `memory_search` over the memories of `user_id`. The MCP
+    /// client acts as that user; it cannot pick another.
+    pub fn with_user(mut self, user_id: &str) -> Self {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    /// Handle one incoming message. Returns the response to send, or `None`
+    /// for notifications and stray responses.
+    pub async fn handle(&self, msg: Value) -> Option<Value> {
+        let id = msg.get("id").cloned();
+        let Some(method) = msg.get("method").and_then(Value::as_str) else {
+            let is_response = msg.get("result").is_some() || msg.get("error").is_some();
+            return match id {
+                Some(id) if !is_response => Some(response(
+                    id,
+                    Err(RpcError::new(INVALID_REQUEST, "missing method")),
+                )),
+                _ => None,
+            };
+        };
+        let Some(id) = id else {
+            if method == "notifications/cancelled" {
+                self.cancel_request(&msg["params"]["requestId"]);
+            } else {
+                debug!(method, "mcp serve: notification");
+            }
+            return None;
+        };
+        let result = if method == "tools/call" {
+            self.call_tool(&id, &msg["params"]).await
+        } else {
+            self.dispatch(method, &msg["params"]).await
+        };
+        Some(response(id, result))
+    }
+
+    /// Abort the running `tools/call` with JSON-RPC id `id`, if any.
+    fn cancel_request(&self, id: &Value) {
+        if let Some(token) = self.in_flight.lock().unwrap().get(&id.to_string()) {
+            debug!(request = %id, "mcp serve: call cancelled by client");
+            token.cancel();
+        }
+    }
+
+    async fn dispatch(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(initialize_result(params["protocolVersion"].as_str())),
+            "ping" => Ok(json!({})),
+            "tools/list" => {
+                let tools: Vec<Value> = self
+                    .published_tools()
+                    .iter()
+                    .map(|t| tool_entry(t.as_ref()))
+                    .collect();
+                Ok(json!({ "tools": tools }))
+            }
+            "resources/list" => self.list_resources(),
+            "resources/templates/list" => Ok(json!({
+                "resourceTemplates": [{
+                    "uriTemplate": format!("{KNOWLEDGE_PREFIX}{{topic}}"),
+                    "name": "knowledge",
+                    "description": "Knowledge base entry by topic",
+                    "mimeType": "text/markdown",
+                }]
+            })),
+            "resources/read" => self.read_resource(params),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method not found: {other}"),
+            )),
+        }
+    }
+
+    fn published_tools(&self) -> &[Box<dyn Tool>] {
+        self.published
+            .get_or_init(|| self.build_published(&self.cancel))
+    }
+
+    /// The published toolset, built by `build_tools` as one turn whose tools
+    /// stop when `cancel` fires.
+    fn build_published(&self, cancel: &CancellationToken) -> Vec<Box<dyn Tool>> {
+        // Reminders created here are delivered to WS clients.
+        let mut tools: Vec<Box<dyn Tool>> = build_tools(
+            Arc::clone(&self.ctx),
+            "ws",
+            None,
+            &self.session_key,
+            None,
+            cancel,
+        )
+        .into_iter()
+        .filter(|t| self.tools.iter().any(|name| name == t.name()))
+        .filter(|t| self.offer_gated || self.ctx.approvals().policy(t.name()) != ToolPolicy::Ask)
+        .collect();
+        if let Some(user_id) = &self.user_id {
+            tools.push(Box::new(MemorySearchTool {
+                ctx: Arc::clone(&self.ctx),
+                user_id: user_id.clone(),
+            }));
+        }
+        tools
+    }
+
+    async fn call_tool(&self, id: &Value, params: &Value) -> Result<Value, RpcError> {
+        let name = params["name"]
+            .as_str()
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing tool name"))?;
+        if !self.published_tools().iter().any(|t| t.name() == name) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("unknown tool: {name}"),
+            ));
+        }
+        let arguments = match params.get("arguments") {
+            Some(Value::Null) | None => json!({}),
+            Some(args) => args.clone(),
+        };
+
+        info!(tool = name, session = %self.session_key, "mcp serve: tool call");
+        let token = self.cancel.child_token();
+        // A fresh toolset per call: a new checkpoint turn, stopped by `token`.
+        let tools = self.build_published(&token);
+        let tool = tools
+            .iter()
+            .find(|t| t.name() == name)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("unknown tool: {name}")))?;
+        let _running = InFlight::register(&self.in_flight, id, token.clone());
+        // The tool sees `token` too; report a cancelled call the same way
+        // whichever side notices first.
+        let result = tokio::select! {
+            biased;
+            _ = token.cancelled() => ToolResult::error("tool call cancelled"),
+            result = tool.execute(arguments) => result,
+        };
+        let _ = self.ctx.memory().log_tool_call(name, &self.session_key);
+        Ok(call_result(&result))
+    }
+
+    fn list_resources(&self) -> Result<Value, RpcError> {
+        let entries = self
+            .ctx
+            .memory()
+            .knowledge_list(MAX_LISTED_RESOURCES)
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?;
+        let mut resources: Vec<Value> = vec![json!({
+            "uri": REMINDERS_URI,
+            "name": "reminders",
+            "description": "Scheduled reminders and jobs",
+            "mimeType": "application/json",
+        })];
+        resources.extend(entries.iter().map(|e| {
+            json!({
+                "uri": format!("{KNOWLEDGE_PREFIX}{}", e.topic),
+                "name": e.topic,
+                "description": if e.tags.is_empty() { format!("Knowledge: {}", e.topic) } else { format!("Knowledge [{}]", e.tags) },
+                "mimeType": "text/markdown",
+            })
+        }));
+        Ok(json!({ "resources": resources }))
+    }
+
+    fn read_resource(&self, params: &Value) -> Result<Value, RpcError> {
+        let uri = params["uri"]
+            .as_str()
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing uri"))?;
+        let not_found = || RpcError::new(RESOURCE_NOT_FOUND, format!("resource not found: {uri}"));
+        let (mime, text) = match parse_resource_uri(uri).ok_or_else(not_found)? {
+            Resource::Knowledge(topic) => {
+                let entry = self
+                    .ctx
+                    .memory()
+                    .knowledge_get(topic)
+                    .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?
+                    .ok_or_else(not_found)?;
+                ("text/markdown", entry.content)
+            }
+            Resource::Reminders => {
+                let jobs = self
+                    .ctx
+                    .scheduler()
+                    .list_jobs()
+                    .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?;
+                let text = serde_json::to_string_pretty(&jobs)
+                    .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?;
+                ("application/json", text)
+            }
+        };
+        Ok(json!({ "contents": [{ "uri": uri, "mimeType": mime, "text": text }] }))
+    }
+}
+
+/// A `tools/call` listed in `in_flight` until it finishes or is dropped.
+struct InFlight<'a> {
+    calls: &'a Mutex<HashMap<String, CancellationToken>>,
+    key: String,
+}
+
+impl<'a> InFlight<'a> {
+    fn register(
+        calls: &'a Mutex<HashMap<String, CancellationToken>>,
+        id: &Value,
+        token: CancellationToken,
+    ) -> Self {
+        let key = id.to_string();
+        calls.lock().unwrap().insert(key.clone(), token);
+        Self { calls, key }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.calls.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl<C: MessageContext + 'static> Drop for McpEndpoint<C> {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+/// `initialize` result; echoes the client's version when supported.
+fn initialize_result(requested: Option<&str>) -> Value {
+    let version = requested
+        .filter(|v| SUPPORTED_VERSIONS.contains(v))
+        .unwrap_or(SUPPORTED_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": {
+            "tools": { "listChanged": false },
+            "resources": { "listChanged": false },
+        },
+        "serverInfo": { "name": "skynet", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Skynet's persistent knowledge base, user memory and reminders. \
+                         Search the knowledge base before asking the user for facts \
+                         that may have been saved before.",
+    })
+}
+
+/// `tools/list` entry; tools reporting `is_read_only()` get `readOnlyHint`.
+fn tool_entry(tool: &dyn Tool) -> Value {
+    json!({
+        "name": tool.name(),
+        "description": tool.description(),
+        "inputSchema": tool.input_schema(),
+        "annotations": { "readOnlyHint": tool.is_read_only() },
+    })
+}
+
+fn call_result(result: &ToolResult) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": result.content }],
+        "isError": result.is_error,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Resource<'a> {
+    Knowledge(&'a str),
+    Reminders,
+}
+
+fn parse_resource_uri(uri: &str) -> Option<Resource<'_>> {
+    if uri == REMINDERS_URI {
+        return Some(Resource::Reminders);
+    }
+    uri.strip_prefix(KNOWLEDGE_PREFIX)
+        .filter(|topic| !topic.is_empty())
+        .map(Resource::Knowledge)
+}
+
+/// `memory_search` — full-text search over the endpoint user's memories.
+/// Only published over MCP; the agent gets user memory through its prompt.
+struct MemorySearchTool<C: MessageContext + 'static> {
+    ctx: Arc<C>,
+    user_id: String,
+}
+
+#[async_trait]
+impl<C: MessageContext + 'static> Tool for MemorySearchTool<C> {
+    fn name(&self) -> &str {
+        "memory_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search what Skynet remembers about you (instructions, preferences, facts). \
+         Returns matching memories, best match first."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Full-text search query."
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum results (default 10, max 50)."
+                }
+            },
+            "required": ["query"]
+        })
+    }
+
+    fn is_concurrency_safe(&self) -> bool {
+        true
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, input: Value) -> ToolResult {
+        let Some(query) = input["query"].as_str() else {
+            return ToolResult::error("missing required parameter: query");
+        };
+        let limit = input["limit"].as_u64().unwrap_or(10).clamp(1, 50) as usize;
+        match self.ctx.memory().search(&self.user_id, query, limit) {
+            Ok(memories) if memories.is_empty() => {
+                ToolResult::success(format!("No memories found for: {query}"))
+            }
+            Ok(memories) => ToolResult::success(
+                memories
+                    .iter()
+                    .map(|m| format!("- [{}] {}: {}", m.category, m.key, m.value))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Err(e) => ToolResult::error(format!("memory_search failed: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use skynet_core::config::{ApprovalConfig, McpConfig, PluginsConfig, WorkspaceConfig};
+    use skynet_memory::manager::MemoryManager;
+    use skynet_memory::types::{MemoryCategory, MemorySource};
+    use skynet_scheduler::SchedulerHandle;
+    use skynet_sessions::{CheckpointStore, SessionManager};
+    use skynet_terminal::manager::TerminalManager;
+    use skynet_users::approvals::ApprovalQueue;
+    use skynet_users::resolver::UserResolver;
+
+    use super::*;
+    use crate::approval::ApprovalBroker;
+    use crate::mcp::McpHub;
+    use crate::plugins::PluginRegistry;
+    use crate::prompt::PromptBuilder;
+    use crate::provider::{ChatRequest, ChatResponse, LlmProvider, ProviderError};
+    use crate::runtime::AgentRuntime;
+    use crate::tools::file_tracker::FileTracker;
+
+    struct NoProvider;
+
+    #[async_trait]
+    impl LlmProvider for NoProvider {
+        fn name(&self) -> &str {
+            "none"
+        }
+        async fn send(&self, _req: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+            Err(ProviderError::Unavailable(
+                "no provider in tests".to_string(),
+            ))
+        }
+    }
+
+    struct TestContext {
+        dir: PathBuf,
+        agent: AgentRuntime,
+        memory: MemoryManager,
+        terminal: tokio::sync::Mutex<TerminalManager>,
+        scheduler: SchedulerHandle,
+        sessions: SessionManager,
+        users: UserResolver,
+        approvals: ApprovalBroker,
+        mcp: McpHub,
+        plugins: PluginRegistry,
+        files: FileTracker,
+        checkpoints: CheckpointStore,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("skynet-mcp-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let open = || rusqlite::Connection::open(dir.join("skynet.db")).unwrap();
+            skynet_users::db::init_db(&open()).unwrap();
+            skynet_memory::db::init_db(&open()).unwrap();
+            skynet_sessions::db::init_db(&open()).unwrap();
+            Self {
+                agent: AgentRuntime::new(
+                    Box::new(NoProvider),
+                    PromptBuilder::load(None),
+                    "test-model".to_string(),
+                )
+                .with_workspace(WorkspaceConfig {
+                    root: Some(dir.join("work").display().to_string()),
+                    ..Default::default()
+                }),
+                memory: MemoryManager::new(open()),
+                terminal: tokio::sync::Mutex::new(TerminalManager::new()),
+                scheduler: SchedulerHandle::new(open()).unwrap(),
+                sessions: SessionManager::new(open()),
+                users: UserResolver::new(Arc::new(std::sync::Mutex::new(open()))),
+                approvals: ApprovalBroker::new(
+                    ApprovalQueue::new(open()),
+                    ApprovalConfig::default(),
+                ),
+                mcp: McpHub::new(&McpConfig::default()),
+                plugins: PluginRegistry::new(&PluginsConfig::default()),
+                files: FileTracker::new(),
+                checkpoints: CheckpointStore::new(open(), dir.join("checkpoints"), 20),
+                dir,
+            }
+        }
+    }
+
+    impl Drop for TestContext {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    impl MessageContext for TestContext {
+        fn agent(&self) -> &AgentRuntime {
+            &self.agent
+        }
+        fn memory(&self) -> &MemoryManager {
+            &self.memory
+        }
+        fn terminal(&self) -> &tokio::sync::Mutex<TerminalManager> {
+            &self.terminal
+        }
+        fn scheduler(&self) -> &SchedulerHandle {
+            &self.scheduler
+        }
+        fn sessions(&self) -> &SessionManager {
+            &self.sessions
+        }
+        fn users(&self) -> &UserResolver {
+            &self.users
+        }
+        fn approvals(&self) -> &ApprovalBroker {
+            &self.approvals
+        }
+        fn mcp(&self) -> &McpHub {
+            &self.mcp
+        }
+        fn plugins(&self) -> &PluginRegistry {
+            &self.plugins
+        }
+        fn files(&self) -> &FileTracker {
+            &self.files
+        }
+        fn checkpoints(&self) -> Option<&CheckpointStore> {
+            Some(&self.checkpoints)
+        }
+    }
+
+    fn endpoint(tools: &[&str]) -> McpEndpoint<TestContext> {
+        let tools: Vec<String> = tools.iter().map(|t| t.to_string()).collect();
+        McpEndpoint::new(Arc::new(TestContext::new()), "mcp:test", &tools)
+    }
+
+    async fn request(endpoint: &McpEndpoint<TestContext>, method: &str, params: Value) -> Value {
+        let msg = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        endpoint.handle(msg).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_initialize_list_and_call() {
+        let endpoint = endpoint(&["knowledge_search"]).with_user("u-1");
+        let memory = endpoint.ctx.memory();
+        for (user, value) in [("u-1", "likes green tea"), ("u-2", "likes black tea")] {
+            memory
+                .learn(
+                    user,
+                    MemoryCategory::Preference,
+                    "drink",
+                    value,
+                    1.0,
+                    MemorySource::UserSaid,
+                )
+                .unwrap();
+        }
+
+        let init = request(
+            &endpoint,
+            "initialize",
+            json!({ "protocolVersion": "2025-03-26" }),
+        )
+        .await;
+        assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+        assert!(endpoint
+            .handle(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await
+            .is_none());
+
+        let list = request(&endpoint, "tools/list", json!({})).await;
+        let names: Vec<&str> = list["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["knowledge_search", "memory_search"]);
+        let schema = &list["result"]["tools"][1]["inputSchema"];
+        assert!(schema["properties"].get("user_id").is_none());
+
+        // a user_id argument cannot widen the search to another user
+        let call = request(
+            &endpoint,
+            "tools/call",
+            json!({ "name": "memory_search", "arguments": { "query": "tea", "user_id": "u-2" } }),
+        )
+        .await;
+        assert_eq!(call["result"]["isError"], false);
+        let text = call["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(
+            text.contains("green tea") && !text.contains("black"),
+            "{text}"
+        );
+
+        let unknown = request(
+            &endpoint,
+            "tools/call",
+            json!({ "name": "execute_command", "arguments": {} }),
+        )
+        .await;
+        assert_eq!(unknown["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn memory_search_needs_a_configured_user() {
+        let endpoint = endpoint(&[]);
+        let list = request(&endpoint, "tools/list", json!({})).await;
+        assert_eq!(list["result"]["tools"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn each_call_is_its_own_checkpoint_turn() {
+        let endpoint = endpoint(&["write_file"]);
+        for path in ["a.txt", "b.txt"] {
+            let call = request(
+                &endpoint,
+                "tools/call",
+                json!({ "name": "write_file", "arguments": { "path": path, "content": "hi" } }),
+            )
+            .await;
+            assert_eq!(call["result"]["isError"], false, "{call}");
+        }
+
+        let turns = endpoint.ctx.checkpoints.list("mcp:test", 10).unwrap();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].files.len(), 1);
+        assert_ne!(turns[0].turn_id, turns[1].turn_id);
+    }
+
+    #[tokio::test]
+    async fn cancelled_notification_aborts_a_running_call() {
+        let endpoint = Arc::new(endpoint(&["execute_command"]));
+        let running = {
+            let endpoint = Arc::clone(&endpoint);
+            tokio::spawn(async move {
+                let msg = json!({
+                    "jsonrpc": "2.0",
+                    "id": "slow",
+                    "method": "tools/call",
+                    "params": { "name": "execute_command", "arguments": { "command": "sleep 5" } },
+                });
+                endpoint.handle(msg).await.unwrap()
+            })
+        };
+        while endpoint.in_flight.lock().unwrap().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        let cancel = json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/cancelled",
+            "params": { "requestId": "slow" },
+        });
+        assert!(endpoint.handle(cancel).await.is_none());
+        let reply = tokio::time::timeout(std::time::Duration::from_secs(5), running)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reply["result"]["isError"], true);
+        assert_eq!(reply["result"]["content"][0]["text"], "tool call cancelled");
+        assert!(endpoint.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn negotiates_protocol_version() {
+        let v = |req| initialize_result(req)["protocolVersion"].clone();
+        assert_eq!(v(Some("2024-11-05")), "2024-11-05");
+        assert_eq!(v(Some("1999-01-01")), SUPPORTED_VERSIONS[0]);
+        assert_eq!(v(None), SUPPORTED_VERSIONS[0]);
+    }
+
+    #[test]
+    fn parses_resource_uris() {
+        assert_eq!(
+            parse_resource_uri("skynet://knowledge/deploy_steps"),
+            Some(Resource::Knowledge("deploy_steps"))
+        );
+        assert_eq!(
+            parse_resource_uri("skynet://reminders"),
+            Some(Resource::Reminders)
+        );
+        assert_eq!(parse_resource_uri("skynet://knowledge/"), None);
+        assert_eq!(parse_resource_uri("file:///etc/passwd"), None);
+    }
+
+    #[test]
+    fn builds_responses() {
+        assert_eq!(
+            response(json!(1), Ok(json!({}))),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": {} })
+        );
+        assert_eq!(
+            response(json!("a"), Err(RpcError::new(METHOD_NOT_FOUND, "nope"))),
+            json!({ "jsonrpc": "2.0", "id": "a", "error": { "code": -32601, "message": "nope" } })
+        );
+        let failed = call_result(&ToolResult::error("boom"));
+        assert_eq!(failed["isError"], true);
+        assert_eq!(failed["content"][0]["text"], "boom");
+    }
+}
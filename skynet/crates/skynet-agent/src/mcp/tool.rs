@@ -0,0 +1,114 @@
+//! `McpTool` — one MCP server tool exposed through the `Tool` trait.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::info;
+
+use crate::tools::{Tool, ToolResult};
+
+use super::{McpServer, McpToolInfo};
+
+/// Longest tool name providers accept.
+const MAX_NAME_LEN: usize = 64;
+
+/// Exposed name for `tool` on `server`: `mcp_{server}_{tool}`, with characters
+/// outside `[A-Za-z0-9_-]` replaced by `_` and cut to 64 characters.
+pub fn tool_name(server: &str, tool: &str) -> String {
+    format!("mcp_{server}_{tool}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_NAME_LEN)
+        .collect()
+}
+
+/// Forwards calls to a tool on an MCP server over the server's current
+/// connection.
+pub struct McpTool {
+    server: Arc<McpServer>,
+    name: String,
+    info: McpToolInfo,
+    description: String,
+}
+
+impl McpTool {
+    pub fn new(server: Arc<McpServer>, info: McpToolInfo) -> Self {
+        let name = tool_name(server.name(), &info.name);
+        let description = match info.description.as_deref() {
+            Some(d) if !d.is_empty() => format!("[MCP: {}] {d}", server.name()),
+            _ => format!("Tool `{}` from MCP server `{}`.", info.name, server.name()),
+        };
+        Self {
+            server,
+            name,
+            info,
+            description,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        self.info.input_schema.clone()
+    }
+
+    /// Tools the server marks `readOnlyHint` may run in parallel.
+    fn is_concurrency_safe(&self) -> bool {
+        self.info.annotations.read_only_hint
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let client = match self.server.client() {
+            Some(c) if !c.is_closed() => c,
+            _ => {
+                return ToolResult::error(format!(
+                    "MCP server `{}` is not connected; it is reconnecting, try again later",
+                    self.server.name()
+                ))
+            }
+        };
+        info!(server = %self.server.name(), tool = %self.info.name, "mcp: calling tool");
+        match client.call_tool(&self.info.name, input).await {
+            Ok(out) if out.is_error => ToolResult::error(out.text),
+            Ok(out) => ToolResult::success(out.text),
+            Err(e) => ToolResult::error(format!(
+                "MCP call to `{}` on `{}` failed: {e}",
+                self.info.name,
+                self.server.name()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_sanitized_and_capped() {
+        assert_eq!(
+            tool_name("github", "create_issue"),
+            "mcp_github_create_issue"
+        );
+        assert_eq!(
+            tool_name("my server", "fetch.url"),
+            "mcp_my_server_fetch_url"
+        );
+        assert_eq!(tool_name("s", &"x".repeat(100)).len(), MAX_NAME_LEN);
+    }
+}
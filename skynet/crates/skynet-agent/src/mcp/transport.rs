@@ -0,0 +1,452 @@
+//! MCP transports — newline-delimited JSON-RPC over a child's stdio, and
+//! streamable HTTP (POST per message, JSON or SSE response).
+//!
+//! Both report when the connection is gone through `closed()`, which the hub's
+//! supervisor waits on to reconnect.
+
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Notify};
+use tracing::{debug, warn};
+
+use skynet_core::config::McpServerConfig;
+
+use crate::cancel::CancellationToken;
+use crate::stream::{parse_sse_line, SseParsed};
+
+use super::McpError;
+
+/// Calls waiting for their response, keyed by JSON-RPC id.
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// Removes a call's `pending` entry when the call finishes or is dropped
+/// (e.g. by the client's timeout), so abandoned ids do not pile up.
+struct PendingGuard<'a> {
+    pending: &'a Pending,
+    id: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// A live connection to one MCP server.
+pub(crate) enum Transport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+impl Transport {
+    /// Open the transport described by `config` (`command` wins over `url`).
+    pub(crate) fn open(server: &str, config: &McpServerConfig) -> Result<Self, McpError> {
+        match (&config.command, &config.url) {
+            (Some(_), _) => StdioTransport::spawn(server, config).map(Transport::Stdio),
+            (None, Some(url)) => HttpTransport::new(url, config).map(Transport::Http),
+            (None, None) => Err(McpError::Config(
+                "set either `command` or `url`".to_string(),
+            )),
+        }
+    }
+
+    /// Send a request and wait for the response message with the same `id`.
+    pub(crate) async fn request(&self, id: u64, message: Value) -> Result<Value, McpError> {
+        match self {
+            Transport::Stdio(t) => t.request(id, message).await,
+            Transport::Http(t) => t.request(id, message).await,
+        }
+    }
+
+    /// Send a notification (no response expected).
+    pub(crate) async fn notify(&self, message: Value) -> Result<(), McpError> {
+        match self {
+            Transport::Stdio(t) => t.write(&message).await,
+            Transport::Http(t) => t.post(&message, None).await.map(|_| ()),
+        }
+    }
+
+    /// Record the negotiated protocol version (sent as a header over HTTP).
+    pub(crate) fn set_protocol_version(&self, version: &str) {
+        if let Transport::Http(t) = self {
+            *t.protocol_version.lock().unwrap() = Some(version.to_string());
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed_token().is_cancelled()
+    }
+
+    /// Resolves once the connection is gone.
+    pub(crate) async fn closed(&self) {
+        self.closed_token().cancelled().await
+    }
+
+    /// Resolves when the server announces `notifications/tools/list_changed`.
+    pub(crate) async fn tools_changed(&self) {
+        match self {
+            Transport::Stdio(t) => t.tools_changed.notified().await,
+            // Server-initiated messages need a standalone GET stream, which is
+            // not opened; HTTP servers are re-listed on reconnect only.
+            Transport::Http(_) => std::future::pending().await,
+        }
+    }
+
+    fn closed_token(&self) -> &CancellationToken {
+        match self {
+            Transport::Stdio(t) => &t.closed,
+            Transport::Http(t) => &t.closed,
+        }
+    }
+}
+
+/// What an incoming JSON-RPC message is.
+enum Incoming<'a> {
+    Response(u64),
+    Request(&'a Value, &'a str),
+    Notification(&'a str),
+    Invalid,
+}
+
+fn classify(msg: &Value) -> Incoming<'_> {
+    match (msg.get("id"), msg.get("method").and_then(Value::as_str)) {
+        (Some(id), Some(method)) => Incoming::Request(id, method),
+        (None, Some(method)) => Incoming::Notification(method),
+        (Some(id), None) => id.as_u64().map_or(Incoming::Invalid, Incoming::Response),
+        (None, None) => Incoming::Invalid,
+    }
+}
+
+/// Reply to a server-initiated request. Only `ping` is supported.
+fn reply_to(id: &Value, method: &str) -> Value {
+    if method == "ping" {
+        serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+    } else {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("method not supported: {method}") }
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// stdio
+// ---------------------------------------------------------------------------
+
+pub(crate) struct StdioTransport {
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: Pending,
+    closed: CancellationToken,
+    tools_changed: Arc<Notify>,
+    /// Held so the process is killed when the transport is dropped.
+    _child: Child,
+}
+
+impl StdioTransport {
+    fn spawn(server: &str, config: &McpServerConfig) -> Result<Self, McpError> {
+        let command = config.command.as_deref().unwrap_or_default();
+        let mut cmd = Command::new(command);
+        cmd.args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(ref cwd) = config.cwd {
+            cmd.current_dir(cwd);
+        }
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| McpError::Transport(format!("failed to spawn `{command}`: {e}")))?;
+
+        let stdin = Arc::new(tokio::sync::Mutex::new(
+            child.stdin.take().expect("stdin is piped"),
+        ));
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let pending: Pending = Arc::default();
+        let closed = CancellationToken::new();
+        let tools_changed = Arc::new(Notify::new());
+
+        let name = server.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                debug!(server = %name, "mcp stderr: {line}");
+            }
+        });
+
+        let name = server.to_string();
+        let reader_stdin = Arc::clone(&stdin);
+        let reader_pending = Arc::clone(&pending);
+        let reader_closed = closed.clone();
+        let reader_changed = Arc::clone(&tools_changed);
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!(server = %name, error = %e, "mcp: stdout read failed");
+                        break;
+                    }
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let msg: Value = match serde_json::from_str(&line) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        debug!(server = %name, "mcp: ignoring non-JSON stdout line: {line}");
+                        continue;
+                    }
+                };
+                match classify(&msg) {
+                    Incoming::Response(id) => {
+                        if let Some(tx) = reader_pending.lock().unwrap().remove(&id) {
+                            let _ = tx.send(msg);
+                        }
+                    }
+                    Incoming::Request(id, method) => {
+                        let mut line = reply_to(id, method).to_string();
+                        line.push('\n');
+                        let mut stdin = reader_stdin.lock().await;
+                        let _ = stdin.write_all(line.as_bytes()).await;
+                        let _ = stdin.flush().await;
+                    }
+                    Incoming::Notification("notifications/tools/list_changed") => {
+                        reader_changed.notify_one();
+                    }
+                    Incoming::Notification(method) => {
+                        debug!(server = %name, method, "mcp: notification ignored");
+                    }
+                    Incoming::Invalid => {
+                        debug!(server = %name, "mcp: ignoring invalid message: {line}");
+                    }
+                }
+            }
+            debug!(server = %name, "mcp: stdout closed");
+            reader_closed.cancel();
+            // Dropping the senders fails every call still waiting.
+            reader_pending.lock().unwrap().clear();
+        });
+
+        Ok(Self {
+            stdin,
+            pending,
+            closed,
+            tools_changed,
+            _child: child,
+        })
+    }
+
+    async fn request(&self, id: u64, message: Value) -> Result<Value, McpError> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        let _guard = PendingGuard {
+            pending: &self.pending,
+            id,
+        };
+        self.write(&message).await?;
+        rx.await.map_err(|_| McpError::Closed)
+    }
+
+    async fn write(&self, message: &Value) -> Result<(), McpError> {
+        if self.closed.is_cancelled() {
+            return Err(McpError::Closed);
+        }
+        let mut line = message.to_string();
+        line.push('\n');
+        let mut stdin = self.stdin.lock().await;
+        let written = async {
+            stdin.write_all(line.as_bytes()).await?;
+            stdin.flush().await
+        }
+        .await;
+        written.map_err(|e| {
+            self.closed.cancel();
+            McpError::Transport(format!("write to server failed: {e}"))
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// streamable HTTP
+// ---------------------------------------------------------------------------
+
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_HEADER: &str = "mcp-protocol-version";
+
+pub(crate) struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    headers: reqwest::header::HeaderMap,
+    session_id: Mutex<Option<String>>,
+    protocol_version: Mutex<Option<String>>,
+    closed: CancellationToken,
+}
+
+impl HttpTransport {
+    fn new(url: &str, config: &McpServerConfig) -> Result<Self, McpError> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| McpError::Config(format!("invalid header name `{name}`: {e}")))?;
+            let value = reqwest::header::HeaderValue::from_str(value)
+                .map_err(|e| McpError::Config(format!("invalid value for `{name}`: {e}")))?;
+            headers.insert(name, value);
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            headers,
+            session_id: Mutex::new(None),
+            protocol_version: Mutex::new(None),
+            closed: CancellationToken::new(),
+        })
+    }
+
+    async fn request(&self, id: u64, message: Value) -> Result<Value, McpError> {
+        self.post(&message, Some(id))
+            .await?
+            .ok_or_else(|| McpError::Protocol(format!("no response to request {id}")))
+    }
+
+    /// POST one message. For requests (`id` set) returns the matching
+    /// response, read from a JSON body or an SSE stream.
+    async fn post(&self, message: &Value, id: Option<u64>) -> Result<Option<Value>, McpError> {
+        if self.closed.is_cancelled() {
+            return Err(McpError::Closed);
+        }
+        let mut req = self
+            .client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .header(
+                reqwest::header::ACCEPT,
+                "application/json, text/event-stream",
+            )
+            .json(message);
+        let session_id = self.session_id.lock().unwrap().clone();
+        if let Some(ref sid) = session_id {
+            req = req.header(SESSION_HEADER, sid);
+        }
+        if let Some(ref version) = *self.protocol_version.lock().unwrap() {
+            req = req.header(PROTOCOL_HEADER, version);
+        }
+
+        let resp = req.send().await.map_err(|e| {
+            self.closed.cancel();
+            McpError::Transport(e.to_string())
+        })?;
+
+        let status = resp.status();
+        if status == reqwest::StatusCode::NOT_FOUND && session_id.is_some() {
+            // The server dropped our session; a fresh connection is needed.
+            self.closed.cancel();
+            return Err(McpError::Closed);
+        }
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(McpError::Transport(format!("HTTP {status}: {body}")));
+        }
+        if let Some(sid) = resp
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            *self.session_id.lock().unwrap() = Some(sid.to_string());
+        }
+        let Some(id) = id else {
+            return Ok(None);
+        };
+
+        let is_sse = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.starts_with("text/event-stream"));
+        if is_sse {
+            return read_sse_response(resp.bytes_stream(), id).await.map(Some);
+        }
+
+        let body: Value = resp
+            .json()
+            .await
+            .map_err(|e| McpError::Protocol(format!("invalid JSON response: {e}")))?;
+        // A batch may carry several messages; pick ours.
+        let found = match body {
+            Value::Array(items) => items.into_iter().find(|m| is_response_to(m, id)),
+            single if is_response_to(&single, id) => Some(single),
+            _ => None,
+        };
+        found
+            .map(Some)
+            .ok_or_else(|| McpError::Protocol(format!("no response to request {id}")))
+    }
+}
+
+fn is_response_to(msg: &Value, id: u64) -> bool {
+    matches!(classify(msg), Incoming::Response(got) if got == id)
+}
+
+/// Read SSE `data:` lines until the response with `id` arrives.
+///
+/// Bytes are buffered until a whole line is in, so a character split
+/// across two network chunks is decoded intact.
+async fn read_sse_response<S, B, E>(mut stream: S, id: u64) -> Result<Value, McpError>
+where
+    S: futures_util::Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    use futures_util::StreamExt;
+
+    let mut line_buf: Vec<u8> = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| McpError::Transport(e.to_string()))?;
+        line_buf.extend_from_slice(chunk.as_ref());
+        while let Some(pos) = line_buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = line_buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(SseParsed::Data(data)) = parse_sse_line(line.trim()) {
+                match serde_json::from_str::<Value>(&data) {
+                    Ok(msg) if is_response_to(&msg, id) => return Ok(msg),
+                    Ok(_) => {}
+                    Err(_) => debug!("mcp: ignoring non-JSON SSE data: {data}"),
+                }
+            }
+        }
+    }
+    Err(McpError::Protocol(format!(
+        "stream ended without a response to request {id}"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sse_lines_survive_split_characters() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{\"text\":\"é😀\"}}\n\n";
+        let bytes = body.as_bytes();
+        // cut inside the two-byte é and again inside the four-byte emoji
+        let e = body.find('é').unwrap() + 1;
+        let emoji = body.find('😀').unwrap() + 2;
+        let chunks = [&bytes[..e], &bytes[e..emoji], &bytes[emoji..]]
+            .map(|c| Ok::<_, std::io::Error>(c.to_vec()));
+
+        let msg = read_sse_response(futures_util::stream::iter(chunks), 7)
+            .await
+            .unwrap();
+        assert_eq!(msg["result"]["text"], "é😀");
+    }
+}
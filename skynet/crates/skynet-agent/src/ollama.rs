@@ -3,7 +3,9 @@ use serde::Deserialize;
 use tokio::sync::mpsc;
 use tracing::{debug, warn};
 
-use crate::provider::{ChatRequest, ChatResponse, LlmProvider, ProviderError};
+use crate::provider::{
+    ChatRequest, ChatResponse, ContentBlock, LlmProvider, MediaSource, ProviderError, ToolCall,
+};
 use crate::stream::StreamEvent;
 
 pub struct OllamaProvider {
@@ -101,7 +103,7 @@ impl LlmProvider for OllamaProvider {
             });
         }
 
-        process_ollama_stream(resp, tx).await;
+        process_ollama_stream(resp.bytes_stream(), tx).await;
         Ok(())
     }
 }
@@ -114,28 +116,162 @@ fn build_request_body(req: &ChatRequest, stream: bool) -> serde_json::Value {
         "content": req.system,
     })];
 
-    for m in &req.messages {
-        messages.push(serde_json::json!({
-            "role": m.role,
-            "content": m.content,
-        }));
+    if let Some(ref raw) = req.raw_messages {
+        messages.extend(translate_raw_messages(raw));
+    } else {
+        let plain: Vec<serde_json::Value> = req
+            .messages
+            .iter()
+            .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
+            .collect();
+        messages.extend(translate_raw_messages(&plain));
     }
 
-    serde_json::json!({
+    let mut body = serde_json::json!({
         "model": req.model,
         "messages": messages,
         "stream": stream,
         "options": {
             "num_predict": req.max_tokens,
         },
-    })
+    });
+
+    // Ollama accepts OpenAI-style function tools.
+    if !req.tools.is_empty() {
+        let tools: Vec<serde_json::Value> = req
+            .tools
+            .iter()
+            .map(|t| {
+                serde_json::json!({
+                    "type": "function",
+                    "function": {
+                        "name": t.name,
+                        "description": t.description,
+                        "parameters": t.input_schema,
+                    },
+                })
+            })
+            .collect();
+        body["tools"] = serde_json::Value::Array(tools);
+    }
+
+    body
+}
+
+/// Convert the tool loop's Anthropic-shaped `raw_messages` into Ollama chat
+/// messages.
+///
+/// Unlike OpenAI, Ollama passes tool arguments as a JSON object and matches
+/// results by `tool_name` rather than a call id, so the id → name mapping is
+/// tracked across the whole history.
+///
+/// Base64 images go into the message's `images` array. Ollama cannot fetch
+/// URLs or read PDFs, so those attachments are replaced by a text note.
+fn translate_raw_messages(raw: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut tool_names: std::collections::HashMap<String, String> =
+        std::collections::HashMap::new();
+
+    for msg in raw {
+        let role = msg.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+        let content = msg.get("content").cloned().unwrap_or_default();
+
+        let Some(blocks) = content.as_array() else {
+            out.push(serde_json::json!({ "role": role, "content": content }));
+            continue;
+        };
+
+        let mut text = String::new();
+        let mut images: Vec<String> = Vec::new();
+        let mut tool_calls: Vec<serde_json::Value> = Vec::new();
+        let mut tool_results: Vec<serde_json::Value> = Vec::new();
+
+        for block in blocks {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("text") => {
+                    text.push_str(block.get("text").and_then(|t| t.as_str()).unwrap_or(""));
+                }
+                Some("image") | Some("document") => {
+                    match serde_json::from_value::<ContentBlock>(block.clone()) {
+                        Ok(ContentBlock::Image {
+                            source: MediaSource::Base64 { data, .. },
+                        }) => images.push(data),
+                        Ok(other) => {
+                            text.push_str(&format!("[Attached {}]\n", other.describe()));
+                        }
+                        Err(_) => {}
+                    }
+                }
+                Some("tool_use") => {
+                    let id = block.get("id").and_then(|v| v.as_str()).unwrap_or("");
+                    let name = block.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                    tool_names.insert(id.to_string(), name.to_string());
+                    tool_calls.push(serde_json::json!({
+                        "function": {
+                            "name": name,
+                            "arguments": block.get("input").cloned().unwrap_or_default(),
+                        },
+                    }));
+                }
+                Some("tool_result") => {
+                    let id = block
+                        .get("tool_use_id")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    tool_results.push(serde_json::json!({
+                        "role": "tool",
+                        "tool_name": tool_names.get(id).cloned().unwrap_or_default(),
+                        "content": crate::provider::tool_result_text(block.get("content")),
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        if role == "assistant" {
+            let mut m = serde_json::json!({ "role": "assistant", "content": text });
+            if !tool_calls.is_empty() {
+                m["tool_calls"] = serde_json::Value::Array(tool_calls);
+            }
+            out.push(m);
+        } else {
+            out.extend(tool_results);
+            if !images.is_empty() {
+                out.push(serde_json::json!({ "role": role, "content": text, "images": images }));
+            } else if !text.is_empty() {
+                out.push(serde_json::json!({ "role": role, "content": text }));
+            }
+        }
+    }
+
+    out
+}
+
+/// Convert Ollama tool calls into `ToolCall`s. Ollama does not always return
+/// a call id, so one is generated to pair the later `tool_result`.
+fn convert_tool_calls(calls: Vec<OllamaToolCall>) -> Vec<ToolCall> {
+    calls
+        .into_iter()
+        .map(|c| ToolCall {
+            id: c
+                .id
+                .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple())),
+            name: c.function.name,
+            input: c.function.arguments,
+        })
+        .collect()
 }
 
 fn parse_response(resp: ApiResponse) -> ChatResponse {
     let content = resp.message.content;
     let tokens_in = resp.prompt_eval_count.unwrap_or(0);
     let tokens_out = resp.eval_count.unwrap_or(0);
-    let stop_reason = if resp.done {
+    let tool_calls = convert_tool_calls(resp.message.tool_calls.unwrap_or_default());
+    // Ollama reports done_reason "stop" even when it calls tools; the tool
+    // loop expects Anthropic's "tool_use".
+    let stop_reason = if !tool_calls.is_empty() {
+        "tool_use".to_string()
+    } else if resp.done {
         "stop".to_string()
     } else {
         String::new()
@@ -146,23 +282,32 @@ fn parse_response(resp: ApiResponse) -> ChatResponse {
         model: resp.model,
         tokens_in,
         tokens_out,
+        cache_write_tokens: 0,
+        cache_read_tokens: 0,
         stop_reason,
-        tool_calls: Vec::new(),
+        tool_calls,
     }
 }
 
 /// Parse Ollama's newline-delimited JSON streaming format.
 /// Each line is a JSON object. When `done` is true the final stats are included.
-async fn process_ollama_stream(resp: reqwest::Response, tx: mpsc::Sender<StreamEvent>) {
+/// Tool calls arrive whole inside a chunk's `message.tool_calls` and are
+/// forwarded as `StreamEvent::ToolUse`.
+async fn process_ollama_stream<S, B, E>(mut byte_stream: S, tx: mpsc::Sender<StreamEvent>)
+where
+    S: futures_util::Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
     use futures_util::StreamExt;
 
     let mut model = String::new();
     let mut tokens_in: u32 = 0;
     let mut tokens_out: u32 = 0;
     let mut stop_reason = String::new();
-    let mut line_buf = String::new();
-
-    let mut byte_stream = resp.bytes_stream();
+    let mut saw_tool_call = false;
+    // Raw bytes until a full line arrives: a chunk may end mid-character.
+    let mut line_buf: Vec<u8> = Vec::new();
 
     while let Some(chunk) = byte_stream.next().await {
         let chunk = match chunk {
@@ -177,17 +322,10 @@ async fn process_ollama_stream(resp: reqwest::Response, tx: mpsc::Sender<StreamE
             }
         };
 
-        let text = match std::str::from_utf8(&chunk) {
-            Ok(t) => t,
-            Err(_) => continue,
-        };
-
-        line_buf.push_str(text);
-        let lines: Vec<&str> = line_buf.split('\n').collect();
-        let (complete, remainder) = lines.split_at(lines.len() - 1);
-        let remainder = remainder.first().unwrap_or(&"").to_string();
-
-        for line in complete {
+        line_buf.extend_from_slice(chunk.as_ref());
+        while let Some(pos) = line_buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = line_buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
             let line = line.trim();
             if line.is_empty() {
                 continue;
@@ -200,20 +338,33 @@ async fn process_ollama_stream(resp: reqwest::Response, tx: mpsc::Sender<StreamE
                         model = chunk_data.model.clone();
                     }
 
+                    let text = chunk_data.message.content;
+                    if !text.is_empty() {
+                        debug!(len = text.len(), "ollama stream text delta");
+                        if tx.send(StreamEvent::TextDelta { text }).await.is_err() {
+                            return; // receiver dropped
+                        }
+                    }
+
+                    let calls =
+                        convert_tool_calls(chunk_data.message.tool_calls.unwrap_or_default());
+                    for call in calls {
+                        saw_tool_call = true;
+                        let event = StreamEvent::ToolUse {
+                            id: call.id,
+                            name: call.name,
+                            input: call.input,
+                        };
+                        if tx.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+
                     if chunk_data.done {
                         // final chunk — collect token counts and stop reason
                         tokens_in = chunk_data.prompt_eval_count.unwrap_or(0);
                         tokens_out = chunk_data.eval_count.unwrap_or(0);
                         stop_reason = chunk_data.done_reason.unwrap_or_else(|| "stop".to_string());
-                    } else {
-                        // incremental text delta
-                        let text = chunk_data.message.content;
-                        if !text.is_empty() {
-                            debug!(len = text.len(), "ollama stream text delta");
-                            if tx.send(StreamEvent::TextDelta { text }).await.is_err() {
-                                return; // receiver dropped
-                            }
-                        }
                     }
                 }
                 Err(e) => {
@@ -221,8 +372,10 @@ async fn process_ollama_stream(resp: reqwest::Response, tx: mpsc::Sender<StreamE
                 }
             }
         }
+    }
 
-        line_buf = remainder;
+    if saw_tool_call {
+        stop_reason = "tool_use".to_string();
     }
 
     let _ = tx
@@ -230,6 +383,8 @@ async fn process_ollama_stream(resp: reqwest::Response, tx: mpsc::Sender<StreamE
             model,
             tokens_in,
             tokens_out,
+            cache_write_tokens: 0,
+            cache_read_tokens: 0,
             stop_reason,
         })
         .await;
@@ -248,7 +403,24 @@ struct ApiResponse {
 
 #[derive(Deserialize)]
 struct OllamaMessage {
+    #[serde(default)]
     content: String,
+    tool_calls: Option<Vec<OllamaToolCall>>,
+}
+
+#[derive(Deserialize)]
+struct OllamaToolCall {
+    /// Only returned by newer Ollama releases.
+    id: Option<String>,
+    function: OllamaFunctionCall,
+}
+
+#[derive(Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    /// Arguments as a JSON object (not a string, unlike OpenAI).
+    #[serde(default)]
+    arguments: serde_json::Value,
 }
 
 // Ollama streaming chunk types
@@ -262,3 +434,239 @@ struct StreamChunk {
     prompt_eval_count: Option<u32>,
     eval_count: Option<u32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Router};
+
+    use super::*;
+    use crate::provider::{Message, Role, ToolDefinition};
+
+    /// Start a mock `/api/chat` server that always answers with `body`.
+    async fn spawn_mock(body: String) -> String {
+        let app = Router::new().route("/api/chat", post(move || async move { body }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn request_with_tools() -> ChatRequest {
+        ChatRequest {
+            model: "llama-test".to_string(),
+            system: "You are a test.".to_string(),
+            system_prompt: None,
+            messages: vec![Message {
+                role: Role::User,
+                content: "say hi".into(),
+            }],
+            max_tokens: 64,
+            stream: false,
+            thinking: None,
+            tools: vec![ToolDefinition {
+                name: "echo".to_string(),
+                description: "Echo text back".to_string(),
+                input_schema: serde_json::json!({ "type": "object" }),
+            }],
+            raw_messages: None,
+        }
+    }
+
+    #[test]
+    fn raw_messages_translate_to_ollama_tool_format() {
+        let mut req = request_with_tools();
+        req.raw_messages = Some(vec![
+            serde_json::json!({ "role": "user", "content": "say hi" }),
+            serde_json::json!({
+                "role": "assistant",
+                "content": [
+                    { "type": "tool_use", "id": "call_1", "name": "echo", "input": { "text": "hi" } }
+                ]
+            }),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    { "type": "tool_result", "tool_use_id": "call_1", "content": "hi", "is_error": false }
+                ]
+            }),
+            serde_json::json!({
+                "role": "assistant",
+                "content": [
+                    { "type": "tool_use", "id": "call_2", "name": "echo", "input": { "text": "again" } }
+                ]
+            }),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    {
+                        "type": "tool_result",
+                        "tool_use_id": "call_2",
+                        "content": [
+                            { "type": "text", "text": "first, " },
+                            { "type": "text", "text": "second" }
+                        ]
+                    }
+                ]
+            }),
+        ]);
+
+        let body = build_request_body(&req, false);
+        let messages = body["messages"].as_array().unwrap();
+
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["name"], "echo");
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"]["text"],
+            "hi"
+        );
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_name"], "echo");
+        assert_eq!(messages[3]["content"], "hi");
+        // Block content is flattened to its text, not sent as raw JSON.
+        assert_eq!(messages[5]["content"], "first, second");
+        assert_eq!(body["tools"][0]["function"]["name"], "echo");
+    }
+
+    #[test]
+    fn attachments_translate_to_ollama_images() {
+        let mut req = request_with_tools();
+        req.messages[0].content = crate::provider::MessageContent::with_attachments(
+            "describe these",
+            &[
+                ContentBlock::Image {
+                    source: MediaSource::Base64 {
+                        media_type: "image/png".to_string(),
+                        data: "iVBORw0K".to_string(),
+                    },
+                },
+                ContentBlock::Document {
+                    source: MediaSource::Url {
+                        url: "https://example.com/a.pdf".to_string(),
+                    },
+                },
+            ],
+        );
+
+        let body = build_request_body(&req, false);
+        let user = &body["messages"][1];
+
+        assert_eq!(user["images"], serde_json::json!(["iVBORw0K"]));
+        assert_eq!(
+            user["content"],
+            "[Attached document (https://example.com/a.pdf)]\ndescribe these"
+        );
+    }
+
+    #[tokio::test]
+    async fn send_parses_tool_calls() {
+        let reply = serde_json::json!({
+            "model": "llama-test",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{ "function": { "name": "echo", "arguments": { "text": "hi" } } }]
+            },
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 12,
+            "eval_count": 4
+        });
+        let provider = OllamaProvider::new(Some(spawn_mock(reply.to_string()).await));
+
+        let resp = provider.send(&request_with_tools()).await.unwrap();
+
+        assert_eq!(resp.stop_reason, "tool_use");
+        assert_eq!(resp.tool_calls.len(), 1);
+        assert!(resp.tool_calls[0].id.starts_with("call_"));
+        assert_eq!(resp.tool_calls[0].name, "echo");
+        assert_eq!(resp.tool_calls[0].input["text"], "hi");
+        assert_eq!(resp.tokens_in, 12);
+    }
+
+    #[tokio::test]
+    async fn stream_emits_deltas_and_tool_calls() {
+        let lines = [
+            serde_json::json!({ "model": "llama-test", "message": { "role": "assistant", "content": "Let me " }, "done": false }),
+            serde_json::json!({ "model": "llama-test", "message": { "role": "assistant", "content": "check." }, "done": false }),
+            serde_json::json!({
+                "model": "llama-test",
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{ "function": { "name": "echo", "arguments": { "text": "hi" } } }]
+                },
+                "done": false
+            }),
+            serde_json::json!({
+                "model": "llama-test",
+                "message": { "role": "assistant", "content": "" },
+                "done": true,
+                "done_reason": "stop",
+                "prompt_eval_count": 7,
+                "eval_count": 3
+            }),
+        ];
+        let body = lines.iter().map(|l| format!("{l}\n")).collect::<String>();
+        let provider = OllamaProvider::new(Some(spawn_mock(body).await));
+
+        let (tx, mut rx) = mpsc::channel(16);
+        provider
+            .send_stream(&request_with_tools(), tx)
+            .await
+            .unwrap();
+
+        let mut text = String::new();
+        let mut tools = Vec::new();
+        let mut done = None;
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::TextDelta { text: t } => text.push_str(&t),
+                StreamEvent::ToolUse { name, input, .. } => tools.push((name, input)),
+                StreamEvent::Done {
+                    model,
+                    tokens_in,
+                    stop_reason,
+                    ..
+                } => done = Some((model, tokens_in, stop_reason)),
+                other => panic!("unexpected event: {other:?}"),
+            }
+        }
+
+        assert_eq!(text, "Let me check.");
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].0, "echo");
+        assert_eq!(tools[0].1["text"], "hi");
+        assert_eq!(
+            done,
+            Some(("llama-test".to_string(), 7, "tool_use".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_survives_characters_split_across_chunks() {
+        let body = concat!(
+            "{\"model\":\"llama-test\",\"message\":{\"role\":\"assistant\",\"content\":\"héllo \"},\"done\":false}\n",
+            "{\"model\":\"llama-test\",\"message\":{\"role\":\"assistant\",\"content\":\"😀\"},\"done\":false}\n",
+            "{\"model\":\"llama-test\",\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}\n",
+        );
+        let bytes = body.as_bytes();
+        // cut inside the two-byte é and again inside the four-byte emoji
+        let e = body.find('é').unwrap() + 1;
+        let emoji = body.find('😀').unwrap() + 2;
+        let chunks = [&bytes[..e], &bytes[e..emoji], &bytes[emoji..]]
+            .map(|c| Ok::<_, std::io::Error>(c.to_vec()));
+
+        let (tx, mut rx) = mpsc::channel(16);
+        process_ollama_stream(futures_util::stream::iter(chunks), tx).await;
+
+        let mut text = String::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::TextDelta { text: t } => text.push_str(&t),
+                StreamEvent::Done { .. } => {}
+                other => panic!("unexpected event: {other:?}"),
+            }
+        }
+        assert_eq!(text, "héllo 😀");
+    }
+}
@@ -3,7 +3,9 @@ use serde::Deserialize;
 use tokio::sync::mpsc;
 use tracing::{debug, warn};
 
-use crate::provider::{ChatRequest, ChatResponse, LlmProvider, ProviderError};
+use crate::provider::{
+    ChatRequest, ChatResponse, ContentBlock, LlmProvider, MediaSource, ProviderError, ToolCall,
+};
 use crate::stream::{parse_sse_line, SseParsed, StreamEvent};
 
 pub struct OpenAiProvider {
@@ -116,7 +118,7 @@ impl LlmProvider for OpenAiProvider {
             });
         }
 
-        process_openai_stream(resp, req.model.clone(), tx).await;
+        process_openai_stream(resp.bytes_stream(), req.model.clone(), tx).await;
         Ok(())
     }
 }
@@ -128,62 +130,248 @@ fn build_request_body(req: &ChatRequest, stream: bool) -> serde_json::Value {
         "content": req.system,
     })];
 
-    for m in &req.messages {
-        messages.push(serde_json::json!({
-            "role": m.role,
-            "content": m.content,
-        }));
+    // The tool loop stores its history as Anthropic-shaped content blocks;
+    // translate them into OpenAI's tool_calls / role:"tool" messages.
+    if let Some(ref raw) = req.raw_messages {
+        for m in raw {
+            messages.extend(translate_raw_message(m));
+        }
+    } else {
+        for m in &req.messages {
+            let m = serde_json::json!({ "role": m.role, "content": m.content });
+            messages.extend(translate_raw_message(&m));
+        }
     }
 
-    serde_json::json!({
+    let mut body = serde_json::json!({
         "model": req.model,
         "messages": messages,
         "max_tokens": req.max_tokens,
         "stream": stream,
-    })
+    });
+
+    // Inject tool definitions as OpenAI function tools.
+    if !req.tools.is_empty() {
+        let tools: Vec<serde_json::Value> = req
+            .tools
+            .iter()
+            .map(|t| {
+                serde_json::json!({
+                    "type": "function",
+                    "function": {
+                        "name": t.name,
+                        "description": t.description,
+                        "parameters": t.input_schema,
+                    },
+                })
+            })
+            .collect();
+        body["tools"] = serde_json::Value::Array(tools);
+    }
+
+    // Ask for a final usage chunk so streamed turns report token counts.
+    if stream {
+        body["stream_options"] = serde_json::json!({ "include_usage": true });
+    }
+
+    body
+}
+
+/// Convert one Anthropic-shaped message from `raw_messages` into one or more
+/// OpenAI chat messages.
+///
+/// - `assistant` text blocks are joined into `content`; `tool_use` blocks
+///   become `tool_calls` with the input serialized as a JSON string.
+/// - `user` `tool_result` blocks each become a separate `role: "tool"`
+///   message; any remaining text blocks follow as a normal user message.
+/// - `image` and `document` blocks turn the user message's `content` into an
+///   array of `image_url` / `file` parts.
+/// - `thinking` blocks are dropped — OpenAI has no equivalent.
+fn translate_raw_message(msg: &serde_json::Value) -> Vec<serde_json::Value> {
+    let role = msg.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+    let content = msg.get("content").cloned().unwrap_or_default();
+
+    let Some(blocks) = content.as_array() else {
+        // Plain string content passes through unchanged.
+        return vec![serde_json::json!({ "role": role, "content": content })];
+    };
+
+    let mut text_parts: Vec<&str> = Vec::new();
+    let mut media_parts: Vec<serde_json::Value> = Vec::new();
+    let mut tool_calls: Vec<serde_json::Value> = Vec::new();
+    let mut tool_results: Vec<serde_json::Value> = Vec::new();
+
+    for block in blocks {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                    text_parts.push(text);
+                }
+            }
+            Some("image") | Some("document") => {
+                if let Some(part) = media_part(block) {
+                    media_parts.push(part);
+                }
+            }
+            Some("tool_use") => {
+                let input = block.get("input").cloned().unwrap_or_default();
+                tool_calls.push(serde_json::json!({
+                    "id": block.get("id"),
+                    "type": "function",
+                    "function": {
+                        "name": block.get("name"),
+                        "arguments": input.to_string(),
+                    },
+                }));
+            }
+            Some("tool_result") => {
+                tool_results.push(serde_json::json!({
+                    "role": "tool",
+                    "tool_call_id": block.get("tool_use_id"),
+                    "content": crate::provider::tool_result_text(block.get("content")),
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    let text = text_parts.join("");
+
+    if role == "assistant" {
+        let mut out = serde_json::json!({ "role": "assistant" });
+        // OpenAI expects `content: null` on a pure tool-call turn.
+        out["content"] = if text.is_empty() && !tool_calls.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::Value::String(text)
+        };
+        if !tool_calls.is_empty() {
+            out["tool_calls"] = serde_json::Value::Array(tool_calls);
+        }
+        return vec![out];
+    }
+
+    // Tool results must directly follow the assistant message that requested them.
+    let mut out = tool_results;
+    if !media_parts.is_empty() {
+        if !text.is_empty() {
+            media_parts.push(serde_json::json!({ "type": "text", "text": text }));
+        }
+        out.push(serde_json::json!({ "role": role, "content": media_parts }));
+    } else if !text.is_empty() {
+        out.push(serde_json::json!({ "role": role, "content": text }));
+    }
+    out
+}
+
+/// Convert an Anthropic `image` / `document` block into an OpenAI content part.
+///
+/// Images become `image_url` parts (base64 as a `data:` URL). PDFs become
+/// `file` parts; OpenAI only accepts inline file data, so a URL-only document
+/// is passed as a text reference instead.
+fn media_part(block: &serde_json::Value) -> Option<serde_json::Value> {
+    match serde_json::from_value::<ContentBlock>(block.clone()).ok()? {
+        ContentBlock::Image { source } => Some(serde_json::json!({
+            "type": "image_url",
+            "image_url": { "url": source.to_url() },
+        })),
+        ContentBlock::Document {
+            source: source @ MediaSource::Base64 { .. },
+        } => Some(serde_json::json!({
+            "type": "file",
+            "file": { "filename": "document.pdf", "file_data": source.to_url() },
+        })),
+        ContentBlock::Document {
+            source: MediaSource::Url { url },
+        } => Some(serde_json::json!({
+            "type": "text",
+            "text": format!("[Attached document: {url}]"),
+        })),
+        ContentBlock::Text { .. } => None,
+    }
+}
+
+/// Map OpenAI's `finish_reason` to the Anthropic-style stop reason the tool
+/// loop and streaming dispatcher check for (`"tool_use"`).
+fn normalize_finish_reason(reason: String) -> String {
+    if reason == "tool_calls" {
+        "tool_use".to_string()
+    } else {
+        reason
+    }
+}
+
+/// Parse a function call's `arguments` string. Malformed JSON yields an empty
+/// object so the tool reports its own missing-parameter error.
+fn parse_arguments(arguments: &str) -> serde_json::Value {
+    serde_json::from_str(arguments).unwrap_or(serde_json::Value::Object(Default::default()))
 }
 
 fn parse_response(resp: ApiResponse) -> ChatResponse {
     let choice = resp.choices.into_iter().next();
-    let content = choice
-        .as_ref()
-        .and_then(|c| c.message.content.as_deref())
-        .unwrap_or("")
-        .to_string();
-    let stop_reason = choice.and_then(|c| c.finish_reason).unwrap_or_default();
+    let (content, api_calls, finish_reason) = match choice {
+        Some(c) => (
+            c.message.content.unwrap_or_default(),
+            c.message.tool_calls.unwrap_or_default(),
+            c.finish_reason.unwrap_or_default(),
+        ),
+        None => (String::new(), Vec::new(), String::new()),
+    };
+
+    let tool_calls: Vec<ToolCall> = api_calls
+        .into_iter()
+        .map(|c| ToolCall {
+            id: c.id,
+            name: c.function.name,
+            input: parse_arguments(&c.function.arguments),
+        })
+        .collect();
+
+    // Some OpenAI-compatible servers return tool calls with finish_reason "stop".
+    let stop_reason = if tool_calls.is_empty() {
+        normalize_finish_reason(finish_reason)
+    } else {
+        "tool_use".to_string()
+    };
 
+    let usage = resp.usage.unwrap_or_default();
     ChatResponse {
         content,
         model: resp.model,
-        tokens_in: resp.usage.as_ref().map(|u| u.prompt_tokens).unwrap_or(0),
-        tokens_out: resp
-            .usage
-            .as_ref()
-            .map(|u| u.completion_tokens)
-            .unwrap_or(0),
+        tokens_in: usage.uncached_prompt_tokens(),
+        tokens_out: usage.completion_tokens,
+        cache_write_tokens: 0,
+        cache_read_tokens: usage.cached_tokens(),
         stop_reason,
-        tool_calls: Vec::new(),
+        tool_calls,
     }
 }
 
 /// Parse OpenAI streaming SSE response and emit StreamEvents.
 /// OpenAI SSE format is identical to standard SSE (event/data lines).
 /// Each data line contains a JSON delta object; `data: [DONE]` signals end.
-async fn process_openai_stream(
-    resp: reqwest::Response,
+///
+/// Tool calls arrive as fragments keyed by `index`; they are accumulated and
+/// emitted as `StreamEvent::ToolUse` once the stream finishes.
+async fn process_openai_stream<S, B, E>(
+    mut byte_stream: S,
     model: String,
     tx: mpsc::Sender<StreamEvent>,
-) {
+) where
+    S: futures_util::Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
     use futures_util::StreamExt;
 
-    let mut tokens_in: u32 = 0;
-    let mut tokens_out: u32 = 0;
+    let mut usage = Usage::default();
     let mut stop_reason = String::new();
-    let mut line_buf = String::new();
-
-    let mut byte_stream = resp.bytes_stream();
+    // Raw bytes until a full line arrives: a chunk may end mid-character.
+    let mut line_buf: Vec<u8> = Vec::new();
+    // Partial tool calls, in `index` order.
+    let mut pending_tools: Vec<PendingToolCall> = Vec::new();
 
-    while let Some(chunk) = byte_stream.next().await {
+    'outer: while let Some(chunk) = byte_stream.next().await {
         let chunk = match chunk {
             Ok(c) => c,
             Err(e) => {
@@ -196,17 +384,10 @@ async fn process_openai_stream(
             }
         };
 
-        let text = match std::str::from_utf8(&chunk) {
-            Ok(t) => t,
-            Err(_) => continue,
-        };
-
-        line_buf.push_str(text);
-        let lines: Vec<&str> = line_buf.split('\n').collect();
-        let (complete, remainder) = lines.split_at(lines.len() - 1);
-        let remainder = remainder.first().unwrap_or(&"").to_string();
-
-        for line in complete {
+        line_buf.extend_from_slice(chunk.as_ref());
+        while let Some(pos) = line_buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = line_buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
             let line = line.trim();
             if line.is_empty() {
                 continue;
@@ -216,22 +397,24 @@ async fn process_openai_stream(
                 {
                     // OpenAI signals end-of-stream with a literal `[DONE]` data value
                     if data.trim() == "[DONE]" {
-                        break;
+                        break 'outer;
                     }
 
                     if let Ok(chunk_resp) = serde_json::from_str::<StreamChunk>(&data) {
                         // capture usage if present (OpenAI can send it on the final chunk)
-                        if let Some(usage) = &chunk_resp.usage {
-                            tokens_in = usage.prompt_tokens;
-                            tokens_out = usage.completion_tokens;
+                        if let Some(chunk_usage) = chunk_resp.usage {
+                            usage = chunk_usage;
                         }
 
                         for choice in &chunk_resp.choices {
                             if let Some(reason) = &choice.finish_reason {
                                 if !reason.is_empty() {
-                                    stop_reason = reason.clone();
+                                    stop_reason = normalize_finish_reason(reason.clone());
                                 }
                             }
+                            for delta in choice.delta.tool_calls.iter().flatten() {
+                                accumulate_tool_delta(&mut pending_tools, delta);
+                            }
                             if let Some(content) = &choice.delta.content {
                                 if !content.is_empty() {
                                     debug!(len = content.len(), "openai stream text delta");
@@ -251,20 +434,67 @@ async fn process_openai_stream(
                 }
             }
         }
+    }
 
-        line_buf = remainder;
+    if !pending_tools.is_empty() {
+        // Mirror the non-streaming path: tool calls always mean "tool_use".
+        stop_reason = "tool_use".to_string();
+        for call in pending_tools {
+            let event = StreamEvent::ToolUse {
+                id: call.id,
+                name: call.name,
+                input: parse_arguments(&call.arguments),
+            };
+            if tx.send(event).await.is_err() {
+                return;
+            }
+        }
     }
 
     let _ = tx
         .send(StreamEvent::Done {
             model,
-            tokens_in,
-            tokens_out,
+            tokens_in: usage.uncached_prompt_tokens(),
+            tokens_out: usage.completion_tokens,
+            cache_write_tokens: 0,
+            cache_read_tokens: usage.cached_tokens(),
             stop_reason,
         })
         .await;
 }
 
+/// A streamed tool call being assembled from `delta.tool_calls` fragments.
+struct PendingToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Merge one tool-call fragment into the pending list. The first fragment for
+/// an index carries the id and name; later ones append to `arguments`.
+fn accumulate_tool_delta(pending: &mut Vec<PendingToolCall>, delta: &StreamToolCallDelta) {
+    let index = delta.index as usize;
+    while pending.len() <= index {
+        pending.push(PendingToolCall {
+            id: String::new(),
+            name: String::new(),
+            arguments: String::new(),
+        });
+    }
+    let call = &mut pending[index];
+    if let Some(id) = &delta.id {
+        call.id.clone_from(id);
+    }
+    if let Some(function) = &delta.function {
+        if let Some(name) = &function.name {
+            call.name.push_str(name);
+        }
+        if let Some(arguments) = &function.arguments {
+            call.arguments.push_str(arguments);
+        }
+    }
+}
+
 // OpenAI API response types (private — deserialization only)
 
 #[derive(Deserialize)]
@@ -283,12 +513,50 @@ struct Choice {
 #[derive(Deserialize)]
 struct ChatMessage {
     content: Option<String>,
+    tool_calls: Option<Vec<ApiToolCall>>,
+}
+
+#[derive(Deserialize)]
+struct ApiToolCall {
+    id: String,
+    function: ApiFunctionCall,
 }
 
 #[derive(Deserialize)]
+struct ApiFunctionCall {
+    name: String,
+    /// JSON-encoded arguments object, as a string.
+    arguments: String,
+}
+
+#[derive(Deserialize, Default)]
 struct Usage {
+    /// All prompt tokens, the cached ones included.
     prompt_tokens: u32,
     completion_tokens: u32,
+    #[serde(default)]
+    prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u32,
+}
+
+impl Usage {
+    /// Prompt tokens served from OpenAI's prompt cache.
+    fn cached_tokens(&self) -> u32 {
+        self.prompt_tokens_details
+            .as_ref()
+            .map_or(0, |d| d.cached_tokens)
+    }
+
+    /// Prompt tokens billed at the full input rate; `TokenUsage::tokens_in`
+    /// excludes cache reads.
+    fn uncached_prompt_tokens(&self) -> u32 {
+        self.prompt_tokens.saturating_sub(self.cached_tokens())
+    }
 }
 
 // OpenAI streaming chunk types
@@ -296,7 +564,7 @@ struct Usage {
 #[derive(Deserialize)]
 struct StreamChunk {
     choices: Vec<StreamChoice>,
-    usage: Option<StreamUsage>,
+    usage: Option<Usage>,
 }
 
 #[derive(Deserialize)]
@@ -308,10 +576,360 @@ struct StreamChoice {
 #[derive(Deserialize)]
 struct StreamDelta {
     content: Option<String>,
+    tool_calls: Option<Vec<StreamToolCallDelta>>,
 }
 
 #[derive(Deserialize)]
-struct StreamUsage {
-    prompt_tokens: u32,
-    completion_tokens: u32,
+struct StreamToolCallDelta {
+    index: u32,
+    id: Option<String>,
+    function: Option<StreamFunctionDelta>,
+}
+
+#[derive(Deserialize)]
+struct StreamFunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{extract::State, routing::post, Json, Router};
+
+    use super::*;
+    use crate::provider::{Message, Role, ToolDefinition};
+    use crate::tools::{Tool, ToolResult};
+
+    /// Requests received by the mock server, and the canned replies it serves in order.
+    #[derive(Clone, Default)]
+    struct Mock {
+        requests: Arc<Mutex<Vec<serde_json::Value>>>,
+        replies: Arc<Mutex<Vec<serde_json::Value>>>,
+    }
+
+    async fn completions(
+        State(mock): State<Mock>,
+        Json(body): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        mock.requests.lock().unwrap().push(body);
+        Json(mock.replies.lock().unwrap().remove(0))
+    }
+
+    /// Start a mock `/v1/chat/completions` server and return its base URL.
+    async fn spawn_mock(replies: Vec<serde_json::Value>) -> (String, Mock) {
+        let mock = Mock::default();
+        *mock.replies.lock().unwrap() = replies;
+        let app = Router::new()
+            .route("/v1/chat/completions", post(completions))
+            .with_state(mock.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}"), mock)
+    }
+
+    fn tool_call_reply() -> serde_json::Value {
+        serde_json::json!({
+            "model": "gpt-test",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "echo", "arguments": "{\"text\":\"hi\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 5 }
+        })
+    }
+
+    fn text_reply(text: &str) -> serde_json::Value {
+        serde_json::json!({
+            "model": "gpt-test",
+            "choices": [{
+                "message": { "role": "assistant", "content": text },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 20, "completion_tokens": 3 }
+        })
+    }
+
+    fn request_with_tools() -> ChatRequest {
+        ChatRequest {
+            model: "gpt-test".to_string(),
+            system: "You are a test.".to_string(),
+            system_prompt: None,
+            messages: vec![Message {
+                role: Role::User,
+                content: "say hi".into(),
+            }],
+            max_tokens: 64,
+            stream: false,
+            thinking: None,
+            tools: vec![ToolDefinition {
+                name: "echo".to_string(),
+                description: "Echo text back".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": { "text": { "type": "string" } },
+                    "required": ["text"]
+                }),
+            }],
+            raw_messages: None,
+        }
+    }
+
+    struct EchoTool;
+
+    #[async_trait]
+    impl Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            "Echo text back"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object" })
+        }
+        async fn execute(&self, input: serde_json::Value) -> ToolResult {
+            ToolResult::success(input["text"].as_str().unwrap_or_default())
+        }
+    }
+
+    #[test]
+    fn raw_messages_translate_to_openai_tool_format() {
+        let mut req = request_with_tools();
+        req.raw_messages = Some(vec![
+            serde_json::json!({ "role": "user", "content": "say hi" }),
+            serde_json::json!({
+                "role": "assistant",
+                "content": [
+                    { "type": "thinking", "thinking": "hmm" },
+                    { "type": "tool_use", "id": "call_1", "name": "echo", "input": { "text": "hi" } }
+                ]
+            }),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    { "type": "tool_result", "tool_use_id": "call_1", "content": "hi", "is_error": false }
+                ]
+            }),
+        ]);
+
+        let body = build_request_body(&req, false);
+        let messages = body["messages"].as_array().unwrap();
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[2]["role"], "assistant");
+        assert!(messages[2]["content"].is_null());
+        assert_eq!(messages[2]["tool_calls"][0]["id"], "call_1");
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["name"], "echo");
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"],
+            "{\"text\":\"hi\"}"
+        );
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_call_id"], "call_1");
+        assert_eq!(messages[3]["content"], "hi");
+
+        assert_eq!(body["tools"][0]["type"], "function");
+        assert_eq!(body["tools"][0]["function"]["name"], "echo");
+        assert_eq!(
+            body["tools"][0]["function"]["parameters"]["required"][0],
+            "text"
+        );
+    }
+
+    #[test]
+    fn attachments_translate_to_openai_content_parts() {
+        let mut req = request_with_tools();
+        let png = MediaSource::Base64 {
+            media_type: "image/png".to_string(),
+            data: "iVBORw0K".to_string(),
+        };
+        let pdf = MediaSource::Base64 {
+            media_type: "application/pdf".to_string(),
+            data: "JVBERi0x".to_string(),
+        };
+        req.messages[0].content = crate::provider::MessageContent::with_attachments(
+            "what is this?",
+            &[
+                ContentBlock::Image { source: png },
+                ContentBlock::Document { source: pdf },
+            ],
+        );
+
+        let body = build_request_body(&req, false);
+        let parts = body["messages"][1]["content"].as_array().unwrap();
+
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0]["type"], "image_url");
+        assert_eq!(
+            parts[0]["image_url"]["url"],
+            "data:image/png;base64,iVBORw0K"
+        );
+        assert_eq!(parts[1]["type"], "file");
+        assert_eq!(
+            parts[1]["file"]["file_data"],
+            "data:application/pdf;base64,JVBERi0x"
+        );
+        assert_eq!(
+            parts[2],
+            serde_json::json!({ "type": "text", "text": "what is this?" })
+        );
+    }
+
+    #[test]
+    fn cached_prompt_tokens_are_split_out() {
+        let usage: Usage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 100,
+            "completion_tokens": 5,
+            "prompt_tokens_details": { "cached_tokens": 60, "audio_tokens": 0 }
+        }))
+        .unwrap();
+        assert_eq!(
+            (usage.uncached_prompt_tokens(), usage.cached_tokens()),
+            (40, 60)
+        );
+
+        let usage: Usage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 100,
+            "completion_tokens": 5
+        }))
+        .unwrap();
+        assert_eq!(
+            (usage.uncached_prompt_tokens(), usage.cached_tokens()),
+            (100, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn send_parses_tool_calls() {
+        let (base_url, mock) = spawn_mock(vec![tool_call_reply()]).await;
+        let provider = OpenAiProvider::new("test-key".to_string(), Some(base_url));
+
+        let resp = provider.send(&request_with_tools()).await.unwrap();
+
+        assert_eq!(resp.stop_reason, "tool_use");
+        assert_eq!(resp.tool_calls.len(), 1);
+        assert_eq!(resp.tool_calls[0].id, "call_1");
+        assert_eq!(resp.tool_calls[0].name, "echo");
+        assert_eq!(resp.tool_calls[0].input["text"], "hi");
+        assert_eq!(resp.tokens_in, 10);
+
+        let sent = mock.requests.lock().unwrap();
+        assert_eq!(sent[0]["tools"][0]["function"]["name"], "echo");
+    }
+
+    #[tokio::test]
+    async fn tool_loop_round_trips_through_openai() {
+        let (base_url, mock) = spawn_mock(vec![tool_call_reply(), text_reply("done")]).await;
+        let provider = OpenAiProvider::new("test-key".to_string(), Some(base_url));
+        let tools: Vec<Box<dyn Tool>> = vec![Box::new(EchoTool)];
+
+        let (resp, called, _) = crate::tools::tool_loop::run_tool_loop(
+            &provider,
+            request_with_tools(),
+            &tools,
+            &crate::context_budget::ContextWindows::default(),
+            &crate::cancel::CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(resp.content, "done");
+        assert_eq!(called, vec!["echo".to_string()]);
+
+        let sent = mock.requests.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        let second = sent[1]["messages"].as_array().unwrap();
+        let tool_msg = second.last().unwrap();
+        assert_eq!(tool_msg["role"], "tool");
+        assert_eq!(tool_msg["tool_call_id"], "call_1");
+        assert_eq!(tool_msg["content"], "hi");
+        assert_eq!(second[second.len() - 2]["tool_calls"][0]["id"], "call_1");
+    }
+
+    #[tokio::test]
+    async fn cancelled_tool_loop_stops_before_next_call() {
+        let (base_url, mock) = spawn_mock(vec![tool_call_reply(), text_reply("done")]).await;
+        let provider = OpenAiProvider::new("test-key".to_string(), Some(base_url));
+        let cancel = crate::cancel::CancellationToken::new();
+
+        // The echo tool cancels the turn while it runs, as `chat.abort` would.
+        struct AbortingTool(crate::cancel::CancellationToken);
+        #[async_trait]
+        impl Tool for AbortingTool {
+            fn name(&self) -> &str {
+                "echo"
+            }
+            fn description(&self) -> &str {
+                "Echo text back"
+            }
+            fn input_schema(&self) -> serde_json::Value {
+                serde_json::json!({ "type": "object" })
+            }
+            async fn execute(&self, _input: serde_json::Value) -> ToolResult {
+                self.0.cancel();
+                ToolResult::success("hi")
+            }
+        }
+        let tools: Vec<Box<dyn Tool>> = vec![Box::new(AbortingTool(cancel.clone()))];
+
+        let (resp, called, _) = crate::tools::tool_loop::run_tool_loop(
+            &provider,
+            request_with_tools(),
+            &tools,
+            &crate::context_budget::ContextWindows::default(),
+            &cancel,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(resp.stop_reason, crate::cancel::ABORTED_STOP_REASON);
+        assert_eq!(called, vec!["echo".to_string()]);
+        assert_eq!(mock.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn stream_survives_characters_split_across_chunks() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"héllo \"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",",
+            "\"function\":{\"name\":\"echo\",\"arguments\":\"{\\\"text\\\":\\\"😀\\\"}\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let bytes = body.as_bytes();
+        // cut inside the two-byte é and again inside the four-byte emoji
+        let e = body.find('é').unwrap() + 1;
+        let emoji = body.find('😀').unwrap() + 2;
+        let chunks = [&bytes[..e], &bytes[e..emoji], &bytes[emoji..]]
+            .map(|c| Ok::<_, std::io::Error>(c.to_vec()));
+
+        let (tx, mut rx) = mpsc::channel(16);
+        process_openai_stream(futures_util::stream::iter(chunks), "gpt-test".into(), tx).await;
+
+        let mut text = String::new();
+        let mut tools = Vec::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::TextDelta { text: t } => text.push_str(&t),
+                StreamEvent::ToolUse { name, input, .. } => tools.push((name, input)),
+                StreamEvent::Done { stop_reason, .. } => assert_eq!(stop_reason, "tool_use"),
+                other => panic!("unexpected event: {other:?}"),
+            }
+        }
+        assert_eq!(text, "héllo ");
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].1["text"], "😀");
+    }
 }
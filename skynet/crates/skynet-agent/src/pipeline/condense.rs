@@ -0,0 +1,381 @@
+//! Session condensation — structured summaries instead of deleted history.
+//!
+//! When a session's active history grows past its token budget, the oldest
+//! turns are sent to the summarizer model (`[agent.condense] model`, default
+//! the model the turn was routed to). It returns a structured summary — current work, decisions,
+//! open items, context — plus memorable user facts. The summary is stored as a
+//! `role = "summary"` conversation row in place of the condensed turns, which
+//! stay in SQLite tagged with `condense_parent = <summary id>`. Facts go to
+//! `user_memory` as before.
+//!
+//! The previous summary is part of the active history, so it is folded into
+//! the next one and the narrative carries forward.
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use skynet_memory::types::{ConversationMessage, MemoryCategory, MemorySource};
+
+use crate::context_budget::estimate_tokens;
+use crate::pricing::TokenUsage;
+use crate::provider::{ChatRequest, Message, Role};
+
+use super::context::MessageContext;
+use super::process::record_session_usage;
+
+const SUMMARY_HEADER: &str = "## Summary of earlier conversation";
+
+const SUMMARIZER_PROMPT: &str = concat!(
+    "You condense the older part of a conversation between a USER and an AI assistant ",
+    "so the assistant can continue without the original turns. ",
+    "A turn marked SUMMARY is an earlier summary — fold it into yours. ",
+    "Return ONLY a JSON object with these fields:\n",
+    r#"{"current_work":"what the user and assistant are working on right now","#,
+    r#""decisions":["decisions made or conclusions reached"],"#,
+    r#""open_items":["unanswered questions, pending tasks, promised follow-ups"],"#,
+    r#""context":"other background needed to continue (names, paths, numbers)","#,
+    r#""facts":[{"key":"short_label","value":"brief_fact","category":"fact|preference|instruction|context"}]}"#,
+    "\nKeep concrete details (file names, commands, values). ",
+    "`facts` are lasting facts about the USER only (max 10); use [] if none."
+);
+
+/// Condense the oldest part of a session when its history exceeds the budget.
+///
+/// Triggered as a fire-and-forget `tokio::spawn` after each assistant turn is
+/// saved, with the model that turn was routed to. Does nothing while the
+/// active history fits within `min(trigger_ratio × context window of
+/// chat_model, max_history_tokens)`, or while the session is already being
+/// condensed.
+pub async fn condense_session_if_needed<C: MessageContext + 'static>(
+    ctx: Arc<C>,
+    session_key: String,
+    chat_model: String,
+) {
+    let Some(_in_flight) = ctx.agent().begin_condense(&session_key) else {
+        debug!(session = %session_key, "condense: already running for this session");
+        return;
+    };
+
+    let turns = match ctx.memory().get_active_turns(&session_key) {
+        Ok(t) => t,
+        Err(e) => {
+            warn!(error = %e, session = %session_key, "condense: get_active_turns failed");
+            return;
+        }
+    };
+
+    let cfg = ctx.agent().condense().clone();
+    let window = ctx
+        .agent()
+        .context_windows()
+        .get(&ctx.agent().provider().resolve_model(&chat_model));
+    let trigger = ((window as f64 * cfg.trigger_ratio) as u32).min(cfg.max_history_tokens);
+
+    let total: u32 = turns.iter().map(turn_tokens).sum();
+    if total <= trigger {
+        return;
+    }
+
+    let count = select_batch(&turns, cfg.keep_recent);
+    if count < 2 {
+        debug!(session = %session_key, total, "condense: nothing old enough to condense");
+        return;
+    }
+    let batch = &turns[..count];
+
+    info!(
+        session = %session_key,
+        history_tokens = total,
+        trigger,
+        turns = count,
+        "condense: token budget exceeded, summarizing oldest turns"
+    );
+
+    let transcript: String = batch
+        .iter()
+        .map(|m| format!("{}: {}", m.role.to_uppercase(), m.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let model = cfg.model.unwrap_or(chat_model);
+    let req = ChatRequest {
+        model: model.clone(),
+        system: SUMMARIZER_PROMPT.to_string(),
+        system_prompt: None,
+        messages: vec![Message {
+            role: Role::User,
+            content: format!("Condense these conversation turns:\n\n{}", transcript).into(),
+        }],
+        max_tokens: 2048,
+        stream: false,
+        thinking: None,
+        tools: Vec::new(),
+        raw_messages: None,
+    };
+
+    let response = match ctx.agent().provider().send(&req).await {
+        Ok(r) => r,
+        Err(e) => {
+            warn!(error = %e, session = %session_key, "condense: summarizer call failed");
+            return;
+        }
+    };
+
+    // Fall back to the raw reply as the summary if it is not valid JSON —
+    // the turns are still kept, so nothing is lost either way.
+    let parsed = parse_summary(&response.content);
+    let content = match &parsed {
+        Some(s) => render_summary(s),
+        None => {
+            warn!(session = %session_key, "condense: summary was not JSON, storing raw text");
+            format!("{SUMMARY_HEADER}\n\n{}", response.content.trim())
+        }
+    };
+
+    let usage = TokenUsage::from(&response);
+    let cost_usd = ctx.agent().pricing().cost_usd(&response.model, &usage);
+    let last = &batch[batch.len() - 1];
+    let summary = ConversationMessage {
+        id: 0,
+        user_id: last.user_id.clone(),
+        session_key: session_key.clone(),
+        channel: last.channel.clone(),
+        role: ConversationMessage::SUMMARY_ROLE.to_string(),
+        content,
+        model_used: Some(response.model.clone()),
+        tokens_in: response.tokens_in,
+        tokens_out: response.tokens_out,
+        cost_usd,
+        // Sort in place of the turns it replaces.
+        created_at: last.created_at.clone(),
+        routing_rule: None,
+        condense_parent: None,
+    };
+
+    // The summarizer call is billed to the session and user like a chat turn,
+    // even if another task condenses the same turns first.
+    record_session_usage(
+        ctx.as_ref(),
+        &session_key,
+        last.user_id.as_deref(),
+        &usage,
+        cost_usd,
+        &response.model,
+    );
+
+    let ids: Vec<i64> = batch.iter().map(|m| m.id).collect();
+    let summary_id = match ctx.memory().condense_turns(&ids, &summary) {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            debug!(session = %session_key, "condense: turns already condensed elsewhere");
+            return;
+        }
+        Err(e) => {
+            warn!(error = %e, session = %session_key, "condense: condense_turns failed");
+            return;
+        }
+    };
+
+    // Use the session_key as the user_id namespace for anonymous sessions.
+    // For channel sessions ("telegram:user123") this keeps facts per-sender.
+    let facts = parsed.map(|s| s.facts).unwrap_or_default();
+    let mut saved = 0usize;
+    for fact in &facts {
+        if fact.key.is_empty() || fact.value.is_empty() {
+            continue;
+        }
+        let category = fact.category.parse().unwrap_or(MemoryCategory::Fact);
+        let _ = ctx.memory().learn(
+            &session_key,
+            category,
+            &fact.key,
+            &fact.value,
+            0.7,
+            MemorySource::Inferred,
+        );
+        saved += 1;
+    }
+
+    info!(
+        session = %session_key,
+        summary_id,
+        turns_condensed = count,
+        facts_saved = saved,
+        "condense: session condensed"
+    );
+}
+
+/// Structured summary returned by the summarizer.
+#[derive(Debug, Default, Deserialize)]
+struct Summary {
+    #[serde(default)]
+    current_work: String,
+    #[serde(default)]
+    decisions: Vec<String>,
+    #[serde(default)]
+    open_items: Vec<String>,
+    #[serde(default)]
+    context: String,
+    #[serde(default)]
+    facts: Vec<Fact>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Fact {
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    value: String,
+    #[serde(default = "default_fact_category")]
+    category: String,
+}
+
+fn default_fact_category() -> String {
+    "fact".to_string()
+}
+
+fn turn_tokens(m: &ConversationMessage) -> u32 {
+    estimate_tokens(&m.content) + 4
+}
+
+/// Number of oldest turns to condense: roughly half of the history's tokens,
+/// never touching the newest `keep_recent` turns, and ending on an assistant
+/// turn so the remaining history still opens with the user.
+fn select_batch(turns: &[ConversationMessage], keep_recent: usize) -> usize {
+    let limit = turns.len().saturating_sub(keep_recent);
+    let total: u32 = turns.iter().map(turn_tokens).sum();
+
+    let mut taken = 0u32;
+    let mut count = 0;
+    while count < limit && taken < total / 2 {
+        taken += turn_tokens(&turns[count]);
+        count += 1;
+    }
+    // Extend to the end of the exchange, then back off if that overran the limit.
+    while count < limit && turns[count].role != "user" {
+        count += 1;
+    }
+    while count > 0 && turns[count - 1].role != "assistant" {
+        count -= 1;
+    }
+    count
+}
+
+/// Extract the JSON object from the reply (it may be wrapped in a code block).
+fn parse_summary(raw: &str) -> Option<Summary> {
+    let raw = raw.trim();
+    let json_str = match (raw.find('{'), raw.rfind('}')) {
+        (Some(s), Some(e)) if e >= s => &raw[s..=e],
+        _ => raw,
+    };
+    serde_json::from_str(json_str).ok()
+}
+
+fn render_summary(s: &Summary) -> String {
+    fn list(items: &[String]) -> String {
+        if items.is_empty() {
+            return "- none".to_string();
+        }
+        items
+            .iter()
+            .map(|i| format!("- {i}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    let mut out = format!("{SUMMARY_HEADER}\n\n### Current work\n");
+    out.push_str(if s.current_work.is_empty() {
+        "none"
+    } else {
+        &s.current_work
+    });
+    out.push_str("\n\n### Decisions\n");
+    out.push_str(&list(&s.decisions));
+    out.push_str("\n\n### Open items\n");
+    out.push_str(&list(&s.open_items));
+    if !s.context.is_empty() {
+        out.push_str("\n\n### Context\n");
+        out.push_str(&s.context);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(role: &str, chars: usize) -> ConversationMessage {
+        ConversationMessage {
+            id: 0,
+            user_id: None,
+            session_key: "web:default".to_string(),
+            channel: "web".to_string(),
+            role: role.to_string(),
+            content: "x".repeat(chars),
+            model_used: None,
+            tokens_in: 0,
+            tokens_out: 0,
+            cost_usd: 0.0,
+            created_at: String::new(),
+            routing_rule: None,
+            condense_parent: None,
+        }
+    }
+
+    fn exchanges(n: usize, chars: usize) -> Vec<ConversationMessage> {
+        (0..n)
+            .flat_map(|_| [turn("user", chars), turn("assistant", chars)])
+            .collect()
+    }
+
+    #[test]
+    fn batch_covers_about_half_and_ends_on_assistant() {
+        let turns = exchanges(10, 400);
+        let count = select_batch(&turns, 6);
+        assert_eq!(count, 10);
+        assert_eq!(turns[count - 1].role, "assistant");
+        assert_eq!(turns[count].role, "user");
+    }
+
+    #[test]
+    fn batch_respects_keep_recent() {
+        // One huge old exchange followed by small ones: never reach into the last 6.
+        let mut turns = exchanges(1, 100);
+        turns.extend(exchanges(4, 4_000));
+        let count = select_batch(&turns, 6);
+        assert!(count <= turns.len() - 6);
+        assert_eq!(count % 2, 0);
+
+        assert_eq!(select_batch(&exchanges(3, 4_000), 6), 0);
+    }
+
+    #[test]
+    fn summary_folds_in_leading_summary_row() {
+        let mut turns = vec![turn("summary", 2_000)];
+        turns.extend(exchanges(6, 400));
+        let count = select_batch(&turns, 4);
+        assert!(count >= 1);
+        assert_eq!(turns[count - 1].role, "assistant");
+    }
+
+    #[test]
+    fn parses_and_renders_structured_summary() {
+        let raw = r#"```json
+{"current_work":"Migrating the bot to Rust","decisions":["Use SQLite"],
+ "open_items":[],"context":"repo at ~/skynet",
+ "facts":[{"key":"lang","value":"prefers Rust","category":"preference"}]}
+```"#;
+        let s = parse_summary(raw).unwrap();
+        assert_eq!(s.facts.len(), 1);
+        let text = render_summary(&s);
+        assert!(text.starts_with(SUMMARY_HEADER));
+        assert!(text.contains("### Current work\nMigrating the bot to Rust"));
+        assert!(text.contains("### Decisions\n- Use SQLite"));
+        assert!(text.contains("### Open items\n- none"));
+        assert!(text.contains("### Context\nrepo at ~/skynet"));
+
+        assert!(parse_summary("not json at all").is_none());
+    }
+}
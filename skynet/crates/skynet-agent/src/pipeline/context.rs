@@ -6,19 +6,40 @@
 
 use skynet_memory::manager::MemoryManager;
 use skynet_scheduler::SchedulerHandle;
+use skynet_sessions::{CheckpointStore, SessionManager};
 use skynet_terminal::manager::TerminalManager;
+use skynet_users::resolver::UserResolver;
 
+use crate::approval::ApprovalBroker;
+use crate::mcp::McpHub;
+use crate::plugins::PluginRegistry;
 use crate::runtime::AgentRuntime;
+use crate::tools::file_tracker::FileTracker;
 
 /// Minimal context interface required by the shared message pipeline.
 ///
 /// Implemented by `AppState` in `skynet-gateway` and any future channel host.
 /// Defined here (in `skynet-agent`) to avoid circular dependency: all channel
 /// crates depend on `skynet-agent`; `skynet-agent` depends only on `skynet-core`,
-/// `skynet-memory`, `skynet-scheduler`, and `skynet-terminal`.
+/// `skynet-memory`, `skynet-scheduler`, `skynet-sessions`, `skynet-terminal`, and
+/// `skynet-users`.
 pub trait MessageContext: Send + Sync {
     fn agent(&self) -> &AgentRuntime;
     fn memory(&self) -> &MemoryManager;
     fn terminal(&self) -> &tokio::sync::Mutex<TerminalManager>;
     fn scheduler(&self) -> &SchedulerHandle;
+    fn sessions(&self) -> &SessionManager;
+    /// Channel identities → users, for role-based routing, budgets and
+    /// plugin permissions.
+    fn users(&self) -> &UserResolver;
+    /// Tool approval policies and pending approval requests.
+    fn approvals(&self) -> &ApprovalBroker;
+    /// Connected MCP servers whose tools are offered to the agent.
+    fn mcp(&self) -> &McpHub;
+    /// Script plugins from `[agent.plugins]`, reloaded as they change.
+    fn plugins(&self) -> &PluginRegistry;
+    /// Files each session has read, for the file tools' stale-write checks.
+    fn files(&self) -> &FileTracker;
+    /// File snapshots for `/undo`; `None` when `[agent.checkpoints]` is disabled.
+    fn checkpoints(&self) -> Option<&CheckpointStore>;
 }
@@ -4,10 +4,14 @@
 //! `process_message_non_streaming` for the common non-streaming path and only
 //! add their own channel-specific formatting on top.
 
-pub mod compact;
+pub mod condense;
 pub mod context;
 pub mod process;
+pub mod undo;
 
-pub use compact::compact_session_if_needed;
+pub use condense::condense_session_if_needed;
 pub use context::MessageContext;
-pub use process::{process_message_non_streaming, ProcessedMessage};
+pub use process::{
+    process_message_non_streaming, record_session_usage, stored_user_content, ProcessedMessage,
+};
+pub use undo::{parse_undo, undo};
@@ -2,21 +2,25 @@
 //!
 //! `process_message_non_streaming` runs the full agentic turn:
 //! load history → build system prompt → build tools → tool loop →
-//! save turns to SQLite → spawn compaction → return `ProcessedMessage`.
+//! save turns to SQLite → spawn condensation → return `ProcessedMessage`.
 //!
 //! The caller only handles channel-specific formatting (WS frame, Discord
 //! chunking, etc.). Everything else is here, once.
 
 use std::sync::Arc;
 
-use tracing::info;
+use tracing::{info, warn};
 
 use skynet_memory::types::ConversationMessage;
+use skynet_sessions::SessionKey;
 
-use crate::provider::{ChatRequest, Message, ProviderError, Role};
+use crate::cancel::{mark_aborted, CancellationToken, ABORTED_STOP_REASON};
+use crate::pricing::TokenUsage;
+use crate::provider::{ChatRequest, ContentBlock, Message, MessageContent, ProviderError, Role};
+use crate::routing::{RoutingInput, SenderInfo};
 use crate::tools::tool_loop;
 
-use super::compact::compact_session_if_needed;
+use super::condense::condense_session_if_needed;
 use super::context::MessageContext;
 
 /// Result of a completed non-streaming pipeline turn.
@@ -25,18 +29,27 @@ pub struct ProcessedMessage {
     pub model: String,
     pub tokens_in: u32,
     pub tokens_out: u32,
+    pub cache_write_tokens: u32,
+    pub cache_read_tokens: u32,
+    /// Cost of every LLM call in the turn, in USD.
+    pub cost_usd: f64,
     pub stop_reason: String,
+    /// `[agent.routing]` rule that picked the model, if any.
+    pub routing_rule: Option<String>,
 }
 
 /// Run the full non-streaming message pipeline for any channel adapter.
 ///
 /// Steps:
 /// 1. Load the last 40 turns of conversation history from SQLite.
-/// 2. Build the system prompt (optionally injecting user memory context).
+/// 2. Build the system prompt (optionally injecting user memory context) and
+///    pick the model (per-request override → `[agent.routing]` → default).
 /// 3. Build the tool list using the context's terminal/memory subsystems.
 /// 4. Run `tool_loop::run_tool_loop` (LLM → tool calls → results → LLM → …).
-/// 5. Persist the user and assistant turns to SQLite.
-/// 6. Spawn `compact_session_if_needed` (fire-and-forget).
+/// 5. Price the turn, persist the user and assistant turns to SQLite and add
+///    the usage to the session's running totals. An aborted turn is saved with
+///    its partial reply marked `[aborted by user]`.
+/// 6. Spawn `condense_session_if_needed` (fire-and-forget).
 /// 7. Return `ProcessedMessage`.
 ///
 /// # Arguments
@@ -44,20 +57,34 @@ pub struct ProcessedMessage {
 /// - `session_key` — unique key for this user/channel conversation
 /// - `channel_name` — label stored alongside conversation rows (e.g. `"discord"`)
 /// - `content` — the user's message text
+/// - `attachments` — images / PDFs sent with the message (may be empty)
 /// - `user_context` — optional pre-rendered user memory context string
 /// - `model_override` — optional per-request model ID (overrides runtime default)
 /// - `channel_id` — optional channel ID for reminder delivery (Discord: `ChannelId.get()`, WS: `None`)
+/// - `sender` — resolved sender for model routing and plugin permissions (`None` = anonymous)
+/// - `cancel` — aborts the turn when cancelled (`chat.abort`)
+#[allow(clippy::too_many_arguments)]
 pub async fn process_message_non_streaming<C: MessageContext + 'static>(
     ctx: &Arc<C>,
     session_key: &str,
     channel_name: &str,
     content: &str,
+    attachments: &[ContentBlock],
     user_context: Option<&str>,
     model_override: Option<&str>,
     channel_id: Option<u64>,
+    sender: Option<&SenderInfo>,
+    cancel: &CancellationToken,
 ) -> Result<ProcessedMessage, ProviderError> {
     // Build tools — includes execute_command, bash PTY session, and reminder scheduling.
-    let tools = crate::tools::build::build_tools(Arc::clone(ctx), channel_name, channel_id);
+    let tools = crate::tools::build::build_tools(
+        Arc::clone(ctx),
+        channel_name,
+        channel_id,
+        session_key,
+        sender,
+        cancel,
+    );
     let tool_defs = crate::tools::build::tool_definitions(&tools);
 
     // Build system prompt, optionally enriched with user memory context.
@@ -83,11 +110,22 @@ pub async fn process_message_non_streaming<C: MessageContext + 'static>(
 
     let plain = system_prompt.to_plain_text();
 
-    // Resolve the model: per-request override takes priority over runtime default.
-    let model = match model_override {
-        Some(m) => m.to_string(),
-        None => ctx.agent().get_model().await,
+    // Resolve the model: per-request override → routing rule → runtime default.
+    let sender = sender.cloned().unwrap_or_default();
+    let routing_input = RoutingInput {
+        message: content,
+        channel: channel_name,
+        user_role: sender.role,
+        tools_used_last_turn: ctx
+            .memory()
+            .last_turn_used_tools(session_key)
+            .unwrap_or(false),
+        budget_remaining_tokens: sender.budget_remaining_tokens,
     };
+    let (model, routing_rule) = ctx
+        .agent()
+        .resolve_model(model_override, &routing_input)
+        .await;
 
     // Load conversation history and append the current user turn.
     let history = ctx
@@ -97,21 +135,21 @@ pub async fn process_message_non_streaming<C: MessageContext + 'static>(
     let mut messages: Vec<Message> = history
         .iter()
         .map(|m| Message {
-            role: if m.role == "assistant" {
+            role: if m.api_role() == "assistant" {
                 Role::Assistant
             } else {
                 Role::User
             },
-            content: m.content.clone(),
+            content: m.content.clone().into(),
         })
         .collect();
     messages.push(Message {
         role: Role::User,
-        content: content.to_string(),
+        content: MessageContent::with_attachments(content, attachments),
     });
 
     let request = ChatRequest {
-        model,
+        model: model.clone(),
         system: plain,
         system_prompt: Some(system_prompt),
         messages,
@@ -122,57 +160,86 @@ pub async fn process_message_non_streaming<C: MessageContext + 'static>(
         raw_messages: None,
     };
 
-    let (r, called_tools) =
-        tool_loop::run_tool_loop(ctx.agent().provider(), request, &tools).await?;
+    let (r, called_tools, usage) = tool_loop::run_tool_loop(
+        ctx.agent().provider(),
+        request,
+        &tools,
+        ctx.agent().context_windows(),
+        cancel,
+    )
+    .await?;
 
     // Transparently log every tool call for usage frequency tracking.
     for tool_name in &called_tools {
         let _ = ctx.memory().log_tool_call(tool_name, session_key);
     }
 
+    let cost_usd = ctx.agent().pricing().cost_usd(&r.model, &usage);
+
     info!(
         tokens_in = r.tokens_in,
         tokens_out = r.tokens_out,
+        cache_read = r.cache_read_tokens,
+        cost_usd,
         model = %r.model,
         session = %session_key,
         "pipeline: chat complete"
     );
 
+    // Bill every turn, including tool-only turns that end without text.
+    record_session_usage(
+        ctx.as_ref(),
+        session_key,
+        sender.user_id.as_deref(),
+        &usage,
+        cost_usd,
+        &r.model,
+    );
+
     // Persist both turns to SQLite for future history.
-    if !r.content.is_empty() {
+    let aborted = r.stop_reason == ABORTED_STOP_REASON;
+    if !r.content.is_empty() || aborted {
         let now = chrono::Utc::now().to_rfc3339();
         let _ = ctx.memory().save_message(&ConversationMessage {
             id: 0,
-            user_id: None,
+            user_id: sender.user_id.clone(),
             session_key: session_key.to_string(),
             channel: channel_name.to_string(),
             role: "user".to_string(),
-            content: content.to_string(),
+            content: stored_user_content(content, attachments),
             model_used: None,
             tokens_in: 0,
             tokens_out: 0,
             cost_usd: 0.0,
             created_at: now.clone(),
+            routing_rule: None,
+            condense_parent: None,
         });
         let _ = ctx.memory().save_message(&ConversationMessage {
             id: 0,
-            user_id: None,
+            user_id: sender.user_id.clone(),
             session_key: session_key.to_string(),
             channel: channel_name.to_string(),
             role: "assistant".to_string(),
-            content: r.content.clone(),
+            content: if aborted {
+                mark_aborted(&r.content)
+            } else {
+                r.content.clone()
+            },
             model_used: Some(r.model.clone()),
             tokens_in: r.tokens_in,
             tokens_out: r.tokens_out,
-            cost_usd: 0.0,
+            cost_usd,
             created_at: now,
+            routing_rule: routing_rule.clone(),
+            condense_parent: None,
         });
 
-        // Fire-and-forget: compact if the session has grown too long.
+        // Fire-and-forget: condense if the history is over its token budget.
         let ctx_clone = Arc::clone(ctx);
         let sk = session_key.to_string();
         tokio::spawn(async move {
-            compact_session_if_needed(ctx_clone, sk).await;
+            condense_session_if_needed(ctx_clone, sk, model).await;
         });
     }
 
@@ -181,6 +248,58 @@ pub async fn process_message_non_streaming<C: MessageContext + 'static>(
         model: r.model,
         tokens_in: r.tokens_in,
         tokens_out: r.tokens_out,
+        cache_write_tokens: r.cache_write_tokens,
+        cache_read_tokens: r.cache_read_tokens,
+        cost_usd,
         stop_reason: r.stop_reason,
+        routing_rule,
     })
 }
+
+/// Text persisted for a user turn. Attachments are not stored — only a note
+/// listing them, so later turns know something was shared.
+pub fn stored_user_content(text: &str, attachments: &[ContentBlock]) -> String {
+    if attachments.is_empty() {
+        return text.to_string();
+    }
+    let note = attachments
+        .iter()
+        .map(ContentBlock::describe)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if text.is_empty() {
+        format!("[Attached: {note}]")
+    } else {
+        format!("[Attached: {note}]\n{text}")
+    }
+}
+
+/// Add a finished turn's tokens and cost to the `sessions` row for
+/// `session_key`, creating the row on first use, and charge the tokens
+/// against the resolved user's daily budget.
+///
+/// Channel session keys (`"discord:123"`, `"web:default"`) become the session
+/// name under the resolved user (or `anonymous`) and the `main` agent.
+/// Failures are logged only — accounting must never fail a turn.
+pub fn record_session_usage<C: MessageContext + ?Sized>(
+    ctx: &C,
+    session_key: &str,
+    user_id: Option<&str>,
+    usage: &TokenUsage,
+    cost_usd: f64,
+    model: &str,
+) {
+    let key = SessionKey::new(user_id.unwrap_or("anonymous"), "main", session_key);
+    let result = ctx.sessions().get_or_create(&key).and_then(|_| {
+        ctx.sessions()
+            .update_stats(&key, usage.total(), cost_usd, model)
+    });
+    if let Err(e) = result {
+        warn!(error = %e, session = %session_key, "failed to update session stats");
+    }
+    if let Some(user_id) = user_id {
+        if let Err(e) = ctx.users().record_token_usage(user_id, usage.total()) {
+            warn!(error = %e, user = %user_id, "failed to record daily token usage");
+        }
+    }
+}
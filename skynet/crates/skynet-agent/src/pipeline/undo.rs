@@ -0,0 +1,96 @@
+//! `/undo` chat command — shared by every channel adapter.
+//!
+//! Rolls back the files changed by the session's last turn (or by a given
+//! turn and every later one) from the host's `CheckpointStore`, and describes
+//! the result as a chat reply.
+
+use tracing::{info, warn};
+
+use super::context::MessageContext;
+
+/// Parse `/undo` or `/undo <turn_id>`. Returns `None` for any other message,
+/// `Some(None)` for the bare command and `Some(Some(turn_id))` otherwise.
+pub fn parse_undo(message: &str) -> Option<Option<&str>> {
+    let trimmed = message.trim();
+    if trimmed.eq_ignore_ascii_case("/undo") {
+        return Some(None);
+    }
+    trimmed
+        .strip_prefix("/undo ")
+        .or_else(|| trimmed.strip_prefix("/undo\t"))
+        .map(|arg| Some(arg.trim()))
+}
+
+/// Restore file checkpoints for `/undo` in `session_key` and describe the result.
+pub fn undo<C: MessageContext + ?Sized>(
+    ctx: &C,
+    session_key: &str,
+    turn_id: Option<&str>,
+) -> String {
+    let Some(store) = ctx.checkpoints() else {
+        return "File checkpoints are disabled (`[agent.checkpoints] enabled = false`)."
+            .to_string();
+    };
+    match store.restore(session_key, turn_id) {
+        Ok(report) if !report.conflicts.is_empty() => {
+            let mut out = format!(
+                "Nothing undone: {} file(s) changed since the agent wrote them. \
+                 Revert those edits or change the files by hand.",
+                report.conflicts.len()
+            );
+            for path in &report.conflicts {
+                out.push_str(&format!("\n- changed `{path}`"));
+            }
+            out
+        }
+        Ok(report) => {
+            info!(session = %session_key, turns = report.turns.len(), "files restored via /undo command");
+            let mut out = format!(
+                "Undid {} turn(s): {} file(s) restored, {} deleted.",
+                report.turns.len(),
+                report.restored.len(),
+                report.deleted.len()
+            );
+            for path in &report.restored {
+                out.push_str(&format!("\n- restored `{path}`"));
+            }
+            for path in &report.deleted {
+                out.push_str(&format!("\n- deleted `{path}`"));
+            }
+            if !report.failed.is_empty() {
+                out.push_str(&format!(
+                    "\n\n{} file(s) could not be restored; the checkpoints are kept, so `/undo` can be retried:",
+                    report.failed.len()
+                ));
+                for failed in &report.failed {
+                    out.push_str(&format!("\n- `{}`: {}", failed.path, failed.error));
+                }
+            }
+            out
+        }
+        Err(skynet_sessions::SessionError::NoCheckpoint(_)) => match turn_id {
+            Some(turn_id) => {
+                format!("No file changes recorded for turn `{turn_id}` in this session.")
+            }
+            None => "Nothing to undo: no file changes are recorded for this session.".to_string(),
+        },
+        Err(e) => {
+            warn!(error = %e, session = %session_key, "/undo failed");
+            format!("Undo failed: {e}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_undo_with_and_without_turn() {
+        assert_eq!(parse_undo("/undo"), Some(None));
+        assert_eq!(parse_undo("  /UNDO "), Some(None));
+        assert_eq!(parse_undo("/undo abc-123 "), Some(Some("abc-123")));
+        assert_eq!(parse_undo("/undone"), None);
+        assert_eq!(parse_undo("please /undo"), None);
+    }
+}
@@ -0,0 +1,420 @@
+//! Plugin registry (`[agent.plugins]`).
+//!
+//! `PluginRegistry` loads every `tool.toml` under the plugin directory into a
+//! `ScriptTool` (see `tools::script_tool` for the manifest and execution
+//! contract), or, for manifests with a `[wasm]` table, into a sandboxed
+//! `WasmTool` (`tools::wasm_tool`, needs the `wasm` feature). With `watch` enabled a filesystem watcher reloads the set
+//! whenever the directory changes, so plugins can be added, edited or removed
+//! without a restart; `tools.reload` does the same on demand.
+//!
+//! `tools(user)` returns the plugins one caller may use: a plugin that
+//! declares a `permission` is offered only when `PermissionChecker` allows it
+//! for the resolved user, and is gated by an approval when the check answers
+//! `NeedsApproval`. Unresolved (anonymous) callers only get plugins without a
+//! permission.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use skynet_core::config::{PluginsConfig, WasmPluginsConfig};
+use skynet_users::permissions::{Permission, PermissionCheck, PermissionChecker};
+use skynet_users::types::User;
+
+use crate::cancel::CancellationToken;
+use crate::tools::script_tool::ScriptTool;
+#[cfg(feature = "wasm")]
+use crate::tools::wasm_tool::WasmTool;
+use crate::tools::Tool;
+
+/// Quiet period after a filesystem event before reloading, so an editor's
+/// burst of writes triggers one reload.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// One loaded plugin, as reported by `tools.list`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub description: String,
+    pub version: Option<String>,
+    /// "script" or "wasm".
+    pub kind: &'static str,
+    /// Script protocol; `None` for WebAssembly plugins.
+    pub protocol: Option<u8>,
+    pub permission: Option<String>,
+    pub dir: String,
+}
+
+/// A plugin directory that failed to load.
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginLoadError {
+    pub dir: String,
+    pub error: String,
+}
+
+/// Snapshot of the registry for `tools.list` / `tools.reload`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginStatus {
+    pub dir: String,
+    pub watching: bool,
+    pub loaded_at: Option<String>,
+    pub plugins: Vec<PluginInfo>,
+    pub errors: Vec<PluginLoadError>,
+}
+
+/// A plugin offered to one caller.
+pub struct OfferedPlugin {
+    pub tool: Box<dyn Tool>,
+    /// `PermissionChecker` answered `NeedsApproval`: each call waits for an
+    /// admin, like an `ask` tool.
+    pub needs_approval: bool,
+}
+
+/// A loaded plugin of either kind.
+#[derive(Clone)]
+enum LoadedPlugin {
+    Script(ScriptTool),
+    #[cfg(feature = "wasm")]
+    Wasm(WasmTool),
+}
+
+impl LoadedPlugin {
+    fn load(
+        dir: &Path,
+        secrets: &Arc<HashMap<String, String>>,
+        wasm: &WasmPluginsConfig,
+    ) -> Result<Self, String> {
+        if !is_wasm(dir) {
+            return ScriptTool::load(dir, Arc::clone(secrets)).map(Self::Script);
+        }
+        #[cfg(feature = "wasm")]
+        return WasmTool::load(dir, Arc::clone(secrets), wasm).map(Self::Wasm);
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = wasm;
+            Err("WebAssembly plugins need skynet built with the `wasm` feature".to_string())
+        }
+    }
+
+    fn tool(&self) -> &dyn Tool {
+        match self {
+            Self::Script(tool) => tool,
+            #[cfg(feature = "wasm")]
+            Self::Wasm(tool) => tool,
+        }
+    }
+
+    fn boxed(&self) -> Box<dyn Tool> {
+        match self {
+            Self::Script(tool) => Box::new(tool.clone()),
+            #[cfg(feature = "wasm")]
+            Self::Wasm(tool) => Box::new(tool.clone()),
+        }
+    }
+
+    fn permission(&self) -> Option<&Permission> {
+        match self {
+            Self::Script(tool) => tool.permission(),
+            #[cfg(feature = "wasm")]
+            Self::Wasm(tool) => tool.permission(),
+        }
+    }
+
+    fn dir(&self) -> &Path {
+        match self {
+            Self::Script(tool) => tool.dir(),
+            #[cfg(feature = "wasm")]
+            Self::Wasm(tool) => tool.dir(),
+        }
+    }
+
+    fn info(&self) -> PluginInfo {
+        let (kind, version, protocol) = match self {
+            Self::Script(tool) => ("script", tool.version(), Some(tool.protocol())),
+            #[cfg(feature = "wasm")]
+            Self::Wasm(tool) => ("wasm", tool.version(), None),
+        };
+        PluginInfo {
+            name: self.tool().name().to_string(),
+            description: self.tool().description().to_string(),
+            version: version.map(str::to_string),
+            kind,
+            protocol,
+            permission: self.permission().map(|p| p.to_string()),
+            dir: self.dir().display().to_string(),
+        }
+    }
+}
+
+/// Whether `tool.toml` in `dir` has a `[wasm]` table.
+fn is_wasm(dir: &Path) -> bool {
+    std::fs::read_to_string(dir.join("tool.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .is_some_and(|table| table.contains_key("wasm"))
+}
+
+#[derive(Default)]
+struct Loaded {
+    plugins: Vec<LoadedPlugin>,
+    errors: Vec<PluginLoadError>,
+    loaded_at: Option<String>,
+}
+
+/// The plugins of the plugin directory. Clones share the same state.
+#[derive(Clone)]
+pub struct PluginRegistry {
+    dir: PathBuf,
+    secrets: Arc<HashMap<String, String>>,
+    wasm: WasmPluginsConfig,
+    loaded: Arc<RwLock<Loaded>>,
+    watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+    shutdown: CancellationToken,
+}
+
+impl PluginRegistry {
+    /// Nothing is loaded until `reload`.
+    pub fn new(config: &PluginsConfig) -> Self {
+        Self {
+            dir: PathBuf::from(&config.dir),
+            secrets: Arc::new(config.secrets.clone()),
+            wasm: config.wasm.clone(),
+            loaded: Arc::new(RwLock::new(Loaded::default())),
+            watcher: Arc::new(Mutex::new(None)),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Re-scan the plugin directory and replace the loaded set.
+    pub fn reload(&self) -> PluginStatus {
+        let (plugins, errors) = load_dir(&self.dir, &self.secrets, &self.wasm);
+        info!(
+            dir = %self.dir.display(),
+            plugins = plugins.len(),
+            errors = errors.len(),
+            "plugins loaded"
+        );
+        *self.loaded.write().unwrap() = Loaded {
+            plugins,
+            errors,
+            loaded_at: Some(chrono::Utc::now().to_rfc3339()),
+        };
+        self.status()
+    }
+
+    pub fn status(&self) -> PluginStatus {
+        let loaded = self.loaded.read().unwrap();
+        PluginStatus {
+            dir: self.dir.display().to_string(),
+            watching: self.watcher.lock().unwrap().is_some(),
+            loaded_at: loaded.loaded_at.clone(),
+            plugins: loaded.plugins.iter().map(LoadedPlugin::info).collect(),
+            errors: loaded.errors.clone(),
+        }
+    }
+
+    /// The plugins `user` may use (`None` = anonymous caller).
+    pub fn tools(&self, user: Option<&User>) -> Vec<OfferedPlugin> {
+        let loaded = self.loaded.read().unwrap();
+        loaded
+            .plugins
+            .iter()
+            .filter_map(|plugin| {
+                let check = match (plugin.permission(), user) {
+                    (None, _) => PermissionCheck::Allowed,
+                    (Some(permission), Some(user)) => PermissionChecker::check(user, permission),
+                    (Some(permission), None) => PermissionCheck::Denied {
+                        reason: format!("{permission} needs a known user"),
+                    },
+                };
+                let needs_approval = match check {
+                    PermissionCheck::Allowed => false,
+                    PermissionCheck::NeedsApproval { .. } => true,
+                    denied => {
+                        debug!(plugin = plugin.tool().name(), ?denied, "plugin not offered");
+                        return None;
+                    }
+                };
+                Some(OfferedPlugin {
+                    tool: plugin.boxed(),
+                    needs_approval,
+                })
+            })
+            .collect()
+    }
+
+    /// Reload whenever the plugin directory changes, until `shutdown`. The
+    /// directory is created if missing so it can be watched.
+    pub fn watch(&self) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("cannot create {}: {e}", self.dir.display()))?;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if event.kind.is_access() => {}
+                Ok(_) => {
+                    let _ = tx.send(());
+                }
+                Err(e) => warn!(error = %e, "plugins: watch error"),
+            })
+            .map_err(|e| e.to_string())?;
+        watcher
+            .watch(&self.dir, RecursiveMode::Recursive)
+            .map_err(|e| e.to_string())?;
+        *self.watcher.lock().unwrap() = Some(watcher);
+
+        let registry = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    event = rx.recv() => if event.is_none() { break },
+                    _ = registry.shutdown.cancelled() => break,
+                }
+                tokio::time::sleep(RELOAD_DEBOUNCE).await;
+                while rx.try_recv().is_ok() {}
+                let reloaded = registry.clone();
+                let _ = tokio::task::spawn_blocking(move || reloaded.reload()).await;
+            }
+        });
+        Ok(())
+    }
+
+    /// Stop watching the plugin directory.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+        self.watcher.lock().unwrap().take();
+    }
+}
+
+/// Load every plugin subdirectory of `dir`, in name order. A directory that
+/// does not exist yet simply has no plugins.
+fn load_dir(
+    dir: &Path,
+    secrets: &Arc<HashMap<String, String>>,
+    wasm: &WasmPluginsConfig,
+) -> (Vec<LoadedPlugin>, Vec<PluginLoadError>) {
+    let mut dirs: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_dir() && p.join("tool.toml").exists())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    dirs.sort();
+
+    let mut plugins: Vec<LoadedPlugin> = Vec::new();
+    let mut errors = Vec::new();
+    for path in dirs {
+        let error = match LoadedPlugin::load(&path, secrets, wasm) {
+            Ok(plugin) => match plugins
+                .iter()
+                .find(|p| p.tool().name() == plugin.tool().name())
+            {
+                Some(other) => format!(
+                    "duplicate tool name '{}' (already loaded from {})",
+                    plugin.tool().name(),
+                    other.dir().display()
+                ),
+                None => {
+                    plugins.push(plugin);
+                    continue;
+                }
+            },
+            Err(e) => e,
+        };
+        warn!(dir = %path.display(), %error, "skipped plugin");
+        errors.push(PluginLoadError {
+            dir: path.display().to_string(),
+            error,
+        });
+    }
+    (plugins, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use skynet_core::types::UserRole;
+
+    use super::*;
+
+    fn user(role: UserRole, can_use_browser: bool) -> User {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        skynet_users::db::init_db(&conn).unwrap();
+        let mut user = skynet_users::identity::create_user(&conn, "u", role).unwrap();
+        user.can_use_browser = can_use_browser;
+        user
+    }
+
+    #[tokio::test]
+    async fn offers_plugins_by_permission_and_reloads_on_change() {
+        let dir = std::env::temp_dir().join(format!("skynet-plugins-{}", uuid::Uuid::new_v4()));
+        let write = |name: &str, extra: &str| {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            std::fs::write(
+                dir.join(name).join("tool.toml"),
+                format!(
+                    "name = \"{name}\"\ndescription = \"d\"\n{extra}\n\
+                     [run]\ncommand = \"sh\"\nscript = \"run.sh\"\n"
+                ),
+            )
+            .unwrap();
+        };
+        write("open", "");
+        write("browse", "permission = \"use_browser\"");
+        std::fs::create_dir_all(dir.join("broken")).unwrap();
+        std::fs::write(dir.join("broken/tool.toml"), "name = ").unwrap();
+        std::fs::create_dir_all(dir.join("sandboxed")).unwrap();
+        std::fs::write(
+            dir.join("sandboxed/tool.toml"),
+            "name = \"sandboxed\"\ndescription = \"d\"\n[wasm]\ncomponent = \"missing.wasm\"\n",
+        )
+        .unwrap();
+
+        let registry = PluginRegistry::new(&PluginsConfig {
+            dir: dir.to_string_lossy().into_owned(),
+            ..Default::default()
+        });
+        let status = registry.reload();
+        let names: Vec<&str> = status.plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["browse", "open"]);
+        assert_eq!(status.errors.len(), 2);
+        assert!(status.plugins.iter().all(|p| p.kind == "script"));
+
+        let offered = |user: Option<&User>| -> Vec<String> {
+            registry
+                .tools(user)
+                .iter()
+                .map(|p| p.tool.name().to_string())
+                .collect()
+        };
+        assert_eq!(offered(None), ["open"]);
+        assert_eq!(offered(Some(&user(UserRole::User, false))), ["open"]);
+        assert_eq!(
+            offered(Some(&user(UserRole::User, true))),
+            ["browse", "open"]
+        );
+        assert_eq!(offered(Some(&user(UserRole::Child, true))), ["open"]);
+
+        registry.watch().unwrap();
+        write("later", "");
+        let mut loaded = false;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if registry.status().plugins.iter().any(|p| p.name == "later") {
+                loaded = true;
+                break;
+            }
+        }
+        assert!(loaded, "watcher did not pick up the new plugin");
+        registry.shutdown();
+        assert!(!registry.status().watching);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
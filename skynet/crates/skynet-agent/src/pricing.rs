@@ -0,0 +1,297 @@
+//! Per-model token pricing and turn cost calculation.
+//!
+//! Prices are USD per million tokens. The built-in table covers the models
+//! Skynet ships aliases for; `[pricing."<model>"]` entries in `skynet.toml`
+//! override or extend it. Models with no price (e.g. local Ollama models)
+//! cost nothing.
+
+use std::collections::HashMap;
+
+use skynet_core::config::ModelPricing;
+use tracing::debug;
+
+use crate::provider::ChatResponse;
+
+/// Token counts for one LLM call or a whole turn.
+///
+/// `tokens_in` excludes cached prompt tokens, matching Anthropic's `usage`
+/// block where cache writes and reads are reported separately.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub tokens_in: u32,
+    pub tokens_out: u32,
+    pub cache_write_tokens: u32,
+    pub cache_read_tokens: u32,
+    /// Share of the counts above that was already priced at another model's
+    /// rate — a subagent running on its own `model` — and what it cost.
+    pub priced: PricedUsage,
+}
+
+/// Tokens priced before they were rolled into a turn, and their cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PricedUsage {
+    pub tokens_in: u32,
+    pub tokens_out: u32,
+    pub cache_write_tokens: u32,
+    pub cache_read_tokens: u32,
+    pub cost_usd: f64,
+}
+
+impl TokenUsage {
+    /// Accumulate another call's usage into this one.
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.tokens_in += other.tokens_in;
+        self.tokens_out += other.tokens_out;
+        self.cache_write_tokens += other.cache_write_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.priced.tokens_in += other.priced.tokens_in;
+        self.priced.tokens_out += other.priced.tokens_out;
+        self.priced.cache_write_tokens += other.priced.cache_write_tokens;
+        self.priced.cache_read_tokens += other.priced.cache_read_tokens;
+        self.priced.cost_usd += other.priced.cost_usd;
+    }
+
+    /// Mark all of this usage as priced at `cost_usd`, so a turn it is rolled
+    /// into bills it as-is instead of at the turn model's rate.
+    pub fn priced_at(mut self, cost_usd: f64) -> Self {
+        self.priced = PricedUsage {
+            tokens_in: self.tokens_in,
+            tokens_out: self.tokens_out,
+            cache_write_tokens: self.cache_write_tokens,
+            cache_read_tokens: self.cache_read_tokens,
+            cost_usd,
+        };
+        self
+    }
+
+    /// Every token billed, cached or not.
+    pub fn total(&self) -> u64 {
+        self.tokens_in as u64
+            + self.tokens_out as u64
+            + self.cache_write_tokens as u64
+            + self.cache_read_tokens as u64
+    }
+}
+
+impl From<&ChatResponse> for TokenUsage {
+    fn from(resp: &ChatResponse) -> Self {
+        Self {
+            tokens_in: resp.tokens_in,
+            tokens_out: resp.tokens_out,
+            cache_write_tokens: resp.cache_write_tokens,
+            cache_read_tokens: resp.cache_read_tokens,
+            priced: PricedUsage::default(),
+        }
+    }
+}
+
+/// Built-in prices: (model, input, output, cache write, cache read).
+const DEFAULT_PRICES: &[(&str, f64, f64, f64, f64)] = &[
+    ("claude-opus-4-6", 5.0, 25.0, 6.25, 0.50),
+    ("claude-opus-4-5", 5.0, 25.0, 6.25, 0.50),
+    ("claude-opus-4-1", 15.0, 75.0, 18.75, 1.50),
+    ("claude-opus-4", 15.0, 75.0, 18.75, 1.50),
+    ("claude-sonnet-4-6", 3.0, 15.0, 3.75, 0.30),
+    ("claude-sonnet-4-5", 3.0, 15.0, 3.75, 0.30),
+    ("claude-sonnet-4", 3.0, 15.0, 3.75, 0.30),
+    ("claude-haiku-4-5", 1.0, 5.0, 1.25, 0.10),
+    ("claude-3-5-haiku", 0.80, 4.0, 1.0, 0.08),
+    ("gpt-4o", 2.50, 10.0, 0.0, 1.25),
+    ("gpt-4o-mini", 0.15, 0.60, 0.0, 0.075),
+    ("gpt-4.1", 2.0, 8.0, 0.0, 0.50),
+    ("gpt-4.1-mini", 0.40, 1.60, 0.0, 0.10),
+];
+
+/// Model → price lookup.
+#[derive(Debug, Clone)]
+pub struct PricingTable {
+    prices: HashMap<String, ModelPricing>,
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        let prices = DEFAULT_PRICES
+            .iter()
+            .map(|&(model, input, output, cache_write, cache_read)| {
+                (
+                    model.to_string(),
+                    ModelPricing {
+                        input,
+                        output,
+                        cache_write,
+                        cache_read,
+                    },
+                )
+            })
+            .collect();
+        Self { prices }
+    }
+}
+
+impl PricingTable {
+    /// Built-in prices with `overrides` (from `[pricing]`) layered on top.
+    pub fn new(overrides: &HashMap<String, ModelPricing>) -> Self {
+        let mut table = Self::default();
+        for (model, price) in overrides {
+            table.prices.insert(model.clone(), *price);
+        }
+        table
+    }
+
+    /// Price for `model`. Falls back to the longest known prefix followed by
+    /// a date or `-latest`, so `claude-haiku-4-5-20251001` resolves to its
+    /// family entry while a variant like `gpt-4.1-nano` stays unpriced.
+    pub fn get(&self, model: &str) -> Option<&ModelPricing> {
+        if let Some(p) = self.prices.get(model) {
+            return Some(p);
+        }
+        self.prices
+            .iter()
+            .filter(|(known, _)| {
+                model
+                    .strip_prefix(known.as_str())
+                    .is_some_and(is_snapshot_suffix)
+            })
+            .max_by_key(|(known, _)| known.len())
+            .map(|(_, p)| p)
+    }
+
+    /// Cost in USD of `usage` on `model`; 0.0 when the model has no price.
+    /// The already-priced share (`usage.priced`) is charged at its own cost.
+    pub fn cost_usd(&self, model: &str, usage: &TokenUsage) -> f64 {
+        let priced = &usage.priced;
+        let Some(p) = self.get(model) else {
+            debug!(model, "no pricing entry, recording zero cost");
+            return priced.cost_usd;
+        };
+        let tokens_in = usage.tokens_in.saturating_sub(priced.tokens_in);
+        let tokens_out = usage.tokens_out.saturating_sub(priced.tokens_out);
+        let cache_write = usage
+            .cache_write_tokens
+            .saturating_sub(priced.cache_write_tokens);
+        let cache_read = usage
+            .cache_read_tokens
+            .saturating_sub(priced.cache_read_tokens);
+        (tokens_in as f64 * p.input
+            + tokens_out as f64 * p.output
+            + cache_write as f64 * p.cache_write
+            + cache_read as f64 * p.cache_read)
+            / 1_000_000.0
+            + priced.cost_usd
+    }
+}
+
+/// `-20251001`, `-2024-08-06` or `-latest`: a snapshot of the same model.
+fn is_snapshot_suffix(rest: &str) -> bool {
+    let Some(rest) = rest.strip_prefix('-') else {
+        return false;
+    };
+    rest == "latest"
+        || (rest.chars().filter(char::is_ascii_digit).count() == 8
+            && rest.chars().all(|c| c.is_ascii_digit() || c == '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(tokens_in: u32, tokens_out: u32, write: u32, read: u32) -> TokenUsage {
+        TokenUsage {
+            tokens_in,
+            tokens_out,
+            cache_write_tokens: write,
+            cache_read_tokens: read,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cost_includes_cache_tokens() {
+        let table = PricingTable::default();
+        let cost = table.cost_usd(
+            "claude-sonnet-4-6",
+            &usage(1_000_000, 100_000, 200_000, 2_000_000),
+        );
+        // 3.00 + 1.50 + 0.75 + 0.60
+        assert!((cost - 5.85).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dated_ids_use_longest_prefix() {
+        let table = PricingTable::default();
+        assert_eq!(
+            table.get("claude-haiku-4-5-20251001"),
+            table.get("claude-haiku-4-5")
+        );
+        assert_eq!(table.get("gpt-4o-mini-2024-07-18").unwrap().input, 0.15);
+        assert_eq!(table.get("gpt-4o-2024-08-06").unwrap().input, 2.50);
+        assert_eq!(table.get("claude-opus-4-20250514").unwrap().input, 15.0);
+        assert_eq!(table.get("gpt-4o-latest").unwrap().input, 2.50);
+    }
+
+    #[test]
+    fn variants_do_not_inherit_the_base_price() {
+        let table = PricingTable::default();
+        assert!(table.get("gpt-4.1-nano").is_none());
+        assert!(table.get("gpt-4o-mini-search-preview").is_none());
+        assert!(table.get("claude-opus-4-7").is_none());
+        assert!(table.get("gpt-4o-2").is_none());
+    }
+
+    #[test]
+    fn unknown_models_are_free() {
+        let table = PricingTable::default();
+        assert_eq!(table.cost_usd("llama3.2", &usage(5_000, 5_000, 0, 0)), 0.0);
+    }
+
+    #[test]
+    fn overrides_replace_and_extend_defaults() {
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            "claude-sonnet-4-6".to_string(),
+            ModelPricing {
+                input: 1.0,
+                output: 2.0,
+                ..Default::default()
+            },
+        );
+        overrides.insert(
+            "llama3.2".to_string(),
+            ModelPricing {
+                input: 0.1,
+                output: 0.1,
+                ..Default::default()
+            },
+        );
+        let table = PricingTable::new(&overrides);
+        let sonnet = table.cost_usd("claude-sonnet-4-6", &usage(1_000_000, 1_000_000, 0, 0));
+        assert!((sonnet - 3.0).abs() < 1e-9);
+        assert!(table.cost_usd("llama3.2", &usage(1_000_000, 0, 0, 0)) > 0.0);
+        assert!(table.get("claude-opus-4-6").is_some());
+    }
+
+    #[test]
+    fn usage_accumulates() {
+        let mut total = TokenUsage::default();
+        total.add(&usage(10, 20, 30, 40));
+        total.add(&usage(1, 2, 3, 4));
+        assert_eq!(total, usage(11, 22, 33, 44));
+        assert_eq!(total.total(), 110);
+    }
+
+    #[test]
+    fn priced_share_keeps_its_own_cost() {
+        let table = PricingTable::default();
+        // A subagent on Haiku, rolled into an Opus turn.
+        let sub = usage(1_000_000, 0, 0, 0);
+        let sub = sub.priced_at(table.cost_usd("claude-haiku-4-5", &sub));
+        let mut turn = usage(1_000_000, 0, 0, 0);
+        turn.add(&sub);
+        assert_eq!(turn.total(), 2_000_000);
+        // 5.00 for the turn's own tokens + 1.00 for the subagent's.
+        let cost = table.cost_usd("claude-opus-4-6", &turn);
+        assert!((cost - 6.0).abs() < 1e-9);
+        // An unpriced turn model still bills the subagent.
+        assert!((table.cost_usd("llama3.2", &turn) - 1.0).abs() < 1e-9);
+    }
+}
@@ -10,7 +10,138 @@ use crate::thinking::ThinkingLevel;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
-    pub content: String,
+    pub content: MessageContent,
+}
+
+/// Message body: plain text, or content blocks when the message carries
+/// images or documents.
+///
+/// Serializes to Anthropic's shape (a string or an array of typed blocks),
+/// which is also the canonical form of `raw_messages`. The OpenAI and Ollama
+/// providers translate it into their own request formats.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl MessageContent {
+    /// User content with `attachments` placed before the text. Plain text
+    /// when there are no attachments; an empty text is left out.
+    pub fn with_attachments(text: &str, attachments: &[ContentBlock]) -> Self {
+        if attachments.is_empty() {
+            return Self::Text(text.to_string());
+        }
+        let mut blocks = attachments.to_vec();
+        if !text.is_empty() {
+            blocks.push(ContentBlock::Text {
+                text: text.to_string(),
+            });
+        }
+        Self::Blocks(blocks)
+    }
+
+    /// The text parts joined together; attachments are skipped.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(s) => s.clone(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(s: &str) -> Self {
+        Self::Text(s.to_string())
+    }
+}
+
+/// One block of a multimodal message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: MediaSource,
+    },
+    /// A PDF document.
+    Document {
+        source: MediaSource,
+    },
+}
+
+impl ContentBlock {
+    /// Whether `media_type` can be sent as an attachment block.
+    pub fn is_supported_attachment(media_type: &str) -> bool {
+        SUPPORTED_IMAGE_TYPES.contains(&media_type) || media_type == PDF_MEDIA_TYPE
+    }
+
+    /// Build an attachment block from its MIME type: `image/*` becomes an
+    /// image, `application/pdf` a document. Other types are not supported by
+    /// the providers and return `None`.
+    pub fn attachment(media_type: &str, source: MediaSource) -> Option<Self> {
+        if SUPPORTED_IMAGE_TYPES.contains(&media_type) {
+            Some(Self::Image { source })
+        } else if media_type == PDF_MEDIA_TYPE {
+            Some(Self::Document { source })
+        } else {
+            None
+        }
+    }
+
+    /// Short label for logs and stored history, e.g. `image (image/png)`.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Text { .. } => "text".to_string(),
+            Self::Image { source } => format!("image ({})", source.describe()),
+            Self::Document { source } => format!("document ({})", source.describe()),
+        }
+    }
+}
+
+/// Image formats accepted by every provider.
+pub const SUPPORTED_IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+pub const PDF_MEDIA_TYPE: &str = "application/pdf";
+
+/// Where the bytes of an image or document come from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+impl MediaSource {
+    /// `data:` URL for base64 sources, the URL itself otherwise.
+    pub fn to_url(&self) -> String {
+        match self {
+            Self::Base64 { media_type, data } => format!("data:{media_type};base64,{data}"),
+            Self::Url { url } => url.clone(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Base64 { media_type, .. } => media_type.clone(),
+            Self::Url { url } => url.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +152,21 @@ pub enum Role {
     System,
 }
 
+/// Flatten a `tool_result` content value (string or text block array) to a string.
+/// Providers without content blocks in tool messages (OpenAI, Ollama) send this.
+pub(crate) fn tool_result_text(content: Option<&serde_json::Value>) -> String {
+    match content {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join(""),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
 /// Tool definition sent to the LLM API.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolDefinition {
@@ -66,6 +212,10 @@ pub struct ChatResponse {
     pub model: String,
     pub tokens_in: u32,
     pub tokens_out: u32,
+    /// Prompt tokens written to the provider's prompt cache (Anthropic).
+    pub cache_write_tokens: u32,
+    /// Prompt tokens served from the provider's prompt cache (Anthropic).
+    pub cache_read_tokens: u32,
     pub stop_reason: String,
     /// Tool calls requested by the LLM. Empty when no tools are called.
     pub tool_calls: Vec<ToolCall>,
@@ -94,11 +244,27 @@ pub trait LlmProvider: Send + Sync {
                 model: resp.model,
                 tokens_in: resp.tokens_in,
                 tokens_out: resp.tokens_out,
+                cache_write_tokens: resp.cache_write_tokens,
+                cache_read_tokens: resp.cache_read_tokens,
                 stop_reason: resp.stop_reason,
             })
             .await;
         Ok(())
     }
+
+    /// Model name the request will actually be sent as, for lookups such as
+    /// the context window. Plain providers use the requested name as is;
+    /// `ProviderRouter` applies the mapping of the slot it would try first.
+    fn resolve_model(&self, model: &str) -> String {
+        model.to_string()
+    }
+
+    /// Circuit-breaker health of the underlying provider(s).
+    /// Plain providers keep no health state; `ProviderRouter` reports one
+    /// entry per slot.
+    fn health(&self) -> Vec<crate::circuit::ProviderHealth> {
+        Vec::new()
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -118,3 +284,37 @@ pub enum ProviderError {
     #[error("Provider unavailable: {0}")]
     Unavailable(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_serializes_to_anthropic_shape() {
+        let plain = MessageContent::with_attachments("hi", &[]);
+        assert_eq!(serde_json::to_value(&plain).unwrap(), "hi");
+
+        let image = ContentBlock::attachment(
+            "image/png",
+            MediaSource::Base64 {
+                media_type: "image/png".to_string(),
+                data: "iVBORw0K".to_string(),
+            },
+        )
+        .unwrap();
+        let content = MessageContent::with_attachments("what is this?", &[image]);
+        assert_eq!(
+            serde_json::to_value(&content).unwrap(),
+            serde_json::json!([
+                { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "iVBORw0K" } },
+                { "type": "text", "text": "what is this?" }
+            ])
+        );
+        assert_eq!(content.text(), "what is this?");
+
+        let url = MediaSource::Url {
+            url: "https://example.com/a.zip".to_string(),
+        };
+        assert!(ContentBlock::attachment("application/zip", url).is_none());
+    }
+}
@@ -1,16 +1,51 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use tokio::sync::mpsc;
 use tracing::{info, warn};
 
+use crate::circuit::{self, CircuitBreaker, CircuitState, ProviderHealth};
 use crate::provider::{ChatRequest, ChatResponse, LlmProvider, ProviderError};
 use crate::stream::StreamEvent;
 
+/// Base and cap for the jittered exponential back-off between retries.
+const BACKOFF_BASE: Duration = Duration::from_millis(200);
+const BACKOFF_MAX: Duration = Duration::from_secs(5);
+/// A 429 whose `Retry-After` is at most this long is waited out on the same
+/// provider; longer waits open the circuit and fail over instead.
+const MAX_INLINE_RETRY_AFTER: Duration = Duration::from_secs(10);
+/// Circuit breaker defaults for slots built with `ProviderSlot::new`.
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// What the router does after a failed attempt on a slot.
+enum NextStep {
+    /// Sleep, then retry the same provider.
+    Retry(Duration),
+    /// Give up on this provider for the current request.
+    NextProvider,
+}
+
 /// Configuration for a single provider slot inside the ProviderRouter.
 pub struct ProviderSlot {
     /// The LLM provider to try.
     pub provider: Box<dyn LlmProvider>,
     /// Maximum number of attempts before moving to the next provider.
     pub max_retries: u32,
+    /// Per-attempt timeout. Bounds the whole call for `send`, and the wait
+    /// for each event (the first one included) for `send_stream`.
+    /// `None` waits for the provider indefinitely.
+    pub timeout: Option<Duration>,
+    /// Requested model name → model name understood by this provider.
+    pub model_map: HashMap<String, String>,
+    /// Model used when the requested model has no entry in `model_map`.
+    /// `None` passes the requested model through unchanged.
+    pub default_model: Option<String>,
+    /// Health state shared by all requests routed through this slot.
+    pub breaker: CircuitBreaker,
 }
 
 impl ProviderSlot {
@@ -18,6 +53,153 @@ impl ProviderSlot {
         Self {
             provider,
             max_retries,
+            timeout: None,
+            model_map: HashMap::new(),
+            default_model: None,
+            breaker: CircuitBreaker::new(DEFAULT_FAILURE_THRESHOLD, DEFAULT_COOLDOWN),
+        }
+    }
+
+    /// Open the circuit after `failure_threshold` consecutive failures and
+    /// skip this provider for `cooldown` before probing it again.
+    pub fn with_circuit(mut self, failure_threshold: u32, cooldown: Duration) -> Self {
+        self.breaker = CircuitBreaker::new(failure_threshold, cooldown);
+        self
+    }
+
+    /// Abort an attempt (and count it as a failure) after `timeout`, or once
+    /// a stream has gone quiet for that long.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Translate requested model names before they reach this provider.
+    pub fn with_model_map(mut self, model_map: HashMap<String, String>) -> Self {
+        self.model_map = model_map;
+        self
+    }
+
+    /// Model to fall back to when the requested one is not in the map.
+    pub fn with_default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = Some(model.into());
+        self
+    }
+
+    /// Model name this slot sends for a request asking for `model`.
+    fn model_for<'a>(&'a self, model: &'a str) -> &'a str {
+        self.model_map
+            .get(model)
+            .or(self.default_model.as_ref())
+            .map_or(model, String::as_str)
+    }
+
+    /// Rewrite the request model for this slot, cloning only when it changes.
+    fn prepare<'a>(&self, req: &'a ChatRequest) -> Cow<'a, ChatRequest> {
+        let model = self.model_for(&req.model);
+        if model != req.model {
+            Cow::Owned(ChatRequest {
+                model: model.to_string(),
+                ..req.clone()
+            })
+        } else {
+            Cow::Borrowed(req)
+        }
+    }
+
+    /// Run one attempt against this slot, applying the configured timeout.
+    async fn attempt<T>(
+        &self,
+        fut: impl Future<Output = Result<T, ProviderError>>,
+    ) -> Result<T, ProviderError> {
+        match self.timeout {
+            Some(limit) => tokio::time::timeout(limit, fut)
+                .await
+                .unwrap_or_else(|_| Err(self.timed_out(limit))),
+            None => fut.await,
+        }
+    }
+
+    fn timed_out(&self, limit: Duration) -> ProviderError {
+        ProviderError::Unavailable(format!(
+            "{} timed out after {}s",
+            self.provider.name(),
+            limit.as_secs()
+        ))
+    }
+
+    /// Stream one attempt through this slot into `tx`.
+    ///
+    /// The timeout applies to the gap before each event rather than the whole
+    /// stream, so a long but steady answer is never cut off. The flag in the
+    /// result is true once any event has reached `tx`.
+    async fn attempt_stream(
+        &self,
+        req: &ChatRequest,
+        tx: &mpsc::Sender<StreamEvent>,
+    ) -> (Result<(), ProviderError>, bool) {
+        let (inner_tx, mut inner_rx) = mpsc::channel(64);
+        let stream = self.provider.send_stream(req, inner_tx);
+        tokio::pin!(stream);
+        let mut forwarded = false;
+        let mut open = true;
+
+        loop {
+            let idle = async {
+                match self.timeout {
+                    Some(limit) => tokio::time::sleep(limit).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                event = inner_rx.recv(), if open => match event {
+                    Some(event) => {
+                        forwarded = true;
+                        let _ = tx.send(event).await;
+                    }
+                    None => open = false,
+                },
+                result = &mut stream => {
+                    // events sent before the provider returned are still queued
+                    while let Ok(event) = inner_rx.try_recv() {
+                        forwarded = true;
+                        let _ = tx.send(event).await;
+                    }
+                    return (result, forwarded);
+                }
+                _ = idle => {
+                    let limit = self.timeout.unwrap_or_default();
+                    return (Err(self.timed_out(limit)), forwarded);
+                }
+            }
+        }
+    }
+
+    /// Update the breaker for a failed attempt and decide what to do next.
+    fn on_failure(&self, err: &ProviderError, attempt: u32) -> NextStep {
+        let more_attempts = attempt < self.max_retries;
+
+        // Request-level errors (bad request, parse failure) would fail the
+        // same way again and say nothing about provider health.
+        if !circuit::is_health_failure(err) {
+            self.breaker.release_probe();
+            return NextStep::NextProvider;
+        }
+
+        if let ProviderError::RateLimited { retry_after_ms } = err {
+            let wait = Duration::from_millis(*retry_after_ms);
+            if more_attempts && wait <= MAX_INLINE_RETRY_AFTER {
+                return NextStep::Retry(wait);
+            }
+            self.breaker.record_failure(err, Some(wait));
+            return NextStep::NextProvider;
+        }
+
+        self.breaker.record_failure(err, None);
+        if more_attempts && self.breaker.state() == CircuitState::Closed {
+            NextStep::Retry(circuit::backoff(attempt, BACKOFF_BASE, BACKOFF_MAX))
+        } else {
+            NextStep::NextProvider
         }
     }
 }
@@ -27,7 +209,12 @@ impl ProviderSlot {
 /// Providers are tried in priority order (index 0 first). If a provider
 /// returns an error after its configured `max_retries`, the router moves
 /// to the next provider in the list. The same logic applies to both
-/// `send()` and `send_stream()`.
+/// `send()` and `send_stream()`, except that a stream which already
+/// forwarded events is never retried or failed over.
+///
+/// Each slot has a circuit breaker that persists across requests: a provider
+/// that keeps failing (or answers 429 with a long `Retry-After`) is skipped
+/// until its cooldown elapses, then probed with a single request.
 pub struct ProviderRouter {
     slots: Vec<ProviderSlot>,
 }
@@ -42,6 +229,13 @@ impl ProviderRouter {
         );
         Self { slots }
     }
+
+    /// Error returned when every slot was skipped or failed.
+    fn exhausted(last_err: Option<ProviderError>) -> ProviderError {
+        last_err.unwrap_or_else(|| {
+            ProviderError::Unavailable("all providers unavailable (circuit open)".to_string())
+        })
+    }
 }
 
 #[async_trait]
@@ -55,10 +249,16 @@ impl LlmProvider for ProviderRouter {
 
         for slot in &self.slots {
             let provider_name = slot.provider.name();
+            if !slot.breaker.allow() {
+                info!(provider = %provider_name, "circuit open, skipping provider");
+                continue;
+            }
+            let req = slot.prepare(req);
 
             for attempt in 0..=slot.max_retries {
-                match slot.provider.send(req).await {
+                match slot.attempt(slot.provider.send(&req)).await {
                     Ok(resp) => {
+                        slot.breaker.record_success();
                         if attempt > 0 {
                             info!(
                                 provider = %provider_name,
@@ -75,21 +275,11 @@ impl LlmProvider for ProviderRouter {
                             err = %e,
                             "provider send failed"
                         );
-
-                        // RateLimited is not retriable — skip remaining retries for this provider
-                        if matches!(e, ProviderError::RateLimited { .. }) {
-                            last_err = Some(e);
-                            break;
-                        }
-
+                        let next = slot.on_failure(&e, attempt);
                         last_err = Some(e);
-
-                        if attempt < slot.max_retries {
-                            // small back-off between retries on the same provider
-                            tokio::time::sleep(tokio::time::Duration::from_millis(
-                                200 * (attempt as u64 + 1),
-                            ))
-                            .await;
+                        match next {
+                            NextStep::Retry(delay) => tokio::time::sleep(delay).await,
+                            NextStep::NextProvider => break,
                         }
                     }
                 }
@@ -102,8 +292,7 @@ impl LlmProvider for ProviderRouter {
         }
 
         // all providers failed — return the last recorded error
-        Err(last_err
-            .unwrap_or_else(|| ProviderError::Unavailable("all providers failed".to_string())))
+        Err(Self::exhausted(last_err))
     }
 
     async fn send_stream(
@@ -115,10 +304,17 @@ impl LlmProvider for ProviderRouter {
 
         for slot in &self.slots {
             let provider_name = slot.provider.name();
+            if !slot.breaker.allow() {
+                info!(provider = %provider_name, "circuit open, skipping stream provider");
+                continue;
+            }
+            let req = slot.prepare(req);
 
             for attempt in 0..=slot.max_retries {
-                match slot.provider.send_stream(req, tx.clone()).await {
+                let (result, forwarded) = slot.attempt_stream(&req, &tx).await;
+                match result {
                     Ok(()) => {
+                        slot.breaker.record_success();
                         if attempt > 0 {
                             info!(
                                 provider = %provider_name,
@@ -135,19 +331,16 @@ impl LlmProvider for ProviderRouter {
                             err = %e,
                             "provider send_stream failed"
                         );
-
-                        if matches!(e, ProviderError::RateLimited { .. }) {
-                            last_err = Some(e);
-                            break;
+                        let next = slot.on_failure(&e, attempt);
+                        if forwarded {
+                            // part of the answer already reached the caller;
+                            // another attempt would stream it a second time
+                            return Err(e);
                         }
-
                         last_err = Some(e);
-
-                        if attempt < slot.max_retries {
-                            tokio::time::sleep(tokio::time::Duration::from_millis(
-                                200 * (attempt as u64 + 1),
-                            ))
-                            .await;
+                        match next {
+                            NextStep::Retry(delay) => tokio::time::sleep(delay).await,
+                            NextStep::NextProvider => break,
                         }
                     }
                 }
@@ -159,8 +352,23 @@ impl LlmProvider for ProviderRouter {
             );
         }
 
-        Err(last_err
-            .unwrap_or_else(|| ProviderError::Unavailable("all providers failed".to_string())))
+        Err(Self::exhausted(last_err))
+    }
+
+    fn resolve_model(&self, model: &str) -> String {
+        let slot = self
+            .slots
+            .iter()
+            .find(|slot| slot.breaker.would_allow())
+            .unwrap_or(&self.slots[0]);
+        slot.model_for(model).to_string()
+    }
+
+    fn health(&self) -> Vec<ProviderHealth> {
+        self.slots
+            .iter()
+            .map(|slot| slot.breaker.snapshot(slot.provider.name()))
+            .collect()
     }
 }
 
@@ -197,6 +405,8 @@ mod tests {
                 model: req.model.clone(),
                 tokens_in: 1,
                 tokens_out: 1,
+                cache_write_tokens: 0,
+                cache_read_tokens: 0,
                 stop_reason: "stop".to_string(),
                 tool_calls: Vec::new(),
             })
@@ -210,7 +420,7 @@ mod tests {
             system_prompt: None,
             messages: vec![Message {
                 role: Role::User,
-                content: "hello".to_string(),
+                content: "hello".into(),
             }],
             max_tokens: 64,
             stream: false,
@@ -242,4 +452,256 @@ mod tests {
         let result = router.send(&dummy_request()).await;
         assert!(result.is_err());
     }
+
+    struct Slow;
+
+    #[async_trait]
+    impl LlmProvider for Slow {
+        fn name(&self) -> &str {
+            "slow"
+        }
+        async fn send(&self, req: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            AlwaysOk.send(req).await
+        }
+    }
+
+    #[tokio::test]
+    async fn router_times_out_and_falls_back() {
+        let router = ProviderRouter::new(vec![
+            ProviderSlot::new(Box::new(Slow), 0).with_timeout(Duration::from_millis(20)),
+            ProviderSlot::new(Box::new(AlwaysOk), 0),
+        ]);
+
+        let result = router.send(&dummy_request()).await;
+        assert_eq!(result.unwrap().content, "ok");
+    }
+
+    #[tokio::test]
+    async fn slot_maps_model_names() {
+        let map = HashMap::from([("test-model".to_string(), "mapped-model".to_string())]);
+        let router = ProviderRouter::new(vec![
+            ProviderSlot::new(Box::new(AlwaysFail), 0).with_default_model("unused"),
+            ProviderSlot::new(Box::new(AlwaysOk), 0)
+                .with_model_map(map)
+                .with_default_model("fallback-model"),
+        ]);
+
+        let resp = router.send(&dummy_request()).await.unwrap();
+        assert_eq!(resp.model, "mapped-model");
+
+        let mut other = dummy_request();
+        other.model = "something-else".to_string();
+        let resp = router.send(&other).await.unwrap();
+        assert_eq!(resp.model, "fallback-model");
+    }
+
+    #[tokio::test]
+    async fn resolve_model_follows_the_first_usable_slot() {
+        let map = HashMap::from([("test-model".to_string(), "mapped-model".to_string())]);
+        let router = ProviderRouter::new(vec![
+            ProviderSlot::new(Box::new(AlwaysFail), 0)
+                .with_default_model("primary-model")
+                .with_circuit(1, Duration::from_secs(60)),
+            ProviderSlot::new(Box::new(AlwaysOk), 0).with_model_map(map),
+        ]);
+        assert_eq!(router.resolve_model("test-model"), "primary-model");
+
+        // once the primary's circuit opens, requests go to the second slot
+        router.send(&dummy_request()).await.unwrap();
+        assert_eq!(router.resolve_model("test-model"), "mapped-model");
+        assert_eq!(router.resolve_model("other"), "other");
+    }
+
+    /// Fails with `err` and counts how often it was called.
+    struct Counting {
+        calls: std::sync::Arc<std::sync::atomic::AtomicU32>,
+        rate_limit_ms: Option<u64>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+        async fn send(&self, _req: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(match self.rate_limit_ms {
+                Some(ms) => ProviderError::RateLimited { retry_after_ms: ms },
+                None => ProviderError::Unavailable("down".to_string()),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn open_circuit_skips_provider_on_later_requests() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let router = ProviderRouter::new(vec![
+            ProviderSlot::new(
+                Box::new(Counting {
+                    calls: calls.clone(),
+                    rate_limit_ms: None,
+                }),
+                0,
+            )
+            .with_circuit(2, Duration::from_secs(60)),
+            ProviderSlot::new(Box::new(AlwaysOk), 0),
+        ]);
+
+        for _ in 0..4 {
+            assert!(router.send(&dummy_request()).await.is_ok());
+        }
+
+        // two failures trip the breaker; later requests go straight to slot 2
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+        let health = router.health();
+        assert_eq!(health[0].state, CircuitState::Open);
+        assert_eq!(health[1].state, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_opens_circuit_without_retrying() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let router = ProviderRouter::new(vec![
+            ProviderSlot::new(
+                Box::new(Counting {
+                    calls: calls.clone(),
+                    rate_limit_ms: Some(120_000),
+                }),
+                3,
+            ),
+            ProviderSlot::new(Box::new(AlwaysOk), 0),
+        ]);
+
+        assert!(router.send(&dummy_request()).await.is_ok());
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let health = router.health();
+        assert_eq!(health[0].state, CircuitState::Open);
+        assert!(health[0].retry_in_ms > 100_000);
+    }
+
+    #[tokio::test]
+    async fn short_retry_after_is_waited_out() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let router = ProviderRouter::new(vec![ProviderSlot::new(
+            Box::new(Counting {
+                calls: calls.clone(),
+                rate_limit_ms: Some(10),
+            }),
+            2,
+        )]);
+
+        assert!(router.send(&dummy_request()).await.is_err());
+        // initial attempt + 2 retries, then the breaker opens
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert_eq!(router.health()[0].state, CircuitState::Open);
+    }
+
+    /// Streams `chunks` deltas `gap` apart, then fails if `fail_after` is set.
+    struct Drip {
+        chunks: usize,
+        gap: Duration,
+        fail_after: bool,
+    }
+
+    #[async_trait]
+    impl LlmProvider for Drip {
+        fn name(&self) -> &str {
+            "drip"
+        }
+        async fn send(&self, req: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+            AlwaysOk.send(req).await
+        }
+        async fn send_stream(
+            &self,
+            _req: &ChatRequest,
+            tx: mpsc::Sender<StreamEvent>,
+        ) -> Result<(), ProviderError> {
+            for i in 0..self.chunks {
+                tokio::time::sleep(self.gap).await;
+                let _ = tx
+                    .send(StreamEvent::TextDelta {
+                        text: i.to_string(),
+                    })
+                    .await;
+            }
+            if self.fail_after {
+                return Err(ProviderError::Unavailable("dropped".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    async fn collect_stream(router: &ProviderRouter) -> (Result<(), ProviderError>, String) {
+        let (tx, mut rx) = mpsc::channel(64);
+        let result = router.send_stream(&dummy_request(), tx).await;
+        let mut text = String::new();
+        while let Ok(event) = rx.try_recv() {
+            if let StreamEvent::TextDelta { text: t } = event {
+                text.push_str(&t);
+            }
+        }
+        (result, text)
+    }
+
+    #[tokio::test]
+    async fn stream_timeout_applies_between_events() {
+        // 6 x 20ms outlasts the 50ms timeout, but no single gap does
+        let router = ProviderRouter::new(vec![
+            ProviderSlot::new(
+                Box::new(Drip {
+                    chunks: 6,
+                    gap: Duration::from_millis(20),
+                    fail_after: false,
+                }),
+                0,
+            )
+            .with_timeout(Duration::from_millis(50)),
+            ProviderSlot::new(Box::new(AlwaysOk), 0),
+        ]);
+
+        let (result, text) = collect_stream(&router).await;
+        assert!(result.is_ok());
+        assert_eq!(text, "012345");
+    }
+
+    #[tokio::test]
+    async fn silent_stream_times_out_and_falls_back() {
+        let router = ProviderRouter::new(vec![
+            ProviderSlot::new(
+                Box::new(Drip {
+                    chunks: 1,
+                    gap: Duration::from_secs(5),
+                    fail_after: false,
+                }),
+                0,
+            )
+            .with_timeout(Duration::from_millis(20)),
+            ProviderSlot::new(Box::new(AlwaysOk), 0),
+        ]);
+
+        let (result, text) = collect_stream(&router).await;
+        assert!(result.is_ok());
+        assert_eq!(text, "ok");
+    }
+
+    #[tokio::test]
+    async fn stream_failing_after_output_does_not_fail_over() {
+        let router = ProviderRouter::new(vec![
+            ProviderSlot::new(
+                Box::new(Drip {
+                    chunks: 2,
+                    gap: Duration::from_millis(1),
+                    fail_after: true,
+                }),
+                2,
+            ),
+            ProviderSlot::new(Box::new(AlwaysOk), 0),
+        ]);
+
+        let (result, text) = collect_stream(&router).await;
+        assert!(result.is_err());
+        assert_eq!(text, "01");
+    }
 }
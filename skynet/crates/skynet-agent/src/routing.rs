@@ -0,0 +1,247 @@
+//! Heuristic per-turn model selection from `[agent.routing]` rules.
+//!
+//! Lets a cheap model answer chit-chat while larger models handle code or
+//! long requests. Rules are checked in order; the first one whose conditions
+//! all hold picks the model. No match means the runtime default is used.
+
+use skynet_core::config::RoutingRule;
+use skynet_core::types::UserRole;
+use skynet_users::resolver::UserResolver;
+use skynet_users::types::User;
+
+/// Facts about the current turn that rules can match on.
+#[derive(Debug, Clone, Default)]
+pub struct RoutingInput<'a> {
+    pub message: &'a str,
+    pub channel: &'a str,
+    /// `None` for anonymous / unresolved senders.
+    pub user_role: Option<UserRole>,
+    pub tools_used_last_turn: bool,
+    /// Remaining daily token budget; `None` when the user has no cap.
+    pub budget_remaining_tokens: Option<u64>,
+}
+
+/// What the channel adapter knows about the sender, resolved from its
+/// channel identity with `SenderInfo::resolve` and passed into the pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct SenderInfo {
+    /// Resolved user ID, recorded on conversation and session rows.
+    pub user_id: Option<String>,
+    pub role: Option<UserRole>,
+    /// Remaining daily token budget; `None` when the user has no cap.
+    pub budget_remaining_tokens: Option<u64>,
+    /// The full user record, for `PermissionChecker` checks (plugin
+    /// permissions).
+    pub user: Option<User>,
+}
+
+impl SenderInfo {
+    /// Resolve the sender's role and remaining daily token budget from its
+    /// channel identity (e.g. `"discord"` and the Discord user ID). Returns
+    /// `None` when resolution fails.
+    pub fn resolve(users: &UserResolver, channel: &str, identifier: &str) -> Option<Self> {
+        let resolved = users.resolve(channel, identifier).ok()?;
+        let user = resolved.user();
+
+        // The daily counter only applies while tokens_reset_date is today.
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let used_today = if user.tokens_reset_date.as_deref() == Some(today.as_str()) {
+            user.tokens_used_today
+        } else {
+            0
+        };
+
+        Some(Self {
+            user_id: Some(user.id.clone()),
+            role: Some(user.role.clone()),
+            budget_remaining_tokens: user
+                .max_tokens_per_day
+                .map(|cap| cap.saturating_sub(used_today)),
+            user: Some(user.clone()),
+        })
+    }
+}
+
+/// Model picked by a routing rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingDecision {
+    pub model: String,
+    pub rule: String,
+}
+
+/// Ordered rule set. Empty by default — routing is opt-in.
+#[derive(Debug, Clone, Default)]
+pub struct ModelRouter {
+    rules: Vec<RoutingRule>,
+}
+
+impl ModelRouter {
+    pub fn new(mut rules: Vec<RoutingRule>) -> Self {
+        // Normalise once so matching is a plain substring check per turn.
+        for rule in &mut rules {
+            for kw in &mut rule.keywords {
+                *kw = kw.to_lowercase();
+            }
+        }
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Return the first matching rule's model, if any.
+    pub fn route(&self, input: &RoutingInput<'_>) -> Option<RoutingDecision> {
+        let lowered = input.message.to_lowercase();
+        let len = input.message.chars().count();
+
+        self.rules
+            .iter()
+            .find(|rule| rule_matches(rule, input, &lowered, len))
+            .map(|rule| RoutingDecision {
+                model: rule.model.clone(),
+                rule: rule.name.clone(),
+            })
+    }
+}
+
+fn rule_matches(rule: &RoutingRule, input: &RoutingInput<'_>, lowered: &str, len: usize) -> bool {
+    if rule.min_length.is_some_and(|min| len < min) {
+        return false;
+    }
+    if rule.max_length.is_some_and(|max| len > max) {
+        return false;
+    }
+    if !rule.keywords.is_empty() && !rule.keywords.iter().any(|kw| lowered.contains(kw.as_str())) {
+        return false;
+    }
+    if !rule.channels.is_empty() && !rule.channels.iter().any(|c| c == input.channel) {
+        return false;
+    }
+    if !rule.roles.is_empty() {
+        let Some(ref role) = input.user_role else {
+            return false;
+        };
+        if !rule.roles.contains(&role.to_string()) {
+            return false;
+        }
+    }
+    if rule
+        .tools_used_last_turn
+        .is_some_and(|want| want != input.tools_used_last_turn)
+    {
+        return false;
+    }
+    if let Some(threshold) = rule.budget_below_tokens {
+        match input.budget_remaining_tokens {
+            Some(remaining) if remaining < threshold => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, model: &str) -> RoutingRule {
+        RoutingRule {
+            name: name.to_string(),
+            model: model.to_string(),
+            min_length: None,
+            max_length: None,
+            keywords: Vec::new(),
+            channels: Vec::new(),
+            roles: Vec::new(),
+            tools_used_last_turn: None,
+            budget_below_tokens: None,
+        }
+    }
+
+    fn input(message: &str) -> RoutingInput<'_> {
+        RoutingInput {
+            message,
+            channel: "web",
+            ..Default::default()
+        }
+    }
+
+    fn router() -> ModelRouter {
+        ModelRouter::new(vec![
+            RoutingRule {
+                budget_below_tokens: Some(1_000),
+                ..rule("low-budget", "haiku")
+            },
+            RoutingRule {
+                keywords: vec!["Rust".to_string(), "stack trace".to_string()],
+                ..rule("code", "opus")
+            },
+            RoutingRule {
+                tools_used_last_turn: Some(true),
+                ..rule("tool-follow-up", "sonnet")
+            },
+            RoutingRule {
+                max_length: Some(40),
+                channels: vec!["discord".to_string()],
+                ..rule("chit-chat", "haiku")
+            },
+        ])
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let r = router();
+        let d = r
+            .route(&input("why does my rust code not compile?"))
+            .unwrap();
+        assert_eq!(d.rule, "code");
+        assert_eq!(d.model, "opus");
+
+        let mut low = input("why does my rust code not compile?");
+        low.budget_remaining_tokens = Some(10);
+        assert_eq!(r.route(&low).unwrap().rule, "low-budget");
+    }
+
+    #[test]
+    fn all_conditions_must_hold() {
+        let r = router();
+        // short, but wrong channel
+        assert_eq!(r.route(&input("hi there")), None);
+
+        let mut discord = input("hi there");
+        discord.channel = "discord";
+        assert_eq!(r.route(&discord).unwrap().rule, "chit-chat");
+
+        // right channel, too long
+        let long = "x".repeat(41);
+        let mut long_discord = input(&long);
+        long_discord.channel = "discord";
+        assert_eq!(r.route(&long_discord), None);
+    }
+
+    #[test]
+    fn tools_and_roles_match() {
+        let r = ModelRouter::new(vec![RoutingRule {
+            roles: vec!["admin".to_string()],
+            ..rule("admins", "opus")
+        }]);
+        assert_eq!(r.route(&input("hello")), None);
+        let mut admin = input("hello");
+        admin.user_role = Some(UserRole::Admin);
+        assert_eq!(r.route(&admin).unwrap().model, "opus");
+
+        let mut follow_up = input("and now?");
+        follow_up.tools_used_last_turn = true;
+        assert_eq!(router().route(&follow_up).unwrap().rule, "tool-follow-up");
+    }
+
+    #[test]
+    fn uncapped_users_never_match_budget_rules() {
+        let r = ModelRouter::new(vec![RoutingRule {
+            budget_below_tokens: Some(1_000),
+            ..rule("low-budget", "haiku")
+        }]);
+        assert_eq!(r.route(&input("hello")), None);
+    }
+}
@@ -1,13 +1,19 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-#[cfg(feature = "hooks")]
-use std::sync::Arc;
-
 use tokio::sync::{mpsc, RwLock};
 use tracing::info;
 
+use skynet_core::config::{CondenseConfig, SearchConfig, WebFetchConfig, WorkspaceConfig};
+
+use crate::context_budget::ContextWindows;
+use crate::pricing::PricingTable;
 use crate::prompt::{PromptBuilder, SessionInfo};
 use crate::provider::{ChatRequest, ChatResponse, LlmProvider, Message, ProviderError, Role};
+use crate::routing::{ModelRouter, RoutingInput};
+use crate::search::SearchProvider;
 use crate::stream::StreamEvent;
 
 #[cfg(feature = "hooks")]
@@ -22,11 +28,43 @@ pub struct AgentRuntime {
     provider: Box<dyn LlmProvider>,
     prompt: RwLock<PromptBuilder>,
     default_model: RwLock<String>,
+    /// Set once an operator picks the model (`/model`, `agent.model`); a
+    /// pinned model wins over routing rules.
+    model_pinned: AtomicBool,
+    /// Per-turn model selection rules (empty = always use `default_model`).
+    routing: ModelRouter,
+    /// Per-model token prices used to cost each turn.
+    pricing: PricingTable,
+    /// Per-model context window sizes for request budgeting.
+    context_windows: ContextWindows,
+    /// When and with which model long sessions are condensed.
+    condense: CondenseConfig,
+    /// Sessions with a condensation in flight.
+    condensing: Mutex<HashSet<String>>,
+    /// Limits and host rules for `web_fetch`.
+    web_fetch: WebFetchConfig,
+    /// Root and path rules for the file tools.
+    workspace: WorkspaceConfig,
+    /// Web search backend; `web_search` is only offered when set.
+    search: Option<Arc<dyn SearchProvider>>,
+    search_config: SearchConfig,
     /// Optional hook engine for LLM observability events.
     #[cfg(feature = "hooks")]
     hooks: Option<Arc<HookEngine>>,
 }
 
+/// An in-flight condensation claimed with `AgentRuntime::begin_condense`.
+pub struct CondenseGuard<'a> {
+    condensing: &'a Mutex<HashSet<String>>,
+    session_key: String,
+}
+
+impl Drop for CondenseGuard<'_> {
+    fn drop(&mut self) {
+        self.condensing.lock().unwrap().remove(&self.session_key);
+    }
+}
+
 impl AgentRuntime {
     pub fn new(
         provider: Box<dyn LlmProvider>,
@@ -37,22 +75,150 @@ impl AgentRuntime {
             provider,
             prompt: RwLock::new(prompt),
             default_model: RwLock::new(default_model),
+            model_pinned: AtomicBool::new(false),
+            routing: ModelRouter::default(),
+            pricing: PricingTable::default(),
+            context_windows: ContextWindows::default(),
+            condense: CondenseConfig::default(),
+            condensing: Mutex::default(),
+            web_fetch: WebFetchConfig::default(),
+            workspace: WorkspaceConfig::default(),
+            search: None,
+            search_config: SearchConfig::default(),
             #[cfg(feature = "hooks")]
             hooks: None,
         }
     }
 
+    /// Attach `[agent.routing]` rules for per-turn model selection.
+    pub fn with_routing(mut self, routing: ModelRouter) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Replace the built-in pricing table (e.g. with `[pricing]` overrides).
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Token prices for cost accounting.
+    pub fn pricing(&self) -> &PricingTable {
+        &self.pricing
+    }
+
+    /// Replace the built-in context window registry.
+    pub fn with_context_windows(mut self, windows: ContextWindows) -> Self {
+        self.context_windows = windows;
+        self
+    }
+
+    /// Context window sizes used to trim requests before sending.
+    pub fn context_windows(&self) -> &ContextWindows {
+        &self.context_windows
+    }
+
+    /// Set `[agent.condense]` options for session condensation.
+    pub fn with_condense(mut self, condense: CondenseConfig) -> Self {
+        self.condense = condense;
+        self
+    }
+
+    /// Session condensation settings.
+    pub fn condense(&self) -> &CondenseConfig {
+        &self.condense
+    }
+
+    /// Claim `session_key` for condensation. Returns `None` while another
+    /// condensation of the session is in flight; the claim is released when
+    /// the guard drops.
+    pub fn begin_condense(&self, session_key: &str) -> Option<CondenseGuard<'_>> {
+        let mut condensing = self.condensing.lock().unwrap();
+        if !condensing.insert(session_key.to_string()) {
+            return None;
+        }
+        Some(CondenseGuard {
+            condensing: &self.condensing,
+            session_key: session_key.to_string(),
+        })
+    }
+
+    /// Set `[agent.web_fetch]` limits and host rules.
+    pub fn with_web_fetch(mut self, web_fetch: WebFetchConfig) -> Self {
+        self.web_fetch = web_fetch;
+        self
+    }
+
+    /// `web_fetch` limits and host rules.
+    pub fn web_fetch(&self) -> &WebFetchConfig {
+        &self.web_fetch
+    }
+
+    /// Set `[agent.workspace]` root and path rules for the file tools.
+    pub fn with_workspace(mut self, workspace: WorkspaceConfig) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
+    /// File tool workspace settings.
+    pub fn workspace(&self) -> &WorkspaceConfig {
+        &self.workspace
+    }
+
+    /// Enable `web_search` with `provider` and the `[search]` options.
+    pub fn with_search(mut self, provider: Arc<dyn SearchProvider>, config: SearchConfig) -> Self {
+        self.search = Some(provider);
+        self.search_config = config;
+        self
+    }
+
+    /// Web search backend, if configured.
+    pub fn search(&self) -> Option<&Arc<dyn SearchProvider>> {
+        self.search.as_ref()
+    }
+
+    /// `[search]` options for `web_search`.
+    pub fn search_config(&self) -> &SearchConfig {
+        &self.search_config
+    }
+
     /// Get the current default model name.
     pub async fn get_model(&self) -> String {
         self.default_model.read().await.clone()
     }
 
-    /// Change the default model at runtime. Returns the previous model.
+    /// Change the default model at runtime and pin it, so routing rules no
+    /// longer replace it. Returns the previous model.
     pub async fn set_model(&self, model: String) -> String {
         let mut guard = self.default_model.write().await;
+        self.model_pinned.store(true, Ordering::Relaxed);
         std::mem::replace(&mut *guard, model)
     }
 
+    /// Pick the model for a turn.
+    ///
+    /// Precedence: per-request override → model pinned with `set_model` →
+    /// first matching routing rule → configured default. Returns the model
+    /// and the name of the rule that chose it (`None` when no rule was
+    /// involved).
+    pub async fn resolve_model(
+        &self,
+        model_override: Option<&str>,
+        input: &RoutingInput<'_>,
+    ) -> (String, Option<String>) {
+        if let Some(m) = model_override {
+            return (m.to_string(), None);
+        }
+        if self.model_pinned.load(Ordering::Relaxed) {
+            return (self.get_model().await, None);
+        }
+        if let Some(decision) = self.routing.route(input) {
+            info!(model = %decision.model, rule = %decision.rule, "model routed");
+            return (decision.model, Some(decision.rule));
+        }
+        (self.get_model().await, None)
+    }
+
     /// Attach a hook engine for LLM observability events.
     #[cfg(feature = "hooks")]
     pub fn with_hooks(mut self, hooks: Arc<HookEngine>) -> Self {
@@ -182,7 +348,7 @@ impl AgentRuntime {
             system_prompt: Some(system_prompt),
             messages: vec![Message {
                 role: Role::User,
-                content: user_message.to_string(),
+                content: user_message.into(),
             }],
             max_tokens: 4096,
             stream: false,
@@ -255,3 +421,76 @@ impl AgentRuntime {
         tokio::spawn(async move { engine.emit_after(ctx) });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use skynet_core::config::RoutingRule;
+
+    use super::*;
+
+    struct NoProvider;
+
+    #[async_trait]
+    impl LlmProvider for NoProvider {
+        fn name(&self) -> &str {
+            "none"
+        }
+        async fn send(&self, _req: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+            Err(ProviderError::Unavailable(
+                "no provider in tests".to_string(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn pinned_model_wins_over_routing_rules() {
+        let runtime = AgentRuntime::new(
+            Box::new(NoProvider),
+            PromptBuilder::load(None),
+            "sonnet".to_string(),
+        )
+        .with_routing(ModelRouter::new(vec![RoutingRule {
+            name: "code".to_string(),
+            model: "opus".to_string(),
+            min_length: None,
+            max_length: None,
+            keywords: vec!["rust".to_string()],
+            channels: Vec::new(),
+            roles: Vec::new(),
+            tools_used_last_turn: None,
+            budget_below_tokens: None,
+        }]));
+        let input = RoutingInput {
+            message: "fix my rust code",
+            channel: "web",
+            ..Default::default()
+        };
+
+        // The configured default is only a fallback for the rules.
+        let (model, rule) = runtime.resolve_model(None, &input).await;
+        assert_eq!((model.as_str(), rule.as_deref()), ("opus", Some("code")));
+
+        runtime.set_model("haiku".to_string()).await;
+        let (model, rule) = runtime.resolve_model(None, &input).await;
+        assert_eq!((model.as_str(), rule), ("haiku", None));
+
+        // A per-request override still beats the pinned model.
+        let (model, _) = runtime.resolve_model(Some("sonnet"), &input).await;
+        assert_eq!(model, "sonnet");
+    }
+
+    #[test]
+    fn one_condensation_per_session_at_a_time() {
+        let runtime = AgentRuntime::new(
+            Box::new(NoProvider),
+            PromptBuilder::load(None),
+            "sonnet".to_string(),
+        );
+        let guard = runtime.begin_condense("web:a").unwrap();
+        assert!(runtime.begin_condense("web:a").is_none());
+        assert!(runtime.begin_condense("web:b").is_some());
+        drop(guard);
+        assert!(runtime.begin_condense("web:a").is_some());
+    }
+}
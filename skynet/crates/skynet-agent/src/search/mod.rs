@@ -0,0 +1,218 @@
+//! Web search backends for the `web_search` tool.
+//!
+//! `SearchProvider` is the extension point; `searxng` is the first backend.
+//! `build_provider` picks the backend named by `[search] provider`, and
+//! `dedupe` / `format_results` turn raw results into what the model sees.
+
+pub mod searxng;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use skynet_core::config::SearchConfig;
+
+/// Longest snippet shown to the model, in characters.
+const MAX_SNIPPET_CHARS: usize = 300;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error("invalid search config: {0}")]
+    Config(String),
+
+    #[error("search request failed: {0}")]
+    Http(String),
+
+    #[error("search backend returned HTTP {status}: {message}")]
+    Status { status: u16, message: String },
+
+    #[error("invalid search response: {0}")]
+    Parse(String),
+}
+
+/// Restrict results to recently published pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeRange {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeRange {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            "year" => Some(Self::Year),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub query: String,
+    /// Results wanted; backends may return more or fewer.
+    pub limit: usize,
+    pub time_range: Option<TimeRange>,
+}
+
+/// One search hit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+    /// Engine or index that produced the hit.
+    pub source: Option<String>,
+    pub published: Option<String>,
+}
+
+/// A web search backend.
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    /// Backend name for logging and error messages.
+    fn name(&self) -> &str;
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchResult>, SearchError>;
+}
+
+/// The backend configured in `[search]`, or `None` when search is not set up.
+pub fn build_provider(config: &SearchConfig) -> Option<Arc<dyn SearchProvider>> {
+    match config.provider.as_str() {
+        "searxng" => {
+            let searxng = config.searxng.as_ref()?;
+            match searxng::SearxngProvider::new(searxng, config.timeout_secs) {
+                Ok(provider) => {
+                    info!(url = %searxng.url, "web search: SearXNG");
+                    Some(Arc::new(provider))
+                }
+                Err(e) => {
+                    warn!(error = %e, "web search disabled");
+                    None
+                }
+            }
+        }
+        other => {
+            warn!(provider = %other, "unknown search provider — web search disabled");
+            None
+        }
+    }
+}
+
+/// Drop results whose URL was already seen. URLs are compared without scheme,
+/// `www.`, fragment, trailing slash and `utm_*` tracking parameters.
+pub fn dedupe(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut seen = HashSet::new();
+    results
+        .into_iter()
+        .filter(|r| seen.insert(normalize_url(&r.url)))
+        .collect()
+}
+
+fn normalize_url(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        return url.trim().to_lowercase();
+    };
+    parsed.set_fragment(None);
+    let query: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(k, _)| !k.starts_with("utm_"))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    let host = parsed.host_str().unwrap_or_default().to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let mut key = format!("{host}{}", parsed.path().trim_end_matches('/'));
+    if !query.is_empty() {
+        let pairs: Vec<String> = query.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        key.push('?');
+        key.push_str(&pairs.join("&"));
+    }
+    key
+}
+
+/// Numbered result list for the model: title, URL, then a one-paragraph
+/// snippet cut at `MAX_SNIPPET_CHARS`.
+pub fn format_results(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let mut entry = format!("{}. {}\n   {}", i + 1, r.title.trim(), r.url);
+            if let Some(ref date) = r.published {
+                entry.push_str(&format!("\n   Published: {date}"));
+            }
+            let snippet = r.snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !snippet.is_empty() {
+                entry.push_str("\n   ");
+                entry.push_str(&truncate_chars(&snippet, MAX_SNIPPET_CHARS));
+            }
+            entry
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => format!("{}…", s[..i].trim_end()),
+        None => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str, title: &str) -> SearchResult {
+        SearchResult {
+            title: title.to_string(),
+            url: url.to_string(),
+            snippet: String::new(),
+            source: None,
+            published: None,
+        }
+    }
+
+    #[test]
+    fn dedupes_equivalent_urls() {
+        let results = dedupe(vec![
+            result("https://www.example.com/post/?utm_source=x#top", "A"),
+            result("http://example.com/post", "B"),
+            result("https://example.com/post?page=2", "C"),
+            result("https://other.org/", "D"),
+        ]);
+        let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, ["A", "C", "D"]);
+    }
+
+    #[test]
+    fn formats_results_for_the_model() {
+        let mut first = result("https://example.com/a", " Rust 1.0 ");
+        first.snippet = format!("Release   notes\n{}", "x".repeat(400));
+        first.published = Some("2015-05-15".to_string());
+        let out = format_results(&[first, result("https://example.com/b", "Second")]);
+        let expected_snippet = format!("Release notes {}…", "x".repeat(300 - 14));
+        assert_eq!(
+            out,
+            format!(
+                "1. Rust 1.0\n   https://example.com/a\n   Published: 2015-05-15\n   {expected_snippet}\n\n\
+                 2. Second\n   https://example.com/b"
+            )
+        );
+    }
+}
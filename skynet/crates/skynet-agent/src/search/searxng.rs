@@ -0,0 +1,225 @@
+//! SearXNG backend — queries a self-hosted instance's JSON API
+//! (`GET /search?q=…&format=json`). No API key is needed.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use skynet_core::config::SearxngConfig;
+
+use super::{SearchError, SearchProvider, SearchQuery, SearchResult};
+
+pub struct SearxngProvider {
+    client: reqwest::Client,
+    search_url: url::Url,
+    config: SearxngConfig,
+}
+
+#[derive(Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearxngResult {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    engine: Option<String>,
+    #[serde(default)]
+    published_date: Option<String>,
+}
+
+impl SearxngProvider {
+    pub fn new(config: &SearxngConfig, timeout_secs: u64) -> Result<Self, SearchError> {
+        let mut base = url::Url::parse(&config.url).map_err(|e| {
+            SearchError::Config(format!("invalid searxng url '{}': {e}", config.url))
+        })?;
+        // Keep a path prefix (`https://host/searx`) when joining.
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        let search_url = base
+            .join("search")
+            .map_err(|e| SearchError::Config(e.to_string()))?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout_secs))
+            .build()
+            .map_err(|e| SearchError::Config(e.to_string()))?;
+        Ok(Self {
+            client,
+            search_url,
+            config: config.clone(),
+        })
+    }
+
+    fn params(&self, query: &SearchQuery) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("q", query.query.clone()),
+            ("format", "json".to_string()),
+            ("pageno", "1".to_string()),
+        ];
+        if !self.config.engines.is_empty() {
+            params.push(("engines", self.config.engines.join(",")));
+        }
+        if !self.config.categories.is_empty() {
+            params.push(("categories", self.config.categories.join(",")));
+        }
+        if let Some(ref language) = self.config.language {
+            params.push(("language", language.clone()));
+        }
+        if let Some(safesearch) = self.config.safesearch {
+            params.push(("safesearch", safesearch.to_string()));
+        }
+        if let Some(range) = query.time_range {
+            params.push(("time_range", range.as_str().to_string()));
+        }
+        params
+    }
+}
+
+#[async_trait]
+impl SearchProvider for SearxngProvider {
+    fn name(&self) -> &str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchResult>, SearchError> {
+        let response = self
+            .client
+            .get(self.search_url.clone())
+            .query(&self.params(query))
+            .send()
+            .await
+            .map_err(|e| SearchError::Http(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = if status == reqwest::StatusCode::FORBIDDEN {
+                "the JSON API is disabled; add `json` to search.formats in SearXNG's settings.yml"
+                    .to_string()
+            } else {
+                response.text().await.unwrap_or_default()
+            };
+            return Err(SearchError::Status {
+                status: status.as_u16(),
+                message,
+            });
+        }
+        let body: SearxngResponse = response
+            .json()
+            .await
+            .map_err(|e| SearchError::Parse(e.to_string()))?;
+        Ok(body
+            .results
+            .into_iter()
+            .map(|r| SearchResult {
+                title: r.title,
+                url: r.url,
+                snippet: r.content.unwrap_or_default(),
+                source: r.engine,
+                published: r.published_date.filter(|d| !d.is_empty()),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::extract::Query;
+    use axum::http::StatusCode;
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::get;
+    use axum::{Json, Router};
+
+    use super::*;
+    use crate::search::TimeRange;
+
+    /// Mock `/search`: echoes the query parameters back in the first result.
+    async fn search(Query(params): Query<HashMap<String, String>>) -> Response {
+        if params.get("format").map(String::as_str) != Some("json") {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        let echo = serde_json::to_string(&params).unwrap();
+        Json(serde_json::json!({
+            "query": params["q"],
+            "results": [
+                {
+                    "url": "https://example.com/rust",
+                    "title": "Rust",
+                    "content": echo,
+                    "engine": "duckduckgo",
+                    "publishedDate": "2026-01-02T00:00:00",
+                },
+                { "url": "https://example.com/other", "title": "Other", "publishedDate": "" },
+            ],
+        }))
+        .into_response()
+    }
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn config(url: String) -> SearxngConfig {
+        SearxngConfig {
+            url,
+            engines: vec!["duckduckgo".into(), "wikipedia".into()],
+            categories: Vec::new(),
+            language: Some("en".into()),
+            safesearch: Some(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn queries_the_json_api() {
+        let url = serve(Router::new().route("/search", get(search))).await;
+        let provider = SearxngProvider::new(&config(url), 5).unwrap();
+        let results = provider
+            .search(&SearchQuery {
+                query: "rust async".into(),
+                limit: 5,
+                time_range: Some(TimeRange::Week),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].source.as_deref(), Some("duckduckgo"));
+        assert_eq!(results[0].published.as_deref(), Some("2026-01-02T00:00:00"));
+        assert_eq!(results[1].published, None);
+        let params: HashMap<String, String> = serde_json::from_str(&results[0].snippet).unwrap();
+        assert_eq!(params["q"], "rust async");
+        assert_eq!(params["engines"], "duckduckgo,wikipedia");
+        assert_eq!(params["language"], "en");
+        assert_eq!(params["safesearch"], "1");
+        assert_eq!(params["time_range"], "week");
+        assert!(!params.contains_key("categories"));
+    }
+
+    #[tokio::test]
+    async fn explains_disabled_json_format() {
+        let app = Router::new().route("/search", get(|| async { StatusCode::FORBIDDEN }));
+        let url = serve(app).await;
+        let provider = SearxngProvider::new(&config(url), 5).unwrap();
+        let err = provider
+            .search(&SearchQuery {
+                query: "x".into(),
+                limit: 5,
+                time_range: None,
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("settings.yml"), "{err}");
+    }
+}
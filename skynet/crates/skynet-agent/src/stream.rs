@@ -21,6 +21,8 @@ pub enum StreamEvent {
         model: String,
         tokens_in: u32,
         tokens_out: u32,
+        cache_write_tokens: u32,
+        cache_read_tokens: u32,
         stop_reason: String,
     },
 
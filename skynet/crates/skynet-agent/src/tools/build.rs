@@ -1,37 +1,116 @@
 //! Tool registry — builds the canonical tool list for any channel adapter.
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
+use crate::approval::{ApprovalGate, Requester, ToolPolicy};
+use crate::cancel::CancellationToken;
 use crate::pipeline::context::MessageContext;
 use crate::provider::ToolDefinition;
+use crate::routing::SenderInfo;
 
 use super::bash_session::BashSessionTool;
+use super::checkpoint::TurnCheckpoint;
+use super::edit_file::EditFileTool;
 use super::execute_command::ExecuteCommandTool;
 use super::knowledge::{KnowledgeSearchTool, KnowledgeWriteTool};
+use super::list_files::ListFilesTool;
+use super::patch_file::PatchFileTool;
+use super::read_file::ReadFileTool;
 use super::reminder::ReminderTool;
+use super::search_files::SearchFilesTool;
+use super::subagent::{SpawnSubagentTool, MAX_SUBAGENT_DEPTH};
+use super::web_fetch::WebFetchTool;
+use super::web_search::WebSearchTool;
+use super::workspace::Workspace;
+use super::write_file::WriteFileTool;
 use super::{to_definitions, Tool};
 
 /// Build the full list of tools available to the AI for a given request.
 ///
 /// Includes:
-/// - `read_file`, `write_file`, `list_files`, `search_files` (filesystem, skynet-agent)
+/// - `read_file`, `write_file`, `list_files`, `search_files`, `patch_file`,
+///   `edit_file` (filesystem, confined to the session's `[agent.workspace]`; writes need a
+///   fresh `read_file` of existing files, see `file_tracker`, and are checkpointed
+///   for `/undo`, see `checkpoint`)
 /// - `execute_command` (one-shot sh -c via TerminalManager)
 /// - `bash` (persistent PTY bash session via TerminalManager)
 /// - `reminder` (schedule proactive reminders via the scheduler)
+/// - `web_fetch` (download a page as Markdown, limited by `[agent.web_fetch]`)
+/// - `web_search` (query the `[search]` backend, when one is configured)
+/// - `spawn_subagent` (delegate a task to a nested agent)
+/// - script plugins from `[agent.plugins]` that the sender's permissions allow
+/// - `mcp_{server}_{tool}` (tools of connected MCP servers)
 ///
 /// `channel_name` and `channel_id` are forwarded to `ReminderTool` so it can
-/// embed the correct delivery target in the persisted job action.
+/// embed the correct delivery target in the persisted job action. `cancel` is
+/// the turn's token; `execute_command` kills its child when it fires. Each
+/// call is a new turn for file checkpoints.
+///
+/// `[agent.approval]` policies are applied last: `deny` tools are dropped and
+/// `ask` tools are wrapped in `ApprovalGate`, with approval requests recorded
+/// against `session_key` / the sender's user. Plugins whose permission check
+/// needs approval are gated the same way unless their policy is `deny`.
 pub fn build_tools<C: MessageContext + 'static>(
     ctx: Arc<C>,
     channel_name: &str,
     channel_id: Option<u64>,
+    session_key: &str,
+    sender: Option<&SenderInfo>,
+    cancel: &CancellationToken,
+) -> Vec<Box<dyn Tool>> {
+    let turn_id = uuid::Uuid::now_v7().to_string();
+    build_tools_at_depth(
+        ctx,
+        channel_name,
+        channel_id,
+        session_key,
+        sender,
+        cancel,
+        &turn_id,
+        0,
+    )
+}
+
+/// `build_tools` for an agent nested `depth` levels deep (0 = the main agent).
+/// `spawn_subagent` is only included below `MAX_SUBAGENT_DEPTH`. Subagents
+/// pass their parent's `turn_id`, so `/undo` rolls back their edits too.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_tools_at_depth<C: MessageContext + 'static>(
+    ctx: Arc<C>,
+    channel_name: &str,
+    channel_id: Option<u64>,
+    session_key: &str,
+    sender: Option<&SenderInfo>,
+    cancel: &CancellationToken,
+    turn_id: &str,
+    depth: usize,
 ) -> Vec<Box<dyn Tool>> {
+    let user_id = sender.and_then(|s| s.user_id.as_deref());
+    let workspace = Arc::new(Workspace::new(
+        ctx.agent().workspace(),
+        session_key,
+        user_id,
+    ));
+    let files = ctx.files().session(session_key);
+    let checkpoint = Arc::new(TurnCheckpoint::new(
+        ctx.checkpoints().cloned(),
+        session_key,
+        turn_id,
+    ));
     let mut tools: Vec<Box<dyn Tool>> = vec![
-        Box::new(super::read_file::ReadFileTool),
-        Box::new(super::write_file::WriteFileTool),
-        Box::new(super::list_files::ListFilesTool),
-        Box::new(super::search_files::SearchFilesTool),
-        Box::new(ExecuteCommandTool::new(Arc::clone(&ctx))),
+        Box::new(ReadFileTool::new(
+            Arc::clone(&workspace),
+            Arc::clone(&files),
+        )),
+        Box::new(WriteFileTool::new(
+            Arc::clone(&workspace),
+            Arc::clone(&files),
+            Arc::clone(&checkpoint),
+        )),
+        Box::new(ListFilesTool::new(Arc::clone(&workspace))),
+        Box::new(SearchFilesTool::new(Arc::clone(&workspace))),
+        Box::new(ExecuteCommandTool::new(Arc::clone(&ctx), cancel.clone())),
         Box::new(BashSessionTool::new(Arc::clone(&ctx))),
         Box::new(ReminderTool::new(
             Arc::clone(&ctx),
@@ -40,16 +119,65 @@ pub fn build_tools<C: MessageContext + 'static>(
         )),
         Box::new(KnowledgeSearchTool::new(Arc::clone(&ctx))),
         Box::new(KnowledgeWriteTool::new(Arc::clone(&ctx))),
-        Box::new(super::patch_file::PatchFileTool),
+        Box::new(PatchFileTool::new(
+            Arc::clone(&workspace),
+            Arc::clone(&files),
+            Arc::clone(&checkpoint),
+        )),
+        Box::new(EditFileTool::new(workspace, files, checkpoint)),
+        Box::new(WebFetchTool::new(ctx.agent().web_fetch().clone())),
     ];
+    if let Some(provider) = ctx.agent().search() {
+        tools.push(Box::new(WebSearchTool::new(
+            Arc::clone(provider),
+            ctx.agent().search_config().clone(),
+            ctx.agent().web_fetch().clone(),
+        )));
+    }
+
+    // Script plugins, checked against the sender's permissions.
+    let mut needs_approval: HashSet<String> = HashSet::new();
+    for plugin in ctx.plugins().tools(sender.and_then(|s| s.user.as_ref())) {
+        if plugin.needs_approval {
+            needs_approval.insert(plugin.tool.name().to_string());
+        }
+        tools.push(plugin.tool);
+    }
+
+    // Tools of every connected `[mcp.servers]` entry (`mcp_{server}_{tool}`).
+    tools.extend(ctx.mcp().tools());
 
-    // Load script plugins from ~/.skynet/tools/ — no restart needed after adding a plugin,
-    // tools are re-scanned on each build_tools() call (i.e. each new message).
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    let tools_dir = std::path::Path::new(&home).join(".skynet/tools");
-    tools.extend(super::script_tool::load_script_tools(&tools_dir));
+    if depth < MAX_SUBAGENT_DEPTH {
+        tools.push(Box::new(SpawnSubagentTool::new(
+            Arc::clone(&ctx),
+            channel_name,
+            channel_id,
+            session_key,
+            sender,
+            cancel.clone(),
+            turn_id,
+            depth,
+        )));
+    }
 
+    let requester = Requester {
+        session_key: session_key.to_string(),
+        channel: channel_name.to_string(),
+        user_id: user_id.map(str::to_string),
+    };
     tools
+        .into_iter()
+        .filter_map(|tool| match ctx.approvals().policy(tool.name()) {
+            ToolPolicy::Auto if !needs_approval.contains(tool.name()) => Some(tool),
+            ToolPolicy::Auto | ToolPolicy::Ask => Some(Box::new(ApprovalGate::new(
+                tool,
+                Arc::clone(&ctx),
+                requester.clone(),
+                cancel.clone(),
+            )) as Box<dyn Tool>),
+            ToolPolicy::Deny => None,
+        })
+        .collect()
 }
 
 /// Convert a tool list to API-level definitions for the LLM request.
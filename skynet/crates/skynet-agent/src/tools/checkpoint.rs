@@ -0,0 +1,110 @@
+//! Per-turn file checkpoints for the writing file tools.
+//!
+//! `write_file`, `patch_file` and `edit_file` call `before_write` right
+//! before they write, so the file's current content is in the
+//! `CheckpointStore` before it changes, and `after_write` once it has, so a
+//! restore can tell whether anyone edited the file since. `/undo` and
+//! `checkpoints.restore` roll it back from there.
+
+use std::path::Path;
+
+use tracing::warn;
+
+use skynet_sessions::CheckpointStore;
+
+/// Snapshots taken on behalf of one turn (and its subagents).
+pub struct TurnCheckpoint {
+    /// `None` when `[agent.checkpoints]` is disabled.
+    store: Option<CheckpointStore>,
+    session_key: String,
+    turn_id: String,
+}
+
+impl TurnCheckpoint {
+    pub fn new(store: Option<CheckpointStore>, session_key: &str, turn_id: &str) -> Self {
+        Self {
+            store,
+            session_key: session_key.to_string(),
+            turn_id: turn_id.to_string(),
+        }
+    }
+
+    /// Checkpointing turned off.
+    pub fn disabled() -> Self {
+        Self::new(None, "", "")
+    }
+
+    /// Snapshot `path` before it is modified. A failed snapshot blocks the
+    /// write, since it could not be undone; `display` is the path as the
+    /// model gave it.
+    pub fn before_write(&self, path: &Path, display: &str) -> Result<(), String> {
+        let Some(ref store) = self.store else {
+            return Ok(());
+        };
+        store
+            .snapshot(&self.session_key, &self.turn_id, path)
+            .map_err(|e| {
+                warn!(error = %e, path = %path.display(), "file checkpoint failed");
+                format!("cannot checkpoint '{display}' before writing it: {e}")
+            })
+    }
+
+    /// Record what was just written to `path`. A failure only means a later
+    /// undo treats the file as changed by someone else and leaves it alone.
+    pub fn after_write(&self, path: &Path, content: &[u8]) {
+        let Some(ref store) = self.store else {
+            return;
+        };
+        if let Err(e) = store.record_write(&self.turn_id, path, content) {
+            warn!(error = %e, path = %path.display(), "recording file checkpoint write failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::tools::file_tracker::SessionFiles;
+    use crate::tools::patch_file::PatchFileTool;
+    use crate::tools::workspace::Workspace;
+    use crate::tools::write_file::WriteFileTool;
+    use crate::tools::Tool;
+
+    #[tokio::test]
+    async fn file_tools_snapshot_before_writing() {
+        let dir = std::env::temp_dir().join(format!("skynet-turn-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        skynet_sessions::db::init_db(&conn).unwrap();
+        let store = CheckpointStore::new(conn, dir.join("blobs"), 10);
+        let existing = dir.join("a.txt");
+        let created = dir.join("b.txt");
+        std::fs::write(&existing, "before").unwrap();
+
+        let workspace = Arc::new(Workspace::default());
+        let files = Arc::new(SessionFiles::default());
+        files.record(&existing, b"before");
+        let turn = Arc::new(TurnCheckpoint::new(Some(store.clone()), "s", "t1"));
+        let patch = PatchFileTool::new(
+            Arc::clone(&workspace),
+            Arc::clone(&files),
+            Arc::clone(&turn),
+        );
+        let write = WriteFileTool::new(workspace, files, turn);
+
+        let input =
+            serde_json::json!({ "path": existing, "old_string": "before", "new_string": "after" });
+        assert!(!patch.execute(input).await.is_error);
+        let input = serde_json::json!({ "path": created, "content": "new" });
+        assert!(!write.execute(input).await.is_error);
+
+        let report = store.restore("s", None).unwrap();
+        assert_eq!(report.turns, ["t1"]);
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "before");
+        assert!(!created.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
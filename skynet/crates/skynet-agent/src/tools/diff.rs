@@ -0,0 +1,179 @@
+//! Line-based unified diffs for tool output.
+//!
+//! The common prefix and suffix are stripped first, and only the changed
+//! middle goes through an LCS table. Edits are usually local, so that table
+//! stays small. Past `MAX_TABLE_CELLS` the middle is shown as a plain delete
+//! plus insert.
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+/// Largest LCS table built; bigger middles are not aligned line by line.
+const MAX_TABLE_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op<'a> {
+    Keep(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Unified diff from `old` to `new` with `--- a/{label}` / `+++ b/{label}`
+/// headers. Empty when the texts are equal.
+pub fn unified_diff(old: &str, new: &str, label: &str) -> String {
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = diff_lines(&a, &b);
+
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        if matches!(op, Op::Keep(_)) {
+            continue;
+        }
+        match groups.last_mut() {
+            Some((_, last)) if i - *last <= 2 * CONTEXT + 1 => *last = i,
+            _ => groups.push((i, i)),
+        }
+    }
+    if groups.is_empty() {
+        return String::new();
+    }
+
+    // Lines of `old` / `new` before each op.
+    let mut old_pos = Vec::with_capacity(ops.len());
+    let mut new_pos = Vec::with_capacity(ops.len());
+    let (mut o, mut n) = (0, 0);
+    for op in &ops {
+        old_pos.push(o);
+        new_pos.push(n);
+        match op {
+            Op::Keep(_) => (o, n) = (o + 1, n + 1),
+            Op::Delete(_) => o += 1,
+            Op::Insert(_) => n += 1,
+        }
+    }
+
+    let mut out = format!("--- a/{label}\n+++ b/{label}\n");
+    for (first, last) in groups {
+        let lo = first.saturating_sub(CONTEXT);
+        let hi = (last + CONTEXT + 1).min(ops.len());
+        let hunk = &ops[lo..hi];
+        let old_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Insert(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Delete(_)))
+            .count();
+        let start = |pos: usize, len: usize| if len == 0 { pos } else { pos + 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            start(old_pos[lo], old_len),
+            old_len,
+            start(new_pos[lo], new_len),
+            new_len
+        ));
+        for op in hunk {
+            let (sign, line) = match op {
+                Op::Keep(line) => (' ', line),
+                Op::Delete(line) => ('-', line),
+                Op::Insert(line) => ('+', line),
+            };
+            out.push(sign);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+fn diff_lines<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<Op<'a>> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<Op> = a[..prefix].iter().map(|l| Op::Keep(l)).collect();
+    if a_mid.len().saturating_mul(b_mid.len()) > MAX_TABLE_CELLS {
+        ops.extend(a_mid.iter().map(|l| Op::Delete(l)));
+        ops.extend(b_mid.iter().map(|l| Op::Insert(l)));
+    } else {
+        ops.extend(lcs_ops(a_mid, b_mid));
+    }
+    ops.extend(a[a.len() - suffix..].iter().map(|l| Op::Keep(l)));
+    ops
+}
+
+/// Line ops from an LCS table of suffix lengths; deletions come before
+/// insertions within a changed run.
+fn lcs_ops<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<Op<'a>> {
+    let width = b.len() + 1;
+    let mut table = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i * width + j] = if a[i] == b[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(a.len() + b.len());
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push(Op::Keep(a[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if j == b.len()
+            || (i < a.len() && table[(i + 1) * width + j] >= table[i * width + j + 1])
+        {
+            ops.push(Op::Delete(a[i]));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(b[j]));
+            j += 1;
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_with_context_and_separate_hunks() {
+        let old: String = (1..=20).map(|i| format!("line {i}\n")).collect();
+        let new = old
+            .replace("line 2\n", "line two\n")
+            .replace("line 15\n", "")
+            .replace("line 20\n", "line 20\nline 21\n");
+        assert_eq!(
+            unified_diff(&old, &new, "f.txt"),
+            "--- a/f.txt\n+++ b/f.txt\n\
+             @@ -1,5 +1,5 @@\n line 1\n-line 2\n+line two\n line 3\n line 4\n line 5\n\
+             @@ -12,9 +12,9 @@\n line 12\n line 13\n line 14\n-line 15\n line 16\n line 17\n \
+             line 18\n line 19\n line 20\n+line 21\n"
+        );
+        assert_eq!(unified_diff(&old, &old, "f.txt"), "");
+    }
+
+    #[test]
+    fn marks_missing_final_newline() {
+        assert_eq!(
+            unified_diff("a\nb", "a\nc", "x"),
+            "--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n\
+             +c\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            unified_diff("", "new\n", "x"),
+            "--- a/x\n+++ b/x\n@@ -0,0 +1,1 @@\n+new\n"
+        );
+    }
+}
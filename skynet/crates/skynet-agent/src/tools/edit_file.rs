@@ -0,0 +1,577 @@
+//! Tool: edit_file — several find/replace edits in one call, with fuzzy
+//! matching and a unified diff of the result.
+//!
+//! Each edit's `old_string` is looked up in three steps:
+//!   1. Exact match. More than one occurrence is an error unless
+//!      `replace_all` is set.
+//!   2. Otherwise, every run of as many lines as `old_string` has is scored
+//!      by edit distance, ignoring leading and repeated whitespace. The best
+//!      run at or above `MIN_SIMILARITY` wins. Another run that does not
+//!      overlap it and scores within `AMBIGUITY_MARGIN` makes the edit
+//!      ambiguous instead.
+//!   3. A fuzzy match replaces whole lines. When the model's indentation was
+//!      off, `new_string` is re-indented by the same difference.
+//!
+//! Edits apply in order to the evolving text. Nothing is written unless all
+//! of them match; then the file is replaced atomically and the diff returned.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::checkpoint::TurnCheckpoint;
+use super::diff::unified_diff;
+use super::file_tracker::SessionFiles;
+use super::workspace::{Access, Workspace};
+use super::{Tool, ToolResult};
+
+/// Lowest similarity (0–1) accepted for a fuzzy match.
+const MIN_SIMILARITY: f64 = 0.85;
+/// A second match this close to the best one makes an edit ambiguous.
+const AMBIGUITY_MARGIN: f64 = 0.02;
+/// Diff lines returned; the rest is cut off.
+const MAX_DIFF_LINES: usize = 300;
+
+pub struct EditFileTool {
+    workspace: Arc<Workspace>,
+    files: Arc<SessionFiles>,
+    checkpoint: Arc<TurnCheckpoint>,
+}
+
+impl EditFileTool {
+    pub fn new(
+        workspace: Arc<Workspace>,
+        files: Arc<SessionFiles>,
+        checkpoint: Arc<TurnCheckpoint>,
+    ) -> Self {
+        Self {
+            workspace,
+            files,
+            checkpoint,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for EditFileTool {
+    fn name(&self) -> &str {
+        "edit_file"
+    }
+
+    fn description(&self) -> &str {
+        "Edit a file with one or more find/replace edits, applied in order. Copy each \
+         old_string from read_file output; small whitespace, indentation or typing \
+         differences are tolerated, but an old_string that matches several places is \
+         rejected — add context to make it unique. The file is only written if every \
+         edit matches. Returns a unified diff of the change, which can be shown to the \
+         user as-is."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Absolute path, or relative to the workspace, of the file to edit."
+                },
+                "edits": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "old_string": {
+                                "type": "string",
+                                "description": "Text to find, copied from read_file output."
+                            },
+                            "new_string": {
+                                "type": "string",
+                                "description": "Replacement text. Empty to delete old_string."
+                            },
+                            "replace_all": {
+                                "type": "boolean",
+                                "description": "Replace every exact occurrence. Default false."
+                            }
+                        },
+                        "required": ["old_string", "new_string"]
+                    }
+                }
+            },
+            "required": ["path", "edits"]
+        })
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let workspace = Arc::clone(&self.workspace);
+        let files = Arc::clone(&self.files);
+        let checkpoint = Arc::clone(&self.checkpoint);
+        super::run_blocking(move || edit_file(&workspace, &files, &checkpoint, input)).await
+    }
+}
+
+struct Edit {
+    old: String,
+    new: String,
+    replace_all: bool,
+}
+
+fn parse_edits(input: &serde_json::Value) -> Result<Vec<Edit>, String> {
+    let edits = match input.get("edits").and_then(|v| v.as_array()) {
+        Some(edits) if !edits.is_empty() => edits,
+        _ => return Err("missing required parameter: edits".to_string()),
+    };
+    edits
+        .iter()
+        .enumerate()
+        .map(|(i, edit)| {
+            let field = |name: &str| {
+                edit.get(name)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("edit {}: missing {name}", i + 1))
+            };
+            Ok(Edit {
+                old: field("old_string")?,
+                new: field("new_string")?,
+                replace_all: edit
+                    .get("replace_all")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+fn edit_file(
+    workspace: &Workspace,
+    files: &SessionFiles,
+    checkpoint: &TurnCheckpoint,
+    input: serde_json::Value,
+) -> ToolResult {
+    let path = match input.get("path").and_then(|v| v.as_str()) {
+        Some(p) => p.to_string(),
+        None => return ToolResult::error("missing required parameter: path"),
+    };
+    let edits = match parse_edits(&input) {
+        Ok(edits) => edits,
+        Err(e) => return ToolResult::error(e),
+    };
+    let resolved = match workspace.resolve("edit_file", &path, Access::Write) {
+        Ok(p) => p,
+        Err(e) => return ToolResult::error(e),
+    };
+    if let Err(e) = files.check_write(&resolved, &path) {
+        return ToolResult::error(e);
+    }
+    let original = match std::fs::read_to_string(&resolved) {
+        Ok(c) => c,
+        Err(e) => return ToolResult::error(format!("failed to read '{}': {}", path, e)),
+    };
+
+    let mut content = original.clone();
+    let mut notes = Vec::new();
+    for (i, edit) in edits.iter().enumerate() {
+        match apply_edit(&content, edit) {
+            Ok((updated, note)) => {
+                content = updated;
+                if let Some(note) = note {
+                    notes.push(format!("Edit {}: {note}.", i + 1));
+                }
+            }
+            Err(e) => {
+                return ToolResult::error(format!(
+                    "edit {} of {} in '{}': {e} No edits were written.",
+                    i + 1,
+                    edits.len(),
+                    path
+                ))
+            }
+        }
+    }
+    if content == original {
+        return ToolResult::success(format!("No changes: '{path}' already has that content."));
+    }
+
+    if let Err(e) = checkpoint.before_write(&resolved, &path) {
+        return ToolResult::error(e);
+    }
+
+    // Write atomically: temp file + rename so a crash mid-write never corrupts the original.
+    let tmp_path = format!("{}.skynet_edit_tmp", resolved.display());
+    if let Err(e) = std::fs::write(&tmp_path, &content) {
+        return ToolResult::error(format!("failed to write temp file '{}': {}", tmp_path, e));
+    }
+    if let Err(e) = std::fs::rename(&tmp_path, &resolved) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return ToolResult::error(format!("failed to rename temp file to '{}': {}", path, e));
+    }
+    files.record_edit(&resolved, content.as_bytes());
+    checkpoint.after_write(&resolved, content.as_bytes());
+
+    let mut output = format!("Edited '{}': {} edit(s) applied.", path, edits.len());
+    for note in notes {
+        output.push('\n');
+        output.push_str(&note);
+    }
+    output.push_str("\n\n```diff\n");
+    output.push_str(&truncate_lines(&unified_diff(&original, &content, &path)));
+    output.push_str("```");
+    ToolResult::success(output)
+}
+
+/// `content` with `edit` applied, plus a note when the match was fuzzy.
+fn apply_edit(content: &str, edit: &Edit) -> Result<(String, Option<String>), String> {
+    if edit.old.is_empty() {
+        return Err("old_string is empty.".to_string());
+    }
+    let exact: Vec<usize> = content
+        .match_indices(edit.old.as_str())
+        .map(|(i, _)| i)
+        .collect();
+    if edit.replace_all {
+        if exact.is_empty() {
+            return Err(
+                "replace_all needs an exact match, and old_string was not found. \
+                 Use read_file and copy the text verbatim."
+                    .to_string(),
+            );
+        }
+        return Ok((content.replace(edit.old.as_str(), &edit.new), None));
+    }
+    match exact.as_slice() {
+        [at] => {
+            let mut updated = content.to_string();
+            updated.replace_range(*at..at + edit.old.len(), &edit.new);
+            return Ok((updated, None));
+        }
+        [] => {}
+        many => {
+            let lines: Vec<String> = many
+                .iter()
+                .map(|&at| line_of(content, at).to_string())
+                .collect();
+            return Err(format!(
+                "old_string matches {} times (lines {}). Add surrounding context to make \
+                 it unique, or set replace_all.",
+                many.len(),
+                lines.join(", ")
+            ));
+        }
+    }
+
+    let found = fuzzy_find(content, &edit.old)?;
+    let mut updated = content.to_string();
+    let replacement = reindent(&found, &edit.old, &edit.new, content.contains("\r\n"));
+    let mut range = found.range.clone();
+    // Deleting whole lines: take the line break too.
+    if replacement.is_empty() && edit.old.ends_with('\n') {
+        range.end = next_line_start(content, range.end);
+    }
+    updated.replace_range(range, &replacement);
+    Ok((
+        updated,
+        Some(format!(
+            "matched line {} at {:.0}% similarity",
+            found.line,
+            found.similarity * 100.0
+        )),
+    ))
+}
+
+struct FuzzyMatch {
+    /// Bytes of the matched lines, without the last line break.
+    range: Range<usize>,
+    /// 1-based first line.
+    line: usize,
+    similarity: f64,
+    /// Indentation of the first non-blank line: (in old_string, in the file).
+    indent: (String, String),
+}
+
+fn fuzzy_find(content: &str, old: &str) -> Result<FuzzyMatch, String> {
+    let spans = line_spans(content);
+    let old_lines: Vec<&str> = old.lines().collect();
+    let n = old_lines.len();
+    let not_found = || {
+        format!(
+            "old_string not found, and no part of the file is at least {:.0}% similar. \
+             Use read_file and copy the text.",
+            MIN_SIMILARITY * 100.0
+        )
+    };
+    if n == 0 || n > spans.len() {
+        return Err(not_found());
+    }
+
+    let norm_old: Vec<Vec<char>> = old_lines.iter().map(|l| normalize(l)).collect();
+    let norm_file: Vec<Vec<char>> = spans
+        .iter()
+        .map(|s| normalize(&content[s.clone()]))
+        .collect();
+    let candidates: Vec<(usize, f64)> = (0..=spans.len() - n)
+        .filter_map(|i| similarity(&norm_old, &norm_file[i..i + n]).map(|s| (i, s)))
+        .collect();
+    let Some(&(best, score)) = candidates
+        .iter()
+        .reduce(|best, c| if c.1 > best.1 { c } else { best })
+    else {
+        return Err(not_found());
+    };
+    let rivals: Vec<String> = candidates
+        .iter()
+        .filter(|(i, s)| i.abs_diff(best) >= n && *s >= score - AMBIGUITY_MARGIN)
+        .map(|(i, _)| (i + 1).to_string())
+        .collect();
+    if !rivals.is_empty() {
+        return Err(format!(
+            "old_string is not an exact match and is about equally similar to lines {} \
+             and {}. Add surrounding context to make it unique.",
+            best + 1,
+            rivals.join(", ")
+        ));
+    }
+
+    let first = old_lines
+        .iter()
+        .position(|l| !l.trim().is_empty())
+        .unwrap_or(0);
+    Ok(FuzzyMatch {
+        range: spans[best].start..spans[best + n - 1].end,
+        line: best + 1,
+        similarity: score,
+        indent: (
+            leading_whitespace(old_lines[first]).to_string(),
+            leading_whitespace(&content[spans[best + first].clone()]).to_string(),
+        ),
+    })
+}
+
+/// `new` as it replaces a fuzzy match: without the final line break (the
+/// match excludes it), shifted to the file's indentation and line endings.
+fn reindent(found: &FuzzyMatch, old: &str, new: &str, crlf: bool) -> String {
+    let new = if old.ends_with('\n') {
+        new.strip_suffix('\n').unwrap_or(new)
+    } else {
+        new
+    };
+    let (from, to) = &found.indent;
+    let lines: Vec<String> = new
+        .split('\n')
+        .map(|line| {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            match line.strip_prefix(from.as_str()) {
+                Some(rest) if from != to && !line.trim().is_empty() => format!("{to}{rest}"),
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    lines.join(if crlf { "\r\n" } else { "\n" })
+}
+
+/// Byte ranges of each line, without line breaks.
+fn line_spans(content: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        spans.push(start..start + text.len());
+        start += line.len();
+    }
+    spans
+}
+
+fn next_line_start(content: &str, at: usize) -> usize {
+    content[at..]
+        .find('\n')
+        .map_or(content.len(), |i| at + i + 1)
+}
+
+fn line_of(content: &str, at: usize) -> usize {
+    content[..at].matches('\n').count() + 1
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// A line as compared for fuzzy matching: trimmed, whitespace runs collapsed.
+fn normalize(line: &str) -> Vec<char> {
+    line.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect()
+}
+
+/// Similarity of two equally long runs of normalized lines: one minus the
+/// summed edit distance over the summed line lengths. `None` below
+/// `MIN_SIMILARITY`, which lets the distance computation stop early.
+fn similarity(old: &[Vec<char>], window: &[Vec<char>]) -> Option<f64> {
+    let total: usize = old
+        .iter()
+        .zip(window)
+        .map(|(a, b)| a.len().max(b.len()))
+        .sum();
+    if total == 0 {
+        return Some(1.0);
+    }
+    let budget = ((1.0 - MIN_SIMILARITY) * total as f64) as usize;
+    let mut spent = 0;
+    for (a, b) in old.iter().zip(window) {
+        spent += bounded_distance(a, b, budget - spent)?;
+    }
+    Some(1.0 - spent as f64 / total as f64)
+}
+
+/// Levenshtein distance between `a` and `b`, or `None` once it exceeds `max`.
+fn bounded_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        let mut row_min = cur[0];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = substitute.min(prev[j + 1] + 1).min(cur[j] + 1);
+            row_min = row_min.min(cur[j + 1]);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    Some(prev[b.len()]).filter(|&d| d <= max)
+}
+
+fn truncate_lines(diff: &str) -> String {
+    let lines: Vec<&str> = diff.lines().collect();
+    if lines.len() <= MAX_DIFF_LINES {
+        return diff.to_string();
+    }
+    format!(
+        "{}\n[… {} more diff lines]\n",
+        lines[..MAX_DIFF_LINES].join("\n"),
+        lines.len() - MAX_DIFF_LINES
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(old: &str, new: &str) -> Edit {
+        Edit {
+            old: old.to_string(),
+            new: new.to_string(),
+            replace_all: false,
+        }
+    }
+
+    const SOURCE: &str = "fn main() {\n    let total = add(1, 2);\n    println!(\"{}\", total);\n}\n\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+
+    #[test]
+    fn matches_exactly_then_fuzzily() {
+        let (out, note) = apply_edit(SOURCE, &edit("add(1, 2)", "add(2, 3)")).unwrap();
+        assert!(out.contains("add(2, 3)") && note.is_none());
+
+        // Wrong indentation and a typo; new_string follows the model's indentation.
+        let (out, note) = apply_edit(
+            SOURCE,
+            &edit(
+                "let total = add(1, 2);\nprintln!(\"{}\", totl);\n",
+                "let total = add(1, 2);\nprintln!(\"total = {}\", total);\n",
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            SOURCE.replace("\"{}\", total", "\"total = {}\", total"),
+            "{out}"
+        );
+        assert!(note.unwrap().starts_with("matched line 2 at 9"));
+
+        let err = apply_edit(SOURCE, &edit("completely different text", "x")).unwrap_err();
+        assert!(err.contains("not found"), "{err}");
+    }
+
+    #[test]
+    fn rejects_ambiguous_matches() {
+        let text = "a {\n    value = 1\n}\nb {\n    value = 1\n}\n";
+        let err = apply_edit(text, &edit("value = 1", "value = 2")).unwrap_err();
+        assert!(err.contains("matches 2 times (lines 2, 5)"), "{err}");
+        let err = apply_edit(text, &edit("  value  = 1", "value = 2")).unwrap_err();
+        assert!(err.contains("lines 2 and 5"), "{err}");
+
+        let all = Edit {
+            replace_all: true,
+            ..edit("value = 1", "value = 2")
+        };
+        let (out, _) = apply_edit(text, &all).unwrap();
+        assert_eq!(out.matches("value = 2").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn applies_all_edits_or_none_and_returns_a_diff() {
+        let dir = std::env::temp_dir().join(format!("skynet-edit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("main.rs");
+        std::fs::write(&file, SOURCE).unwrap();
+        let files = Arc::new(SessionFiles::default());
+        files.record(&file, SOURCE.as_bytes());
+        let tool = EditFileTool::new(
+            Arc::new(Workspace::default()),
+            Arc::clone(&files),
+            Arc::new(TurnCheckpoint::disabled()),
+        );
+        let path = file.to_string_lossy().into_owned();
+
+        let result = tool
+            .execute(serde_json::json!({ "path": path, "edits": [
+                { "old_string": "add(1, 2)", "new_string": "add(2, 3)" },
+                { "old_string": "no such line", "new_string": "x" },
+            ]}))
+            .await;
+        assert!(result.is_error);
+        assert!(
+            result.content.starts_with("edit 2 of 2"),
+            "{}",
+            result.content
+        );
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), SOURCE);
+
+        let result = tool
+            .execute(serde_json::json!({ "path": path, "edits": [
+                { "old_string": "add(1, 2)", "new_string": "add(2, 3)" },
+                {
+                    "old_string": "  fn add(a: i32, b: i32) -> i32{\n      a + b\n",
+                    "new_string": "  fn add(a: i32, b: i32) -> i32 {\n      a.saturating_add(b)\n",
+                },
+            ]}))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        let (summary, diff) = result.content.split_once("\n\n").unwrap();
+        assert_eq!(
+            summary,
+            format!(
+                "Edited '{path}': 2 edit(s) applied.\nEdit 2: matched line 6 at 97% similarity."
+            )
+        );
+        assert_eq!(
+            diff,
+            format!(
+                "```diff\n--- a/{path}\n+++ b/{path}\n@@ -1,8 +1,8 @@\n fn main() {{\n\
+                 -    let total = add(1, 2);\n+    let total = add(2, 3);\n     println!(\"{{}}\", total);\n\
+                 \x20}}\n \n fn add(a: i32, b: i32) -> i32 {{\n-    a + b\n+    a.saturating_add(b)\n }}\n```"
+            )
+        );
+        // The write was recorded, so a second call needs no re-read.
+        assert!(files.check_write(&file, &path).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
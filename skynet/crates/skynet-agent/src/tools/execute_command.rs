@@ -7,6 +7,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 
+use crate::cancel::CancellationToken;
 use crate::pipeline::context::MessageContext;
 
 use super::{Tool, ToolResult};
@@ -14,14 +15,16 @@ use super::{Tool, ToolResult};
 /// Tool that executes shell commands via the terminal subsystem.
 ///
 /// Respects the safety checker (denylist/allowlist) and timeout enforcement
-/// built into `TerminalManager`.
+/// built into `TerminalManager`. The running command is killed when the
+/// turn's `cancel` token fires.
 pub struct ExecuteCommandTool<C: MessageContext + 'static> {
     ctx: Arc<C>,
+    cancel: CancellationToken,
 }
 
 impl<C: MessageContext + 'static> ExecuteCommandTool<C> {
-    pub fn new(ctx: Arc<C>) -> Self {
-        Self { ctx }
+    pub fn new(ctx: Arc<C>, cancel: CancellationToken) -> Self {
+        Self { ctx, cancel }
     }
 }
 
@@ -56,7 +59,10 @@ impl<C: MessageContext + 'static> Tool for ExecuteCommandTool<C> {
             None => return ToolResult::error("missing required parameter: command"),
         };
 
-        let opts = skynet_terminal::ExecOptions::default();
+        let opts = skynet_terminal::ExecOptions {
+            cancel: Some(self.cancel.clone()),
+            ..Default::default()
+        };
         match self.ctx.terminal().lock().await.exec(command, opts).await {
             Ok(result) => {
                 let mut output = String::new();
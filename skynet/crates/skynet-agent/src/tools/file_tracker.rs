@@ -0,0 +1,313 @@
+//! Read-before-write tracking for the file tools.
+//!
+//! `read_file` records the size, mtime and SHA-256 of every file it reads,
+//! per session. `write_file`, `patch_file` and `edit_file` refuse to touch an
+//! existing file that the session has not read, or that changed on disk
+//! since, so the agent never overwrites edits it has not seen. A read that
+//! was truncated or limited to a line range counts as partial: it allows
+//! targeted edits, but `write_file` needs the whole file to have been read.
+//! Successful writes record the new content, so consecutive edits need no
+//! re-read.
+//!
+//! Both maps are bounded: past `MAX_SESSIONS` sessions or `MAX_FILES` files
+//! per session the least recently used entry is dropped, which only means
+//! the agent has to read that file again.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+use sha2::{Digest, Sha256};
+
+/// Sessions tracked at once.
+const MAX_SESSIONS: usize = 256;
+/// Files tracked per session.
+const MAX_FILES: usize = 1024;
+
+/// What a session last saw of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: [u8; 32],
+    /// Only part of the content reached the model.
+    partial: bool,
+    used: Instant,
+}
+
+/// Per-session file stamps, shared by every turn of the process.
+#[derive(Default)]
+pub struct FileTracker {
+    sessions: Mutex<HashMap<String, (Arc<SessionFiles>, Instant)>>,
+}
+
+impl FileTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The files seen in `session_key` (subagents share their parent's).
+    pub fn session(&self, session_key: &str) -> Arc<SessionFiles> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if !sessions.contains_key(session_key) {
+            evict_oldest(&mut sessions, MAX_SESSIONS, |(_, used)| *used);
+        }
+        let (files, used) = sessions
+            .entry(session_key.to_string())
+            .or_insert_with(|| (Arc::default(), Instant::now()));
+        *used = Instant::now();
+        Arc::clone(files)
+    }
+}
+
+/// Drop least recently used entries until one more fits under `cap`.
+fn evict_oldest<K, V>(map: &mut HashMap<K, V>, cap: usize, used: impl Fn(&V) -> Instant)
+where
+    K: Clone + Eq + std::hash::Hash,
+{
+    while map.len() >= cap {
+        let Some(oldest) = map
+            .iter()
+            .min_by_key(|(_, v)| used(v))
+            .map(|(k, _)| k.clone())
+        else {
+            return;
+        };
+        map.remove(&oldest);
+    }
+}
+
+/// Files one session has read or written, keyed by canonical path.
+#[derive(Default)]
+pub struct SessionFiles {
+    seen: Mutex<HashMap<PathBuf, FileStamp>>,
+}
+
+impl SessionFiles {
+    /// Remember `content` as the current state of `path`, read or written
+    /// in full.
+    pub fn record(&self, path: &Path, content: &[u8]) {
+        self.insert(path, content, |_| false);
+    }
+
+    /// Remember `content` as the current state of `path` when the model was
+    /// shown only part of it. A full view of the same content is kept.
+    pub fn record_partial(&self, path: &Path, content: &[u8]) {
+        let hash: [u8; 32] = Sha256::digest(content).into();
+        self.insert(path, content, |prev| {
+            prev.is_none_or(|p| p.partial || p.hash != hash)
+        });
+    }
+
+    /// Remember `content` after a targeted edit. The model has seen as much
+    /// of the file as before, so a partial read stays partial.
+    pub fn record_edit(&self, path: &Path, content: &[u8]) {
+        self.insert(path, content, |prev| prev.is_some_and(|p| p.partial));
+    }
+
+    fn insert(&self, path: &Path, content: &[u8], partial: impl Fn(Option<&FileStamp>) -> bool) {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut seen = self.seen.lock().unwrap();
+        let stamp = FileStamp {
+            len: content.len() as u64,
+            modified,
+            hash: Sha256::digest(content).into(),
+            partial: partial(seen.get(path)),
+            used: Instant::now(),
+        };
+        if !seen.contains_key(path) {
+            evict_oldest(&mut seen, MAX_FILES, |s| s.used);
+        }
+        seen.insert(path.to_path_buf(), stamp);
+    }
+
+    /// Whether `path` may be overwritten as a whole: as `check_write`, and
+    /// the session has read all of it, not just a truncated or ranged part.
+    pub fn check_overwrite(&self, path: &Path, display: &str) -> Result<(), String> {
+        self.check_write(path, display)?;
+        let partial = self
+            .seen
+            .lock()
+            .unwrap()
+            .get(path)
+            .is_some_and(|s| s.partial);
+        if partial {
+            return Err(format!(
+                "Only part of '{display}' has been read in this session (the output \
+                 was truncated or limited by offset/limit). Use edit_file or \
+                 patch_file to change it, or read the whole file before overwriting it."
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether `path` may be edited: it does not exist, or it is unchanged
+    /// since this session last read or wrote it. The error tells the model
+    /// to re-read the file; `display` is the path as it gave it.
+    pub fn check_write(&self, path: &Path, display: &str) -> Result<(), String> {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return Ok(());
+        };
+        let Some(stamp) = self.seen.lock().unwrap().get(path).cloned() else {
+            return Err(format!(
+                "'{display}' has not been read in this session. \
+                 Use read_file first, then retry."
+            ));
+        };
+        let modified = metadata.modified().ok();
+        if metadata.len() == stamp.len && modified == stamp.modified {
+            return Ok(());
+        }
+        // Touched but maybe not changed (e.g. saved without edits).
+        let unchanged = metadata.len() == stamp.len
+            && std::fs::read(path)
+                .map(|content| <[u8; 32]>::from(Sha256::digest(&content)) == stamp.hash)
+                .unwrap_or(false);
+        if unchanged {
+            self.seen
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), FileStamp { modified, ..stamp });
+            return Ok(());
+        }
+        Err(format!(
+            "'{display}' changed on disk since it was last read. \
+             Use read_file to see the current content, then retry."
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::tools::checkpoint::TurnCheckpoint;
+    use crate::tools::patch_file::PatchFileTool;
+    use crate::tools::read_file::ReadFileTool;
+    use crate::tools::workspace::Workspace;
+    use crate::tools::write_file::WriteFileTool;
+    use crate::tools::Tool;
+
+    #[tokio::test]
+    async fn writes_require_a_fresh_read() {
+        let dir = std::env::temp_dir().join(format!("skynet-files-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.md").to_string_lossy().into_owned();
+        std::fs::write(&file, "one\n").unwrap();
+
+        let workspace = Arc::new(Workspace::default());
+        let tracker = FileTracker::new();
+        let files = tracker.session("s");
+        let checkpoint = Arc::new(TurnCheckpoint::disabled());
+        let read = ReadFileTool::new(Arc::clone(&workspace), Arc::clone(&files));
+        let write = WriteFileTool::new(
+            Arc::clone(&workspace),
+            Arc::clone(&files),
+            Arc::clone(&checkpoint),
+        );
+        let patch = PatchFileTool::new(
+            Arc::clone(&workspace),
+            Arc::clone(&files),
+            Arc::clone(&checkpoint),
+        );
+        let read_input = serde_json::json!({ "path": file });
+        let edit = |old: &str, new: &str| serde_json::json!({ "path": file, "old_string": old, "new_string": new });
+
+        // Existing file, never read.
+        let result = write
+            .execute(serde_json::json!({ "path": file, "content": "x" }))
+            .await;
+        assert!(result.content.contains("has not been read"), "{result:?}");
+
+        // Read, then two edits in a row.
+        assert!(!read.execute(read_input.clone()).await.is_error);
+        assert!(!patch.execute(edit("one", "two")).await.is_error);
+        assert!(!patch.execute(edit("two", "three")).await.is_error);
+
+        // Another session has not read it.
+        let other = PatchFileTool::new(Arc::clone(&workspace), tracker.session("t"), checkpoint);
+        assert!(other.execute(edit("three", "four")).await.is_error);
+
+        // A human edits the file in parallel.
+        std::thread::sleep(Duration::from_millis(10));
+        std::fs::write(&file, "three, edited\n").unwrap();
+        let result = patch.execute(edit("three", "four")).await;
+        assert!(result.content.contains("changed on disk"), "{result:?}");
+
+        // Touched without changes is fine.
+        assert!(!read.execute(read_input).await.is_error);
+        std::thread::sleep(Duration::from_millis(10));
+        std::fs::write(&file, "three, edited\n").unwrap();
+        assert!(!patch.execute(edit("edited", "merged")).await.is_error);
+
+        // New files need no read.
+        let new_file = dir.join("new.md").to_string_lossy().into_owned();
+        let result = write
+            .execute(serde_json::json!({ "path": new_file, "content": "x" }))
+            .await;
+        assert!(!result.is_error, "{result:?}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn overwrites_need_a_complete_read() {
+        let dir = std::env::temp_dir().join(format!("skynet-files-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("log.txt").to_string_lossy().into_owned();
+        std::fs::write(&file, "one\ntwo\nthree\n").unwrap();
+
+        let workspace = Arc::new(Workspace::default());
+        let files = Arc::new(SessionFiles::default());
+        let checkpoint = Arc::new(TurnCheckpoint::disabled());
+        let read = ReadFileTool::new(Arc::clone(&workspace), Arc::clone(&files));
+        let write = WriteFileTool::new(
+            Arc::clone(&workspace),
+            Arc::clone(&files),
+            Arc::clone(&checkpoint),
+        );
+        let patch = PatchFileTool::new(Arc::clone(&workspace), Arc::clone(&files), checkpoint);
+        let overwrite = serde_json::json!({ "path": file, "content": "x" });
+
+        // Only the first line was shown: edits are fine, overwrites are not,
+        // even after an edit.
+        let ranged = serde_json::json!({ "path": file, "limit": 1 });
+        assert!(!read.execute(ranged).await.is_error);
+        let result = write.execute(overwrite.clone()).await;
+        assert!(result.content.contains("Only part of"), "{result:?}");
+        let edit = serde_json::json!({ "path": file, "old_string": "one", "new_string": "1" });
+        assert!(!patch.execute(edit).await.is_error);
+        assert!(write.execute(overwrite.clone()).await.is_error);
+
+        // A full read allows it, and a later ranged read keeps the full view.
+        assert!(
+            !read
+                .execute(serde_json::json!({ "path": file }))
+                .await
+                .is_error
+        );
+        let ranged = serde_json::json!({ "path": file, "offset": 2 });
+        assert!(!read.execute(ranged).await.is_error);
+        let result = write.execute(overwrite).await;
+        assert!(!result.is_error, "{result:?}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let now = Instant::now();
+        let mut map: HashMap<&str, Instant> = HashMap::from([
+            ("old", now),
+            ("mid", now + Duration::from_secs(1)),
+            ("new", now + Duration::from_secs(2)),
+        ]);
+        evict_oldest(&mut map, 3, |used| *used);
+        assert_eq!(map.len(), 2);
+        assert!(!map.contains_key("old"));
+        evict_oldest(&mut map, 3, |used| *used);
+        assert_eq!(map.len(), 2);
+    }
+}
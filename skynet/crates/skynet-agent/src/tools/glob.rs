@@ -0,0 +1,116 @@
+//! Path globs for the workspace sandbox, on top of `globset`.
+//!
+//! `*` matches any run of characters within one path component, `?` one
+//! character, `[abc]` / `[a-z]` / `[!abc]` a character class, `{a,b}` either
+//! alternative, and a `**` component any number of components (including
+//! none). A pattern with a leading `/` is anchored at the filesystem root;
+//! any other pattern may start at any depth, so `.env` behaves like
+//! `**/.env`. A trailing `/**` also matches the directory itself.
+
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: String,
+    set: GlobSet,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let anchored = if pattern.starts_with('/') {
+            pattern.to_string()
+        } else {
+            format!("**/{pattern}")
+        };
+        let mut variants = vec![anchored.as_str()];
+        if let Some(dir) = anchored.strip_suffix("/**") {
+            variants.push(dir);
+        }
+        let mut set = GlobSetBuilder::new();
+        for variant in variants {
+            let glob = GlobBuilder::new(variant)
+                .literal_separator(true)
+                .allow_unclosed_class(true)
+                .build()
+                .map_err(|e| format!("invalid glob '{pattern}': {}", e.kind()))?;
+            set.add(glob);
+        }
+        let set = set
+            .build()
+            .map_err(|e| format!("invalid glob '{pattern}': {e}"))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            set,
+        })
+    }
+
+    /// A glob matching `text` literally, metacharacters included.
+    pub fn literal(text: &str) -> Self {
+        Self::new(&globset::escape(text)).expect("escaped globs are valid")
+    }
+
+    /// The pattern as written.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether `path` matches. An absolute path should already be canonical;
+    /// a relative one is matched as if it started at `/`.
+    pub fn is_match(&self, path: &Path) -> bool {
+        if path.is_absolute() {
+            self.set.is_match(path)
+        } else {
+            self.set.is_match(Path::new("/").join(path))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).unwrap().is_match(Path::new(path))
+    }
+
+    #[test]
+    fn matches_components_and_depth() {
+        assert!(matches("**/.ssh/**", "/home/me/.ssh/id_ed25519"));
+        assert!(matches("**/.ssh/**", "/home/me/.ssh"));
+        assert!(!matches("**/.ssh/**", "/home/me/ssh/key"));
+        assert!(matches(".env", "/srv/app/.env"));
+        assert!(!matches(".env", "/srv/app/.env.example"));
+        assert!(matches("/srv/**/*.rs", "/srv/app/src/main.rs"));
+        assert!(!matches("/srv/**/*.rs", "/home/srv/main.rs"));
+        assert!(matches("/srv/*", "/srv/notes"));
+        assert!(!matches("/srv/*", "/srv/app/notes"));
+    }
+
+    #[test]
+    fn matches_wildcards_within_a_component() {
+        assert!(matches("*.pem", "/etc/ssl/server.pem"));
+        assert!(matches("id_*", "/keys/id_rsa"));
+        assert!(matches("file?.txt", "/a/file1.txt"));
+        assert!(!matches("file?.txt", "/a/file10.txt"));
+        assert!(matches("log[0-9].txt", "/a/log7.txt"));
+        assert!(!matches("log[!0-9].txt", "/a/log7.txt"));
+        assert!(matches("[]x].md", "/a/].md"));
+        assert!(matches("a[b", "/a[b"));
+        assert!(matches("*.{pem,key}", "/etc/ssl/server.key"));
+    }
+
+    #[test]
+    fn relative_paths_match_from_the_top() {
+        assert!(matches("/src/*.rs", "src/main.rs"));
+        assert!(!matches("/src/*.rs", "crates/src/main.rs"));
+        assert!(matches("*.rs", "crates/src/main.rs"));
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        assert!(Glob::new("{a,b").is_err());
+        assert!(Glob::literal("{a,b").is_match(Path::new("/x/{a,b")));
+    }
+}
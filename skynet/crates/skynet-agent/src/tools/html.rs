@@ -0,0 +1,800 @@
+//! HTML → Markdown conversion for `web_fetch`.
+//!
+//! A small forgiving tokenizer plus a renderer that keeps the readable parts
+//! of a page: headings, paragraphs, lists, links, images, emphasis, code,
+//! quotes and tables. Navigation, scripts, styles, forms and other chrome are
+//! dropped. When the page has a `<main>` element (or exactly one `<article>`),
+//! only that part is rendered.
+
+use url::Url;
+
+/// Elements whose content is never rendered.
+const SKIPPED: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "nav", "footer", "aside", "form",
+    "iframe", "button", "select", "textarea", "canvas", "dialog", "menu",
+];
+
+/// Elements without an end tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose content is raw text up to the matching end tag.
+const RAW_TEXT: &[&str] = &["script", "style", "textarea", "title"];
+
+/// Elements that start a new block.
+const BLOCK: &[&str] = &[
+    "address",
+    "article",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "header",
+    "main",
+    "p",
+    "section",
+    "summary",
+];
+
+/// A converted page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Markdown {
+    /// Contents of `<title>`, if any.
+    pub title: Option<String>,
+    pub body: String,
+}
+
+/// Convert an HTML document to Markdown. Relative links and image sources are
+/// resolved against `base` when given.
+pub fn to_markdown(html: &str, base: Option<&Url>) -> Markdown {
+    let tokens = tokenize(html);
+    let title = tokens.iter().enumerate().find_map(|(i, t)| match t {
+        Token::Start { name, .. } if name == "title" => match tokens.get(i + 1) {
+            Some(Token::Text(text)) => Some(
+                collapse_whitespace(&decode_entities(text))
+                    .trim()
+                    .to_string(),
+            ),
+            _ => None,
+        },
+        _ => None,
+    });
+    let (start, end) = content_range(&tokens);
+    let mut renderer = Renderer::new(base);
+    for token in &tokens[start..end] {
+        renderer.token(token);
+    }
+    Markdown {
+        title: title.filter(|t| !t.is_empty()),
+        body: renderer.finish(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tokenizer
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Start {
+        name: String,
+        attrs: Vec<(String, String)>,
+    },
+    End(String),
+    Text(String),
+}
+
+impl Token {
+    fn attr(&self, key: &str) -> Option<&str> {
+        match self {
+            Token::Start { attrs, .. } => attrs
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str()),
+            _ => None,
+        }
+    }
+}
+
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            tokens.push(Token::Text(rest.to_string()));
+            break;
+        };
+        if lt > 0 {
+            tokens.push(Token::Text(rest[..lt].to_string()));
+            rest = &rest[lt..];
+        }
+        // `rest` starts with '<'.
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |i| &after[i + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |i| &rest[i + 1..]);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let name = tag_name(after);
+            if name.is_empty() {
+                tokens.push(Token::Text("</".to_string()));
+                rest = after;
+            } else {
+                tokens.push(Token::End(name));
+                rest = after.find('>').map_or("", |i| &after[i + 1..]);
+            }
+        } else {
+            let name = tag_name(&rest[1..]);
+            if name.is_empty() {
+                tokens.push(Token::Text("<".to_string()));
+                rest = &rest[1..];
+                continue;
+            }
+            let (attrs, after) = parse_attrs(&rest[1 + name.len()..]);
+            rest = after;
+            if RAW_TEXT.contains(&name.as_str()) {
+                let close = format!("</{name}");
+                let end = find_ignore_ascii_case(rest, &close).unwrap_or(rest.len());
+                let text = &rest[..end];
+                rest = &rest[end..];
+                tokens.push(Token::Start {
+                    name: name.clone(),
+                    attrs,
+                });
+                if !text.is_empty() {
+                    tokens.push(Token::Text(text.to_string()));
+                }
+            } else {
+                tokens.push(Token::Start { name, attrs });
+            }
+        }
+    }
+    tokens
+}
+
+/// Lowercased tag name at the start of `s`.
+fn tag_name(s: &str) -> String {
+    s.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Parse attributes up to and including the closing `>`.
+fn parse_attrs(mut s: &str) -> (Vec<(String, String)>, &str) {
+    let mut attrs = Vec::new();
+    loop {
+        s = s.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if s.is_empty() {
+            return (attrs, s);
+        }
+        if let Some(after) = s.strip_prefix('>') {
+            return (attrs, after);
+        }
+        let name_len = s
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(s.len())
+            .max(1);
+        let name = s[..name_len].to_ascii_lowercase();
+        s = s[name_len..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = s.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let body = &after[1..];
+                    let end = body.find(q).unwrap_or(body.len());
+                    value = body[..end].to_string();
+                    s = body.get(end + 1..).unwrap_or("");
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    value = after[..end].to_string();
+                    s = &after[end..];
+                }
+            }
+        }
+        attrs.push((name, decode_entities(&value)));
+    }
+}
+
+fn find_ignore_ascii_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Token range to render: the first `<main>`, else a lone `<article>`, else
+/// everything.
+fn content_range(tokens: &[Token]) -> (usize, usize) {
+    let starts = |tag: &str| -> Vec<usize> {
+        tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| matches!(t, Token::Start { name, .. } if name == tag))
+            .map(|(i, _)| i)
+            .collect()
+    };
+    let main = starts("main");
+    let articles = starts("article");
+    let (tag, start) = match (main.first(), articles.as_slice()) {
+        (Some(&i), _) => ("main", i),
+        (None, [i]) => ("article", *i),
+        _ => return (0, tokens.len()),
+    };
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::Start { name, .. } if name == tag => depth += 1,
+            Token::End(name) if name == tag => {
+                depth -= 1;
+                if depth == 0 {
+                    return (start, i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    (start, tokens.len())
+}
+
+// ---------------------------------------------------------------------------
+// Renderer
+// ---------------------------------------------------------------------------
+
+struct ListState {
+    ordered: bool,
+    next: u32,
+}
+
+struct Renderer<'a> {
+    base: Option<&'a Url>,
+    out: String,
+    at_line_start: bool,
+    /// Whitespace seen since the last text, emitted lazily as one space.
+    pending_space: bool,
+    /// Open elements whose content is dropped.
+    skip: Vec<String>,
+    lists: Vec<ListState>,
+    quote_depth: usize,
+    pre_depth: usize,
+    /// A code fence was opened and its first line not yet started, so a
+    /// `<code class="language-…">` can still add the language.
+    fence_open: bool,
+    /// Hrefs of open links; `None` for links rendered as plain text.
+    links: Vec<Option<String>>,
+    /// Cells in the current table row.
+    row: Vec<String>,
+    /// Rows rendered in the current table.
+    table_rows: usize,
+    /// `out` is redirected here while inside a table cell.
+    cell: Option<String>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(base: Option<&'a Url>) -> Self {
+        Self {
+            base,
+            out: String::new(),
+            at_line_start: true,
+            pending_space: false,
+            skip: Vec::new(),
+            lists: Vec::new(),
+            quote_depth: 0,
+            pre_depth: 0,
+            fence_open: false,
+            links: Vec::new(),
+            row: Vec::new(),
+            table_rows: 0,
+            cell: None,
+        }
+    }
+
+    fn token(&mut self, token: &Token) {
+        match token {
+            Token::Start { name, .. } => {
+                if SKIPPED.contains(&name.as_str()) {
+                    if !VOID.contains(&name.as_str()) {
+                        self.skip.push(name.clone());
+                    }
+                } else if self.skip.is_empty() {
+                    self.start(name, token);
+                }
+            }
+            Token::End(name) => {
+                if let Some(pos) = self.skip.iter().rposition(|n| n == name) {
+                    self.skip.truncate(pos);
+                } else if self.skip.is_empty() {
+                    self.end(name);
+                }
+            }
+            Token::Text(text) if self.skip.is_empty() => self.text(text),
+            Token::Text(_) => {}
+        }
+    }
+
+    fn start(&mut self, name: &str, token: &Token) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.blank_line();
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                self.raw(&format!("{} ", "#".repeat(level)));
+            }
+            "br" => self.newline(),
+            "hr" => {
+                self.blank_line();
+                self.raw("---");
+                self.blank_line();
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.blank_line();
+                } else {
+                    self.line_break();
+                }
+                let start = token.attr("start").and_then(|s| s.parse().ok());
+                self.lists.push(ListState {
+                    ordered: name == "ol",
+                    next: start.unwrap_or(1),
+                });
+            }
+            "li" => {
+                self.line_break();
+                let depth = self.lists.len().max(1);
+                let marker = match self.lists.last_mut() {
+                    Some(list) if list.ordered => {
+                        list.next += 1;
+                        format!("{}. ", list.next - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.write_prefix_for(depth - 1);
+                self.raw(&marker);
+            }
+            "blockquote" => {
+                self.blank_line();
+                self.quote_depth += 1;
+            }
+            "pre" => {
+                self.blank_line();
+                let lang = token
+                    .attr("class")
+                    .and_then(code_language)
+                    .unwrap_or_default();
+                self.raw(&format!("```{lang}"));
+                self.fence_open = true;
+                self.pre_depth += 1;
+            }
+            "code" if self.pre_depth == 0 => self.inline("`"),
+            "code" if self.fence_open && self.out.ends_with("```") => {
+                if let Some(lang) = token.attr("class").and_then(code_language) {
+                    self.out.push_str(&lang);
+                }
+            }
+            "strong" | "b" => self.inline("**"),
+            "em" | "i" => self.inline("*"),
+            "a" => {
+                let href = token.attr("href").and_then(|h| self.resolve(h));
+                if href.is_some() {
+                    self.inline("[");
+                }
+                self.links.push(href);
+            }
+            "img" => {
+                let alt = token.attr("alt").unwrap_or_default().trim().to_string();
+                if let Some(src) = token.attr("src").and_then(|s| self.resolve(s)) {
+                    self.inline(&format!("![{alt}]({src})"));
+                }
+            }
+            "table" => {
+                self.blank_line();
+                self.table_rows = 0;
+            }
+            "tr" => self.row.clear(),
+            "td" | "th" => {
+                self.cell = Some(String::new());
+                self.pending_space = false;
+            }
+            _ if BLOCK.contains(&name) => self.block_break(),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.blank_line(),
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            "blockquote" => {
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.blank_line();
+            }
+            "pre" if self.pre_depth > 0 => {
+                self.pre_depth -= 1;
+                self.fence_open = false;
+                self.line_break();
+                self.raw("```");
+                self.blank_line();
+            }
+            "code" if self.pre_depth == 0 => self.inline("`"),
+            "strong" | "b" => self.inline("**"),
+            "em" | "i" => self.inline("*"),
+            "a" => {
+                if let Some(Some(href)) = self.links.pop() {
+                    self.inline(&format!("]({href})"));
+                }
+            }
+            "td" | "th" => {
+                if let Some(cell) = self.cell.take() {
+                    self.row.push(cell.trim().replace('|', "\\|"));
+                }
+            }
+            "tr" => self.finish_row(),
+            "table" => {
+                self.finish_row();
+                self.blank_line();
+            }
+            _ if BLOCK.contains(&name) => self.block_break(),
+            _ => {}
+        }
+    }
+
+    fn finish_row(&mut self) {
+        if let Some(cell) = self.cell.take() {
+            self.row.push(cell.trim().to_string());
+        }
+        if self.row.is_empty() {
+            return;
+        }
+        let cells = std::mem::take(&mut self.row);
+        self.line_break();
+        self.raw(&format!("| {} |", cells.join(" | ")));
+        if self.table_rows == 0 {
+            // Markdown tables need a header separator after the first row.
+            self.newline();
+            self.raw(&format!("|{}", " --- |".repeat(cells.len())));
+        }
+        self.table_rows += 1;
+    }
+
+    fn text(&mut self, text: &str) {
+        let text = decode_entities(text);
+        if self.pre_depth > 0 {
+            let mut text = text.as_str();
+            if std::mem::take(&mut self.fence_open) {
+                self.newline();
+                // A newline right after `<pre>` is not part of the content.
+                text = text.strip_prefix('\n').unwrap_or(text);
+            }
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    self.newline();
+                }
+                if !line.is_empty() {
+                    self.raw(line);
+                }
+            }
+            return;
+        }
+        let starts_with_space = text.starts_with(char::is_whitespace);
+        let ends_with_space = text.ends_with(char::is_whitespace);
+        let collapsed = collapse_whitespace(&text);
+        let words = collapsed.trim();
+        if words.is_empty() {
+            self.pending_space |= !text.is_empty();
+            return;
+        }
+        self.pending_space |= starts_with_space;
+        self.inline(words);
+        self.pending_space = ends_with_space;
+    }
+
+    /// Write inline content, emitting a pending space first.
+    fn inline(&mut self, s: &str) {
+        if let Some(cell) = self.cell.as_mut() {
+            if self.pending_space && !cell.is_empty() {
+                cell.push(' ');
+            }
+            cell.push_str(s);
+            self.pending_space = false;
+            return;
+        }
+        if self.pending_space && !self.at_line_start {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+        self.raw(s);
+    }
+
+    /// Write `s` as-is, starting the line's prefix first if needed.
+    fn raw(&mut self, s: &str) {
+        if let Some(cell) = self.cell.as_mut() {
+            cell.push_str(s);
+            return;
+        }
+        if self.at_line_start {
+            self.write_prefix_for(self.lists.len());
+        }
+        self.out.push_str(s);
+        self.at_line_start = false;
+    }
+
+    /// Blockquote markers plus list indentation for `depth` nesting levels.
+    fn write_prefix_for(&mut self, depth: usize) {
+        if !self.at_line_start {
+            return;
+        }
+        self.out.push_str(&"> ".repeat(self.quote_depth));
+        if self.pre_depth == 0 {
+            self.out.push_str(&"  ".repeat(depth));
+        }
+        self.at_line_start = false;
+    }
+
+    fn newline(&mut self) {
+        if self.cell.is_some() {
+            self.pending_space = true;
+            return;
+        }
+        trim_trailing_spaces(&mut self.out);
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.at_line_start = true;
+        self.pending_space = false;
+    }
+
+    /// End the current line unless already at the start of one.
+    fn line_break(&mut self) {
+        if !self.at_line_start || self.cell.is_some() {
+            self.newline();
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if self.cell.is_some() {
+            self.pending_space = true;
+            return;
+        }
+        trim_trailing_spaces(&mut self.out);
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            let missing = if self.out.ends_with('\n') { 1 } else { 2 };
+            for _ in 0..missing {
+                self.out.push('\n');
+            }
+        }
+        self.at_line_start = true;
+        self.pending_space = false;
+    }
+
+    /// Paragraph break, or a line break inside list items.
+    fn block_break(&mut self) {
+        if self.lists.is_empty() {
+            self.blank_line();
+        } else {
+            self.line_break();
+        }
+    }
+
+    fn resolve(&self, href: &str) -> Option<String> {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") {
+            return None;
+        }
+        match self.base {
+            Some(base) => base.join(href).ok().map(String::from),
+            None => Some(href.to_string()),
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut out = String::with_capacity(self.out.len());
+        let mut blank_run = 0;
+        for line in self.out.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                blank_run += 1;
+                if blank_run > 1 {
+                    continue;
+                }
+            } else {
+                blank_run = 0;
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        out.trim().to_string()
+    }
+}
+
+fn trim_trailing_spaces(s: &mut String) {
+    let len = s.trim_end_matches(' ').len();
+    s.truncate(len);
+}
+
+/// `language-rust` / `lang-rust` class → `rust`.
+fn code_language(class: &str) -> Option<String> {
+    class.split_whitespace().find_map(|c| {
+        c.strip_prefix("language-")
+            .or_else(|| c.strip_prefix("lang-"))
+            .map(str::to_string)
+    })
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_space = false;
+    for c in s.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+/// Decode numeric character references and the common named entities.
+fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .bytes()
+            .take(12)
+            .position(|b| b == b';')
+            .and_then(|end| decode_entity(&rest[1..=end]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(num) = entity.strip_prefix('#') {
+        let code = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "mdash" => '—',
+        "ndash" => '–',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "middot" => '·',
+        "bull" => '•',
+        "times" => '×',
+        "deg" => '°',
+        "euro" => '€',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn md(html: &str) -> String {
+        to_markdown(html, None).body
+    }
+
+    #[test]
+    fn converts_blocks_and_inline_markup() {
+        let html = "<h1>Title</h1><p>Some <b>bold</b> and <em>italic</em>\n   text with \
+                    <code>code</code>.</p><p>Second<br>line</p><hr><h3>Sub</h3>";
+        assert_eq!(
+            md(html),
+            "# Title\n\nSome **bold** and *italic* text with `code`.\n\nSecond\nline\n\n---\n\n### Sub"
+        );
+    }
+
+    #[test]
+    fn drops_chrome_and_scripts() {
+        let html = r#"<html><head><title>Page &amp; co</title><style>p { color: red }</style></head>
+            <body><nav><a href="/">Home</a></nav>
+            <script>if (a < b) { document.write("<p>x</p>") }</script>
+            <p>Body</p><footer>© 2026</footer></body></html>"#;
+        let page = to_markdown(html, None);
+        assert_eq!(page.title.as_deref(), Some("Page & co"));
+        assert_eq!(page.body, "Body");
+    }
+
+    #[test]
+    fn prefers_main_content() {
+        let html = "<div>Sidebar</div><main><p>Real content</p></main><div>Ads</div>";
+        assert_eq!(md(html), "Real content");
+        let html = "<div>Related</div><article><h2>Post</h2><p>Text</p></article>";
+        assert_eq!(md(html), "## Post\n\nText");
+        // Several articles (an index page): keep everything.
+        let html = "<article>A</article><article>B</article>";
+        assert_eq!(md(html), "A\n\nB");
+    }
+
+    #[test]
+    fn renders_nested_lists() {
+        let html =
+            "<ul><li>One</li><li>Two<ol start=3><li>a</li><li>b</li></ol></li></ul><p>After</p>";
+        assert_eq!(md(html), "- One\n- Two\n  3. a\n  4. b\n\nAfter");
+    }
+
+    #[test]
+    fn resolves_links_and_images() {
+        let base = Url::parse("https://example.com/docs/page.html").unwrap();
+        let html = r##"<p><a href="intro.html">Intro</a>, <a href="#top">top</a>,
+            <a href="https://other.org/">other</a> <img src="/img/logo.png" alt="Logo"></p>"##;
+        assert_eq!(
+            to_markdown(html, Some(&base)).body,
+            "[Intro](https://example.com/docs/intro.html), top, [other](https://other.org/) \
+             ![Logo](https://example.com/img/logo.png)"
+        );
+    }
+
+    #[test]
+    fn keeps_preformatted_text() {
+        let html =
+            "<pre><code class=\"language-rust\">\nfn main() {\n    let x = 1 &lt; 2;\n}</code></pre>";
+        assert_eq!(md(html), "```rust\nfn main() {\n    let x = 1 < 2;\n}\n```");
+    }
+
+    #[test]
+    fn renders_tables_and_quotes() {
+        let html = "<table><tr><th>Name</th><th>Size</th></tr><tr><td>a|b</td><td><b>1</b> KB</td></tr></table>\
+                    <blockquote><p>Quoted</p></blockquote>";
+        assert_eq!(
+            md(html),
+            "| Name | Size |\n| --- | --- |\n| a\\|b | **1** KB |\n\n> Quoted"
+        );
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            decode_entities("a &lt;b&gt; &#65;&#x42; &mdash; &bogus; & c"),
+            "a <b> AB — &bogus; & c"
+        );
+    }
+}
@@ -57,6 +57,14 @@ impl<C: MessageContext + 'static> Tool for KnowledgeSearchTool<C> {
         })
     }
 
+    fn is_concurrency_safe(&self) -> bool {
+        true
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, input: serde_json::Value) -> ToolResult {
         let query = match input.get("query").and_then(|v| v.as_str()) {
             Some(q) if !q.trim().is_empty() => q.to_string(),
@@ -1,13 +1,35 @@
 //! Tool: list_files — list directory contents with type and size info.
+//!
+//! Lists one directory, or with `recursive` a tree down to `max_depth`
+//! levels, indented two spaces per level. Entries come from `walk.rs`, so
+//! ignore files and the `include` / `exclude` globs apply.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use async_trait::async_trait;
 
+use super::walk::{modified, SortOrder, WalkFilters};
+use super::workspace::{Access, Workspace};
 use super::{Tool, ToolResult};
 
 /// Maximum entries returned to avoid overwhelming the context window.
 const MAX_ENTRIES: usize = 1_000;
+/// Depth of a recursive listing when `max_depth` is not given.
+const DEFAULT_TREE_DEPTH: usize = 3;
+/// Upper bound for `max_depth`.
+const MAX_TREE_DEPTH: usize = 10;
+
+pub struct ListFilesTool {
+    workspace: Arc<Workspace>,
+}
 
-pub struct ListFilesTool;
+impl ListFilesTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
 
 #[async_trait]
 impl Tool for ListFilesTool {
@@ -16,8 +38,10 @@ impl Tool for ListFilesTool {
     }
 
     fn description(&self) -> &str {
-        "List the contents of a directory. Each entry shows its type (file/dir) \
-         and size in bytes. Returns at most 1000 entries."
+        "List the contents of a directory, or with recursive=true a tree of its \
+         subdirectories (indented two spaces per level). Each entry shows its type \
+         (file/dir) and size in bytes. Honours .gitignore and .ignore files and skips \
+         .git. Returns at most 1000 entries."
     }
 
     fn input_schema(&self) -> serde_json::Value {
@@ -26,60 +50,207 @@ impl Tool for ListFilesTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "Absolute or relative path to the directory."
+                    "description": "Absolute path, or relative to the workspace, of the directory."
+                },
+                "recursive": {
+                    "type": "boolean",
+                    "description": "List subdirectories as a tree. Default false."
+                },
+                "max_depth": {
+                    "type": "integer",
+                    "description": "Levels listed when recursive (1-10). Default 3."
+                },
+                "include": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only list files matching one of these globs, relative to path, e.g. ['*.rs']. Directories without a matching file are hidden."
+                },
+                "exclude": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Hide files and directories matching these globs, e.g. ['node_modules', '*.lock']."
+                },
+                "sort": {
+                    "type": "string",
+                    "enum": ["name", "modified"],
+                    "description": "Order within each directory: name (default) or modified (newest first)."
+                },
+                "no_ignore": {
+                    "type": "boolean",
+                    "description": "Also list entries excluded by .gitignore / .ignore. Default false."
                 }
             },
             "required": ["path"]
         })
     }
 
-    async fn execute(&self, input: serde_json::Value) -> ToolResult {
-        let path = match input.get("path").and_then(|v| v.as_str()) {
-            Some(p) => p.to_string(),
-            None => return ToolResult::error("missing required parameter: path"),
-        };
+    fn is_concurrency_safe(&self) -> bool {
+        true
+    }
 
-        let read_dir = match std::fs::read_dir(&path) {
-            Ok(rd) => rd,
-            Err(e) => {
-                return ToolResult::error(format!("failed to list '{}': {}", path, e));
-            }
-        };
+    fn is_read_only(&self) -> bool {
+        true
+    }
 
-        let mut entries: Vec<String> = Vec::new();
-        let mut truncated = false;
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let workspace = Arc::clone(&self.workspace);
+        super::run_blocking(move || list_files(&workspace, input)).await
+    }
+}
 
-        for entry in read_dir {
-            if entries.len() >= MAX_ENTRIES {
-                truncated = true;
-                break;
-            }
+/// One listed file or directory.
+struct Entry {
+    depth: usize,
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+}
 
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue,
-            };
+fn list_files(workspace: &Workspace, input: serde_json::Value) -> ToolResult {
+    let path = match input.get("path").and_then(|v| v.as_str()) {
+        Some(p) => p.to_string(),
+        None => return ToolResult::error("missing required parameter: path"),
+    };
+    let filters = match WalkFilters::from_input(&input) {
+        Ok(f) => f,
+        Err(e) => return ToolResult::error(e),
+    };
+    let sort = match SortOrder::from_input(&input, SortOrder::Name) {
+        Ok(s) => s,
+        Err(e) => return ToolResult::error(e),
+    };
+    let recursive = input
+        .get("recursive")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let max_depth = if recursive {
+        input
+            .get("max_depth")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_TREE_DEPTH, |n| {
+                (n as usize).clamp(1, MAX_TREE_DEPTH)
+            })
+    } else {
+        1
+    };
 
-            let metadata = match entry.metadata() {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
+    let resolved = match workspace.resolve("list_files", &path, Access::List) {
+        Ok(p) => p,
+        Err(e) => return ToolResult::error(e),
+    };
+    if let Err(e) = std::fs::read_dir(&resolved) {
+        return ToolResult::error(format!("failed to list '{}': {}", path, e));
+    }
 
-            let name = entry.file_name().to_string_lossy().to_string();
-            let kind = if metadata.is_dir() { "dir" } else { "file" };
-            let size = metadata.len();
+    let mut walker = filters.walker(&resolved);
+    walker.max_depth(Some(max_depth));
+    match sort {
+        SortOrder::Name => walker.sort_by_file_name(|a, b| a.cmp(b)),
+        SortOrder::Modified => walker.sort_by_file_path(|a, b| modified(b).cmp(&modified(a))),
+    };
 
-            entries.push(format!("[{}] {} ({} bytes)", kind, name, size));
+    let mut entries: Vec<Entry> = Vec::new();
+    for entry in walker.build().filter_map(|e| e.ok()) {
+        if entry.depth() == 0 {
+            continue;
         }
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        // Hide entries the workspace rules would refuse to open.
+        if !workspace.permits(entry.path(), Access::Read) {
+            continue;
+        }
+        if !is_dir && !filters.includes(&resolved, entry.path()) {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        entries.push(Entry {
+            depth: entry.depth(),
+            path: entry.into_path(),
+            is_dir,
+            size,
+        });
+    }
+    if filters.has_include() {
+        prune_empty_dirs(&mut entries, &resolved);
+    }
 
-        // Sort for deterministic output.
-        entries.sort();
+    let truncated = entries.len() > MAX_ENTRIES;
+    let mut output = entries
+        .iter()
+        .take(MAX_ENTRIES)
+        .map(|e| {
+            let name = e.path.file_name().unwrap_or_default().to_string_lossy();
+            let kind = if e.is_dir { "dir" } else { "file" };
+            let indent = "  ".repeat(e.depth - 1);
+            format!("{}[{}] {} ({} bytes)", indent, kind, name, e.size)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if truncated {
+        output.push_str(&format!("\n\n[truncated at {} entries]", MAX_ENTRIES));
+    }
 
-        let mut output = entries.join("\n");
-        if truncated {
-            output.push_str(&format!("\n\n[truncated at {} entries]", MAX_ENTRIES));
-        }
+    ToolResult::success(output)
+}
+
+/// Drop directories that contain no listed file.
+fn prune_empty_dirs(entries: &mut Vec<Entry>, root: &Path) {
+    let mut used: HashSet<&Path> = HashSet::new();
+    for entry in entries.iter().filter(|e| !e.is_dir) {
+        used.extend(
+            entry
+                .path
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| *dir != root),
+        );
+    }
+    let used: HashSet<PathBuf> = used.into_iter().map(Path::to_path_buf).collect();
+    entries.retain(|e| !e.is_dir || used.contains(&e.path));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_a_tree_to_the_depth_limit() {
+        let dir = std::env::temp_dir().join(format!("skynet-list-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src/tools/deep")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.join("src/tools/mod.rs"), "").unwrap();
+        std::fs::write(dir.join("src/tools/deep/x.rs"), "").unwrap();
+        std::fs::write(dir.join("docs/readme.md"), "").unwrap();
+        let workspace = Workspace::default();
+        let list = |input: serde_json::Value| {
+            let mut input = input;
+            input["path"] = dir.to_string_lossy().into_owned().into();
+            list_files(&workspace, input).content
+        };
+
+        let flat = list(serde_json::json!({}));
+        let names: Vec<&str> = flat.lines().map(|l| l.split(' ').nth(1).unwrap()).collect();
+        assert_eq!(names, [".gitignore", "docs", "src"]);
+
+        let tree =
+            list(serde_json::json!({ "recursive": true, "max_depth": 3, "include": ["*.rs"] }));
+        let lines: Vec<&str> = tree
+            .lines()
+            .map(|l| l.split(" (").next().unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "[dir] src",
+                "  [file] main.rs",
+                "  [dir] tools",
+                "    [file] mod.rs",
+            ]
+        );
 
-        ToolResult::success(output)
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
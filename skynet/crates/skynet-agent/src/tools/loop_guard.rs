@@ -0,0 +1,190 @@
+//! Repetition and stuck-agent detection for the tool loop.
+//!
+//! `LoopGuard` watches every tool call the model makes in a turn:
+//!
+//! - a hash of (tool name, input, result) over a sliding window of the last
+//!   `WINDOW` calls — the same call producing the same result `REPEAT_LIMIT`
+//!   times means the model is going in circles;
+//! - the number of consecutive failed calls — `ERROR_LIMIT` in a row means it
+//!   is stuck on something it cannot fix.
+//!
+//! The first detection returns a corrective message for the model; if the
+//! problem is still there on the next round of calls the turn is ended.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use crate::provider::ToolCall;
+
+use super::ToolResult;
+
+/// Number of recent tool calls compared for repetition.
+const WINDOW: usize = 8;
+
+/// Identical call + result seen this many times within `WINDOW` is a loop.
+const REPEAT_LIMIT: usize = 3;
+
+/// This many failed tool calls in a row means the agent is stuck.
+const ERROR_LIMIT: usize = 4;
+
+/// Stop reason reported when the guard ends a turn.
+pub const LOOP_STOP_REASON: &str = "loop_detected";
+
+/// What the tool loop should do after a round of tool calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopVerdict {
+    /// Nothing suspicious — carry on.
+    Continue,
+    /// Send this corrective message to the model with the tool results.
+    Nudge(String),
+    /// End the turn; the string explains why.
+    Stop(String),
+}
+
+/// Per-turn repetition and error-streak detector.
+#[derive(Debug, Default)]
+pub struct LoopGuard {
+    recent: VecDeque<u64>,
+    consecutive_errors: usize,
+    nudged: bool,
+}
+
+impl LoopGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one round of tool calls and their results (in call order).
+    pub fn observe(&mut self, calls: &[ToolCall], results: &[ToolResult]) -> LoopVerdict {
+        let mut problem = None;
+
+        for (call, result) in calls.iter().zip(results) {
+            let fingerprint = fingerprint(call, result);
+            if self.recent.len() == WINDOW {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(fingerprint);
+            let repeats = self.recent.iter().filter(|&&f| f == fingerprint).count();
+
+            if result.is_error {
+                self.consecutive_errors += 1;
+            } else {
+                self.consecutive_errors = 0;
+            }
+
+            if problem.is_none() && repeats >= REPEAT_LIMIT {
+                problem = Some(format!(
+                    "`{}` was called {repeats} times with the same input and got the same result",
+                    call.name
+                ));
+            } else if problem.is_none() && self.consecutive_errors >= ERROR_LIMIT {
+                problem = Some(format!(
+                    "the last {} tool calls failed (latest: `{}`)",
+                    self.consecutive_errors, call.name
+                ));
+            }
+        }
+
+        match problem {
+            None => {
+                self.nudged = false;
+                LoopVerdict::Continue
+            }
+            Some(problem) if !self.nudged => {
+                self.nudged = true;
+                LoopVerdict::Nudge(format!(
+                    "[loop guard] {problem}. Repeating it will not help. Change your \
+                     approach: use a different tool or different input, or stop and \
+                     explain to the user what is blocking you."
+                ))
+            }
+            Some(problem) => LoopVerdict::Stop(problem),
+        }
+    }
+}
+
+/// Text appended to the assistant reply when the guard ends a turn.
+pub fn stop_note(reason: &str) -> String {
+    format!("[Stopped: {reason}. The agent appeared to be stuck, so the turn was ended early.]")
+}
+
+fn fingerprint(call: &ToolCall, result: &ToolResult) -> u64 {
+    let mut h = DefaultHasher::new();
+    call.name.hash(&mut h);
+    call.input.to_string().hash(&mut h);
+    result.content.hash(&mut h);
+    result.is_error.hash(&mut h);
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, input: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: "call".to_string(),
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    #[test]
+    fn repeated_identical_call_nudges_then_stops() {
+        let mut guard = LoopGuard::new();
+        let c = [call(
+            "execute_command",
+            serde_json::json!({ "command": "make" }),
+        )];
+        let r = [ToolResult::success("make: *** No rule to make target")];
+
+        assert_eq!(guard.observe(&c, &r), LoopVerdict::Continue);
+        assert_eq!(guard.observe(&c, &r), LoopVerdict::Continue);
+        assert!(matches!(guard.observe(&c, &r), LoopVerdict::Nudge(_)));
+        assert!(matches!(guard.observe(&c, &r), LoopVerdict::Stop(_)));
+    }
+
+    #[test]
+    fn different_results_are_not_repetition() {
+        let mut guard = LoopGuard::new();
+        let c = [call("read_file", serde_json::json!({ "path": "log.txt" }))];
+        for i in 0..6 {
+            let r = [ToolResult::success(format!("line {i}"))];
+            assert_eq!(guard.observe(&c, &r), LoopVerdict::Continue);
+        }
+    }
+
+    #[test]
+    fn error_streak_is_detected_and_reset_by_success() {
+        let mut guard = LoopGuard::new();
+        let fail = |n: u32| {
+            (
+                [call(
+                    "bash",
+                    serde_json::json!({ "command": format!("try {n}") }),
+                )],
+                [ToolResult::error(format!("failed {n}"))],
+            )
+        };
+
+        for n in 0..3 {
+            let (c, r) = fail(n);
+            assert_eq!(guard.observe(&c, &r), LoopVerdict::Continue);
+        }
+        let ok = [ToolResult::success("fine")];
+        assert_eq!(
+            guard.observe(&[call("bash", serde_json::json!({}))], &ok),
+            LoopVerdict::Continue
+        );
+
+        for n in 3..6 {
+            let (c, r) = fail(n);
+            assert_eq!(guard.observe(&c, &r), LoopVerdict::Continue);
+        }
+        let (c, r) = fail(6);
+        assert!(matches!(guard.observe(&c, &r), LoopVerdict::Nudge(_)));
+        let (c, r) = fail(7);
+        assert!(matches!(guard.observe(&c, &r), LoopVerdict::Stop(_)));
+    }
+}
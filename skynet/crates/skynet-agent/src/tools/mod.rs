@@ -5,20 +5,35 @@
 
 pub mod bash_session;
 pub mod build;
+pub mod checkpoint;
+pub mod diff;
+pub mod edit_file;
 pub mod execute_command;
+pub mod file_tracker;
+pub mod glob;
+pub mod html;
 pub mod knowledge;
 pub mod list_files;
+pub mod loop_guard;
 pub mod patch_file;
 pub mod read_file;
 pub mod reminder;
 pub mod script_tool;
 pub mod search_files;
+pub mod subagent;
 pub mod tool_loop;
+pub mod walk;
+#[cfg(feature = "wasm")]
+pub mod wasm_tool;
+pub mod web_fetch;
+pub mod web_search;
+pub mod workspace;
 pub mod write_file;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::pricing::TokenUsage;
 use crate::provider::ToolDefinition;
 
 /// Result of executing a tool.
@@ -28,6 +43,10 @@ pub struct ToolResult {
     pub content: String,
     /// Whether the tool execution failed.
     pub is_error: bool,
+    /// LLM tokens the tool spent itself (a subagent's turn). The tool loop
+    /// adds them to the parent turn's usage; never sent to the model.
+    #[serde(skip)]
+    pub usage: Option<TokenUsage>,
 }
 
 impl ToolResult {
@@ -35,6 +54,7 @@ impl ToolResult {
         Self {
             content: content.into(),
             is_error: false,
+            usage: None,
         }
     }
 
@@ -42,8 +62,15 @@ impl ToolResult {
         Self {
             content: message.into(),
             is_error: true,
+            usage: None,
         }
     }
+
+    /// Attach the tokens spent producing this result.
+    pub fn with_usage(mut self, usage: TokenUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
 }
 
 /// Trait that all tools must implement.
@@ -57,6 +84,30 @@ pub trait Tool: Send + Sync {
     fn input_schema(&self) -> serde_json::Value;
     /// Execute the tool with the given input.
     async fn execute(&self, input: serde_json::Value) -> ToolResult;
+    /// Whether calls to this tool may run at the same time as other
+    /// concurrency-safe calls from the same response. Only read-only tools
+    /// should opt in; everything else runs one call at a time.
+    fn is_concurrency_safe(&self) -> bool {
+        false
+    }
+    /// Whether the tool never changes anything outside the conversation:
+    /// no file writes, commands or requests with side effects. Read-only
+    /// subagents get only these tools. Being safe to run in parallel does not
+    /// imply this, so tools opt in separately.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+}
+
+/// Run a tool's blocking work on the blocking thread pool so that concurrent
+/// calls actually overlap instead of stalling the async task.
+pub(crate) async fn run_blocking<F>(f: F) -> ToolResult
+where
+    F: FnOnce() -> ToolResult + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| ToolResult::error(format!("tool task failed: {e}")))
 }
 
 /// Convert a slice of tools to API-level tool definitions.
@@ -5,17 +5,43 @@
 //! files that would overflow a full read_file → write_file round-trip.
 //!
 //! Behaviour mirrors the Edit tool used by Claude Code:
-//!   1. Read the file from disk.
+//!   1. Check the file was read this session and is unchanged since
+//!      (`file_tracker`), then read it from disk.
 //!   2. Find `old` (exact match, whitespace-sensitive).
 //!   3. Replace with `new` (first occurrence, or all if replace_all=true).
-//!   4. Write the result back atomically via a temp file + rename.
+//!   4. Snapshot the old content (`checkpoint`), write the result back
+//!      atomically via a temp file + rename, and record the new content as
+//!      seen.
 //!   5. Return a one-line summary or a clear error if `old` was not found.
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 
+use super::checkpoint::TurnCheckpoint;
+use super::file_tracker::SessionFiles;
+use super::workspace::{Access, Workspace};
 use super::{Tool, ToolResult};
 
-pub struct PatchFileTool;
+pub struct PatchFileTool {
+    workspace: Arc<Workspace>,
+    files: Arc<SessionFiles>,
+    checkpoint: Arc<TurnCheckpoint>,
+}
+
+impl PatchFileTool {
+    pub fn new(
+        workspace: Arc<Workspace>,
+        files: Arc<SessionFiles>,
+        checkpoint: Arc<TurnCheckpoint>,
+    ) -> Self {
+        Self {
+            workspace,
+            files,
+            checkpoint,
+        }
+    }
+}
 
 #[async_trait]
 impl Tool for PatchFileTool {
@@ -28,7 +54,8 @@ impl Tool for PatchFileTool {
          Prefer this over write_file when changing only part of a file — it is safer \
          (only the matched region changes) and much cheaper on tokens. \
          The match is exact and whitespace-sensitive: copy the old text verbatim \
-         from read_file output. Returns an error if old_string is not found or is ambiguous."
+         from read_file output. Returns an error if old_string is not found or is ambiguous, \
+         or if the file was not read in this session or changed since."
     }
 
     fn input_schema(&self) -> serde_json::Value {
@@ -37,7 +64,7 @@ impl Tool for PatchFileTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "Absolute or relative path to the file to edit."
+                    "description": "Absolute path, or relative to the workspace, of the file to edit."
                 },
                 "old_string": {
                     "type": "string",
@@ -74,8 +101,16 @@ impl Tool for PatchFileTool {
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
 
+        let resolved = match self.workspace.resolve("patch_file", &path, Access::Write) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(e),
+        };
+        if let Err(e) = self.files.check_write(&resolved, &path) {
+            return ToolResult::error(e);
+        }
+
         // Read current content.
-        let content = match std::fs::read_to_string(&path) {
+        let content = match std::fs::read_to_string(&resolved) {
             Ok(c) => c,
             Err(e) => return ToolResult::error(format!("failed to read '{}': {}", path, e)),
         };
@@ -103,15 +138,21 @@ impl Tool for PatchFileTool {
             content.replacen(old.as_str(), new.as_str(), 1)
         };
 
+        if let Err(e) = self.checkpoint.before_write(&resolved, &path) {
+            return ToolResult::error(e);
+        }
+
         // Write atomically: temp file + rename so a crash mid-write never corrupts the original.
-        let tmp_path = format!("{}.skynet_patch_tmp", path);
+        let tmp_path = format!("{}.skynet_patch_tmp", resolved.display());
         if let Err(e) = std::fs::write(&tmp_path, &updated) {
             return ToolResult::error(format!("failed to write temp file '{}': {}", tmp_path, e));
         }
-        if let Err(e) = std::fs::rename(&tmp_path, &path) {
+        if let Err(e) = std::fs::rename(&tmp_path, &resolved) {
             let _ = std::fs::remove_file(&tmp_path);
             return ToolResult::error(format!("failed to rename temp file to '{}': {}", path, e));
         }
+        self.files.record_edit(&resolved, updated.as_bytes());
+        self.checkpoint.after_write(&resolved, updated.as_bytes());
 
         let occurrences = if replace_all {
             format!("{} occurrence(s)", count)
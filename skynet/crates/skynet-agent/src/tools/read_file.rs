@@ -1,13 +1,26 @@
 //! Tool: read_file — read the contents of a file from disk.
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 
+use super::file_tracker::SessionFiles;
+use super::workspace::{Access, Workspace};
 use super::{Tool, ToolResult};
 
 /// Maximum characters returned by read_file to avoid flooding the context window.
 const MAX_OUTPUT_CHARS: usize = 30_000;
 
-pub struct ReadFileTool;
+pub struct ReadFileTool {
+    workspace: Arc<Workspace>,
+    files: Arc<SessionFiles>,
+}
+
+impl ReadFileTool {
+    pub fn new(workspace: Arc<Workspace>, files: Arc<SessionFiles>) -> Self {
+        Self { workspace, files }
+    }
+}
 
 #[async_trait]
 impl Tool for ReadFileTool {
@@ -26,7 +39,7 @@ impl Tool for ReadFileTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "Absolute or relative path to the file."
+                    "description": "Absolute path, or relative to the workspace, of the file."
                 },
                 "offset": {
                     "type": "integer",
@@ -41,50 +54,83 @@ impl Tool for ReadFileTool {
         })
     }
 
+    fn is_concurrency_safe(&self) -> bool {
+        true
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, input: serde_json::Value) -> ToolResult {
-        let path = match input.get("path").and_then(|v| v.as_str()) {
-            Some(p) => p.to_string(),
-            None => return ToolResult::error("missing required parameter: path"),
-        };
+        let workspace = Arc::clone(&self.workspace);
+        let files = Arc::clone(&self.files);
+        super::run_blocking(move || read_file(&workspace, &files, input)).await
+    }
+}
 
-        let content = match std::fs::read_to_string(&path) {
-            Ok(c) => c,
-            Err(e) => return ToolResult::error(format!("failed to read '{}': {}", path, e)),
-        };
+fn read_file(workspace: &Workspace, files: &SessionFiles, input: serde_json::Value) -> ToolResult {
+    let path = match input.get("path").and_then(|v| v.as_str()) {
+        Some(p) => p.to_string(),
+        None => return ToolResult::error("missing required parameter: path"),
+    };
+    let resolved = match workspace.resolve("read_file", &path, Access::Read) {
+        Ok(p) => p,
+        Err(e) => return ToolResult::error(e),
+    };
 
-        let offset = input
-            .get("offset")
-            .and_then(|v| v.as_u64())
-            .map(|v| v.saturating_sub(1) as usize); // convert to 0-based
-        let limit = input
-            .get("limit")
-            .and_then(|v| v.as_u64())
-            .map(|v| v as usize);
-
-        let result = if offset.is_some() || limit.is_some() {
-            let start = offset.unwrap_or(0);
-            let lines: Vec<&str> = content.lines().skip(start).collect();
-            let lines = if let Some(n) = limit {
-                &lines[..n.min(lines.len())]
-            } else {
-                &lines
-            };
-            lines.join("\n")
-        } else {
-            content
-        };
+    let content = match std::fs::read_to_string(&resolved) {
+        Ok(c) => c,
+        Err(e) => return ToolResult::error(format!("failed to read '{}': {}", path, e)),
+    };
+
+    let offset = input
+        .get("offset")
+        .and_then(|v| v.as_u64())
+        .map(|v| v.saturating_sub(1) as usize); // convert to 0-based
+    let limit = input
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize);
 
-        // Truncate if needed to avoid overwhelming the context window.
-        let result = if result.len() > MAX_OUTPUT_CHARS {
-            format!(
-                "{}\n\n[output truncated at {} characters]",
-                &result[..MAX_OUTPUT_CHARS],
-                MAX_OUTPUT_CHARS,
-            )
+    // Whether the model is shown less than the whole file.
+    let mut partial = false;
+    let result = if offset.is_some() || limit.is_some() {
+        let start = offset.unwrap_or(0);
+        let lines: Vec<&str> = content.lines().skip(start).collect();
+        let lines = if let Some(n) = limit {
+            partial = n < lines.len();
+            &lines[..n.min(lines.len())]
         } else {
-            result
+            &lines
         };
+        partial |= start > 0;
+        lines.join("\n")
+    } else {
+        content.clone()
+    };
 
-        ToolResult::success(result)
+    // Truncate if needed to avoid overwhelming the context window.
+    let result = if result.len() > MAX_OUTPUT_CHARS {
+        partial = true;
+        let mut end = MAX_OUTPUT_CHARS;
+        while !result.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!(
+            "{}\n\n[output truncated at {} characters]",
+            &result[..end],
+            MAX_OUTPUT_CHARS,
+        )
+    } else {
+        result
+    };
+
+    if partial {
+        files.record_partial(&resolved, content.as_bytes());
+    } else {
+        files.record(&resolved, content.as_bytes());
     }
+
+    ToolResult::success(result)
 }
@@ -9,26 +9,54 @@
 //!     run.py      ← entry point (any language)
 //! ```
 //!
+//! Plugins are loaded by `plugins::PluginRegistry`, which also reloads them
+//! when the directory changes.
+//!
 //! ## Execution contract
 //!
+//! Protocol 1 (default):
+//!
 //! - Parameters are passed as a JSON string in the `SKYNET_INPUT` env variable.
 //! - The script writes its result to **stdout** (plain text or JSON, any format).
 //! - Exit code 0 = success, non-zero = error.
 //! - Stderr is captured and appended to the error message on failure.
+//!
+//! Protocol 2 (`protocol = 2`):
+//!
+//! - Parameters are written as JSON to **stdin**, so large payloads fit.
+//! - The script prints a JSON envelope to stdout:
+//!   `{"content": "...", "is_error": false, "attachments": [{"path": "out.png",
+//!   "media_type": "image/png"}]}`. Attachment paths are relative to the plugin
+//!   directory and may not leave it. They are listed after the content as
+//!   text markers only; the files are not sent to the model or the channel.
+//! - A non-zero exit code always makes the result an error.
+//!
+//! Both protocols:
+//!
+//! - The script runs in the plugin directory with a cleared environment: only
+//!   `BASE_ENV`, the variables listed in `env` and the declared `secrets`
+//!   (from `[agent.plugins.secrets]`) are set. Secret values are redacted
+//!   from the output.
 //! - Default timeout: 30 seconds (overridable per plugin in `tool.toml`).
+//! - With `permission` set, the plugin is only offered to callers for whom
+//!   `PermissionChecker` allows it (see `plugins`).
 //!
 //! ## Manifest format (`tool.toml`)
 //!
 //! ```toml
 //! name        = "my_plugin"
 //! description = "What this tool does — shown to the AI"
-//! version     = "1.0.0"   # optional
-//! author      = "you"     # optional
+//! version     = "1.0.0"            # optional
+//! author      = "you"              # optional
+//! permission  = "execute_commands" # optional, a `Permission` name
 //!
 //! [run]
-//! command = "python3"   # interpreter: bash, python3, node, ruby, …
-//! script  = "run.py"    # entry point, relative to the plugin directory
-//! timeout = 30          # seconds (optional, default 30)
+//! command  = "python3"          # interpreter: bash, python3, node, ruby, …
+//! script   = "run.py"           # entry point, relative to the plugin directory
+//! timeout  = 30                 # seconds (optional, default 30)
+//! protocol = 2                  # optional, default 1
+//! env      = ["LC_ALL"]         # extra variables passed through (optional)
+//! secrets  = ["GITHUB_TOKEN"]   # keys of [agent.plugins.secrets] (optional)
 //!
 //! [[input.params]]
 //! name        = "prompt"
@@ -44,14 +72,26 @@
 //! default     = 1
 //! ```
 
-use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::sync::Arc;
 
 use async_trait::async_trait;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use tracing::{info, warn};
+use tokio::io::AsyncWriteExt;
+
+use skynet_users::permissions::Permission;
 
 use super::{Tool, ToolResult};
 
+/// Variables every plugin gets from the gateway's environment.
+const BASE_ENV: &[&str] = &["PATH", "HOME", "LANG", "TMPDIR"];
+
+/// Newest execution protocol.
+const MAX_PROTOCOL: u8 = 2;
+
 // ---------------------------------------------------------------------------
 // Manifest types
 // ---------------------------------------------------------------------------
@@ -62,6 +102,9 @@ struct ToolManifest {
     description: String,
     #[serde(default)]
     version: Option<String>,
+    /// `Permission` the caller needs, e.g. "execute_commands".
+    #[serde(default)]
+    permission: Option<String>,
     run: RunConfig,
     #[serde(default)]
     input: InputConfig,
@@ -76,18 +119,60 @@ struct RunConfig {
     /// Maximum execution time in seconds.
     #[serde(default = "default_timeout")]
     timeout: u64,
+    /// Execution contract, see the module docs.
+    #[serde(default = "default_protocol")]
+    protocol: u8,
+    /// Gateway environment variables passed through besides `BASE_ENV`.
+    #[serde(default)]
+    env: Vec<String>,
+    /// `[agent.plugins.secrets]` keys set as environment variables.
+    #[serde(default)]
+    secrets: Vec<String>,
 }
 
 fn default_timeout() -> u64 {
     30
 }
 
+fn default_protocol() -> u8 {
+    1
+}
+
+/// `[[input.params]]`, shared with the WebAssembly plugin manifest.
 #[derive(Debug, Deserialize, Default)]
-struct InputConfig {
+pub(super) struct InputConfig {
     #[serde(default)]
     params: Vec<ParamDef>,
 }
 
+impl InputConfig {
+    /// JSON Schema of the tool input, built from the param list.
+    pub(super) fn schema(&self) -> serde_json::Value {
+        let mut properties = serde_json::Map::new();
+        let mut required: Vec<serde_json::Value> = Vec::new();
+
+        for param in &self.params {
+            let mut prop = serde_json::json!({
+                "type": param.type_,
+                "description": param.description,
+            });
+            if let Some(default) = &param.default {
+                prop["default"] = default.clone();
+            }
+            properties.insert(param.name.clone(), prop);
+            if param.required {
+                required.push(serde_json::Value::String(param.name.clone()));
+            }
+        }
+
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ParamDef {
     name: String,
@@ -100,59 +185,215 @@ struct ParamDef {
     default: Option<serde_json::Value>,
 }
 
+/// Protocol 2 result printed on stdout.
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    is_error: bool,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Attachment {
+    path: PathBuf,
+    #[serde(default)]
+    media_type: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
 // ---------------------------------------------------------------------------
 // ScriptTool
 // ---------------------------------------------------------------------------
 
-/// A single plugin tool loaded from a `tool.toml` manifest.
+/// A single plugin tool loaded from a `tool.toml` manifest. Cheap to clone;
+/// the registry hands out a clone per turn.
+#[derive(Clone)]
 pub struct ScriptTool {
-    manifest: ToolManifest,
+    manifest: Arc<ToolManifest>,
+    permission: Option<Permission>,
     /// Absolute path to the plugin directory.
     dir: PathBuf,
+    /// Every configured secret; only the declared ones reach the script.
+    secrets: Arc<HashMap<String, String>>,
 }
 
 impl ScriptTool {
-    /// Try to load a plugin from `dir`. Returns `None` on any parse error.
-    fn load(dir: &Path) -> Option<Self> {
-        let manifest_path = dir.join("tool.toml");
-        let content = std::fs::read_to_string(&manifest_path)
-            .map_err(
-                |e| warn!(path = %manifest_path.display(), error = %e, "cannot read tool.toml"),
-            )
-            .ok()?;
-        let manifest: ToolManifest = toml::from_str(&content)
-            .map_err(|e| warn!(path = %manifest_path.display(), error = %e, "invalid tool.toml"))
-            .ok()?;
-        Some(Self {
-            manifest,
+    /// Load the plugin in `dir`, or say why its `tool.toml` is unusable.
+    pub fn load(dir: &Path, secrets: Arc<HashMap<String, String>>) -> Result<Self, String> {
+        let manifest: ToolManifest = read_manifest(dir)?;
+        if manifest.name.trim().is_empty() {
+            return Err("invalid tool.toml: name is empty".to_string());
+        }
+        if !(1..=MAX_PROTOCOL).contains(&manifest.run.protocol) {
+            return Err(format!(
+                "invalid tool.toml: unsupported protocol {} (expected 1 or 2)",
+                manifest.run.protocol
+            ));
+        }
+        let permission = parse_permission(manifest.permission.as_deref())?;
+        Ok(Self {
+            manifest: Arc::new(manifest),
+            permission,
             dir: dir.to_path_buf(),
+            secrets,
         })
     }
 
-    /// Build the JSON Schema `properties` object from the param list.
-    fn build_schema(&self) -> serde_json::Value {
-        let mut properties = serde_json::Map::new();
-        let mut required: Vec<serde_json::Value> = Vec::new();
+    pub fn version(&self) -> Option<&str> {
+        self.manifest.version.as_deref()
+    }
 
-        for param in &self.manifest.input.params {
-            let mut prop = serde_json::json!({
-                "type": param.type_,
-                "description": param.description,
-            });
-            if let Some(default) = &param.default {
-                prop["default"] = default.clone();
+    pub fn protocol(&self) -> u8 {
+        self.manifest.run.protocol
+    }
+
+    /// Permission the caller needs; `None` = offered to everyone.
+    pub fn permission(&self) -> Option<&Permission> {
+        self.permission.as_ref()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The script's command with its allowlisted environment.
+    fn command(&self, input: &str) -> Result<tokio::process::Command, String> {
+        let run = &self.manifest.run;
+        let mut command = tokio::process::Command::new(&run.command);
+        command
+            .arg(self.dir.join(&run.script))
+            .current_dir(&self.dir)
+            .env_clear();
+        for name in BASE_ENV
+            .iter()
+            .copied()
+            .chain(run.env.iter().map(String::as_str))
+        {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
             }
-            properties.insert(param.name.clone(), prop);
-            if param.required {
-                required.push(serde_json::Value::String(param.name.clone()));
+        }
+        for name in &run.secrets {
+            match self.secrets.get(name) {
+                Some(value) => command.env(name, value),
+                None => return Err(missing_secret(&self.manifest.name, name)),
+            };
+        }
+        if run.protocol == 1 {
+            command.env("SKYNET_INPUT", input);
+        }
+        Ok(command)
+    }
+
+    /// `text` with the values of the declared secrets masked.
+    fn redact(&self, text: &str) -> String {
+        redact(text, &self.manifest.run.secrets, &self.secrets)
+    }
+
+    fn result(&self, out: Output) -> ToolResult {
+        let stdout = self.redact(String::from_utf8_lossy(&out.stdout).trim());
+        let stderr = self.redact(String::from_utf8_lossy(&out.stderr).trim());
+        let exit = out.status.code().unwrap_or(-1);
+
+        if self.manifest.run.protocol >= 2 {
+            match serde_json::from_str::<Envelope>(&stdout) {
+                Ok(envelope) => return self.envelope_result(envelope, exit, &stderr),
+                Err(e) if out.status.success() => {
+                    return ToolResult::error(format!(
+                        "plugin '{}' printed an invalid result envelope ({}):\n{}",
+                        self.manifest.name, e, stdout
+                    ))
+                }
+                // A crashed plugin rarely prints its envelope; report it as protocol 1 does.
+                Err(_) => {}
             }
         }
 
-        serde_json::json!({
-            "type": "object",
-            "properties": properties,
-            "required": required,
-        })
+        if out.status.success() {
+            let content = if stdout.is_empty() {
+                "(no output)"
+            } else {
+                &stdout
+            };
+            ToolResult::success(content.to_string())
+        } else {
+            let mut msg = stdout;
+            if !stderr.is_empty() {
+                if !msg.is_empty() {
+                    msg.push('\n');
+                }
+                msg.push_str(&format!("[stderr]: {}", stderr));
+            }
+            msg.push_str(&format!("\n[exit: {}]", exit));
+            ToolResult::error(msg)
+        }
+    }
+
+    fn envelope_result(&self, envelope: Envelope, exit: i32, stderr: &str) -> ToolResult {
+        let mut content = envelope.content;
+        for attachment in &envelope.attachments {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            let Some(path) = self.attachment_path(&attachment.path) else {
+                content.push_str(&format!(
+                    "[attachment refused: '{}' is outside the plugin directory]",
+                    attachment.path.display()
+                ));
+                continue;
+            };
+            let name = attachment
+                .name
+                .clone()
+                .or_else(|| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+                .unwrap_or_default();
+            let media_type = attachment
+                .media_type
+                .as_deref()
+                .unwrap_or("application/octet-stream");
+            let missing = if path.exists() { "" } else { ", missing" };
+            content.push_str(&format!(
+                "[attachment: {} ({}{}) {}]",
+                name,
+                media_type,
+                missing,
+                path.display()
+            ));
+        }
+        if exit != 0 {
+            if !stderr.is_empty() {
+                content.push_str(&format!("\n[stderr]: {}", stderr));
+            }
+            content.push_str(&format!("\n[exit: {}]", exit));
+        }
+        if content.is_empty() {
+            content = "(no output)".to_string();
+        }
+        ToolResult {
+            content,
+            is_error: envelope.is_error || exit != 0,
+            usage: None,
+        }
+    }
+
+    /// `path` joined to the plugin directory, or `None` when it is absolute,
+    /// climbs out with `..`, or is a symlink to somewhere outside.
+    fn attachment_path(&self, path: &Path) -> Option<PathBuf> {
+        if !path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return None;
+        }
+        let joined = self.dir.join(path);
+        match (joined.canonicalize(), self.dir.canonicalize()) {
+            (Ok(resolved), Ok(root)) if !resolved.starts_with(&root) => None,
+            _ => Some(joined),
+        }
     }
 }
 
@@ -167,22 +408,23 @@ impl Tool for ScriptTool {
     }
 
     fn input_schema(&self) -> serde_json::Value {
-        self.build_schema()
+        self.manifest.input.schema()
     }
 
     async fn execute(&self, input: serde_json::Value) -> ToolResult {
-        let script_path = self.dir.join(&self.manifest.run.script);
-        let command = self.manifest.run.command.clone();
         let timeout_secs = self.manifest.run.timeout;
         let input_str = input.to_string();
+        let command = match self.command(&input_str) {
+            Ok(c) => c,
+            Err(e) => return ToolResult::error(e),
+        };
+        let stdin = (self.manifest.run.protocol >= 2).then_some(input_str);
 
-        let run = tokio::process::Command::new(&command)
-            .arg(&script_path)
-            .env("SKYNET_INPUT", &input_str)
-            .current_dir(&self.dir)
-            .output();
-
-        let result = tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), run).await;
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(timeout_secs),
+            run(command, stdin),
+        )
+        .await;
 
         match result {
             Err(_) => ToolResult::error(format!(
@@ -193,66 +435,154 @@ impl Tool for ScriptTool {
                 "failed to launch plugin '{}': {}",
                 self.manifest.name, e
             )),
-            Ok(Ok(out)) => {
-                let stdout = String::from_utf8_lossy(&out.stdout).trim().to_string();
-                let stderr = String::from_utf8_lossy(&out.stderr).trim().to_string();
-
-                if out.status.success() {
-                    let content = if stdout.is_empty() {
-                        "(no output)"
-                    } else {
-                        &stdout
-                    };
-                    ToolResult::success(content.to_string())
-                } else {
-                    let mut msg = stdout;
-                    if !stderr.is_empty() {
-                        if !msg.is_empty() {
-                            msg.push('\n');
-                        }
-                        msg.push_str(&format!("[stderr]: {}", stderr));
-                    }
-                    msg.push_str(&format!("\n[exit: {}]", out.status.code().unwrap_or(-1)));
-                    ToolResult::error(msg)
-                }
-            }
+            Ok(Ok(out)) => self.result(out),
         }
     }
 }
 
-// ---------------------------------------------------------------------------
-// Loader
-// ---------------------------------------------------------------------------
+/// Parse `dir/tool.toml`.
+pub(super) fn read_manifest<T: DeserializeOwned>(dir: &Path) -> Result<T, String> {
+    let content = std::fs::read_to_string(dir.join("tool.toml"))
+        .map_err(|e| format!("cannot read tool.toml: {e}"))?;
+    toml::from_str(&content).map_err(|e| format!("invalid tool.toml: {e}"))
+}
 
-/// Scan `tools_dir` for subdirectories that contain a `tool.toml` and load
-/// each one as a `ScriptTool`. Silently skips invalid or missing manifests.
-pub fn load_script_tools(tools_dir: &Path) -> Vec<Box<dyn Tool>> {
-    let mut tools: Vec<Box<dyn Tool>> = Vec::new();
-
-    let entries = match std::fs::read_dir(tools_dir) {
-        Ok(e) => e,
-        Err(_) => return tools, // directory doesn't exist yet — that's fine
-    };
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.is_dir() && path.join("tool.toml").exists() {
-            match ScriptTool::load(&path) {
-                Some(tool) => {
-                    info!(
-                        name = %tool.manifest.name,
-                        version = ?tool.manifest.version,
-                        dir = %path.display(),
-                        "loaded script plugin"
-                    );
-                    tools.push(Box::new(tool));
-                }
-                None => {
-                    warn!(dir = %path.display(), "skipped plugin: invalid tool.toml");
-                }
-            }
+/// The manifest's `permission`, if any.
+pub(super) fn parse_permission(name: Option<&str>) -> Result<Option<Permission>, String> {
+    name.map(str::parse::<Permission>)
+        .transpose()
+        .map_err(|e| format!("invalid tool.toml: {e}"))
+}
+
+pub(super) fn missing_secret(plugin: &str, name: &str) -> String {
+    format!("plugin '{plugin}' needs secret '{name}', which is not set in [agent.plugins.secrets]")
+}
+
+/// `text` with the values of the secrets in `names` masked.
+pub(super) fn redact(text: &str, names: &[String], secrets: &HashMap<String, String>) -> String {
+    let mut text = text.to_string();
+    for name in names {
+        if let Some(value) = secrets.get(name).filter(|v| !v.is_empty()) {
+            text = text.replace(value.as_str(), "[redacted]");
         }
     }
+    text
+}
 
-    tools
+/// Run `command` to completion, feeding it `stdin` when given. The child is
+/// killed if the future is dropped (timeout).
+async fn run(
+    mut command: tokio::process::Command,
+    stdin: Option<String>,
+) -> std::io::Result<Output> {
+    command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = command.spawn()?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        // Written from its own task so a script that prints before reading
+        // all of its input cannot deadlock against us.
+        tokio::spawn(async move {
+            let _ = pipe.write_all(input.as_bytes()).await;
+        });
+    }
+    child.wait_with_output().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(dir: &Path, manifest: &str, script: &str) -> ScriptTool {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("tool.toml"), manifest).unwrap();
+        std::fs::write(dir.join("run.sh"), script).unwrap();
+        let secrets = HashMap::from([("API_TOKEN".to_string(), "s3cret".to_string())]);
+        ScriptTool::load(dir, Arc::new(secrets)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn protocol_2_reads_stdin_and_returns_an_envelope() {
+        let dir = std::env::temp_dir().join(format!("skynet-plugin-{}", uuid::Uuid::new_v4()));
+        let tool = plugin(
+            &dir,
+            "name = \"echo\"\ndescription = \"d\"\npermission = \"use_browser\"\n\
+             [run]\ncommand = \"sh\"\nscript = \"run.sh\"\nprotocol = 2\nsecrets = [\"API_TOKEN\"]\n",
+            "input=$(cat | tr -d '\"')\n\
+             touch out.txt\n\
+             ln -sfn / link\n\
+             printf '{\"content\": \"%s %s %s\", \"attachments\": [{\"path\": \"out.txt\"}, \
+               {\"path\": \"../x\"}, {\"path\": \"/etc/passwd\"}, {\"path\": \"link\"}]}' \
+               \"$input\" \"${SKYNET_INPUT:-none}\" \"$API_TOKEN\"\n",
+        );
+        assert_eq!(tool.permission(), Some(&Permission::UseBrowser));
+
+        let result = tool.execute(serde_json::json!({ "q": 1 })).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert_eq!(
+            result.content,
+            format!(
+                "{{q:1}} none [redacted]\n[attachment: out.txt (application/octet-stream) {}]\n\
+                 [attachment refused: '../x' is outside the plugin directory]\n\
+                 [attachment refused: '/etc/passwd' is outside the plugin directory]\n\
+                 [attachment refused: 'link' is outside the plugin directory]",
+                dir.join("out.txt").display()
+            )
+        );
+
+        std::fs::write(dir.join("run.sh"), "echo not json").unwrap();
+        let result = tool.execute(serde_json::json!({})).await;
+        assert!(result.is_error && result.content.contains("invalid result envelope"));
+
+        std::fs::write(
+            dir.join("run.sh"),
+            "echo '{\"content\": \"bad\", \"is_error\": true}'",
+        )
+        .unwrap();
+        let result = tool.execute(serde_json::json!({})).await;
+        assert!(result.is_error);
+        assert_eq!(result.content, "bad");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn protocol_1_gets_env_input_and_no_unlisted_variables() {
+        let dir = std::env::temp_dir().join(format!("skynet-plugin-{}", uuid::Uuid::new_v4()));
+        // cargo sets CARGO_PKG_NAME for the test process; only `env` lets it through
+        let tool = plugin(
+            &dir,
+            "name = \"env\"\ndescription = \"d\"\n[run]\ncommand = \"sh\"\nscript = \"run.sh\"\n",
+            "echo \"$SKYNET_INPUT ${CARGO_PKG_NAME:-unset}\"\n",
+        );
+        let result = tool.execute(serde_json::json!({ "a": "b" })).await;
+        assert_eq!(result.content, "{\"a\":\"b\"} unset");
+
+        std::fs::write(
+            dir.join("tool.toml"),
+            "name = \"env\"\ndescription = \"d\"\n\
+             [run]\ncommand = \"sh\"\nscript = \"run.sh\"\nenv = [\"CARGO_PKG_NAME\"]\n",
+        )
+        .unwrap();
+        let tool = ScriptTool::load(&dir, Arc::default()).unwrap();
+        let result = tool.execute(serde_json::json!({})).await;
+        assert_eq!(result.content, "{} skynet-agent");
+
+        std::fs::write(
+            dir.join("tool.toml"),
+            "name = \"env\"\ndescription = \"d\"\npermission = \"fly\"\n\
+             [run]\ncommand = \"sh\"\nscript = \"run.sh\"\n",
+        )
+        .unwrap();
+        let err = ScriptTool::load(&dir, Arc::default()).err().unwrap();
+        assert_eq!(err, "invalid tool.toml: unknown permission: fly");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
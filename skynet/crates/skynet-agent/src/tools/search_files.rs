@@ -1,13 +1,42 @@
-//! Tool: search_files — recursively search file contents for a substring pattern.
+//! Tool: search_files — recursively search file contents for a regex.
+//!
+//! Files come from `walk.rs` (ignore files, `include` / `exclude` globs) and
+//! are searched most recently modified first by default, so fresh work shows
+//! up before the matches in old code.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use async_trait::async_trait;
+use regex::{Regex, RegexBuilder};
 
+use super::walk::{sort_paths, SortOrder, WalkFilters};
+use super::workspace::{Access, Workspace};
 use super::{Tool, ToolResult};
 
-/// Maximum number of matching lines returned.
-const MAX_MATCHES: usize = 100;
+/// Default number of matching lines returned.
+const DEFAULT_MAX_MATCHES: usize = 100;
+/// Upper bound for `max_results`.
+const MAX_MATCHES: usize = 500;
+/// Upper bound for `context`.
+const MAX_CONTEXT: usize = 10;
+/// Files looked at per search; the rest of a huge tree is not searched.
+const MAX_FILES: usize = 20_000;
+/// Characters shown per line; minified files would flood the output.
+const MAX_LINE_CHARS: usize = 300;
+/// Larger files (logs, dumps, binaries) are skipped rather than read whole.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+pub struct SearchFilesTool {
+    workspace: Arc<Workspace>,
+}
 
-pub struct SearchFilesTool;
+impl SearchFilesTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
 
 #[async_trait]
 impl Tool for SearchFilesTool {
@@ -16,9 +45,12 @@ impl Tool for SearchFilesTool {
     }
 
     fn description(&self) -> &str {
-        "Recursively search file contents for a substring pattern. Returns matching \
-         lines in `file:line_number: content` format. Skips binary files and the .git \
-         directory. Returns at most 100 matches."
+        "Recursively search file contents for a regular expression. Returns matching \
+         lines as `file:line_number: content`; with `context`, surrounding lines are \
+         shown as `file-line_number- content` and groups are separated by `--`. \
+         Honours .gitignore and .ignore files and skips binary files and .git. \
+         Files are searched most recently modified first. Returns at most 100 \
+         matches unless max_results is set."
     }
 
     fn input_schema(&self) -> serde_json::Value {
@@ -27,141 +59,337 @@ impl Tool for SearchFilesTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "Root directory to search in."
+                    "description": "Absolute path, or relative to the workspace, of the directory to search."
                 },
                 "pattern": {
                     "type": "string",
-                    "description": "Substring to search for (case-sensitive)."
+                    "description": "Regular expression (Rust regex syntax) matched against each line."
+                },
+                "literal": {
+                    "type": "boolean",
+                    "description": "Treat pattern as plain text instead of a regex. Default false."
+                },
+                "case_insensitive": {
+                    "type": "boolean",
+                    "description": "Ignore case. Default false."
+                },
+                "include": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only search files matching one of these globs, relative to path, e.g. ['*.rs', 'src/**/*.toml']."
+                },
+                "exclude": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Skip files and directories matching these globs, e.g. ['tests/**', '*.min.js']."
                 },
-                "file_pattern": {
+                "context": {
+                    "type": "integer",
+                    "description": "Lines of context before and after each match (0-10). Default 0."
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum matching lines returned (1-500). Default 100."
+                },
+                "sort": {
                     "type": "string",
-                    "description": "Optional filename suffix filter, e.g. '.rs' or '.toml'."
+                    "enum": ["modified", "name"],
+                    "description": "File order: modified (newest first, default) or name."
+                },
+                "no_ignore": {
+                    "type": "boolean",
+                    "description": "Also search files excluded by .gitignore / .ignore. Default false."
                 }
             },
             "required": ["path", "pattern"]
         })
     }
 
-    async fn execute(&self, input: serde_json::Value) -> ToolResult {
-        let root = match input.get("path").and_then(|v| v.as_str()) {
-            Some(p) => p.to_string(),
-            None => return ToolResult::error("missing required parameter: path"),
-        };
-
-        let pattern = match input.get("pattern").and_then(|v| v.as_str()) {
-            Some(p) => p.to_string(),
-            None => return ToolResult::error("missing required parameter: pattern"),
-        };
+    fn is_concurrency_safe(&self) -> bool {
+        true
+    }
 
-        let file_pattern = input
-            .get("file_pattern")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
+    fn is_read_only(&self) -> bool {
+        true
+    }
 
-        let mut matches: Vec<String> = Vec::new();
-        let mut truncated = false;
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let workspace = Arc::clone(&self.workspace);
+        super::run_blocking(move || search_files(&workspace, input)).await
+    }
+}
 
-        search_dir(
-            std::path::Path::new(&root),
-            &pattern,
-            file_pattern.as_deref(),
-            &mut matches,
-            &mut truncated,
-        );
+fn search_files(workspace: &Workspace, input: serde_json::Value) -> ToolResult {
+    let root = match input.get("path").and_then(|v| v.as_str()) {
+        Some(p) => p.to_string(),
+        None => return ToolResult::error("missing required parameter: path"),
+    };
 
-        if matches.is_empty() {
-            return ToolResult::success("No matches found.");
+    let pattern = match input.get("pattern").and_then(|v| v.as_str()) {
+        Some(p) => p.to_string(),
+        None => return ToolResult::error("missing required parameter: pattern"),
+    };
+    let flag = |key: &str| input.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+    let regex = match build_regex(&pattern, flag("literal"), flag("case_insensitive")) {
+        Ok(r) => r,
+        Err(e) => return ToolResult::error(e),
+    };
+    let mut filters = match WalkFilters::from_input(&input) {
+        Ok(f) => f,
+        Err(e) => return ToolResult::error(e),
+    };
+    // Filename suffix filter accepted from older callers, e.g. ".rs".
+    if let Some(suffix) = input.get("file_pattern").and_then(|v| v.as_str()) {
+        if let Err(e) = filters.include(&format!("*{}", globset::escape(suffix))) {
+            return ToolResult::error(e);
         }
+    }
+    let sort = match SortOrder::from_input(&input, SortOrder::Modified) {
+        Ok(s) => s,
+        Err(e) => return ToolResult::error(e),
+    };
+    let count = |key: &str, default: usize, max: usize| {
+        input
+            .get(key)
+            .and_then(|v| v.as_u64())
+            .map_or(default, |n| (n as usize).min(max))
+    };
+    let context = count("context", 0, MAX_CONTEXT);
+    let max_matches = count("max_results", DEFAULT_MAX_MATCHES, MAX_MATCHES).max(1);
+
+    let root = match workspace.resolve("search_files", &root, Access::List) {
+        Ok(p) => p,
+        Err(e) => return ToolResult::error(e),
+    };
 
-        let mut output = matches.join("\n");
-        if truncated {
-            output.push_str(&format!("\n\n[truncated at {} matches]", MAX_MATCHES));
+    let mut collected = collect_files(workspace, &root, &filters);
+    sort_paths(&mut collected.files, sort);
+
+    let mut output: Vec<String> = Vec::new();
+    let mut matches = 0;
+    for file in &collected.files {
+        if matches >= max_matches {
+            break;
         }
+        matches += search_file(file, &regex, context, max_matches - matches, &mut output);
+    }
 
-        ToolResult::success(output)
+    let mut output = if output.is_empty() {
+        "No matches found.".to_string()
+    } else {
+        output.join("\n")
+    };
+    if matches >= max_matches {
+        output.push_str(&format!("\n\n[truncated at {} matches]", max_matches));
+    }
+    if collected.too_large > 0 {
+        output.push_str(&format!(
+            "\n\n[{} file(s) over {} MB were not searched]",
+            collected.too_large,
+            MAX_FILE_BYTES / (1024 * 1024)
+        ));
     }
+    if collected.truncated {
+        output.push_str(&format!(
+            "\n\n[only the first {} files were searched; narrow path or include]",
+            MAX_FILES
+        ));
+    }
+
+    ToolResult::success(output)
 }
 
-/// Recursively walk `dir`, collecting substring matches.
-fn search_dir(
-    dir: &std::path::Path,
-    pattern: &str,
-    file_pattern: Option<&str>,
-    matches: &mut Vec<String>,
-    truncated: &mut bool,
-) {
-    let read_dir = match std::fs::read_dir(dir) {
-        Ok(rd) => rd,
-        Err(_) => return,
+fn build_regex(pattern: &str, literal: bool, case_insensitive: bool) -> Result<Regex, String> {
+    let source = if literal {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
     };
+    RegexBuilder::new(&source)
+        .case_insensitive(case_insensitive)
+        .build()
+        .map_err(|e| format!("invalid regex '{}': {}", pattern, e))
+}
 
-    let mut entries: Vec<std::path::PathBuf> =
-        read_dir.filter_map(|e| e.ok()).map(|e| e.path()).collect();
-
-    // Deterministic traversal order.
-    entries.sort();
+/// Files picked for a search by `collect_files`.
+#[derive(Default)]
+struct Collected {
+    files: Vec<PathBuf>,
+    /// The walk stopped at `MAX_FILES`.
+    truncated: bool,
+    /// Files left out for being over `MAX_FILE_BYTES`.
+    too_large: usize,
+}
 
-    for entry in entries {
-        if *truncated {
-            return;
+/// Files under `root` that the filters and workspace allow.
+fn collect_files(workspace: &Workspace, root: &Path, filters: &WalkFilters) -> Collected {
+    let mut collected = Collected::default();
+    for entry in filters.walker(root).build().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
         }
-
-        // Skip .git directory.
-        if entry.file_name().map(|n| n == ".git").unwrap_or(false) {
+        let too_large = entry.metadata().is_ok_and(|m| m.len() > MAX_FILE_BYTES);
+        let path = entry.into_path();
+        if !filters.includes(root, &path) || !workspace.permits(&path, Access::Read) {
             continue;
         }
-
-        if entry.is_dir() {
-            search_dir(&entry, pattern, file_pattern, matches, truncated);
-        } else if entry.is_file() {
-            // Apply optional filename suffix filter.
-            if let Some(fp) = file_pattern {
-                let name = entry.to_string_lossy();
-                if !name.ends_with(fp) {
-                    continue;
-                }
-            }
-
-            search_file(&entry, pattern, matches, truncated);
+        if too_large {
+            collected.too_large += 1;
+            continue;
+        }
+        if collected.files.len() >= MAX_FILES {
+            collected.truncated = true;
+            break;
         }
+        collected.files.push(path);
     }
+    collected
 }
 
-/// Search a single file for the pattern, appending results to `matches`.
+/// Search a single file, appending at most `limit` matches (plus context) to
+/// `output`. Returns the number of matching lines added.
 fn search_file(
-    path: &std::path::Path,
-    pattern: &str,
-    matches: &mut Vec<String>,
-    truncated: &mut bool,
-) {
-    let content = match std::fs::read(path) {
-        Ok(b) => b,
-        Err(_) => return,
-    };
+    path: &Path,
+    regex: &Regex,
+    context: usize,
+    limit: usize,
+    output: &mut Vec<String>,
+) -> usize {
+    // Read at most one byte past the cap, in case the file grew since the walk.
+    let mut content = Vec::new();
+    let read = std::fs::File::open(path)
+        .and_then(|f| f.take(MAX_FILE_BYTES + 1).read_to_end(&mut content));
+    if read.is_err() || content.len() as u64 > MAX_FILE_BYTES {
+        return 0;
+    }
 
     // Skip files that look binary (contain a null byte in the first 8 KB).
     let probe = &content[..content.len().min(8192)];
     if probe.contains(&0u8) {
-        return;
+        return 0;
     }
 
     let text = match std::str::from_utf8(&content) {
         Ok(t) => t,
-        Err(_) => return, // skip files with invalid UTF-8
+        Err(_) => return 0, // skip files with invalid UTF-8
     };
 
+    let lines: Vec<&str> = text.lines().collect();
+    let hits: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| regex.is_match(line))
+        .map(|(i, _)| i)
+        .take(limit)
+        .collect();
+    if hits.is_empty() {
+        return 0;
+    }
+
     let display_path = path.to_string_lossy();
+    if context == 0 {
+        for &i in &hits {
+            output.push(format!("{}:{}: {}", display_path, i + 1, clip(lines[i])));
+        }
+        return hits.len();
+    }
 
-    for (line_idx, line) in text.lines().enumerate() {
-        if *truncated {
-            return;
+    // Merge overlapping context windows into groups separated by `--`.
+    let mut shown_until = 0;
+    for (n, &hit) in hits.iter().enumerate() {
+        let start = hit.saturating_sub(context).max(shown_until);
+        let end = (hit + context + 1).min(lines.len());
+        if (n == 0 || start > shown_until) && !output.is_empty() {
+            output.push("--".to_string());
         }
-        if line.contains(pattern) {
-            matches.push(format!("{}:{}: {}", display_path, line_idx + 1, line));
-            if matches.len() >= MAX_MATCHES {
-                *truncated = true;
-                return;
-            }
+        for (i, line) in lines.iter().enumerate().take(end).skip(start) {
+            // A later hit inside this window is printed as a match.
+            let sep = if hits[n..].contains(&i) { ':' } else { '-' };
+            output.push(format!(
+                "{}{sep}{}{sep} {}",
+                display_path,
+                i + 1,
+                clip(line)
+            ));
         }
+        shown_until = shown_until.max(end);
+    }
+    hits.len()
+}
+
+/// `line` cut to `MAX_LINE_CHARS` characters.
+fn clip(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((at, _)) => format!("{}…", &line[..at]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn touch(path: &Path, content: &str, age_secs: u64) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn searches_by_regex_with_ignore_files_and_globs() {
+        let dir = std::env::temp_dir().join(format!("skynet-search-{}", uuid::Uuid::new_v4()));
+        touch(&dir.join(".gitignore"), "target/\n*.log\n", 100);
+        touch(&dir.join("src/old.rs"), "fn alpha() {}\n", 50);
+        touch(&dir.join("src/new.rs"), "// x\nfn beta() {}\n// y\n", 10);
+        touch(&dir.join("notes.md"), "fn gamma in prose\n", 20);
+        touch(&dir.join("target/gen.rs"), "fn hidden() {}\n", 5);
+        touch(&dir.join("debug.log"), "fn logged() {}\n", 5);
+        touch(&dir.join(".git/HEAD"), "fn head() {}\n", 5);
+        let workspace = Workspace::default();
+        let root = dir.to_string_lossy().into_owned();
+        let search = |extra: serde_json::Value| {
+            let mut input = serde_json::json!({ "path": root, "pattern": r"^fn \w+\(" });
+            input
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            search_files(&workspace, input).content
+        };
+        let new = dir.join("src/new.rs").to_string_lossy().into_owned();
+        let old = dir.join("src/old.rs").to_string_lossy().into_owned();
+
+        // Newest first; ignored files, .git and non-matching lines left out.
+        assert_eq!(
+            search(serde_json::json!({})),
+            format!("{new}:2: fn beta() {{}}\n{old}:1: fn alpha() {{}}")
+        );
+        assert_eq!(
+            search(serde_json::json!({ "context": 1, "sort": "name" })),
+            format!("{new}-1- // x\n{new}:2: fn beta() {{}}\n{new}-3- // y\n--\n{old}:1: fn alpha() {{}}")
+        );
+        let all = search(serde_json::json!({ "no_ignore": true, "exclude": ["*.log"] }));
+        assert!(all.contains("gen.rs") && !all.contains("debug.log") && !all.contains("HEAD"));
+        let only_old = search(serde_json::json!({ "include": ["old.*"] }));
+        assert!(only_old.starts_with(&old) && !only_old.contains("new.rs"));
+        let excluded = search(serde_json::json!({ "exclude": ["/src"] }));
+        assert_eq!(excluded, "No matches found.");
+        let err = search(serde_json::json!({ "pattern": "fn (" }));
+        assert!(err.starts_with("invalid regex"), "{err}");
+
+        // Oversized files are skipped, and the output says so.
+        let big = "fn huge() {}\n".repeat(MAX_FILE_BYTES as usize / 13 + 1);
+        touch(&dir.join("src/big.rs"), &big, 1);
+        let with_big = search(serde_json::json!({}));
+        assert!(!with_big.contains("huge"), "{with_big}");
+        assert!(
+            with_big.ends_with("[1 file(s) over 2 MB were not searched]"),
+            "{with_big}"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
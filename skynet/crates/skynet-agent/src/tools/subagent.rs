@@ -0,0 +1,230 @@
+//! `spawn_subagent` tool — delegate a task to a nested agent.
+//!
+//! The subagent runs its own `run_tool_loop` with a fresh message history
+//! (just the task) and a short system prompt, so the parent's context is not
+//! spent on the intermediate steps — only the final answer comes back. It
+//! shares the parent's host context (memory DB, terminal, approvals) and
+//! cancellation token. Its token usage is attached to the tool result and
+//! rolled up into the parent turn.
+//!
+//! Nesting is capped at `MAX_SUBAGENT_DEPTH`: subagents at the limit are
+//! built without `spawn_subagent`.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::{info, warn};
+
+use crate::cancel::{CancellationToken, ABORTED_STOP_REASON};
+use crate::pipeline::context::MessageContext;
+use crate::provider::{ChatRequest, Message, Role};
+use crate::routing::SenderInfo;
+
+use super::build::build_tools_at_depth;
+use super::{to_definitions, tool_loop, Tool, ToolResult};
+
+/// Deepest level a subagent may run at (the main agent is depth 0).
+pub const MAX_SUBAGENT_DEPTH: usize = 2;
+
+const SUBAGENT_PROMPT: &str = concat!(
+    "You are a subagent working on one task delegated by another AI agent. ",
+    "You cannot talk to the user; the agent that called you only sees your final reply. ",
+    "Use your tools to do the task, then reply with a complete, self-contained answer: ",
+    "the result, key findings (file paths, values, command output that matters) and ",
+    "anything you could not finish. Do not ask questions — make reasonable assumptions ",
+    "and state them."
+);
+
+/// Which tools the subagent gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Only tools that report `is_read_only()`.
+    ReadOnly,
+    /// Every tool the parent has, subject to the same approval policies.
+    Full,
+}
+
+/// Runs a task in an isolated nested tool loop and returns its final answer.
+pub struct SpawnSubagentTool<C: MessageContext + 'static> {
+    ctx: Arc<C>,
+    channel_name: String,
+    channel_id: Option<u64>,
+    session_key: String,
+    sender: Option<SenderInfo>,
+    cancel: CancellationToken,
+    /// The parent's turn, which the subagent's file edits are checkpointed under.
+    turn_id: String,
+    /// Depth of the agent that owns this tool; the subagent runs at `depth + 1`.
+    depth: usize,
+}
+
+impl<C: MessageContext + 'static> SpawnSubagentTool<C> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        ctx: Arc<C>,
+        channel_name: &str,
+        channel_id: Option<u64>,
+        session_key: &str,
+        sender: Option<&SenderInfo>,
+        cancel: CancellationToken,
+        turn_id: &str,
+        depth: usize,
+    ) -> Self {
+        Self {
+            ctx,
+            channel_name: channel_name.to_string(),
+            channel_id,
+            session_key: session_key.to_string(),
+            sender: sender.cloned(),
+            cancel,
+            turn_id: turn_id.to_string(),
+            depth,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: MessageContext + 'static> Tool for SpawnSubagentTool<C> {
+    fn name(&self) -> &str {
+        "spawn_subagent"
+    }
+
+    fn description(&self) -> &str {
+        "Delegate a self-contained task to a subagent with its own empty conversation. \
+         Use it for research or multi-step work whose intermediate steps you do not need \
+         to see (e.g. 'find where X is configured and summarise it'). The subagent cannot \
+         see this conversation, so put everything it needs in the task. Returns the \
+         subagent's final answer."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "task": {
+                    "type": "string",
+                    "description": "Complete instructions for the subagent, including all needed context."
+                },
+                "mode": {
+                    "type": "string",
+                    "enum": ["read_only", "full"],
+                    "description": "read_only (default): only tools without side effects (reading files and the knowledge base, web search). full: all tools, including commands and file writes."
+                },
+                "model": {
+                    "type": "string",
+                    "description": "Optional model ID for the subagent. Defaults to the agent's model."
+                }
+            },
+            "required": ["task"]
+        })
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let task = match input.get("task").and_then(|v| v.as_str()) {
+            Some(t) if !t.trim().is_empty() => t,
+            _ => return ToolResult::error("missing required parameter: task"),
+        };
+        let mode = match input.get("mode").and_then(|v| v.as_str()) {
+            None | Some("read_only") => Mode::ReadOnly,
+            Some("full") => Mode::Full,
+            Some(other) => {
+                return ToolResult::error(format!(
+                    "invalid mode '{other}': expected read_only or full"
+                ))
+            }
+        };
+        let depth = self.depth + 1;
+        if depth > MAX_SUBAGENT_DEPTH {
+            return ToolResult::error(format!(
+                "subagents cannot nest deeper than {MAX_SUBAGENT_DEPTH} levels"
+            ));
+        }
+
+        let mut tools = build_tools_at_depth(
+            Arc::clone(&self.ctx),
+            &self.channel_name,
+            self.channel_id,
+            &self.session_key,
+            self.sender.as_ref(),
+            &self.cancel,
+            &self.turn_id,
+            depth,
+        );
+        if mode == Mode::ReadOnly {
+            tools.retain(|t| t.is_read_only());
+        }
+
+        let model = match input.get("model").and_then(|v| v.as_str()) {
+            Some(m) if !m.is_empty() => m.to_string(),
+            _ => self.ctx.agent().get_model().await,
+        };
+
+        info!(
+            depth,
+            ?mode,
+            model = %model,
+            tools = tools.len(),
+            session = %self.session_key,
+            "spawning subagent"
+        );
+
+        let request = ChatRequest {
+            model,
+            system: SUBAGENT_PROMPT.to_string(),
+            system_prompt: None,
+            messages: vec![Message {
+                role: Role::User,
+                content: task.into(),
+            }],
+            max_tokens: 4096,
+            stream: false,
+            thinking: None,
+            tools: to_definitions(&tools),
+            raw_messages: None,
+        };
+
+        let (resp, called_tools, usage) = match tool_loop::run_tool_loop(
+            self.ctx.agent().provider(),
+            request,
+            &tools,
+            self.ctx.agent().context_windows(),
+            &self.cancel,
+        )
+        .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                warn!(depth, error = %e, "subagent failed");
+                return ToolResult::error(format!("subagent failed: {e}"));
+            }
+        };
+
+        for tool_name in &called_tools {
+            let _ = self
+                .ctx
+                .memory()
+                .log_tool_call(tool_name, &self.session_key);
+        }
+
+        // Price the run at the subagent's own model before it joins the turn.
+        let cost_usd = self.ctx.agent().pricing().cost_usd(&resp.model, &usage);
+        info!(
+            depth,
+            tokens_in = usage.tokens_in,
+            tokens_out = usage.tokens_out,
+            cost_usd,
+            tool_calls = called_tools.len(),
+            stop_reason = %resp.stop_reason,
+            "subagent finished"
+        );
+
+        let result = if resp.stop_reason == ABORTED_STOP_REASON {
+            ToolResult::error("subagent aborted with the turn")
+        } else if resp.content.trim().is_empty() {
+            ToolResult::error("subagent finished without an answer")
+        } else {
+            ToolResult::success(resp.content)
+        };
+        result.with_usage(usage.priced_at(cost_usd))
+    }
+}
@@ -1,27 +1,54 @@
 //! Tool execution loop — the core agentic behavior.
 //!
 //! Flow: prompt → LLM → if tool_use → execute tools → inject results → LLM → repeat
-//! Stops when: stop_reason is not "tool_use", max iterations reached, or error.
+//! Stops when: stop_reason is not "tool_use", max iterations reached, error,
+//! the turn is cancelled (`chat.abort`), or `LoopGuard` finds the agent stuck.
+//! Every request is fitted to the model's context window before it is sent.
+//! Consecutive concurrency-safe tool calls from one response run in parallel.
+
+use std::future::Future;
 
 use tracing::{debug, info, warn};
 
+use crate::cancel::{CancellationToken, ABORTED_STOP_REASON};
+use crate::context_budget::{self, ContextWindows};
+use crate::pricing::TokenUsage;
 use crate::provider::{ChatRequest, ChatResponse, LlmProvider, ToolCall};
 
+use super::loop_guard::{self, LoopGuard, LoopVerdict, LOOP_STOP_REASON};
 use super::{Tool, ToolResult};
 
 /// Maximum tool loop iterations to prevent runaway agents.
 const MAX_ITERATIONS: usize = 25;
 
+/// Maximum concurrency-safe tool calls in flight at once.
+pub const MAX_PARALLEL_TOOLS: usize = 4;
+
 /// Run the full tool execution loop (non-streaming).
 ///
 /// Starts from `initial_request`, which must have `messages` or `raw_messages` set.
-/// Returns the final `ChatResponse` and the deduplicated list of tool names called
-/// during the loop. The caller uses the tool name list for transparent usage tracking.
+/// Returns the final `ChatResponse`, the deduplicated list of tool names called
+/// during the loop and the turn's `TokenUsage`. The caller uses the tool name
+/// list for transparent usage tracking. Token counts on the returned response
+/// and usage are summed over every iteration — including tokens reported by
+/// tools such as `spawn_subagent` — so the caller can bill the whole turn; the
+/// usage also keeps the share tools already priced at their own model's rate.
+///
+/// When `cancel` fires, an in-flight provider call is dropped and the loop
+/// stops before the next iteration. The response then carries the assistant
+/// text produced so far and stop reason `"aborted"`.
+///
+/// Every round of tool calls is checked by a `LoopGuard`. On the first sign of
+/// repetition or an error streak a corrective note is sent to the model with
+/// the tool results; if it persists the turn ends with stop reason
+/// `"loop_detected"` and the reason appended to the reply.
 pub async fn run_tool_loop(
     provider: &dyn LlmProvider,
     initial_request: ChatRequest,
     tools: &[Box<dyn Tool>],
-) -> Result<(ChatResponse, Vec<String>), crate::provider::ProviderError> {
+    windows: &ContextWindows,
+    cancel: &CancellationToken,
+) -> Result<(ChatResponse, Vec<String>, TokenUsage), crate::provider::ProviderError> {
     let mut raw_messages: Vec<serde_json::Value> =
         if let Some(ref raw) = initial_request.raw_messages {
             raw.clone()
@@ -36,22 +63,46 @@ pub async fn run_tool_loop(
     let mut last_response: Option<ChatResponse> = None;
     // Collect every tool name called across all iterations.
     let mut called_tools: Vec<String> = Vec::new();
+    let mut usage = TokenUsage::default();
+    // Assistant text from tool-calling iterations, returned if the turn is aborted.
+    let mut partial = String::new();
+    let mut guard = LoopGuard::new();
 
     for iteration in 0..MAX_ITERATIONS {
+        if cancel.is_cancelled() {
+            info!(iteration, "tool loop aborted");
+            let model = last_response.map_or(initial_request.model, |r| r.model);
+            let resp = ended_early(model, partial, ABORTED_STOP_REASON, &usage);
+            return Ok((resp, called_tools, usage));
+        }
+
         let mut req = initial_request.clone();
         req.raw_messages = Some(raw_messages.clone());
+        let window = windows.get(&provider.resolve_model(&req.model));
+        context_budget::fit_request(&mut req, window);
 
         debug!(iteration, "tool loop iteration");
 
-        let response = provider.send(&req).await?;
+        let response = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                info!(iteration, "tool loop aborted during provider call");
+                let model = last_response.map_or(initial_request.model, |r| r.model);
+                let resp = ended_early(model, partial, ABORTED_STOP_REASON, &usage);
+                return Ok((resp, called_tools, usage));
+            }
+            response = provider.send(&req) => response?,
+        };
+        usage.add(&TokenUsage::from(&response));
 
         if response.tool_calls.is_empty() || response.stop_reason != "tool_use" {
             info!(iteration, "tool loop complete — no more tool calls");
-            return Ok((response, called_tools));
+            return Ok((with_usage(response, &usage), called_tools, usage));
         }
 
         let mut assistant_content: Vec<serde_json::Value> = Vec::new();
 
+        partial.push_str(&response.content);
         if !response.content.is_empty() {
             assistant_content.push(serde_json::json!({
                 "type": "text",
@@ -73,17 +124,48 @@ pub async fn run_tool_loop(
             "content": assistant_content,
         }));
 
-        let mut tool_result_content: Vec<serde_json::Value> = Vec::new();
+        called_tools.extend(response.tool_calls.iter().map(|c| c.name.clone()));
+        let results = execute_tool_calls(tools, &response.tool_calls, |call| {
+            execute_tool(tools, call)
+        })
+        .await;
+        // Subagents report the tokens they spent; bill them to this turn.
+        for u in results.iter().filter_map(|r| r.usage.as_ref()) {
+            usage.add(u);
+        }
 
-        for call in &response.tool_calls {
-            called_tools.push(call.name.clone());
-            let result = execute_tool(tools, call).await;
-            tool_result_content.push(serde_json::json!({
-                "type": "tool_result",
-                "tool_use_id": call.id,
-                "content": result.content,
-                "is_error": result.is_error,
-            }));
+        let verdict = guard.observe(&response.tool_calls, &results);
+
+        let mut tool_result_content: Vec<serde_json::Value> = response
+            .tool_calls
+            .iter()
+            .zip(results)
+            .map(|(call, result)| {
+                serde_json::json!({
+                    "type": "tool_result",
+                    "tool_use_id": call.id,
+                    "content": result.content,
+                    "is_error": result.is_error,
+                })
+            })
+            .collect();
+
+        match verdict {
+            LoopVerdict::Continue => {}
+            LoopVerdict::Nudge(message) => {
+                warn!(iteration, %message, "tool loop: agent looks stuck, nudging");
+                tool_result_content.push(serde_json::json!({ "type": "text", "text": message }));
+            }
+            LoopVerdict::Stop(reason) => {
+                warn!(iteration, %reason, "tool loop: agent stuck, ending turn");
+                let mut content = partial;
+                if !content.is_empty() {
+                    content.push_str("\n\n");
+                }
+                content.push_str(&loop_guard::stop_note(&reason));
+                let resp = ended_early(response.model, content, LOOP_STOP_REASON, &usage);
+                return Ok((resp, called_tools, usage));
+            }
         }
 
         raw_messages.push(serde_json::json!({
@@ -100,7 +182,7 @@ pub async fn run_tool_loop(
     );
 
     if let Some(resp) = last_response {
-        Ok((resp, called_tools))
+        Ok((with_usage(resp, &usage), called_tools, usage))
     } else {
         Err(crate::provider::ProviderError::Parse(format!(
             "tool loop exceeded {MAX_ITERATIONS} iterations without a final response"
@@ -108,6 +190,83 @@ pub async fn run_tool_loop(
     }
 }
 
+/// Response for a turn the loop ended itself (aborted, stuck): `content` and
+/// the usage of the calls that completed.
+fn ended_early(
+    model: String,
+    content: String,
+    stop_reason: &str,
+    usage: &TokenUsage,
+) -> ChatResponse {
+    with_usage(
+        ChatResponse {
+            content,
+            model,
+            tokens_in: 0,
+            tokens_out: 0,
+            cache_write_tokens: 0,
+            cache_read_tokens: 0,
+            stop_reason: stop_reason.to_string(),
+            tool_calls: Vec::new(),
+        },
+        usage,
+    )
+}
+
+/// Replace a response's token counts with the turn totals.
+fn with_usage(mut resp: ChatResponse, usage: &TokenUsage) -> ChatResponse {
+    resp.tokens_in = usage.tokens_in;
+    resp.tokens_out = usage.tokens_out;
+    resp.cache_write_tokens = usage.cache_write_tokens;
+    resp.cache_read_tokens = usage.cache_read_tokens;
+    resp
+}
+
+/// Run one response's tool calls through `run` and return the results in call
+/// order.
+///
+/// Consecutive calls to concurrency-safe tools (`Tool::is_concurrency_safe`)
+/// run together, at most `MAX_PARALLEL_TOOLS` at a time. Any other call —
+/// including unknown tools — runs alone, after everything before it has
+/// finished and before anything after it starts.
+pub async fn execute_tool_calls<'a, F, Fut>(
+    tools: &[Box<dyn Tool>],
+    calls: &'a [ToolCall],
+    run: F,
+) -> Vec<ToolResult>
+where
+    F: Fn(&'a ToolCall) -> Fut,
+    Fut: Future<Output = ToolResult>,
+{
+    let is_safe = |call: &ToolCall| {
+        tools
+            .iter()
+            .find(|t| t.name() == call.name)
+            .is_some_and(|t| t.is_concurrency_safe())
+    };
+
+    let mut results = Vec::with_capacity(calls.len());
+    let mut rest = calls;
+    while let Some(first) = rest.first() {
+        let batch_len = if is_safe(first) {
+            rest.iter().take_while(|c| is_safe(c)).count()
+        } else {
+            1
+        };
+        let (batch, tail) = rest.split_at(batch_len);
+        if batch.len() == 1 {
+            results.push(run(first).await);
+        } else {
+            debug!(calls = batch.len(), "running tool calls in parallel");
+            for chunk in batch.chunks(MAX_PARALLEL_TOOLS) {
+                results.extend(futures_util::future::join_all(chunk.iter().map(&run)).await);
+            }
+        }
+        rest = tail;
+    }
+    results
+}
+
 /// Find and execute the named tool. Returns an error ToolResult if not found.
 async fn execute_tool(tools: &[Box<dyn Tool>], call: &ToolCall) -> ToolResult {
     match tools.iter().find(|t| t.name() == call.name) {
@@ -118,3 +277,203 @@ async fn execute_tool(tools: &[Box<dyn Tool>], call: &ToolCall) -> ToolResult {
         None => ToolResult::error(format!("unknown tool: {}", call.name)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    /// Records how many of its calls overlap; echoes `input.n` back.
+    struct Probe {
+        name: &'static str,
+        safe: bool,
+        running: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for Probe {
+        fn name(&self) -> &str {
+            self.name
+        }
+        fn description(&self) -> &str {
+            "probe"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object" })
+        }
+        fn is_concurrency_safe(&self) -> bool {
+            self.safe
+        }
+        async fn execute(&self, input: serde_json::Value) -> ToolResult {
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            // Later calls finish first, so ordering comes from the executor.
+            let n = input["n"].as_u64().unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(50 - n * 5)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            ToolResult::success(n.to_string())
+        }
+    }
+
+    fn call(name: &str, n: u64) -> ToolCall {
+        ToolCall {
+            id: format!("call_{n}"),
+            name: name.to_string(),
+            input: serde_json::json!({ "n": n }),
+        }
+    }
+
+    fn probes() -> (Vec<Box<dyn Tool>>, Arc<AtomicUsize>) {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tools: Vec<Box<dyn Tool>> = vec![
+            Box::new(Probe {
+                name: "read",
+                safe: true,
+                running: Arc::clone(&running),
+                peak: Arc::clone(&peak),
+            }),
+            Box::new(Probe {
+                name: "write",
+                safe: false,
+                running,
+                peak: Arc::clone(&peak),
+            }),
+        ];
+        (tools, peak)
+    }
+
+    #[tokio::test]
+    async fn safe_calls_run_in_parallel_and_keep_order() {
+        let (tools, peak) = probes();
+        let calls: Vec<ToolCall> = (0..6).map(|n| call("read", n)).collect();
+
+        let results = execute_tool_calls(&tools, &calls, |c| execute_tool(&tools, c)).await;
+
+        let order: Vec<&str> = results.iter().map(|r| r.content.as_str()).collect();
+        assert_eq!(order, ["0", "1", "2", "3", "4", "5"]);
+        assert_eq!(peak.load(Ordering::SeqCst), MAX_PARALLEL_TOOLS);
+    }
+
+    #[tokio::test]
+    async fn unsafe_calls_run_alone() {
+        let (tools, peak) = probes();
+        let calls = vec![
+            call("write", 0),
+            call("write", 1),
+            call("unknown", 2),
+            call("write", 3),
+        ];
+
+        let results = execute_tool_calls(&tools, &calls, |c| execute_tool(&tools, c)).await;
+
+        assert_eq!(results.len(), 4);
+        assert!(results[2].is_error);
+        assert_eq!(results[3].content, "3");
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
+
+    /// Asks for one `delegate` call, then answers.
+    struct Scripted {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmProvider for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+        async fn send(
+            &self,
+            req: &ChatRequest,
+        ) -> Result<ChatResponse, crate::provider::ProviderError> {
+            let first = self.calls.fetch_add(1, Ordering::SeqCst) == 0;
+            Ok(ChatResponse {
+                content: if first {
+                    String::new()
+                } else {
+                    "done".to_string()
+                },
+                model: req.model.clone(),
+                tokens_in: 10,
+                tokens_out: 5,
+                cache_write_tokens: 0,
+                cache_read_tokens: 0,
+                stop_reason: if first { "tool_use" } else { "end_turn" }.to_string(),
+                tool_calls: if first {
+                    vec![call("delegate", 0)]
+                } else {
+                    Vec::new()
+                },
+            })
+        }
+    }
+
+    /// Stands in for `spawn_subagent`: reports tokens spent on its own.
+    struct Delegate;
+
+    #[async_trait]
+    impl Tool for Delegate {
+        fn name(&self) -> &str {
+            "delegate"
+        }
+        fn description(&self) -> &str {
+            "delegate"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object" })
+        }
+        async fn execute(&self, _input: serde_json::Value) -> ToolResult {
+            ToolResult::success("answer").with_usage(TokenUsage {
+                tokens_in: 100,
+                tokens_out: 50,
+                cache_write_tokens: 0,
+                cache_read_tokens: 0,
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_usage_is_rolled_into_the_turn() {
+        let provider = Scripted {
+            calls: AtomicUsize::new(0),
+        };
+        let tools: Vec<Box<dyn Tool>> = vec![Box::new(Delegate)];
+        let request = ChatRequest {
+            model: "test-model".to_string(),
+            system: String::new(),
+            system_prompt: None,
+            messages: vec![crate::provider::Message {
+                role: crate::provider::Role::User,
+                content: "go".into(),
+            }],
+            max_tokens: 100,
+            stream: false,
+            thinking: None,
+            tools: crate::tools::to_definitions(&tools),
+            raw_messages: None,
+        };
+        let windows = ContextWindows::new(&Default::default());
+
+        let (resp, called, usage) = run_tool_loop(
+            &provider,
+            request,
+            &tools,
+            &windows,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(resp.content, "done");
+        assert_eq!(called, ["delegate"]);
+        assert_eq!((resp.tokens_in, resp.tokens_out), (120, 60));
+        assert_eq!(usage.total(), 180);
+    }
+}
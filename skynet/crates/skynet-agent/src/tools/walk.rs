@@ -0,0 +1,140 @@
+//! Directory walking shared by `search_files` and `list_files`.
+//!
+//! Walks honour `.gitignore` (also outside a git repository), `.ignore` and
+//! `.git/info/exclude` unless the call sets `no_ignore`. The `.git`
+//! directory itself is always skipped, other dotfiles are not. `include` and
+//! `exclude` globs (`tools/glob.rs` syntax) match the path relative to the
+//! walk's root, so a leading `/` anchors a pattern there. `exclude` prunes
+//! whole directories; `include` only applies to files.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use ignore::WalkBuilder;
+
+use super::glob::Glob;
+
+/// How a walk's entries are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Name,
+    /// Most recently modified first.
+    Modified,
+}
+
+impl SortOrder {
+    /// The `sort` input parameter, or `default` when absent.
+    pub fn from_input(input: &serde_json::Value, default: Self) -> Result<Self, String> {
+        match input.get("sort").and_then(|v| v.as_str()) {
+            None => Ok(default),
+            Some("name") => Ok(Self::Name),
+            Some("modified") => Ok(Self::Modified),
+            Some(other) => Err(format!("invalid sort '{other}': expected name or modified")),
+        }
+    }
+}
+
+/// The filtering parameters common to both tools.
+#[derive(Debug, Clone, Default)]
+pub struct WalkFilters {
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    no_ignore: bool,
+}
+
+impl WalkFilters {
+    /// Read `include`, `exclude` (string or array of strings) and `no_ignore`.
+    pub fn from_input(input: &serde_json::Value) -> Result<Self, String> {
+        Ok(Self {
+            include: globs(input, "include")?,
+            exclude: globs(input, "exclude")?,
+            no_ignore: input
+                .get("no_ignore")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        })
+    }
+
+    /// Also require files to match `glob`.
+    pub fn include(&mut self, glob: &str) -> Result<(), String> {
+        self.include.push(Glob::new(glob)?);
+        Ok(())
+    }
+
+    /// Walker over `root` that applies the ignore files and `exclude`.
+    pub fn walker(&self, root: &Path) -> WalkBuilder {
+        let use_ignore = !self.no_ignore;
+        let mut builder = WalkBuilder::new(root);
+        builder
+            .hidden(false)
+            .parents(use_ignore)
+            .ignore(use_ignore)
+            .git_ignore(use_ignore)
+            .git_exclude(use_ignore)
+            .git_global(false)
+            .require_git(false)
+            .follow_links(false);
+        let root = root.to_path_buf();
+        let exclude = self.exclude.clone();
+        builder.filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            if is_dir && entry.file_name() == ".git" {
+                return false;
+            }
+            let relative = relative_to(&root, entry.path());
+            !exclude.iter().any(|g| g.is_match(relative))
+        });
+        builder
+    }
+
+    /// Whether any `include` glob is set.
+    pub fn has_include(&self) -> bool {
+        !self.include.is_empty()
+    }
+
+    /// Whether a file at `path` (under `root`) passes the `include` globs.
+    pub fn includes(&self, root: &Path, path: &Path) -> bool {
+        let relative = relative_to(root, path);
+        self.include.is_empty() || self.include.iter().any(|g| g.is_match(relative))
+    }
+}
+
+/// `path` relative to `root`, or unchanged when it is not below it.
+fn relative_to<'a>(root: &Path, path: &'a Path) -> &'a Path {
+    path.strip_prefix(root).unwrap_or(path)
+}
+
+/// Modification time of `path`, `UNIX_EPOCH` when unknown.
+pub fn modified(path: &Path) -> SystemTime {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Sort `paths` by `order`; modification times are read from disk.
+pub fn sort_paths(paths: &mut [PathBuf], order: SortOrder) {
+    match order {
+        SortOrder::Name => paths.sort(),
+        SortOrder::Modified => {
+            paths.sort_by_cached_key(|p| (std::cmp::Reverse(modified(p)), p.clone()))
+        }
+    }
+}
+
+fn globs(input: &serde_json::Value, key: &str) -> Result<Vec<Glob>, String> {
+    match input.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(Vec::new()),
+        Some(serde_json::Value::String(s)) => Ok(vec![Glob::new(s)?]),
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .map(|item| match item.as_str() {
+                Some(s) => Glob::new(s),
+                None => Err(format!("'{key}' must contain strings")),
+            })
+            .collect(),
+        Some(_) => Err(format!("'{key}' must be a string or an array of strings")),
+    }
+}
@@ -0,0 +1,661 @@
+//! Sandboxed WebAssembly plugin tools, loaded from `~/.skynet/tools/` next to
+//! script plugins.
+//!
+//! A WebAssembly plugin is a WASI preview 2 component that exports the
+//! `plugin` world of `wit/plugin.wit`:
+//!
+//! ```text
+//! ~/.skynet/tools/
+//!   my_plugin/
+//!     tool.toml     ← manifest with a [wasm] table instead of [run]
+//!     plugin.wasm   ← the component
+//!     data/         ← optional, the only files the plugin can see
+//! ```
+//!
+//! ## Execution contract
+//!
+//! - `run(input)` gets the tool input as a JSON string and returns the result
+//!   text (`ok`) or an error message (`err`).
+//! - Each call gets a fresh instance with no ambient authority: no host
+//!   environment, no sockets, no clocks beyond WASI's, stdout discarded and
+//!   stderr captured for error messages.
+//! - Files: only the `files` subdirectory, preopened as `/`, read-only unless
+//!   `writable = true`. Without `files` the plugin sees no filesystem.
+//! - HTTP: only through the `http` import, and only to `allow_hosts` (same
+//!   matching as `[agent.web_fetch]`); local and private addresses are always
+//!   refused. An empty `allow_hosts` means no network.
+//! - Limits: `fuel` (roughly, executed instructions), `memory_mb` of linear
+//!   memory and `timeout` seconds, each capped by `[agent.plugins.wasm]`.
+//! - Declared `secrets` are set as WASI environment variables and redacted
+//!   from the result.
+//!
+//! ## Manifest format (`tool.toml`)
+//!
+//! ```toml
+//! name        = "weather"
+//! description = "Current weather for a city"
+//! version     = "0.1.0"           # optional
+//! permission  = "use_browser"     # optional, a `Permission` name
+//!
+//! [wasm]
+//! component   = "plugin.wasm"     # relative to the plugin directory
+//! timeout     = 30                # seconds (optional, default 30)
+//! fuel        = 1_000_000_000     # optional, default 1e9
+//! memory_mb   = 64                # optional, default 64
+//! files       = "data"            # optional preopened subdirectory
+//! writable    = false             # optional, default false
+//! allow_hosts = ["api.open-meteo.com"]
+//! secrets     = ["WEATHER_KEY"]   # keys of [agent.plugins.secrets] (optional)
+//!
+//! [[input.params]]
+//! name        = "city"
+//! type        = "string"
+//! description = "City name"
+//! required    = true
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use url::Url;
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Config, Engine, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::pipe::MemoryOutputPipe;
+use wasmtime_wasi::{DirPerms, FilePerms, IoView, WasiCtx, WasiCtxBuilder, WasiView};
+
+use skynet_core::config::{WasmPluginsConfig, WebFetchConfig};
+use skynet_users::permissions::Permission;
+
+use super::script_tool::{missing_secret, parse_permission, read_manifest, redact, InputConfig};
+use super::web_fetch::{check_url, error_chain, host_matches, http_client};
+use super::{Tool, ToolResult};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        world: "plugin",
+        path: "wit",
+        async: true,
+    });
+}
+
+use bindings::skynet::plugin::http;
+use bindings::{Plugin, PluginPre};
+
+const USER_AGENT: &str = concat!("skynet/", env!("CARGO_PKG_VERSION"), " (wasm plugin)");
+
+/// Bytes of an HTTP response body handed to a plugin.
+const MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Captured stderr per call.
+const STDERR_CAPACITY: usize = 64 * 1024;
+
+/// Fuel between yields to the runtime, so `timeout` can interrupt a busy loop.
+const FUEL_YIELD_INTERVAL: u64 = 100_000;
+
+// ---------------------------------------------------------------------------
+// Manifest types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct WasmManifest {
+    name: String,
+    description: String,
+    #[serde(default)]
+    version: Option<String>,
+    /// `Permission` the caller needs, e.g. "use_browser".
+    #[serde(default)]
+    permission: Option<String>,
+    wasm: WasmConfig,
+    #[serde(default)]
+    input: InputConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct WasmConfig {
+    /// Component file, relative to the plugin directory.
+    component: String,
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+    #[serde(default = "default_fuel")]
+    fuel: u64,
+    #[serde(default = "default_memory_mb")]
+    memory_mb: u64,
+    /// Subdirectory preopened as `/`.
+    #[serde(default)]
+    files: Option<String>,
+    #[serde(default)]
+    writable: bool,
+    /// Hosts the `http` import may reach; empty = none.
+    #[serde(default)]
+    allow_hosts: Vec<String>,
+    /// `[agent.plugins.secrets]` keys set as environment variables.
+    #[serde(default)]
+    secrets: Vec<String>,
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_fuel() -> u64 {
+    1_000_000_000
+}
+
+fn default_memory_mb() -> u64 {
+    64
+}
+
+// ---------------------------------------------------------------------------
+// Runtime
+// ---------------------------------------------------------------------------
+
+/// Shared engine: async, fuel metering, component model.
+fn engine() -> Result<&'static Engine, String> {
+    static ENGINE: OnceLock<Result<Engine, String>> = OnceLock::new();
+    ENGINE
+        .get_or_init(|| {
+            let mut config = Config::new();
+            config
+                .async_support(true)
+                .consume_fuel(true)
+                .wasm_component_model(true);
+            Engine::new(&config).map_err(|e| format!("cannot start the WebAssembly runtime: {e}"))
+        })
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// Per-call store data.
+struct HostState {
+    wasi: WasiCtx,
+    table: ResourceTable,
+    limits: StoreLimits,
+    http: HttpAccess,
+}
+
+impl IoView for HostState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+impl WasiView for HostState {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl http::Host for HostState {
+    async fn fetch(&mut self, req: http::Request) -> Result<http::Response, String> {
+        self.http.fetch(req).await
+    }
+}
+
+/// The `http` import of one plugin.
+#[derive(Debug, Clone)]
+struct HttpAccess {
+    /// `allow_hosts` plus the plugin's timeout; never `allow_private`.
+    rules: WebFetchConfig,
+}
+
+impl HttpAccess {
+    fn new(allow_hosts: Vec<String>, timeout_secs: u64) -> Self {
+        Self {
+            rules: WebFetchConfig {
+                allow_hosts,
+                allow_private: false,
+                max_bytes: MAX_RESPONSE_BYTES,
+                timeout_secs,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The host is in `allow_hosts`; an empty list allows nothing.
+    fn check_host(&self, url: &Url) -> Result<(), String> {
+        let host = url
+            .host_str()
+            .unwrap_or_default()
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        if self
+            .rules
+            .allow_hosts
+            .iter()
+            .any(|p| host_matches(&host, p))
+        {
+            Ok(())
+        } else {
+            Err(format!("host '{host}' is not in the plugin's allow_hosts"))
+        }
+    }
+
+    async fn fetch(&self, req: http::Request) -> Result<http::Response, String> {
+        let url =
+            Url::parse(req.url.trim()).map_err(|e| format!("invalid URL '{}': {e}", req.url))?;
+        self.check_host(&url)?;
+        check_url(&self.rules, &url)?;
+        let method = reqwest::Method::from_bytes(req.method.to_ascii_uppercase().as_bytes())
+            .map_err(|_| format!("invalid HTTP method '{}'", req.method))?;
+
+        let mut request = http_client(&self.rules, USER_AGENT)?.request(method, url.clone());
+        for (name, value) in req.headers {
+            request = request.header(name, value);
+        }
+        if let Some(body) = req.body {
+            request = request.body(body);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("failed to fetch {url}: {}", error_chain(&e)))?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let mut body = Vec::new();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| format!("failed to read {url}: {}", error_chain(&e)))?;
+            if body.len() + chunk.len() > self.rules.max_bytes {
+                return Err(format!(
+                    "response from {url} is larger than {} bytes",
+                    self.rules.max_bytes
+                ));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(http::Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// WasmTool
+// ---------------------------------------------------------------------------
+
+/// A WebAssembly component plugin. The component is compiled and linked once,
+/// at load; each call instantiates it in a fresh store.
+#[derive(Clone)]
+pub struct WasmTool {
+    manifest: Arc<WasmManifest>,
+    permission: Option<Permission>,
+    dir: PathBuf,
+    /// Canonical path of `files`.
+    files: Option<PathBuf>,
+    fuel: u64,
+    memory_bytes: usize,
+    timeout: Duration,
+    pre: Arc<PluginPre<HostState>>,
+    secrets: Arc<HashMap<String, String>>,
+}
+
+impl WasmTool {
+    /// Load and compile the plugin in `dir`, clamping its limits to `caps`.
+    pub fn load(
+        dir: &Path,
+        secrets: Arc<HashMap<String, String>>,
+        caps: &WasmPluginsConfig,
+    ) -> Result<Self, String> {
+        let manifest: WasmManifest = read_manifest(dir)?;
+        if manifest.name.trim().is_empty() {
+            return Err("invalid tool.toml: name is empty".to_string());
+        }
+        let permission = parse_permission(manifest.permission.as_deref())?;
+        let wasm = &manifest.wasm;
+        let files = wasm
+            .files
+            .as_deref()
+            .map(|files| inside(dir, files))
+            .transpose()?;
+
+        let engine = engine()?;
+        let component = Component::from_file(engine, inside(dir, &wasm.component)?)
+            .map_err(|e| format!("cannot compile {}: {e}", wasm.component))?;
+        let mut linker = Linker::new(engine);
+        wasmtime_wasi::add_to_linker_async(&mut linker).map_err(|e| e.to_string())?;
+        Plugin::add_to_linker(&mut linker, |state: &mut HostState| state)
+            .map_err(|e| e.to_string())?;
+        let pre = linker
+            .instantiate_pre(&component)
+            .and_then(PluginPre::new)
+            .map_err(|e| format!("{} is not a skynet plugin component: {e:#}", wasm.component))?;
+
+        Ok(Self {
+            fuel: wasm.fuel.min(caps.max_fuel),
+            memory_bytes: usize::try_from(wasm.memory_mb.min(caps.max_memory_mb) << 20)
+                .unwrap_or(usize::MAX),
+            timeout: Duration::from_secs(wasm.timeout.min(caps.max_timeout_secs)),
+            manifest: Arc::new(manifest),
+            permission,
+            dir: dir.to_path_buf(),
+            files,
+            pre: Arc::new(pre),
+            secrets,
+        })
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.manifest.version.as_deref()
+    }
+
+    /// Permission the caller needs; `None` = offered to everyone.
+    pub fn permission(&self) -> Option<&Permission> {
+        self.permission.as_ref()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// A fresh store with the plugin's capabilities and limits, writing
+    /// stderr to `stderr`.
+    fn store(&self, stderr: &MemoryOutputPipe) -> Result<Store<HostState>, String> {
+        let wasm = &self.manifest.wasm;
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stderr(stderr.clone())
+            .allow_tcp(false)
+            .allow_udp(false)
+            .allow_ip_name_lookup(false);
+        for name in &wasm.secrets {
+            match self.secrets.get(name) {
+                Some(value) => wasi.env(name, value),
+                None => return Err(missing_secret(&self.manifest.name, name)),
+            };
+        }
+        if let Some(files) = &self.files {
+            let (dir_perms, file_perms) = if wasm.writable {
+                (DirPerms::all(), FilePerms::all())
+            } else {
+                (DirPerms::READ, FilePerms::READ)
+            };
+            wasi.preopened_dir(files, "/", dir_perms, file_perms)
+                .map_err(|e| format!("cannot open {}: {e}", files.display()))?;
+        }
+
+        let state = HostState {
+            wasi: wasi.build(),
+            table: ResourceTable::new(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(self.memory_bytes)
+                .build(),
+            http: HttpAccess::new(wasm.allow_hosts.clone(), self.timeout.as_secs()),
+        };
+        let mut store = Store::new(engine()?, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.fuel).map_err(|e| e.to_string())?;
+        store
+            .fuel_async_yield_interval(Some(FUEL_YIELD_INTERVAL))
+            .map_err(|e| e.to_string())?;
+        Ok(store)
+    }
+
+    /// Instantiate the component and call `run`. The outer error is a trap.
+    async fn run(
+        &self,
+        store: &mut Store<HostState>,
+        input: &str,
+    ) -> wasmtime::Result<Result<String, String>> {
+        let plugin = self.pre.instantiate_async(&mut *store).await?;
+        plugin.call_run(&mut *store, input).await
+    }
+
+    /// `message`, then the captured stderr, with secrets masked.
+    fn error(&self, message: String, stderr: &MemoryOutputPipe) -> ToolResult {
+        let stderr = String::from_utf8_lossy(&stderr.contents())
+            .trim()
+            .to_string();
+        let mut message = message;
+        if !stderr.is_empty() {
+            message.push_str(&format!("\n[stderr]: {stderr}"));
+        }
+        ToolResult::error(redact(&message, &self.manifest.wasm.secrets, &self.secrets))
+    }
+}
+
+/// `path` under `dir`, refusing anything that resolves outside of it.
+fn inside(dir: &Path, path: &str) -> Result<PathBuf, String> {
+    let resolved = dir
+        .join(path)
+        .canonicalize()
+        .map_err(|e| format!("invalid tool.toml: cannot open '{path}': {e}"))?;
+    let root = dir.canonicalize().map_err(|e| e.to_string())?;
+    if resolved.starts_with(&root) {
+        Ok(resolved)
+    } else {
+        Err(format!(
+            "invalid tool.toml: '{path}' is outside the plugin directory"
+        ))
+    }
+}
+
+#[async_trait]
+impl Tool for WasmTool {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn description(&self) -> &str {
+        &self.manifest.description
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        self.manifest.input.schema()
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let stderr = MemoryOutputPipe::new(STDERR_CAPACITY);
+        let mut store = match self.store(&stderr) {
+            Ok(store) => store,
+            Err(e) => return ToolResult::error(e),
+        };
+        let name = &self.manifest.name;
+        match tokio::time::timeout(self.timeout, self.run(&mut store, &input.to_string())).await {
+            Err(_) => self.error(
+                format!(
+                    "plugin '{name}' timed out after {}s",
+                    self.timeout.as_secs()
+                ),
+                &stderr,
+            ),
+            Ok(Err(trap)) => {
+                let reason = match trap.downcast_ref::<Trap>() {
+                    Some(Trap::OutOfFuel) => "ran out of fuel".to_string(),
+                    _ => format!("trapped: {trap:#}"),
+                };
+                self.error(format!("plugin '{name}' {reason}"), &stderr)
+            }
+            Ok(Ok(Err(message))) => self.error(message, &stderr),
+            Ok(Ok(Ok(content))) => {
+                let content = redact(content.trim(), &self.manifest.wasm.secrets, &self.secrets);
+                ToolResult::success(if content.is_empty() {
+                    "(no output)".to_string()
+                } else {
+                    content
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_encoder::{
+        BlockType, CanonicalOption, CodeSection, ComponentBuilder, ComponentExportKind,
+        ComponentValType, ConstExpr, DataSection, ExportKind, ExportSection, Function,
+        FunctionSection, Instruction, MemorySection, MemoryType, Module, ModuleArg,
+        PrimitiveValType, TypeSection, ValType,
+    };
+
+    use super::*;
+
+    /// A component whose `run` returns `ok("pong")`, or spins forever first
+    /// when `spin` is set.
+    fn component(spin: bool) -> Vec<u8> {
+        let mut types = TypeSection::new();
+        types
+            .ty()
+            .function([ValType::I32, ValType::I32], [ValType::I32]);
+        types.ty().function([ValType::I32; 4], [ValType::I32]);
+        let mut functions = FunctionSection::new();
+        functions.function(0).function(1);
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            minimum: 1,
+            maximum: None,
+            memory64: false,
+            shared: false,
+            page_size_log2: None,
+        });
+        let mut exports = ExportSection::new();
+        exports
+            .export("memory", ExportKind::Memory, 0)
+            .export("run", ExportKind::Func, 0)
+            .export("cabi_realloc", ExportKind::Func, 1);
+
+        // `run` returns a pointer to (tag 0 = ok, string pointer, length).
+        let mut run = Function::new([]);
+        if spin {
+            run.instruction(&Instruction::Loop(BlockType::Empty))
+                .instruction(&Instruction::Br(0))
+                .instruction(&Instruction::End);
+        }
+        run.instruction(&Instruction::I32Const(16))
+            .instruction(&Instruction::End);
+        // Every allocation (only the input string) lands at 1024.
+        let mut realloc = Function::new([]);
+        realloc
+            .instruction(&Instruction::I32Const(1024))
+            .instruction(&Instruction::End);
+        let mut code = CodeSection::new();
+        code.function(&run).function(&realloc);
+        let mut data = DataSection::new();
+        let mut ret = vec![0u8; 4];
+        ret.extend_from_slice(&64u32.to_le_bytes());
+        ret.extend_from_slice(&4u32.to_le_bytes());
+        data.active(0, &ConstExpr::i32_const(16), ret).active(
+            0,
+            &ConstExpr::i32_const(64),
+            b"pong".iter().copied(),
+        );
+
+        let mut module = Module::new();
+        module
+            .section(&types)
+            .section(&functions)
+            .section(&memories)
+            .section(&exports)
+            .section(&code)
+            .section(&data);
+
+        let mut builder = ComponentBuilder::default();
+        let module = builder.core_module(&module);
+        let instance = builder.core_instantiate(module, Vec::<(&str, ModuleArg)>::new());
+        let memory = builder.core_alias_export(instance, "memory", ExportKind::Memory);
+        let realloc = builder.core_alias_export(instance, "cabi_realloc", ExportKind::Func);
+        let run = builder.core_alias_export(instance, "run", ExportKind::Func);
+        let string = ComponentValType::Primitive(PrimitiveValType::String);
+        let (result, encoder) = builder.type_defined();
+        encoder.result(Some(string), Some(string));
+        let (func_type, mut encoder) = builder.type_function();
+        encoder
+            .params([("input", string)])
+            .result(ComponentValType::Type(result));
+        let func = builder.lift_func(
+            run,
+            func_type,
+            [
+                CanonicalOption::UTF8,
+                CanonicalOption::Memory(memory),
+                CanonicalOption::Realloc(realloc),
+            ],
+        );
+        builder.export("run", ComponentExportKind::Func, func, None);
+        builder.finish()
+    }
+
+    fn plugin_dir(name: &str, wasm: &str, component: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("skynet-wasm-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("plugin.wasm"), component).unwrap();
+        std::fs::write(
+            dir.join("tool.toml"),
+            format!(
+                "name = \"{name}\"\ndescription = \"d\"\n\
+                 [wasm]\ncomponent = \"plugin.wasm\"\n{wasm}\n"
+            ),
+        )
+        .unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn runs_components_within_fuel_and_path_limits() {
+        let caps = WasmPluginsConfig::default();
+        let secrets = Arc::new(HashMap::new());
+
+        let dir = plugin_dir("pong", "", &component(false));
+        let tool = WasmTool::load(&dir, Arc::clone(&secrets), &caps).unwrap();
+        let result = tool.execute(serde_json::json!({"q": 1})).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert_eq!(result.content, "pong");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dir = plugin_dir("spin", "fuel = 1_000_000", &component(true));
+        let tool = WasmTool::load(&dir, Arc::clone(&secrets), &caps).unwrap();
+        let result = tool.execute(serde_json::json!({})).await;
+        assert!(result.is_error);
+        assert_eq!(result.content, "plugin 'spin' ran out of fuel");
+
+        std::fs::write(dir.join("plugin.wasm"), b"not wasm").unwrap();
+        let err = WasmTool::load(&dir, Arc::clone(&secrets), &caps)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("cannot compile plugin.wasm"), "{err}");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dir = plugin_dir("escape", "files = \"..\"", &component(false));
+        let err = WasmTool::load(&dir, secrets, &caps).err().unwrap();
+        assert!(err.contains("outside the plugin directory"), "{err}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn http_is_limited_to_allowed_hosts() {
+        let request = |url: &str| http::Request {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+        };
+
+        let none = HttpAccess::new(Vec::new(), 5);
+        let err = none.fetch(request("https://example.com/")).await.err();
+        assert_eq!(
+            err.as_deref(),
+            Some("host 'example.com' is not in the plugin's allow_hosts")
+        );
+
+        let local = HttpAccess::new(vec!["localhost".to_string(), "127.0.0.1".to_string()], 5);
+        for url in ["http://localhost:1/", "http://127.0.0.1:1/"] {
+            let err = local.fetch(request(url)).await.err().unwrap();
+            assert!(err.contains("local or private address"), "{err}");
+        }
+        let err = local.fetch(request("http://api.example.com/")).await.err();
+        assert_eq!(
+            err.as_deref(),
+            Some("host 'api.example.com' is not in the plugin's allow_hosts")
+        );
+    }
+}
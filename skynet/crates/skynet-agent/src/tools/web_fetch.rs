@@ -0,0 +1,673 @@
+//! Tool: web_fetch — download a web page and return it as Markdown.
+//!
+//! Downloads are bounded by `[agent.web_fetch]`: `timeout_secs`, `max_bytes`
+//! and host rules that are checked for the URL and every redirect. Unless
+//! `allow_private` is set, hosts that are or resolve to loopback, private or
+//! link-local addresses are refused: literal addresses by `check_url`, names
+//! by the client's DNS resolver, so redirects and re-resolved names are
+//! covered too. HTML is converted to Markdown by
+//! `tools::html`; other text is returned as-is. Each call returns at most
+//! `max_chars` characters, and longer pages are read on with `offset`.
+
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use reqwest::header::CONTENT_TYPE;
+use tracing::info;
+use url::{Host, Url};
+
+use skynet_core::config::WebFetchConfig;
+
+use super::html;
+use super::{Tool, ToolResult};
+
+const MAX_REDIRECTS: usize = 5;
+const USER_AGENT: &str = concat!("skynet/", env!("CARGO_PKG_VERSION"), " (web_fetch)");
+
+/// A downloaded page, converted to text.
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    /// URL after redirects.
+    pub url: String,
+    pub title: Option<String>,
+    /// Markdown for HTML pages, the body for other text.
+    pub text: String,
+    /// The body was cut off at `max_bytes`.
+    pub truncated: bool,
+}
+
+/// Download `url` within `config`'s limits and convert it to text.
+pub async fn fetch_page(config: &WebFetchConfig, url: &str) -> Result<FetchedPage, String> {
+    let url = Url::parse(url.trim()).map_err(|e| format!("invalid URL '{url}': {e}"))?;
+    check_url(config, &url)?;
+    let client = http_client(config, USER_AGENT)?;
+
+    let response = client
+        .get(url.clone())
+        .header(
+            "accept",
+            "text/html,application/xhtml+xml,text/*;q=0.9,*/*;q=0.5",
+        )
+        .send()
+        .await
+        .map_err(|e| format!("failed to fetch {url}: {}", error_chain(&e)))?;
+    let final_url = response.url().clone();
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {status} from {final_url}"));
+    }
+    let media_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let kind = content_kind(&media_type).ok_or_else(|| {
+        format!("unsupported content type '{media_type}' at {final_url}: only HTML and text can be read")
+    })?;
+
+    let mut body = Vec::new();
+    let mut truncated = false;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk =
+            chunk.map_err(|e| format!("failed to read {final_url}: {}", error_chain(&e)))?;
+        let room = config.max_bytes - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let body = String::from_utf8_lossy(&body);
+
+    let is_html = match kind {
+        ContentKind::Html => true,
+        ContentKind::Text => false,
+        // No content type: sniff.
+        ContentKind::Unknown => {
+            let head = body.trim_start().get(..15).unwrap_or_default();
+            head.to_ascii_lowercase().starts_with("<!doctype html") || head.starts_with("<html")
+        }
+    };
+    let (title, text) = if is_html {
+        let page = html::to_markdown(&body, Some(&final_url));
+        (page.title, page.body)
+    } else {
+        (None, body.into_owned())
+    };
+    Ok(FetchedPage {
+        url: final_url.to_string(),
+        title,
+        text,
+        truncated,
+    })
+}
+
+enum ContentKind {
+    Html,
+    Text,
+    Unknown,
+}
+
+fn content_kind(media_type: &str) -> Option<ContentKind> {
+    match media_type {
+        "" => Some(ContentKind::Unknown),
+        "text/html" | "application/xhtml+xml" => Some(ContentKind::Html),
+        t if t.starts_with("text/")
+            || t.ends_with("+json")
+            || t.ends_with("+xml")
+            || matches!(
+                t,
+                "application/json" | "application/xml" | "application/javascript"
+            ) =>
+        {
+            Some(ContentKind::Text)
+        }
+        _ => None,
+    }
+}
+
+/// HTTP client with `config`'s timeout that follows at most `MAX_REDIRECTS`
+/// redirects, checking each target with `check_url`. Unless `allow_private`,
+/// names are resolved by `PublicResolver`.
+pub(crate) fn http_client(
+    config: &WebFetchConfig,
+    user_agent: &str,
+) -> Result<reqwest::Client, String> {
+    client_with(
+        config,
+        user_agent,
+        PublicResolver {
+            lookup: system_lookup,
+            blocked: is_private,
+        },
+    )
+}
+
+fn client_with(
+    config: &WebFetchConfig,
+    user_agent: &str,
+    resolver: PublicResolver,
+) -> Result<reqwest::Client, String> {
+    let rules = config.clone();
+    let redirect = reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error(format!("more than {MAX_REDIRECTS} redirects"))
+        } else if let Err(e) = check_url(&rules, attempt.url()) {
+            attempt.error(e)
+        } else {
+            attempt.follow()
+        }
+    });
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .redirect(redirect)
+        .user_agent(user_agent);
+    if !config.allow_private {
+        builder = builder.dns_resolver(Arc::new(resolver));
+    }
+    builder
+        .build()
+        .map_err(|e| format!("failed to build HTTP client: {e}"))
+}
+
+type LookupFuture = Pin<Box<dyn Future<Output = std::io::Result<Vec<SocketAddr>>> + Send>>;
+type Lookup = fn(String) -> LookupFuture;
+
+fn system_lookup(host: String) -> LookupFuture {
+    Box::pin(async move { Ok(tokio::net::lookup_host((host.as_str(), 0)).await?.collect()) })
+}
+
+/// Resolver that drops `blocked` addresses. Every connection, including one
+/// for a redirect, resolves through it, so a name cannot be pointed at a
+/// local or private address after it was checked.
+struct PublicResolver {
+    lookup: Lookup,
+    blocked: fn(IpAddr) -> bool,
+}
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        let lookup = (self.lookup)(host.clone());
+        let blocked = self.blocked;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = lookup
+                .await
+                .map_err(|e| format!("failed to resolve '{host}': {e}"))?;
+            let public: Vec<SocketAddr> =
+                addrs.iter().copied().filter(|a| !blocked(a.ip())).collect();
+            if public.is_empty() {
+                return Err(match addrs.first() {
+                    Some(addr) => format!(
+                        "host '{host}' resolves to a local or private address ({})",
+                        addr.ip()
+                    ),
+                    None => format!("failed to resolve '{host}'"),
+                }
+                .into());
+            }
+            Ok(Box::new(public.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Scheme and host rules for the URL and each redirect. Literal addresses
+/// are checked here; names are checked when `PublicResolver` resolves them.
+pub(crate) fn check_url(config: &WebFetchConfig, url: &Url) -> Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!(
+            "unsupported URL scheme '{}': only http and https are allowed",
+            url.scheme()
+        ));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("URL has no host: {url}"))?
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    if config.deny_hosts.iter().any(|p| host_matches(&host, p)) {
+        return Err(format!(
+            "host '{host}' is blocked by agent.web_fetch.deny_hosts"
+        ));
+    }
+    if !config.allow_hosts.is_empty() && !config.allow_hosts.iter().any(|p| host_matches(&host, p))
+    {
+        return Err(format!(
+            "host '{host}' is not in agent.web_fetch.allow_hosts"
+        ));
+    }
+    if !config.allow_private {
+        let private = match url.host() {
+            Some(Host::Domain(_)) => host == "localhost" || host.ends_with(".localhost"),
+            Some(Host::Ipv4(ip)) => is_private(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => is_private(IpAddr::V6(ip)),
+            None => false,
+        };
+        if private {
+            return Err(format!("host '{host}' is a local or private address"));
+        }
+    }
+    Ok(())
+}
+
+/// `host` is `pattern` or one of its subdomains.
+pub(crate) fn host_matches(host: &str, pattern: &str) -> bool {
+    let pattern = pattern
+        .trim()
+        .trim_start_matches("*.")
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    !pattern.is_empty()
+        && (host == pattern
+            || host
+                .strip_suffix(pattern.as_str())
+                .is_some_and(|rest| rest.ends_with('.')))
+}
+
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // 100.64.0.0/10, carrier-grade NAT
+                || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_private(IpAddr::V4(v4)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || first & 0xfe00 == 0xfc00 // unique local
+                    || first & 0xffc0 == 0xfe80 // link-local
+            }
+        },
+    }
+}
+
+/// A reqwest error with its sources, which carry the redirect policy's reason.
+pub(crate) fn error_chain(e: &reqwest::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = std::error::Error::source(e);
+    while let Some(s) = source {
+        message.push_str(&format!(": {s}"));
+        source = s.source();
+    }
+    message
+}
+
+/// Up to `max_chars` characters of `text` from character `offset`, ending at
+/// a paragraph break when one falls in the second half. Returns the slice and
+/// the character offset just past it.
+pub(crate) fn page_chunk(text: &str, offset: usize, max_chars: usize) -> (&str, usize) {
+    let start = text
+        .char_indices()
+        .nth(offset)
+        .map_or(text.len(), |(i, _)| i);
+    let rest = &text[start..];
+    let Some((end, _)) = rest.char_indices().nth(max_chars) else {
+        return (rest, offset + rest.chars().count());
+    };
+    let chunk = &rest[..end];
+    let chunk = match chunk.rfind("\n\n") {
+        Some(i) if i >= end / 2 => &chunk[..i],
+        _ => chunk,
+    };
+    (chunk, offset + chunk.chars().count())
+}
+
+pub struct WebFetchTool {
+    config: WebFetchConfig,
+}
+
+impl WebFetchTool {
+    pub fn new(config: WebFetchConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Tool for WebFetchTool {
+    fn name(&self) -> &str {
+        "web_fetch"
+    }
+
+    fn description(&self) -> &str {
+        "Download a web page (http or https) and return its readable content as Markdown, \
+         without navigation, scripts or styles. Long pages are returned in parts: when the \
+         result says so, call again with the given `offset` to read on."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "Absolute http(s) URL of the page."
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Character offset to continue from (optional, default 0)."
+                },
+                "max_chars": {
+                    "type": "integer",
+                    "description": "Maximum characters to return (optional)."
+                }
+            },
+            "required": ["url"]
+        })
+    }
+
+    fn is_concurrency_safe(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let Some(url) = input.get("url").and_then(|v| v.as_str()) else {
+            return ToolResult::error("missing required parameter: url");
+        };
+        let offset = input.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+        let max_chars = input
+            .get("max_chars")
+            .and_then(|v| v.as_u64())
+            .map_or(self.config.max_chars, |n| n as usize)
+            .clamp(1, self.config.max_chars.max(1));
+
+        info!(url, offset, "web_fetch");
+        let page = match fetch_page(&self.config, url).await {
+            Ok(page) => page,
+            Err(e) => return ToolResult::error(e),
+        };
+
+        let total = page.text.chars().count();
+        if offset > 0 && offset >= total {
+            return ToolResult::error(format!(
+                "offset {offset} is past the end of the page ({total} characters)"
+            ));
+        }
+        let (chunk, next) = page_chunk(&page.text, offset, max_chars);
+
+        let mut out = format!("URL: {}\n", page.url);
+        if let Some(ref title) = page.title {
+            out.push_str(&format!("Title: {title}\n"));
+        }
+        out.push('\n');
+        out.push_str(if chunk.trim().is_empty() {
+            "(no readable content)"
+        } else {
+            chunk
+        });
+        if next < total {
+            out.push_str(&format!(
+                "\n\n[Characters {offset}-{next} of {total}. Call web_fetch again with \
+                 offset={next} to continue.]"
+            ));
+        }
+        if page.truncated {
+            out.push_str(&format!(
+                "\n\n[Download stopped at {} bytes; the rest of the page was not read.]",
+                self.config.max_bytes
+            ));
+        }
+        ToolResult::success(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::response::Redirect;
+    use axum::routing::get;
+    use axum::Router;
+
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>Fixture</title><script>var x = "<p>hidden</p>";</script></head>
+<body><nav><a href="/">Home</a> | <a href="/about">About</a></nav>
+<main><h1>Hello</h1><p>See the <a href="docs/intro">intro</a>.</p></main>
+<footer>Footer text</footer></body></html>"#;
+
+    /// Local server with fixture pages; returns its base URL.
+    async fn serve() -> String {
+        let long = format!(
+            "<html><body>{}</body></html>",
+            (0..50)
+                .map(|i| format!("<p>Paragraph {i} {}</p>", "lorem ipsum ".repeat(8)))
+                .collect::<String>()
+        );
+        let app = Router::new()
+            .route(
+                "/page",
+                get(|| async { ([("content-type", "text/html; charset=utf-8")], PAGE) }),
+            )
+            .route(
+                "/long",
+                get(move || async move { ([("content-type", "text/html")], long) }),
+            )
+            .route(
+                "/big",
+                get(|| async { ([("content-type", "text/plain")], "x".repeat(10_000)) }),
+            )
+            .route(
+                "/image",
+                get(|| async { ([("content-type", "image/png")], vec![0u8; 16]) }),
+            )
+            .route("/moved", get(|| async { Redirect::temporary("/page") }))
+            .route(
+                "/evil",
+                get(|| async { Redirect::temporary("http://blocked.test/") }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn local_config() -> WebFetchConfig {
+        WebFetchConfig {
+            allow_private: true,
+            ..WebFetchConfig::default()
+        }
+    }
+
+    #[test]
+    fn host_rules() {
+        assert!(host_matches("docs.example.com", "example.com"));
+        assert!(host_matches("example.com", "*.example.com"));
+        assert!(!host_matches("badexample.com", "example.com"));
+
+        let config = WebFetchConfig {
+            allow_hosts: vec!["example.com".into(), "10.0.0.5".into()],
+            deny_hosts: vec!["secret.example.com".into()],
+            ..WebFetchConfig::default()
+        };
+        let check = |u: &str| check_url(&config, &Url::parse(u).unwrap());
+        assert!(check("https://www.example.com/a").is_ok());
+        assert!(check("https://secret.example.com/").is_err());
+        assert!(check("https://other.org/").is_err());
+        assert!(check("ftp://example.com/").is_err());
+        // Allow-listed but private.
+        assert!(check("http://10.0.0.5/").is_err());
+
+        let open = WebFetchConfig::default();
+        let check = |u: &str| check_url(&open, &Url::parse(u).unwrap());
+        assert!(check("http://localhost:8080/").is_err());
+        assert!(check("http://127.0.0.1/").is_err());
+        assert!(check("http://[::ffff:192.168.1.1]/").is_err());
+        assert!(check("http://[fe80::1]/").is_err());
+        assert!(check("http://100.100.0.1/").is_err());
+        assert!(check("http://93.184.216.34/").is_ok());
+    }
+
+    #[test]
+    fn chunks_on_paragraph_breaks() {
+        let text = "aaaa\n\nbbbb\n\ncc";
+        assert_eq!(page_chunk(text, 0, 9), ("aaaa", 4));
+        assert_eq!(page_chunk(text, 4, 9), ("\n\nbbbb", 10));
+        assert_eq!(page_chunk(text, 10, 100), ("\n\ncc", 14));
+        assert_eq!(page_chunk("héllo", 1, 2), ("él", 3));
+    }
+
+    #[tokio::test]
+    async fn fetches_html_as_markdown() {
+        let base = serve().await;
+        let tool = WebFetchTool::new(local_config());
+        let result = tool
+            .execute(serde_json::json!({ "url": format!("{base}/moved") }))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        assert_eq!(
+            result.content,
+            format!(
+                "URL: {base}/page\nTitle: Fixture\n\n# Hello\n\nSee the [intro]({base}/docs/intro)."
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn continues_long_pages() {
+        let base = serve().await;
+        let tool = WebFetchTool::new(WebFetchConfig {
+            max_chars: 1_000,
+            ..local_config()
+        });
+        let url = format!("{base}/long");
+        let mut offset = 0;
+        let mut seen = String::new();
+        for _ in 0..20 {
+            let result = tool
+                .execute(serde_json::json!({ "url": url, "offset": offset }))
+                .await;
+            assert!(!result.is_error, "{}", result.content);
+            let Some(i) = result.content.find("offset=") else {
+                seen.push_str(&result.content);
+                break;
+            };
+            seen.push_str(&result.content);
+            offset = result.content[i + 7..]
+                .split(' ')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap();
+        }
+        assert!(seen.contains("Paragraph 0 "));
+        assert!(seen.contains("Paragraph 49 "));
+
+        let past = tool
+            .execute(serde_json::json!({ "url": url, "offset": 1_000_000 }))
+            .await;
+        assert!(past.is_error);
+    }
+
+    #[tokio::test]
+    async fn enforces_limits() {
+        let base = serve().await;
+        let tool = WebFetchTool::new(WebFetchConfig {
+            max_bytes: 1_000,
+            ..local_config()
+        });
+        let big = tool
+            .execute(serde_json::json!({ "url": format!("{base}/big") }))
+            .await;
+        assert!(big.content.contains(&"x".repeat(1_000)));
+        assert!(!big.content.contains(&"x".repeat(1_001)));
+        assert!(big.content.contains("Download stopped at 1000 bytes"));
+
+        let image = tool
+            .execute(serde_json::json!({ "url": format!("{base}/image") }))
+            .await;
+        assert!(image.is_error);
+        assert!(image.content.contains("image/png"));
+
+        let missing = tool
+            .execute(serde_json::json!({ "url": format!("{base}/missing") }))
+            .await;
+        assert!(missing.is_error);
+        assert!(missing.content.contains("404"));
+
+        let denied = WebFetchTool::new(WebFetchConfig {
+            deny_hosts: vec!["blocked.test".into()],
+            ..local_config()
+        })
+        .execute(serde_json::json!({ "url": format!("{base}/evil") }))
+        .await;
+        assert!(denied.is_error);
+        assert!(denied.content.contains("deny_hosts"), "{}", denied.content);
+
+        let private = WebFetchTool::new(WebFetchConfig::default())
+            .execute(serde_json::json!({ "url": format!("{base}/page") }))
+            .await;
+        assert!(private.is_error);
+    }
+
+    #[tokio::test]
+    async fn redirects_to_names_of_private_addresses_are_refused() {
+        // Stand-ins: 127.0.0.1 plays a public host, 127.0.0.2 a private one.
+        let internal = Router::new().route("/", get(|| async { "internal secret" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.2:0").await.unwrap();
+        let internal_port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, internal).await.unwrap() });
+
+        let public = Router::new()
+            .route("/", get(|| async { "public page" }))
+            .route(
+                "/hop",
+                get(move || async move {
+                    Redirect::temporary(&format!("http://internal.test:{internal_port}/"))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, public).await.unwrap() });
+
+        fn lookup(host: String) -> LookupFuture {
+            let ip = if host == "internal.test" {
+                [127, 0, 0, 2]
+            } else {
+                [127, 0, 0, 1]
+            };
+            Box::pin(async move { Ok(vec![SocketAddr::from((ip, 0))]) })
+        }
+        let client = client_with(
+            &WebFetchConfig::default(),
+            USER_AGENT,
+            PublicResolver {
+                lookup,
+                blocked: |ip| ip == IpAddr::from([127, 0, 0, 2]),
+            },
+        )
+        .unwrap();
+
+        let ok = client
+            .get(format!("http://public.test:{port}/"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(ok.text().await.unwrap(), "public page");
+
+        let err = client
+            .get(format!("http://public.test:{port}/hop"))
+            .send()
+            .await
+            .unwrap_err();
+        let err = error_chain(&err);
+        assert!(
+            err.contains("host 'internal.test' resolves to a local or private address"),
+            "{err}"
+        );
+    }
+}
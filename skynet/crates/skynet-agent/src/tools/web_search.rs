@@ -0,0 +1,297 @@
+//! Tool: web_search — query the `[search]` backend and list the results.
+//!
+//! Results are de-duplicated and formatted by `crate::search`. With
+//! `fetch_top` (from config or the call), the top pages are also downloaded
+//! through `web_fetch::fetch_page`, so `[agent.web_fetch]` rules apply, and
+//! their beginnings are appended.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::info;
+
+use skynet_core::config::{SearchConfig, WebFetchConfig};
+
+use crate::search::{self, SearchProvider, SearchQuery, TimeRange};
+
+use super::web_fetch::{fetch_page, page_chunk};
+use super::{Tool, ToolResult};
+
+const MAX_RESULTS: usize = 20;
+const MAX_FETCH_TOP: usize = 5;
+
+pub struct WebSearchTool {
+    provider: Arc<dyn SearchProvider>,
+    config: SearchConfig,
+    web_fetch: WebFetchConfig,
+}
+
+impl WebSearchTool {
+    pub fn new(
+        provider: Arc<dyn SearchProvider>,
+        config: SearchConfig,
+        web_fetch: WebFetchConfig,
+    ) -> Self {
+        Self {
+            provider,
+            config,
+            web_fetch,
+        }
+    }
+
+    /// The first `fetch_max_chars` of each page, in result order.
+    async fn fetch_pages(&self, urls: &[&str]) -> String {
+        let pages =
+            futures_util::future::join_all(urls.iter().map(|url| fetch_page(&self.web_fetch, url)))
+                .await;
+        pages
+            .into_iter()
+            .zip(urls)
+            .enumerate()
+            .map(|(i, (page, url))| match page {
+                Ok(page) => {
+                    let (text, next) = page_chunk(&page.text, 0, self.config.fetch_max_chars);
+                    let mut section = format!("--- Result {}: {} ---\n{}", i + 1, page.url, text);
+                    if next < page.text.chars().count() {
+                        section.push_str(&format!(
+                            "\n[… use web_fetch with offset={next} to read on]"
+                        ));
+                    }
+                    section
+                }
+                Err(e) => format!("--- Result {}: {url} ---\n[fetch failed: {e}]", i + 1),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[async_trait]
+impl Tool for WebSearchTool {
+    fn name(&self) -> &str {
+        "web_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search the web. Returns a numbered list of results with title, URL and snippet. \
+         Use web_fetch to read a result in full, or set `fetch_top` to include the \
+         beginning of the top pages directly."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Search query."
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Number of results (optional, max 20)."
+                },
+                "time_range": {
+                    "type": "string",
+                    "enum": ["day", "week", "month", "year"],
+                    "description": "Only results published within this period (optional)."
+                },
+                "fetch_top": {
+                    "type": "integer",
+                    "description": "Also download the top N results (optional, max 5)."
+                }
+            },
+            "required": ["query"]
+        })
+    }
+
+    fn is_concurrency_safe(&self) -> bool {
+        true
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let query = match input.get("query").and_then(|v| v.as_str()) {
+            Some(q) if !q.trim().is_empty() => q.trim().to_string(),
+            _ => return ToolResult::error("missing required parameter: query"),
+        };
+        let limit = input
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .map_or(self.config.max_results, |n| n as usize)
+            .clamp(1, MAX_RESULTS);
+        let time_range = match input.get("time_range").and_then(|v| v.as_str()) {
+            None => None,
+            Some(s) => match TimeRange::parse(s) {
+                Some(range) => Some(range),
+                None => {
+                    return ToolResult::error(format!(
+                        "invalid time_range '{s}': expected day, week, month or year"
+                    ))
+                }
+            },
+        };
+        let fetch_top = input
+            .get("fetch_top")
+            .and_then(|v| v.as_u64())
+            .map_or(self.config.fetch_top, |n| n as usize)
+            .min(MAX_FETCH_TOP);
+
+        info!(provider = self.provider.name(), query = %query, "web_search");
+        let results = match self
+            .provider
+            .search(&SearchQuery {
+                query: query.clone(),
+                limit,
+                time_range,
+            })
+            .await
+        {
+            Ok(results) => results,
+            Err(e) => return ToolResult::error(format!("web search failed: {e}")),
+        };
+        let mut results = search::dedupe(results);
+        results.truncate(limit);
+        if results.is_empty() {
+            return ToolResult::success(format!("No results for: {query}"));
+        }
+
+        let mut out = search::format_results(&results);
+        if fetch_top > 0 {
+            let urls: Vec<&str> = results
+                .iter()
+                .take(fetch_top)
+                .map(|r| r.url.as_str())
+                .collect();
+            out.push_str("\n\n");
+            out.push_str(&self.fetch_pages(&urls).await);
+        }
+        ToolResult::success(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use axum::routing::get;
+    use axum::Router;
+
+    use super::*;
+    use crate::search::{SearchError, SearchResult};
+
+    /// Returns fixed results and records the last query.
+    struct MockProvider {
+        results: Vec<SearchResult>,
+        last: Mutex<Option<SearchQuery>>,
+    }
+
+    #[async_trait]
+    impl SearchProvider for MockProvider {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchResult>, SearchError> {
+            *self.last.lock().unwrap() = Some(query.clone());
+            Ok(self.results.clone())
+        }
+    }
+
+    fn hit(url: String, title: &str) -> SearchResult {
+        SearchResult {
+            title: title.to_string(),
+            url,
+            snippet: format!("About {title}"),
+            source: None,
+            published: None,
+        }
+    }
+
+    fn tool(results: Vec<SearchResult>, fetch_top: usize) -> (WebSearchTool, Arc<MockProvider>) {
+        let provider = Arc::new(MockProvider {
+            results,
+            last: Mutex::new(None),
+        });
+        let config = SearchConfig {
+            fetch_top,
+            fetch_max_chars: 40,
+            ..SearchConfig::default()
+        };
+        let web_fetch = WebFetchConfig {
+            allow_private: true,
+            ..WebFetchConfig::default()
+        };
+        (
+            WebSearchTool::new(provider.clone(), config, web_fetch),
+            provider,
+        )
+    }
+
+    #[tokio::test]
+    async fn lists_deduplicated_results() {
+        let (tool, provider) = tool(
+            vec![
+                hit("https://example.com/a".into(), "A"),
+                hit("https://www.example.com/a/".into(), "A again"),
+                hit("https://example.com/b".into(), "B"),
+                hit("https://example.com/c".into(), "C"),
+            ],
+            0,
+        );
+        let result = tool
+            .execute(serde_json::json!({ "query": "rust", "max_results": 2, "time_range": "year" }))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        assert_eq!(
+            result.content,
+            "1. A\n   https://example.com/a\n   About A\n\n2. B\n   https://example.com/b\n   About B"
+        );
+        let last = provider.last.lock().unwrap().clone().unwrap();
+        assert_eq!((last.limit, last.time_range), (2, Some(TimeRange::Year)));
+
+        let bad = tool
+            .execute(serde_json::json!({ "query": "rust", "time_range": "decade" }))
+            .await;
+        assert!(bad.is_error);
+    }
+
+    #[tokio::test]
+    async fn fetches_top_results() {
+        let app = Router::new().route(
+            "/page",
+            get(|| async {
+                (
+                    [("content-type", "text/html")],
+                    "<h1>Fetched</h1><p>First paragraph.</p><p>Second paragraph is longer.</p>",
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (tool, _) = tool(
+            vec![
+                hit(format!("http://{addr}/page"), "Page"),
+                hit(format!("http://{addr}/missing"), "Missing"),
+                hit("https://example.com/c".into(), "Not fetched"),
+            ],
+            2,
+        );
+        let result = tool.execute(serde_json::json!({ "query": "q" })).await;
+        assert!(!result.is_error, "{}", result.content);
+        let pages = result.content.split_once("\n\n--- ").unwrap().1;
+        assert_eq!(
+            pages,
+            format!(
+                "Result 1: http://{addr}/page ---\n# Fetched\n\nFirst paragraph.\n\
+                 [… use web_fetch with offset=27 to read on]\n\n\
+                 --- Result 2: http://{addr}/missing ---\n\
+                 [fetch failed: HTTP 404 Not Found from http://{addr}/missing]"
+            )
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Workspace sandbox shared by the file tools.
+//!
+//! `Workspace::resolve` turns a tool's `path` argument into a canonical
+//! absolute path — symlinks and `..` resolved, including for files that do
+//! not exist yet — and checks it against `[agent.workspace]`: the (possibly
+//! per-user or per-session) root, then the deny and allow globs. Every
+//! rejection is logged under the `skynet::audit` target.
+
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+use skynet_core::config::{WorkspaceConfig, WorkspaceScope};
+
+use super::glob::Glob;
+
+/// Symlinks followed while resolving one path before giving up.
+const MAX_SYMLINKS: usize = 40;
+
+/// What a tool is about to do with a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Walk a directory. `allow` globs apply to the files found, not to the
+    /// directory itself.
+    List,
+}
+
+impl Access {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::List => "list",
+        }
+    }
+}
+
+/// The file tools' view of the filesystem for one session.
+#[derive(Debug, Default)]
+pub struct Workspace {
+    /// Configured root for this session, before canonicalization.
+    root: Option<PathBuf>,
+    allow: Vec<Glob>,
+    deny: Vec<Glob>,
+    session_key: String,
+    user_id: Option<String>,
+}
+
+impl Workspace {
+    pub fn new(config: &WorkspaceConfig, session_key: &str, user_id: Option<&str>) -> Self {
+        let root = config.root.as_deref().map(|root| {
+            let root = expand_home(root);
+            match (config.scope, user_id) {
+                (WorkspaceScope::Shared, _) => root,
+                (WorkspaceScope::User, Some(user)) => root.join("users").join(dir_name(user)),
+                (WorkspaceScope::User, None) | (WorkspaceScope::Session, _) => {
+                    root.join("sessions").join(dir_name(session_key))
+                }
+            }
+        });
+        let globs = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| {
+                    let p = expand_home(p).to_string_lossy().into_owned();
+                    Glob::new(&p).unwrap_or_else(|e| {
+                        warn!(error = %e, "matching workspace glob literally");
+                        Glob::literal(&p)
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        // The gateway's own files are denied by their exact canonical path.
+        let protected = config.protected.iter().map(|p| {
+            let p = expand_home(p);
+            let p = std::path::absolute(&p).unwrap_or(p);
+            Glob::literal(&canonicalize(&p).unwrap_or(p).to_string_lossy())
+        });
+        Self {
+            root,
+            allow: globs(&config.allow),
+            deny: globs(&config.deny).into_iter().chain(protected).collect(),
+            session_key: session_key.to_string(),
+            user_id: user_id.map(str::to_string),
+        }
+    }
+
+    /// Canonical path for `path`, or a tool error if `tool` may not `access`
+    /// it. Relative paths start at the workspace root (the working directory
+    /// when no root is configured).
+    pub fn resolve(&self, tool: &str, path: &str, access: Access) -> Result<PathBuf, String> {
+        let checked = self.root_dir().and_then(|root| {
+            let base = match root {
+                Some(ref root) => root.clone(),
+                None => std::env::current_dir().map_err(|e| e.to_string())?,
+            };
+            let resolved = canonicalize(&base.join(path))
+                .map_err(|e| format!("cannot resolve '{path}': {e}"))?;
+            self.check(root.as_deref(), &resolved, access)?;
+            Ok(resolved)
+        });
+        checked.map_err(|reason| {
+            warn!(
+                target: "skynet::audit",
+                tool,
+                access = access.as_str(),
+                path,
+                session = %self.session_key,
+                user = self.user_id.as_deref().unwrap_or("-"),
+                reason = %reason,
+                "file access denied"
+            );
+            format!("access denied: {reason}")
+        })
+    }
+
+    /// Whether a file or directory found while walking a resolved directory
+    /// may be shown. Entries are re-resolved so a symlink cannot lead out of
+    /// the workspace; rejected entries are skipped silently.
+    pub fn permits(&self, path: &Path, access: Access) -> bool {
+        let Ok(root) = self.root_dir() else {
+            return false;
+        };
+        match canonicalize(path) {
+            Ok(resolved) => self.check(root.as_deref(), &resolved, access).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// The canonical root, created on first use; `None` when unrestricted.
+    fn root_dir(&self) -> Result<Option<PathBuf>, String> {
+        let Some(ref root) = self.root else {
+            return Ok(None);
+        };
+        std::fs::create_dir_all(root)
+            .and_then(|_| root.canonicalize())
+            .map(Some)
+            .map_err(|e| format!("workspace '{}' is unavailable: {e}", root.display()))
+    }
+
+    fn check(&self, root: Option<&Path>, path: &Path, access: Access) -> Result<(), String> {
+        if let Some(root) = root {
+            if !path.starts_with(root) {
+                return Err(format!(
+                    "'{}' is outside the workspace '{}'",
+                    path.display(),
+                    root.display()
+                ));
+            }
+        }
+        if let Some(glob) = self.deny.iter().find(|g| g.is_match(path)) {
+            return Err(format!(
+                "'{}' matches deny rule '{}'",
+                path.display(),
+                glob.as_str()
+            ));
+        }
+        let is_dir = access == Access::List || path.is_dir();
+        if !is_dir && !self.allow.is_empty() && !self.allow.iter().any(|g| g.is_match(path)) {
+            return Err(format!("'{}' matches no allow rule", path.display()));
+        }
+        Ok(())
+    }
+}
+
+/// Absolute path with every symlink and `..` resolved. Unlike
+/// `Path::canonicalize`, the last components may be missing (a file about
+/// to be written); a dangling symlink resolves to its target.
+fn canonicalize(path: &Path) -> std::io::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    for _ in 0..MAX_SYMLINKS {
+        match path.canonicalize() {
+            Ok(resolved) => return Ok(resolved),
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            Err(e) => {
+                let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                    return Err(e);
+                };
+                let parent = canonicalize(parent)?;
+                match std::fs::read_link(parent.join(name)) {
+                    // Dangling symlink: resolve what it points at instead.
+                    Ok(target) => path = parent.join(target),
+                    Err(_) => return Ok(parent.join(name)),
+                }
+            }
+        }
+    }
+    Err(std::io::Error::other("too many levels of symbolic links"))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => {
+            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+            Path::new(&home).join(rest)
+        }
+        None => PathBuf::from(path),
+    }
+}
+
+/// `key` as a single safe directory name.
+fn dir_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match name.trim_start_matches('.') {
+        "" => "_".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("skynet-ws-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    fn config(root: &Path) -> WorkspaceConfig {
+        WorkspaceConfig {
+            root: Some(root.to_string_lossy().into_owned()),
+            ..WorkspaceConfig::default()
+        }
+    }
+
+    #[test]
+    fn confines_paths_to_the_root() {
+        let dir = scratch();
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(dir.join("secret.txt"), "x").unwrap();
+        std::os::unix::fs::symlink(dir.join("secret.txt"), root.join("link")).unwrap();
+        std::os::unix::fs::symlink(dir.join("nowhere"), root.join("dangling")).unwrap();
+        let ws = Workspace::new(&config(&root), "s", None);
+
+        assert_eq!(
+            ws.resolve("read_file", "src/../notes.md", Access::Write),
+            Ok(root.join("notes.md"))
+        );
+        assert_eq!(
+            ws.resolve("write_file", "new/dir/file.txt", Access::Write),
+            Ok(root.join("new/dir/file.txt"))
+        );
+        for escape in ["../secret.txt", "link", "dangling", "/etc/passwd"] {
+            let err = ws.resolve("read_file", escape, Access::Read).unwrap_err();
+            assert!(err.contains("outside the workspace"), "{escape}: {err}");
+        }
+        assert!(ws.permits(&root.join("src"), Access::List));
+        assert!(!ws.permits(&root.join("link"), Access::Read));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn applies_deny_and_allow_globs() {
+        let dir = scratch();
+        std::fs::create_dir_all(dir.join(".ssh")).unwrap();
+        let mut config = config(&dir);
+        config.allow = vec!["*.md".into()];
+        let ws = Workspace::new(&config, "s", None);
+
+        let err = ws
+            .resolve("read_file", ".ssh/id_rsa", Access::Read)
+            .unwrap_err();
+        assert!(err.contains("deny rule '**/.ssh/**'"), "{err}");
+        assert!(ws.resolve("list_files", ".ssh", Access::List).is_err());
+        assert!(ws
+            .resolve("write_file", "skynet.toml", Access::Write)
+            .is_err());
+        assert!(ws.resolve("read_file", "notes.md", Access::Read).is_ok());
+        let err = ws
+            .resolve("read_file", "notes.txt", Access::Read)
+            .unwrap_err();
+        assert!(err.contains("matches no allow rule"), "{err}");
+        assert!(ws.resolve("list_files", ".", Access::List).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn denies_the_gateway_own_files() {
+        let dir = scratch();
+        std::fs::write(dir.join("custom.toml"), "x").unwrap();
+        let mut config = config(&dir);
+        config.protected = vec![
+            dir.join("custom.toml").to_string_lossy().into_owned(),
+            dir.join("data/[x].db").to_string_lossy().into_owned(),
+        ];
+        let ws = Workspace::new(&config, "s", None);
+
+        for denied in ["custom.toml", "data/[x].db", "skynet.db", "skynet.db-wal"] {
+            let err = ws.resolve("read_file", denied, Access::Read).unwrap_err();
+            assert!(err.contains("deny rule"), "{denied}: {err}");
+        }
+        assert!(ws.resolve("read_file", "data/x.db", Access::Read).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn scopes_the_root_per_user_or_session() {
+        let dir = scratch();
+        let mut config = config(&dir);
+        config.scope = WorkspaceScope::User;
+
+        let ws = Workspace::new(&config, "discord:dm:1", Some("u-1"));
+        assert_eq!(
+            ws.resolve("write_file", "a.txt", Access::Write),
+            Ok(dir.join("users/u-1/a.txt"))
+        );
+        let ws = Workspace::new(&config, "discord:dm:1", None);
+        assert_eq!(
+            ws.resolve("write_file", "a.txt", Access::Write),
+            Ok(dir.join("sessions/discord_dm_1/a.txt"))
+        );
+        let err = ws
+            .resolve("read_file", "../../users/u-1/a.txt", Access::Read)
+            .unwrap_err();
+        assert!(err.contains("outside the workspace"), "{err}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
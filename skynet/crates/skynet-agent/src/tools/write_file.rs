@@ -1,10 +1,33 @@
 //! Tool: write_file — write content to a file, creating parent directories as needed.
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 
+use super::checkpoint::TurnCheckpoint;
+use super::file_tracker::SessionFiles;
+use super::workspace::{Access, Workspace};
 use super::{Tool, ToolResult};
 
-pub struct WriteFileTool;
+pub struct WriteFileTool {
+    workspace: Arc<Workspace>,
+    files: Arc<SessionFiles>,
+    checkpoint: Arc<TurnCheckpoint>,
+}
+
+impl WriteFileTool {
+    pub fn new(
+        workspace: Arc<Workspace>,
+        files: Arc<SessionFiles>,
+        checkpoint: Arc<TurnCheckpoint>,
+    ) -> Self {
+        Self {
+            workspace,
+            files,
+            checkpoint,
+        }
+    }
+}
 
 #[async_trait]
 impl Tool for WriteFileTool {
@@ -14,7 +37,8 @@ impl Tool for WriteFileTool {
 
     fn description(&self) -> &str {
         "Write content to a file. Creates parent directories if they do not exist. \
-         Overwrites the file if it already exists."
+         Overwrites the file if it already exists; an existing file must have been \
+         read with read_file first and not changed since."
     }
 
     fn input_schema(&self) -> serde_json::Value {
@@ -23,7 +47,7 @@ impl Tool for WriteFileTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "Absolute or relative path to write to."
+                    "description": "Absolute path, or relative to the workspace, of the file to write."
                 },
                 "content": {
                     "type": "string",
@@ -44,9 +68,20 @@ impl Tool for WriteFileTool {
             Some(c) => c.to_string(),
             None => return ToolResult::error("missing required parameter: content"),
         };
+        let resolved = match self.workspace.resolve("write_file", &path, Access::Write) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(e),
+        };
+        if let Err(e) = self.files.check_overwrite(&resolved, &path) {
+            return ToolResult::error(e);
+        }
+
+        if let Err(e) = self.checkpoint.before_write(&resolved, &path) {
+            return ToolResult::error(e);
+        }
 
         // Create parent directories if needed.
-        if let Some(parent) = std::path::Path::new(&path).parent() {
+        if let Some(parent) = resolved.parent() {
             if !parent.as_os_str().is_empty() {
                 if let Err(e) = std::fs::create_dir_all(parent) {
                     return ToolResult::error(format!(
@@ -58,9 +93,11 @@ impl Tool for WriteFileTool {
         }
 
         let byte_len = content.len();
-        if let Err(e) = std::fs::write(&path, content) {
+        if let Err(e) = std::fs::write(&resolved, &content) {
             return ToolResult::error(format!("failed to write '{}': {}", path, e));
         }
+        self.files.record(&resolved, content.as_bytes());
+        self.checkpoint.after_write(&resolved, content.as_bytes());
 
         ToolResult::success(format!("File written: {} bytes to '{}'", byte_len, path))
     }
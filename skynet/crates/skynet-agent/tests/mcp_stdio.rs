@@ -0,0 +1,108 @@
+// MCP client against the `mcp_test_server` binary spawned over stdio.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use skynet_agent::mcp::{McpClient, McpConnectionState, McpHub, McpServerStatus};
+use skynet_core::config::{McpConfig, McpServerConfig};
+
+fn server_config() -> McpServerConfig {
+    McpServerConfig {
+        command: Some(env!("CARGO_BIN_EXE_mcp_test_server").to_string()),
+        args: Vec::new(),
+        env: HashMap::new(),
+        cwd: None,
+        url: None,
+        headers: HashMap::new(),
+        enabled: true,
+        timeout_secs: 10,
+    }
+}
+
+/// Poll `hub.status()` until `pred` holds for the only server.
+async fn wait_for(hub: &McpHub, pred: impl Fn(&McpServerStatus) -> bool) -> McpServerStatus {
+    for _ in 0..100 {
+        let status = hub.status().remove(0);
+        if pred(&status) {
+            return status;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("timed out; last status: {:?}", hub.status());
+}
+
+#[tokio::test]
+async fn client_lists_and_calls_tools() {
+    let client = McpClient::connect("test", &server_config()).await.unwrap();
+    assert_eq!(client.server_name(), "mcp-test-server");
+
+    let tools = client.list_tools().await.unwrap();
+    let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["echo", "add", "fail", "exit"]);
+    assert!(tools[0].annotations.read_only_hint);
+
+    let out = client
+        .call_tool("echo", serde_json::json!({ "text": "hi" }))
+        .await
+        .unwrap();
+    assert_eq!((out.text.as_str(), out.is_error), ("hi", false));
+
+    let out = client
+        .call_tool("add", serde_json::json!({ "a": 2, "b": 3 }))
+        .await
+        .unwrap();
+    assert_eq!(out.text, "5");
+
+    assert!(
+        client
+            .call_tool("fail", serde_json::json!({}))
+            .await
+            .unwrap()
+            .is_error
+    );
+    assert!(client
+        .call_tool("nope", serde_json::json!({}))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn hub_exposes_tools_and_reconnects() {
+    let config = McpConfig {
+        servers: HashMap::from([("test".to_string(), server_config())]),
+        ..McpConfig::default()
+    };
+    let hub = McpHub::new(&config);
+    hub.start();
+
+    let status = wait_for(&hub, |s| s.state == McpConnectionState::Connected).await;
+    assert_eq!(status.transport, "stdio");
+    assert_eq!(
+        status.tools,
+        [
+            "mcp_test_echo",
+            "mcp_test_add",
+            "mcp_test_fail",
+            "mcp_test_exit"
+        ]
+    );
+
+    let tools = hub.tools();
+    let echo = tools.iter().find(|t| t.name() == "mcp_test_echo").unwrap();
+    assert!(echo.is_concurrency_safe());
+    let result = echo.execute(serde_json::json!({ "text": "hello" })).await;
+    assert_eq!((result.content.as_str(), result.is_error), ("hello", false));
+
+    // Kill the server mid-call: the call fails and the hub reconnects.
+    let exit = tools.iter().find(|t| t.name() == "mcp_test_exit").unwrap();
+    assert!(exit.execute(serde_json::json!({})).await.is_error);
+    let status = wait_for(&hub, |s| s.state != McpConnectionState::Connected).await;
+    assert!(status.last_error.is_some());
+    let status = wait_for(&hub, |s| s.state == McpConnectionState::Connected).await;
+    assert_eq!(status.reconnect_attempts, 0);
+
+    let result = echo.execute(serde_json::json!({ "text": "again" })).await;
+    assert_eq!(result.content, "again");
+
+    hub.shutdown();
+}
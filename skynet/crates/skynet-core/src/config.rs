@@ -3,6 +3,7 @@ use figment::{
     Figment,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 // Protocol constants — must match OpenClaw wire protocol exactly
 pub const PROTOCOL_VERSION: u32 = 3;
@@ -26,6 +27,16 @@ pub struct SkynetConfig {
     pub channels: ChannelsConfig,
     #[serde(default)]
     pub webhooks: WebhooksConfig,
+    /// Per-model price overrides (`[pricing."<model>"]`), merged over the
+    /// built-in table in `skynet-agent::pricing`.
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPricing>,
+    /// External Model Context Protocol servers (`[mcp.servers.<name>]`).
+    #[serde(default)]
+    pub mcp: McpConfig,
+    /// Web search backend for the `web_search` tool (`[search]`).
+    #[serde(default)]
+    pub search: SearchConfig,
 }
 
 impl Default for SkynetConfig {
@@ -44,10 +55,21 @@ impl Default for SkynetConfig {
             agent: AgentConfig {
                 model: "claude-sonnet-4-6".to_string(),
                 soul_path: None,
+                routing: RoutingConfig::default(),
+                context_windows: HashMap::new(),
+                condense: CondenseConfig::default(),
+                approval: ApprovalConfig::default(),
+                web_fetch: WebFetchConfig::default(),
+                workspace: WorkspaceConfig::default(),
+                checkpoints: CheckpointConfig::default(),
+                plugins: PluginsConfig::default(),
             },
             providers: ProvidersConfig::default(),
             channels: ChannelsConfig::default(),
             webhooks: WebhooksConfig::default(),
+            mcp: McpConfig::default(),
+            search: SearchConfig::default(),
+            pricing: HashMap::new(),
         }
     }
 }
@@ -84,6 +106,327 @@ pub struct AgentConfig {
     #[serde(default = "default_model")]
     pub model: String,
     pub soul_path: Option<String>,
+    /// Per-turn model selection rules (`[agent.routing]`).
+    #[serde(default)]
+    pub routing: RoutingConfig,
+    /// Context window sizes in tokens (`[agent.context_windows]`), keyed by
+    /// model ID or prefix. Overrides the built-in registry in
+    /// `skynet-agent::context_budget`.
+    #[serde(default)]
+    pub context_windows: HashMap<String, u32>,
+    /// Session condensation (`[agent.condense]`).
+    #[serde(default)]
+    pub condense: CondenseConfig,
+    /// Per-tool approval policies (`[agent.approval]`).
+    #[serde(default)]
+    pub approval: ApprovalConfig,
+    /// Limits for the `web_fetch` tool (`[agent.web_fetch]`).
+    #[serde(default)]
+    pub web_fetch: WebFetchConfig,
+    /// Where the file tools may read and write (`[agent.workspace]`).
+    #[serde(default)]
+    pub workspace: WorkspaceConfig,
+    /// File snapshots taken before agent edits (`[agent.checkpoints]`).
+    #[serde(default)]
+    pub checkpoints: CheckpointConfig,
+    /// Script plugins from `tool.toml` manifests (`[agent.plugins]`).
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+}
+
+/// Whether a tool runs freely, waits for an admin, or is not offered at all.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolPolicy {
+    #[default]
+    Auto,
+    /// Each call is queued and runs only once an admin approves it.
+    Ask,
+    /// The tool is removed from the model's tool list.
+    Deny,
+}
+
+/// Tool approval policies. Tools not listed in `tools` use `default`; a call
+/// to an `ask` tool that nobody decides within `timeout_secs` is rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalConfig {
+    /// Policy for tools not listed in `tools`.
+    #[serde(default)]
+    pub default: ToolPolicy,
+    /// Per-tool overrides, keyed by tool name.
+    #[serde(default)]
+    pub tools: HashMap<String, ToolPolicy>,
+    /// How long a call waits for a decision before it is expired and rejected.
+    #[serde(default = "default_approval_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            default: ToolPolicy::Auto,
+            tools: HashMap::new(),
+            timeout_secs: default_approval_timeout_secs(),
+        }
+    }
+}
+
+impl ApprovalConfig {
+    /// Effective policy for `tool`.
+    pub fn policy(&self, tool: &str) -> ToolPolicy {
+        self.tools.get(tool).copied().unwrap_or(self.default)
+    }
+}
+
+/// Limits and host rules for `web_fetch`.
+///
+/// Host patterns match the host itself and its subdomains (`example.com`
+/// matches `docs.example.com`); a leading `*.` is ignored. `deny_hosts` wins
+/// over `allow_hosts`, and an empty `allow_hosts` allows every host.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebFetchConfig {
+    #[serde(default)]
+    pub allow_hosts: Vec<String>,
+    #[serde(default)]
+    pub deny_hosts: Vec<String>,
+    /// Allow `localhost` and loopback, private and link-local addresses.
+    #[serde(default)]
+    pub allow_private: bool,
+    /// Bytes downloaded per page; the rest is cut off.
+    #[serde(default = "default_web_fetch_max_bytes")]
+    pub max_bytes: usize,
+    #[serde(default = "default_web_fetch_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Characters returned per call; longer pages are continued with `offset`.
+    #[serde(default = "default_web_fetch_max_chars")]
+    pub max_chars: usize,
+}
+
+impl Default for WebFetchConfig {
+    fn default() -> Self {
+        Self {
+            allow_hosts: Vec::new(),
+            deny_hosts: Vec::new(),
+            allow_private: false,
+            max_bytes: default_web_fetch_max_bytes(),
+            timeout_secs: default_web_fetch_timeout_secs(),
+            max_chars: default_web_fetch_max_chars(),
+        }
+    }
+}
+
+/// How `[agent.workspace] root` is divided between sessions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceScope {
+    /// Every session shares `root`.
+    #[default]
+    Shared,
+    /// `root/users/{user_id}`; sessions without a user fall back to `session`.
+    User,
+    /// `root/sessions/{session_key}`.
+    Session,
+}
+
+/// Sandbox for `read_file`, `write_file`, `list_files`, `search_files`,
+/// `patch_file` and `edit_file`.
+///
+/// Paths are canonicalized (symlinks and `..` resolved) before they are
+/// checked. `root` defaults to `~/.skynet/workspace`; `root = "/"` opens the
+/// whole filesystem, leaving only the glob rules. Globs match the canonical absolute path: `*` and `?`
+/// stay within one component, `**` spans any number of them, `~/` expands
+/// to `$HOME`, and a pattern without a leading `/` matches at any depth
+/// (`.env` is `**/.env`). `deny` wins over `allow`; an empty `allow` allows
+/// every path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    /// Directory the file tools are confined to; relative paths start here.
+    #[serde(default = "default_workspace_root")]
+    pub root: Option<String>,
+    #[serde(default)]
+    pub scope: WorkspaceScope,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default = "default_workspace_deny")]
+    pub deny: Vec<String>,
+    /// Files the gateway runs on — the loaded config and the database with
+    /// its WAL files. Always denied; filled in by `SkynetConfig::load`
+    /// because their paths are only known at runtime.
+    #[serde(skip)]
+    pub protected: Vec<String>,
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> Self {
+        Self {
+            root: default_workspace_root(),
+            scope: WorkspaceScope::Shared,
+            allow: Vec::new(),
+            deny: default_workspace_deny(),
+            protected: Vec::new(),
+        }
+    }
+}
+
+/// Snapshots of every file `write_file`, `patch_file` or `edit_file` is about
+/// to modify, so `/undo` and `checkpoints.restore` can roll a turn back.
+///
+/// Old contents are stored once per distinct content under `dir`; the index
+/// lives in the main database. Changes made through `execute_command` or
+/// `bash` are not captured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointConfig {
+    #[serde(default = "bool_true")]
+    pub enabled: bool,
+    /// Blob directory (default: `~/.skynet/checkpoints`).
+    #[serde(default = "default_checkpoint_dir")]
+    pub dir: String,
+    /// Turns kept per session; older checkpoints are pruned.
+    #[serde(default = "default_checkpoint_keep_turns")]
+    pub keep_turns: usize,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: default_checkpoint_dir(),
+            keep_turns: default_checkpoint_keep_turns(),
+        }
+    }
+}
+
+/// Script plugins: one subdirectory with a `tool.toml` per plugin.
+///
+/// `secrets` holds values plugins may ask for by name in `[run] secrets`;
+/// a plugin only ever receives the secrets it declares.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginsConfig {
+    /// Plugin directory (default: `~/.skynet/tools`).
+    #[serde(default = "default_plugins_dir")]
+    pub dir: String,
+    /// Reload plugins when the directory changes instead of at startup only.
+    #[serde(default = "bool_true")]
+    pub watch: bool,
+    #[serde(default)]
+    pub secrets: HashMap<String, String>,
+    #[serde(default)]
+    pub wasm: WasmPluginsConfig,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_plugins_dir(),
+            watch: true,
+            secrets: HashMap::new(),
+            wasm: WasmPluginsConfig::default(),
+        }
+    }
+}
+
+/// Upper bounds for WebAssembly plugins (`[agent.plugins.wasm]`). A manifest
+/// may ask for less, never more.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmPluginsConfig {
+    /// Fuel (roughly, executed instructions) per call.
+    #[serde(default = "default_wasm_max_fuel")]
+    pub max_fuel: u64,
+    /// Linear memory per call, in MiB.
+    #[serde(default = "default_wasm_max_memory_mb")]
+    pub max_memory_mb: u64,
+    #[serde(default = "default_wasm_max_timeout_secs")]
+    pub max_timeout_secs: u64,
+}
+
+impl Default for WasmPluginsConfig {
+    fn default() -> Self {
+        Self {
+            max_fuel: default_wasm_max_fuel(),
+            max_memory_mb: default_wasm_max_memory_mb(),
+            max_timeout_secs: default_wasm_max_timeout_secs(),
+        }
+    }
+}
+
+/// When and how long sessions are condensed into a summary turn.
+///
+/// Condensation starts once a session's active history is estimated above
+/// `trigger_ratio` of the chat model's context window or `max_history_tokens`,
+/// whichever is lower. Condensed rows are kept, tagged with the summary's id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CondenseConfig {
+    /// Model that writes the summary. Defaults to the model the turn was routed to.
+    pub model: Option<String>,
+    #[serde(default = "default_condense_trigger_ratio")]
+    pub trigger_ratio: f64,
+    #[serde(default = "default_condense_max_history_tokens")]
+    pub max_history_tokens: u32,
+    /// Most recent turns that are never condensed.
+    #[serde(default = "default_condense_keep_recent")]
+    pub keep_recent: usize,
+}
+
+impl Default for CondenseConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            trigger_ratio: default_condense_trigger_ratio(),
+            max_history_tokens: default_condense_max_history_tokens(),
+            keep_recent: default_condense_keep_recent(),
+        }
+    }
+}
+
+/// Heuristic model routing. Rules are evaluated in order and the first match
+/// picks the model for the turn; when none match, `agent.model` is used.
+/// An explicit per-request `model` always wins over routing, and so does a
+/// model an operator picked with `/model` or the `agent.model` method.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RoutingConfig {
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+}
+
+/// One `[[agent.routing.rules]]` entry. Every condition that is set must hold
+/// for the rule to match; unset conditions are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// Rule name, recorded on the conversation row when it matches.
+    pub name: String,
+    /// Model to use when the rule matches.
+    pub model: String,
+    /// Message length bounds, in characters.
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// Matches when the message contains any of these (case-insensitive).
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Matches only on these channels (e.g. "discord", "web").
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Matches only for these user roles ("admin", "user", "child").
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Matches on whether the previous turn in the session called tools.
+    pub tools_used_last_turn: Option<bool>,
+    /// Matches when the user's remaining daily token budget is below this.
+    /// Users without a daily cap never match.
+    pub budget_below_tokens: Option<u64>,
+}
+
+/// Token prices for one model, in USD per million tokens.
+///
+/// `input` is the uncached prompt rate; `cache_write` / `cache_read` apply to
+/// prompt tokens written to / served from the provider's prompt cache.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+    #[serde(default)]
+    pub cache_read: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,6 +448,43 @@ pub struct ProvidersConfig {
     pub anthropic: Option<AnthropicConfig>,
     pub openai: Option<OpenAiProviderConfig>,
     pub ollama: Option<OllamaConfig>,
+    /// Priority-ordered failover chain (`[[providers.chain]]`).
+    /// When non-empty the gateway builds a `ProviderRouter` from it instead
+    /// of using the first configured provider.
+    #[serde(default)]
+    pub chain: Vec<ProviderChainEntry>,
+}
+
+/// One provider in the failover chain.
+///
+/// Credentials fall back to the matching `[providers.<kind>]` section (and then
+/// the usual env vars) when not set on the entry itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderChainEntry {
+    /// Provider kind: "anthropic", "openai" or "ollama".
+    pub provider: String,
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+    /// Retries on this provider before moving to the next one.
+    #[serde(default = "default_chain_max_retries")]
+    pub max_retries: u32,
+    /// Per-attempt timeout in seconds (0 = no timeout). For streaming calls
+    /// it limits the wait for each event instead of the whole answer.
+    #[serde(default = "default_chain_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Consecutive failures before the circuit opens and the provider is skipped.
+    #[serde(default = "default_chain_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds an open circuit waits before probing the provider again
+    /// (doubles on each failed probe).
+    #[serde(default = "default_chain_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// Model used when the requested model has no entry in `models`.
+    pub model: Option<String>,
+    /// Requested model → provider-specific model name,
+    /// e.g. `"claude-sonnet-4-6" = "gpt-4o"`.
+    #[serde(default)]
+    pub models: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -149,6 +529,12 @@ pub struct DiscordConfig {
     /// Defaults to true.
     #[serde(default = "bool_true")]
     pub dm_allowed: bool,
+    /// Channel where tool approval requests are posted with Approve / Reject
+    /// buttons. Unset = approvals are only offered over WS.
+    pub approval_channel_id: Option<u64>,
+    /// Discord user IDs allowed to press the approval buttons.
+    #[serde(default)]
+    pub approvers: Vec<u64>,
 }
 
 fn bool_true() -> bool {
@@ -189,6 +575,124 @@ pub struct WebhooksConfig {
     pub sources: Vec<WebhookSourceConfig>,
 }
 
+/// Web search for the `web_search` tool. The tool is offered once the
+/// section for `provider` is configured (e.g. `[search.searxng]`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// Backend to query. Only `searxng` exists so far.
+    #[serde(default = "default_search_provider")]
+    pub provider: String,
+    #[serde(default)]
+    pub searxng: Option<SearxngConfig>,
+    /// Results returned when the model does not ask for a number (max 20).
+    #[serde(default = "default_search_max_results")]
+    pub max_results: usize,
+    /// Top results downloaded with `web_fetch` and appended to the results.
+    /// 0 leaves fetching to the model.
+    #[serde(default)]
+    pub fetch_top: usize,
+    /// Characters kept from each fetched page.
+    #[serde(default = "default_search_fetch_max_chars")]
+    pub fetch_max_chars: usize,
+    #[serde(default = "default_search_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            provider: default_search_provider(),
+            searxng: None,
+            max_results: default_search_max_results(),
+            fetch_top: 0,
+            fetch_max_chars: default_search_fetch_max_chars(),
+            timeout_secs: default_search_timeout_secs(),
+        }
+    }
+}
+
+/// A SearXNG instance. Its `settings.yml` must list `json` under
+/// `search.formats`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearxngConfig {
+    /// Base URL, e.g. `http://localhost:8888`.
+    pub url: String,
+    /// Engines to query (SearXNG defaults when empty).
+    #[serde(default)]
+    pub engines: Vec<String>,
+    /// Categories to search (SearXNG defaults when empty).
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Search language, e.g. `en`.
+    pub language: Option<String>,
+    /// 0 = off, 1 = moderate, 2 = strict.
+    pub safesearch: Option<u8>,
+}
+
+/// Model Context Protocol: servers whose tools are offered to the agent, and
+/// Skynet's own MCP server.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct McpConfig {
+    /// Servers keyed by name; tools are exposed as `mcp_{name}_{tool}`.
+    #[serde(default)]
+    pub servers: HashMap<String, McpServerConfig>,
+    /// Skynet as an MCP server (`[mcp.serve]`).
+    #[serde(default)]
+    pub serve: McpServeConfig,
+}
+
+/// What Skynet publishes to MCP clients, over `/mcp` or `--mcp-stdio`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServeConfig {
+    /// Mount the streamable HTTP endpoint at `/mcp` (gateway auth required).
+    /// `--mcp-stdio` works regardless.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Agent tools offered to MCP clients, by name.
+    #[serde(default = "default_mcp_serve_tools")]
+    pub tools: Vec<String>,
+    /// Skynet user the MCP client acts as. `memory_search` is offered over
+    /// this user's memories only, and not at all when unset.
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+impl Default for McpServeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tools: default_mcp_serve_tools(),
+            user_id: None,
+        }
+    }
+}
+
+/// One MCP server. Set `command` to spawn it over stdio, or `url` to reach it
+/// over streamable HTTP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Executable to spawn (stdio transport).
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables for the spawned process.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Working directory for the spawned process.
+    pub cwd: Option<String>,
+    /// Endpoint URL (streamable HTTP transport).
+    pub url: Option<String>,
+    /// Extra HTTP headers, e.g. `Authorization`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// When false the server is listed but never started.
+    #[serde(default = "bool_true")]
+    pub enabled: bool,
+    /// Per-request timeout, including tool calls.
+    #[serde(default = "default_mcp_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
 fn default_port() -> u16 {
     DEFAULT_PORT
 }
@@ -207,6 +711,104 @@ fn default_openai_base_url() -> String {
 fn default_ollama_base_url() -> String {
     "http://localhost:11434".to_string()
 }
+fn default_chain_max_retries() -> u32 {
+    1
+}
+fn default_chain_timeout_secs() -> u64 {
+    300
+}
+fn default_chain_failure_threshold() -> u32 {
+    3
+}
+fn default_chain_cooldown_secs() -> u64 {
+    30
+}
+fn default_condense_trigger_ratio() -> f64 {
+    0.5
+}
+fn default_condense_max_history_tokens() -> u32 {
+    24_000
+}
+fn default_condense_keep_recent() -> usize {
+    6
+}
+fn default_approval_timeout_secs() -> u64 {
+    300
+}
+fn default_web_fetch_max_bytes() -> usize {
+    2 * 1024 * 1024
+}
+fn default_web_fetch_timeout_secs() -> u64 {
+    20
+}
+fn default_web_fetch_max_chars() -> usize {
+    20_000
+}
+fn default_workspace_root() -> Option<String> {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    Some(format!("{}/.skynet/workspace", home))
+}
+fn default_workspace_deny() -> Vec<String> {
+    [
+        "**/.ssh/**",
+        "**/.gnupg/**",
+        "**/.aws/**",
+        "**/.env",
+        "**/skynet.toml",
+        "**/skynet.db",
+        "**/skynet.db-wal",
+        "**/skynet.db-shm",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+fn default_checkpoint_dir() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    format!("{}/.skynet/checkpoints", home)
+}
+fn default_checkpoint_keep_turns() -> usize {
+    50
+}
+fn default_plugins_dir() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    format!("{}/.skynet/tools", home)
+}
+fn default_wasm_max_fuel() -> u64 {
+    10_000_000_000
+}
+fn default_wasm_max_memory_mb() -> u64 {
+    256
+}
+fn default_wasm_max_timeout_secs() -> u64 {
+    120
+}
+fn default_search_provider() -> String {
+    "searxng".to_string()
+}
+fn default_search_max_results() -> usize {
+    5
+}
+fn default_search_fetch_max_chars() -> usize {
+    3_000
+}
+fn default_search_timeout_secs() -> u64 {
+    15
+}
+fn default_mcp_timeout_secs() -> u64 {
+    60
+}
+fn default_mcp_serve_tools() -> Vec<String> {
+    [
+        "knowledge_search",
+        "knowledge_write",
+        "reminder",
+        "read_file",
+        "list_files",
+        "search_files",
+    ]
+    .map(String::from)
+    .to_vec()
+}
 fn default_db_path() -> String {
     let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
     format!("{}/.skynet/skynet.db", home)
@@ -224,14 +826,31 @@ impl SkynetConfig {
             .map(String::from)
             .unwrap_or_else(default_config_path);
 
-        let config: SkynetConfig = Figment::new()
+        let mut config: SkynetConfig = Figment::new()
             .merge(Toml::file(&path))
             .merge(Env::prefixed("SKYNET_").split("_"))
             .extract()
             .map_err(|e| crate::error::SkynetError::Config(e.to_string()))?;
 
+        config.protect_own_files(Some(&path));
         Ok(config)
     }
+
+    /// Deny the file tools the config file at `config_path` (default
+    /// `~/.skynet/skynet.toml`) and the configured database, whatever their
+    /// names, so the agent cannot read accounts or rewrite its own limits.
+    pub fn protect_own_files(&mut self, config_path: Option<&str>) {
+        let config_path = config_path
+            .map(String::from)
+            .unwrap_or_else(default_config_path);
+        let db = &self.database.path;
+        self.agent.workspace.protected = vec![
+            config_path,
+            db.clone(),
+            format!("{db}-wal"),
+            format!("{db}-shm"),
+        ];
+    }
 }
 
 fn default_config_path() -> String {
@@ -0,0 +1,41 @@
+//! SQLite helpers shared by the crates that own tables in `skynet.db`.
+
+use rusqlite::{Connection, Result};
+
+/// `CREATE TABLE IF NOT EXISTS` never changes an existing table, so columns
+/// introduced later are added here when a database predates them.
+pub fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    decl: &str,
+) -> Result<()> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        rusqlite::params![table, column],
+        |row| row.get(0),
+    )?;
+    if exists == 0 {
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_a_column_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (id INTEGER)").unwrap();
+        add_column_if_missing(&conn, "t", "note", "TEXT").unwrap();
+        add_column_if_missing(&conn, "t", "note", "TEXT").unwrap();
+        let columns: i64 = conn
+            .query_row("SELECT COUNT(*) FROM pragma_table_info('t')", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(columns, 2);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod config;
+pub mod db;
 pub mod error;
 pub mod reminder;
 pub mod types;
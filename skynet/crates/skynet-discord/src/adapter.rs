@@ -20,6 +20,8 @@ pub struct DiscordAdapter<C: DiscordAppContext + 'static> {
     token: String,
     require_mention: bool,
     dm_allowed: bool,
+    approval_channel_id: Option<u64>,
+    approvers: Vec<u64>,
 }
 
 impl<C: DiscordAppContext + 'static> DiscordAdapter<C> {
@@ -29,6 +31,8 @@ impl<C: DiscordAppContext + 'static> DiscordAdapter<C> {
             token: config.bot_token.clone(),
             require_mention: config.require_mention,
             dm_allowed: config.dm_allowed,
+            approval_channel_id: config.approval_channel_id,
+            approvers: config.approvers.clone(),
         }
     }
 
@@ -63,6 +67,17 @@ impl<C: DiscordAppContext + 'static> DiscordAdapter<C> {
             tokio::spawn(crate::proactive::run_discord_delivery(http, rx));
         }
 
+        // Spawn the approval notifier once, for the same reason.
+        if let Some(channel_id) = self.approval_channel_id {
+            let http = Arc::clone(&first_client.http);
+            let rx = self.ctx.approvals().subscribe();
+            tokio::spawn(crate::approval::run_approval_notifier(
+                http,
+                serenity::model::id::ChannelId::new(channel_id),
+                rx,
+            ));
+        }
+
         let mut client = first_client;
 
         loop {
@@ -95,6 +110,7 @@ impl<C: DiscordAppContext + 'static> DiscordAdapter<C> {
             ctx: Arc::clone(&self.ctx),
             require_mention: self.require_mention,
             dm_allowed: self.dm_allowed,
+            approvers: self.approvers.clone(),
             bot_id: OnceLock::new(),
         };
 
@@ -0,0 +1,198 @@
+//! Tool approvals on Discord — requests are posted to the configured approval
+//! channel with Approve / Reject buttons.
+//!
+//! `run_approval_notifier` posts each new request and edits the message once
+//! it is resolved (from Discord, over WS, by expiry or by an aborted turn).
+//! Button clicks arrive in `DiscordHandler::interaction_create` and go through
+//! `handle_button`, which only accepts clicks from `approvers`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteraction, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage,
+    MessageId,
+};
+use serenity::prelude::Context;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tracing::{info, warn};
+
+use skynet_agent::approval::{ApprovalEvent, ApprovalRequest, ApprovalStatus};
+
+use crate::context::DiscordAppContext;
+
+/// Prefix of the custom_id on approval buttons: `approval:<approve|reject>:<id>`.
+const BUTTON_PREFIX: &str = "approval:";
+
+/// Longest tool input shown in the request message.
+const MAX_INPUT_CHARS: usize = 1200;
+
+/// Post approval requests to `channel_id` and update them once resolved.
+///
+/// Spawned once in `adapter.rs` when `approval_channel_id` is configured.
+pub async fn run_approval_notifier(
+    http: Arc<serenity::http::Http>,
+    channel_id: ChannelId,
+    mut rx: Receiver<ApprovalEvent>,
+) {
+    // Request id → the message posted for it.
+    let mut posted: HashMap<String, MessageId> = HashMap::new();
+    loop {
+        match rx.recv().await {
+            Ok(ApprovalEvent::Requested(req)) => {
+                let msg = CreateMessage::new()
+                    .content(request_text(&req))
+                    .components(vec![buttons(&req.id)]);
+                match channel_id.send_message(&http, msg).await {
+                    Ok(m) => {
+                        posted.insert(req.id, m.id);
+                    }
+                    Err(e) => {
+                        warn!(id = %req.id, error = %e, "discord: failed to post approval request")
+                    }
+                }
+            }
+            Ok(ApprovalEvent::Resolved(req)) => {
+                let Some(message_id) = posted.remove(&req.id) else {
+                    continue;
+                };
+                let edit = EditMessage::new()
+                    .content(format!("{}\n{}", request_text(&req), outcome_text(&req)))
+                    .components(Vec::new());
+                if let Err(e) = channel_id.edit_message(&http, message_id, edit).await {
+                    warn!(id = %req.id, error = %e, "discord: failed to update approval message");
+                }
+            }
+            Err(RecvError::Lagged(n)) => warn!(skipped = n, "discord: approval notifier lagged"),
+            Err(RecvError::Closed) => break,
+        }
+    }
+    info!("discord approval notifier exiting (channel closed)");
+}
+
+/// Handle a click on an approval button. Returns false if `interaction` is
+/// not an approval button, so the caller can try other handlers.
+pub async fn handle_button<C: DiscordAppContext + 'static>(
+    app: &C,
+    approvers: &[u64],
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+) -> bool {
+    let Some((approve, id)) = parse_custom_id(&interaction.data.custom_id) else {
+        return false;
+    };
+
+    let user = interaction.user.id.get();
+    let reply = if !approvers.contains(&user) {
+        Some("You are not allowed to decide approval requests.".to_string())
+    } else {
+        match app
+            .approvals()
+            .decide(id, approve, &format!("discord:{user}"), None)
+        {
+            // The notifier edits the message with the outcome.
+            Ok(Some(req)) if req.status != ApprovalStatus::Expired => None,
+            Ok(Some(_)) => Some("This request has expired.".to_string()),
+            Ok(None) => Some("This request was already resolved.".to_string()),
+            Err(e) => {
+                warn!(id, error = %e, "discord: approval decision failed");
+                Some(format!("⚠️ Could not record the decision: {e}"))
+            }
+        }
+    };
+
+    let response = match reply {
+        None => CreateInteractionResponse::Acknowledge,
+        Some(text) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(text)
+                .ephemeral(true),
+        ),
+    };
+    if let Err(e) = interaction.create_response(&ctx.http, response).await {
+        warn!(id, error = %e, "discord: failed to answer approval button");
+    }
+    true
+}
+
+/// Split `approval:<approve|reject>:<id>` into (approve, id).
+fn parse_custom_id(custom_id: &str) -> Option<(bool, &str)> {
+    let rest = custom_id.strip_prefix(BUTTON_PREFIX)?;
+    let (action, id) = rest.split_once(':')?;
+    match action {
+        "approve" => Some((true, id)),
+        "reject" => Some((false, id)),
+        _ => None,
+    }
+}
+
+fn buttons(id: &str) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{BUTTON_PREFIX}approve:{id}"))
+            .label("Approve")
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{BUTTON_PREFIX}reject:{id}"))
+            .label("Reject")
+            .style(ButtonStyle::Danger),
+    ])
+}
+
+fn request_text(req: &ApprovalRequest) -> String {
+    let tool = req.action_details["tool"]
+        .as_str()
+        .unwrap_or(&req.action_type);
+    let session = req.context["session_key"].as_str().unwrap_or("unknown");
+    let mut input = serde_json::to_string_pretty(&req.action_details["input"]).unwrap_or_default();
+    if input.chars().count() > MAX_INPUT_CHARS {
+        input = input.chars().take(MAX_INPUT_CHARS).collect::<String>() + "\n…";
+    }
+    let expires = req
+        .expires_at
+        .as_deref()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| format!("\nExpires <t:{}:R>", t.timestamp()))
+        .unwrap_or_default();
+    format!(
+        "🔐 **Approval needed** — `{tool}` (session `{session}`)\n```json\n{input}\n```{expires}"
+    )
+}
+
+fn outcome_text(req: &ApprovalRequest) -> String {
+    let by = req
+        .decided_by
+        .as_deref()
+        .map(|b| format!(" by {b}"))
+        .unwrap_or_default();
+    let reason = req
+        .reason
+        .as_deref()
+        .map(|r| format!(": {r}"))
+        .unwrap_or_default();
+    match req.status {
+        ApprovalStatus::Approved => format!("✅ Approved{by}"),
+        ApprovalStatus::Rejected => format!("❌ Rejected{by}{reason}"),
+        ApprovalStatus::Expired => "⌛ Expired — no decision in time".to_string(),
+        ApprovalStatus::Cancelled => format!("🚫 Cancelled{reason}"),
+        ApprovalStatus::Pending => "⏳ Pending".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_button_ids() {
+        assert_eq!(
+            parse_custom_id("approval:approve:abc-1"),
+            Some((true, "abc-1"))
+        );
+        assert_eq!(
+            parse_custom_id("approval:reject:abc-1"),
+            Some((false, "abc-1"))
+        );
+        assert_eq!(parse_custom_id("approval:maybe:abc-1"), None);
+        assert_eq!(parse_custom_id("other:approve:abc-1"), None);
+    }
+}
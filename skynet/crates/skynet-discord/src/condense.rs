@@ -0,0 +1,6 @@
+//! Session condensation for Discord — re-exported from the shared pipeline.
+//!
+//! The canonical implementation lives in `skynet_agent::pipeline::condense`.
+//! This re-export lets `handler.rs` use a `crate::condense::…` path.
+
+pub use skynet_agent::pipeline::condense_session_if_needed;
@@ -1,21 +1,30 @@
 use std::sync::{Arc, OnceLock};
 
+use base64::Engine;
 use serenity::async_trait;
-use serenity::model::channel::Message;
+use serenity::model::application::Interaction;
+use serenity::model::channel::{Attachment, Message};
 use serenity::model::gateway::Ready;
 use serenity::model::id::UserId;
 use serenity::model::user::OnlineStatus;
 use serenity::prelude::{Context, EventHandler};
+use skynet_agent::cancel::CancellationToken;
+use skynet_agent::provider::{ContentBlock, MediaSource};
 use tracing::{info, warn};
 
 use crate::context::DiscordAppContext;
 use crate::send;
 
+/// Largest attachment downloaded and forwarded to the model (20 MB).
+const MAX_ATTACHMENT_BYTES: u32 = 20 * 1024 * 1024;
+
 /// Serenity event handler wired to the AI backend.
 pub struct DiscordHandler<C: DiscordAppContext + 'static> {
     pub ctx: Arc<C>,
     pub require_mention: bool,
     pub dm_allowed: bool,
+    /// Discord user IDs allowed to press tool approval buttons.
+    pub approvers: Vec<u64>,
     pub bot_id: OnceLock<UserId>,
 }
 
@@ -29,6 +38,13 @@ impl<C: DiscordAppContext + 'static> EventHandler for DiscordHandler<C> {
         info!(name = %ready.user.name, "Discord bot connected");
     }
 
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Component(component) = interaction {
+            crate::approval::handle_button(self.ctx.as_ref(), &self.approvers, &ctx, &component)
+                .await;
+        }
+    }
+
     async fn message(&self, ctx: Context, msg: Message) {
         if msg.author.bot {
             return;
@@ -53,7 +69,7 @@ impl<C: DiscordAppContext + 'static> EventHandler for DiscordHandler<C> {
         };
 
         let content = strip_mention(&msg.content).trim().to_string();
-        if content.is_empty() {
+        if content.is_empty() && msg.attachments.is_empty() {
             return;
         }
 
@@ -62,9 +78,24 @@ impl<C: DiscordAppContext + 'static> EventHandler for DiscordHandler<C> {
         let app = Arc::clone(&self.ctx);
         let http = Arc::clone(&ctx.http);
         let channel_id = msg.channel_id;
+        let author_id = msg.author.id;
+        let files = msg.attachments;
 
         tokio::spawn(async move {
-            process_message(app, http, channel_id, session_key, content).await;
+            let attachments = download_attachments(&files).await;
+            if content.is_empty() && attachments.is_empty() {
+                return;
+            }
+            process_message(
+                app,
+                http,
+                channel_id,
+                author_id,
+                session_key,
+                content,
+                attachments,
+            )
+            .await;
         });
     }
 }
@@ -80,25 +111,81 @@ fn strip_mention(s: &str) -> &str {
     trimmed
 }
 
+/// Download the images and PDFs attached to a message as base64 content
+/// blocks. Other file types and files over `MAX_ATTACHMENT_BYTES` are skipped.
+async fn download_attachments(files: &[Attachment]) -> Vec<ContentBlock> {
+    let mut blocks = Vec::new();
+    for file in files {
+        // Discord may append parameters, e.g. "text/plain; charset=utf-8".
+        let media_type = file
+            .content_type
+            .as_deref()
+            .and_then(|t| t.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        if !ContentBlock::is_supported_attachment(&media_type) {
+            info!(file = %file.filename, media_type, "Discord attachment type not supported, skipping");
+            continue;
+        }
+        if file.size > MAX_ATTACHMENT_BYTES {
+            warn!(file = %file.filename, size = file.size, "Discord attachment too large, skipping");
+            continue;
+        }
+        match file.download().await {
+            Ok(bytes) => {
+                let source = MediaSource::Base64 {
+                    data: base64::engine::general_purpose::STANDARD.encode(bytes),
+                    media_type: media_type.clone(),
+                };
+                blocks.extend(ContentBlock::attachment(&media_type, source));
+            }
+            Err(e) => {
+                warn!(error = %e, file = %file.filename, "Discord attachment download failed")
+            }
+        }
+    }
+    blocks
+}
+
 async fn process_message<C: DiscordAppContext + 'static>(
     ctx: Arc<C>,
     http: Arc<serenity::http::Http>,
     channel_id: serenity::model::id::ChannelId,
+    author_id: UserId,
     session_key: String,
     content: String,
+    attachments: Vec<ContentBlock>,
 ) {
-    use skynet_agent::pipeline::process_message_non_streaming;
+    use skynet_agent::pipeline::{parse_undo, process_message_non_streaming, undo};
+    use skynet_agent::routing::SenderInfo;
+
+    // `/undo [turn_id]` rolls back this session's file changes without a model call.
+    if let Some(turn_id) = parse_undo(&content) {
+        let reply = undo(ctx.as_ref(), &session_key, turn_id);
+        if let Err(e) = send::send_chunked(&http, channel_id, &reply).await {
+            warn!(error = %e, session = %session_key, "Discord send_chunked failed");
+        }
+        return;
+    }
+
+    // The Discord user as a skynet user, for role-based routing, budgets and
+    // plugin permissions.
+    let sender = SenderInfo::resolve(ctx.users(), "discord", &author_id.to_string());
 
     // Run the full agentic turn: history load, system prompt, tool loop,
-    // memory save, and session compaction are all handled by the shared pipeline.
+    // memory save, and session condensation are all handled by the shared pipeline.
     let response = match process_message_non_streaming(
         &ctx,
         &session_key,
         "discord",
         &content,
+        &attachments,
         None, // no pre-built user context (discord doesn't use UserResolver yet)
         None, // no per-request model override
         Some(channel_id.get()), // pass Discord channel ID for ReminderTool delivery routing
+        sender.as_ref(),
+        &CancellationToken::new(), // Discord has no chat.abort
     )
     .await
     {
@@ -1,5 +1,6 @@
 pub mod adapter;
-pub mod compact;
+pub mod approval;
+pub mod condense;
 pub mod context;
 pub mod error;
 pub mod handler;
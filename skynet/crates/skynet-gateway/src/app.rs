@@ -3,11 +3,16 @@ use axum::{
     Router,
 };
 use dashmap::DashMap;
+use skynet_agent::approval::ApprovalBroker;
+use skynet_agent::cancel::CancelRegistry;
+use skynet_agent::mcp::McpHub;
+use skynet_agent::plugins::PluginRegistry;
 use skynet_agent::runtime::AgentRuntime;
+use skynet_agent::tools::file_tracker::FileTracker;
 use skynet_core::config::SkynetConfig;
 use skynet_memory::manager::MemoryManager;
 use skynet_scheduler::SchedulerHandle;
-use skynet_sessions::SessionManager;
+use skynet_sessions::{CheckpointStore, SessionManager};
 use skynet_terminal::manager::TerminalManager;
 use skynet_users::resolver::UserResolver;
 use std::sync::{
@@ -36,9 +41,23 @@ pub struct AppState {
     pub terminal: tokio::sync::Mutex<TerminalManager>,
     /// Active WS connections: conn_id -> message sender.
     pub ws_clients: DashMap<String, mpsc::Sender<String>>,
+    /// In-flight `chat.send` turns, cancellable via `chat.abort`.
+    pub runs: CancelRegistry,
+    /// Tool approval policies and parked tool calls (`approval.*`).
+    pub approvals: ApprovalBroker,
+    /// External MCP server connections (`mcp.status`).
+    pub mcp: McpHub,
+    /// Script plugins (`tools.list`, `tools.reload`).
+    pub plugins: PluginRegistry,
+    /// Files each session has read or written (stale-write protection).
+    pub files: FileTracker,
+    /// Snapshots of files the agent modified (`/undo`, `checkpoints.*`);
+    /// `None` when `[agent.checkpoints]` is disabled.
+    pub checkpoints: Option<CheckpointStore>,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         config: SkynetConfig,
         agent: AgentRuntime,
@@ -47,6 +66,10 @@ impl AppState {
         sessions: SessionManager,
         scheduler: SchedulerHandle,
         terminal: TerminalManager,
+        approvals: ApprovalBroker,
+        mcp: McpHub,
+        plugins: PluginRegistry,
+        checkpoints: Option<CheckpointStore>,
     ) -> Self {
         Self {
             config,
@@ -60,6 +83,12 @@ impl AppState {
             scheduler,
             terminal: tokio::sync::Mutex::new(terminal),
             ws_clients: DashMap::new(),
+            runs: CancelRegistry::new(),
+            approvals,
+            mcp,
+            plugins,
+            files: FileTracker::new(),
+            checkpoints,
         }
     }
 
@@ -85,11 +114,39 @@ impl skynet_agent::pipeline::MessageContext for AppState {
     fn scheduler(&self) -> &skynet_scheduler::SchedulerHandle {
         &self.scheduler
     }
+
+    fn sessions(&self) -> &skynet_sessions::SessionManager {
+        &self.sessions
+    }
+
+    fn users(&self) -> &UserResolver {
+        &self.users
+    }
+
+    fn approvals(&self) -> &ApprovalBroker {
+        &self.approvals
+    }
+
+    fn mcp(&self) -> &McpHub {
+        &self.mcp
+    }
+
+    fn plugins(&self) -> &PluginRegistry {
+        &self.plugins
+    }
+
+    fn files(&self) -> &FileTracker {
+        &self.files
+    }
+
+    fn checkpoints(&self) -> Option<&CheckpointStore> {
+        self.checkpoints.as_ref()
+    }
 }
 
 /// Assemble the full Axum router.
 pub fn build_router(state: Arc<AppState>) -> Router {
-    Router::new()
+    let mut router = Router::new()
         .route("/", get(crate::http::ui::ui_handler))
         .route("/health", get(crate::http::health::health_handler))
         .route("/ws", get(crate::ws::connection::ws_handler))
@@ -100,7 +157,15 @@ pub fn build_router(state: Arc<AppState>) -> Router {
         .route(
             "/webhooks/{source}",
             post(crate::http::webhooks::webhook_handler),
-        )
+        );
+    if state.config.mcp.serve.enabled {
+        let endpoint = crate::http::mcp::endpoint(&state);
+        router = router.route(
+            "/mcp",
+            post(crate::http::mcp::mcp_handler).layer(axum::Extension(endpoint)),
+        );
+    }
+    router
         .with_state(state)
         .layer(tower_http::trace::TraceLayer::new_for_http())
 }
@@ -5,9 +5,18 @@ use std::sync::Arc;
 use crate::app::AppState;
 
 /// GET /health — liveness probe, returns server metadata.
+///
+/// `status` is "degraded" when every provider circuit is open, i.e. chat
+/// requests would currently fail without reaching an LLM.
 pub async fn health_handler(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let providers = state.agent.provider().health();
+    let all_open = !providers.is_empty()
+        && providers
+            .iter()
+            .all(|p| p.state == skynet_agent::circuit::CircuitState::Open);
     Json(json!({
-        "status": "ok",
+        "status": if all_open { "degraded" } else { "ok" },
+        "providers": providers,
         "version": env!("CARGO_PKG_VERSION"),
         "protocol": skynet_core::config::PROTOCOL_VERSION,
         "ws_clients": state.ws_clients.len(),
@@ -0,0 +1,129 @@
+//! MCP server endpoint — POST /mcp (streamable HTTP, JSON responses only).
+//!
+//! Mounted when `[mcp.serve] enabled = true`. Requests authenticate with the
+//! gateway credential as `Authorization: Bearer <token or password>`, per
+//! `gateway.auth.mode`. Each POST carries one JSON-RPC message or a batch;
+//! the protocol itself lives in `skynet_agent::mcp::server`.
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+use skynet_agent::mcp::server::{self, McpEndpoint, RpcError};
+use skynet_core::config::{AuthMode, SkynetConfig};
+
+use crate::app::AppState;
+
+/// Session key MCP tool calls over HTTP are recorded under.
+const SESSION_KEY: &str = "mcp:http";
+
+/// The endpoint every `/mcp` request is served by, so its tool list is built
+/// once and `notifications/cancelled` reaches calls from other requests.
+pub fn endpoint(state: &Arc<AppState>) -> Arc<McpEndpoint<AppState>> {
+    let serve = &state.config.mcp.serve;
+    let mut endpoint = McpEndpoint::new(Arc::clone(state), SESSION_KEY, &serve.tools);
+    if let Some(user_id) = &serve.user_id {
+        endpoint = endpoint.with_user(user_id);
+    }
+    Arc::new(endpoint)
+}
+
+/// POST /mcp
+pub async fn mcp_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(endpoint): Extension<Arc<McpEndpoint<AppState>>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(e) = verify_auth(&headers, &state.config) {
+        warn!(error = %e, "mcp: unauthorized request");
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "error": e }))).into_response();
+    }
+
+    let msg: Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => {
+            let err = RpcError::new(server::PARSE_ERROR, format!("parse error: {e}"));
+            return Json(server::response(Value::Null, Err(err))).into_response();
+        }
+    };
+
+    let reply = match msg {
+        Value::Array(batch) => {
+            let mut replies = Vec::new();
+            for msg in batch {
+                replies.extend(endpoint.handle(msg).await);
+            }
+            (!replies.is_empty()).then_some(Value::Array(replies))
+        }
+        msg => endpoint.handle(msg).await,
+    };
+    match reply {
+        Some(reply) => Json(reply).into_response(),
+        // Only notifications or responses: nothing to send back.
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// Check the bearer credential against `gateway.auth`.
+fn verify_auth(headers: &HeaderMap, config: &SkynetConfig) -> Result<(), String> {
+    let expected = match config.gateway.auth.mode {
+        AuthMode::None => return Ok(()),
+        AuthMode::Token => config.gateway.auth.token.as_deref(),
+        AuthMode::Password => config.gateway.auth.password.as_deref(),
+        ref other => return Err(format!("auth mode {:?} not supported for /mcp", other)),
+    };
+    let expected = expected.ok_or_else(|| "no gateway credential configured".to_string())?;
+
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| "missing bearer token".to_string())?;
+
+    if token == expected {
+        Ok(())
+    } else {
+        Err("invalid bearer token".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {token}").parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn rejects_missing_or_wrong_credentials() {
+        let mut config = SkynetConfig::default();
+        config.gateway.auth.mode = AuthMode::Token;
+        config.gateway.auth.token = Some("t0k3n".to_string());
+
+        assert_eq!(verify_auth(&bearer("t0k3n"), &config), Ok(()));
+        assert_eq!(
+            verify_auth(&bearer("guess"), &config),
+            Err("invalid bearer token".to_string())
+        );
+        assert_eq!(
+            verify_auth(&HeaderMap::new(), &config),
+            Err("missing bearer token".to_string())
+        );
+
+        config.gateway.auth.token = None;
+        assert!(verify_auth(&bearer("t0k3n"), &config).is_err());
+        config.gateway.auth.mode = AuthMode::Tailscale;
+        assert!(verify_auth(&bearer("t0k3n"), &config).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod health;
+pub mod mcp;
 pub mod openai_compat;
 pub mod ui;
 pub mod webhooks;
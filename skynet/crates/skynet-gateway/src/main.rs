@@ -5,16 +5,27 @@ use tracing::info;
 mod app;
 mod auth;
 mod http;
+mod mcp_stdio;
 pub mod tools;
 mod ws;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // --mcp-stdio: serve MCP on stdin/stdout instead of running the gateway
+    let mcp_stdio = std::env::args().skip(1).any(|a| a == "--mcp-stdio");
+
+    // stdout carries protocol messages in stdio mode, so logs go to stderr
+    let log_writer = if mcp_stdio {
+        tracing_subscriber::fmt::writer::BoxMakeWriter::new(std::io::stderr)
+    } else {
+        tracing_subscriber::fmt::writer::BoxMakeWriter::new(std::io::stdout)
+    };
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "skynet_gateway=info,tower_http=debug".into()),
         )
+        .with_writer(log_writer)
         .init();
 
     // load config: explicit path > SKYNET_CONFIG env > ~/.skynet/skynet.toml
@@ -22,7 +33,9 @@ async fn main() -> anyhow::Result<()> {
     let config =
         skynet_core::config::SkynetConfig::load(config_path.as_deref()).unwrap_or_else(|e| {
             tracing::warn!("Config load failed ({}), using defaults", e);
-            skynet_core::config::SkynetConfig::default()
+            let mut config = skynet_core::config::SkynetConfig::default();
+            config.protect_own_files(config_path.as_deref());
+            config
         });
 
     let bind = config.gateway.bind.clone();
@@ -49,6 +62,45 @@ async fn main() -> anyhow::Result<()> {
     ));
     let memory = skynet_memory::manager::MemoryManager::new(rusqlite::Connection::open(db_path)?);
     let sessions = skynet_sessions::SessionManager::new(rusqlite::Connection::open(db_path)?);
+    let checkpoints = if config.agent.checkpoints.enabled {
+        Some(skynet_sessions::CheckpointStore::new(
+            rusqlite::Connection::open(db_path)?,
+            &config.agent.checkpoints.dir,
+            config.agent.checkpoints.keep_turns,
+        ))
+    } else {
+        None
+    };
+    let approval_queue =
+        skynet_users::approvals::ApprovalQueue::new(rusqlite::Connection::open(db_path)?);
+    // in stdio mode a gateway may be running on the same database and own
+    // the pending approval requests
+    let approvals = if mcp_stdio {
+        skynet_agent::approval::ApprovalBroker::shared(
+            approval_queue,
+            config.agent.approval.clone(),
+        )
+    } else {
+        skynet_agent::approval::ApprovalBroker::new(approval_queue, config.agent.approval.clone())
+    };
+
+    // MCP servers connect in the background; their tools appear once listed.
+    let mcp = skynet_agent::mcp::McpHub::new(&config.mcp);
+    if !mcp_stdio {
+        mcp.start();
+        if !config.mcp.servers.is_empty() {
+            info!(servers = config.mcp.servers.len(), "MCP client hub started");
+        }
+    }
+
+    // Script plugins; the watcher reloads them as ~/.skynet/tools changes.
+    let plugins = skynet_agent::plugins::PluginRegistry::new(&config.agent.plugins);
+    plugins.reload();
+    if !mcp_stdio && config.agent.plugins.watch {
+        if let Err(e) = plugins.watch() {
+            tracing::warn!(error = %e, "plugin directory watch failed; use tools.reload");
+        }
+    }
 
     // Fired-job channel: SchedulerEngine → DeliveryRouter task
     let (fired_tx, fired_rx) = tokio::sync::mpsc::channel::<skynet_scheduler::Job>(256);
@@ -67,8 +119,27 @@ async fn main() -> anyhow::Result<()> {
     // initialize LLM provider from config
     let provider = build_provider(&config);
     let prompt = skynet_agent::prompt::PromptBuilder::load(config.agent.soul_path.as_deref());
+    let routing = skynet_agent::routing::ModelRouter::new(config.agent.routing.rules.clone());
+    if !routing.is_empty() {
+        info!(
+            rules = config.agent.routing.rules.len(),
+            "model routing enabled"
+        );
+    }
     let agent =
-        skynet_agent::runtime::AgentRuntime::new(provider, prompt, config.agent.model.clone());
+        skynet_agent::runtime::AgentRuntime::new(provider, prompt, config.agent.model.clone())
+            .with_routing(routing)
+            .with_pricing(skynet_agent::pricing::PricingTable::new(&config.pricing))
+            .with_context_windows(skynet_agent::context_budget::ContextWindows::new(
+                &config.agent.context_windows,
+            ))
+            .with_condense(config.agent.condense.clone())
+            .with_web_fetch(config.agent.web_fetch.clone())
+            .with_workspace(config.agent.workspace.clone());
+    let agent = match skynet_agent::search::build_provider(&config.search) {
+        Some(search) => agent.with_search(search, config.search.clone()),
+        None => agent,
+    };
 
     // terminal manager — no DB needed, all state is in-process
     let terminal = skynet_terminal::manager::TerminalManager::new();
@@ -81,7 +152,17 @@ async fn main() -> anyhow::Result<()> {
         sessions,
         scheduler_handle,
         terminal,
+        approvals,
+        mcp,
+        plugins,
+        checkpoints,
     ));
+
+    // stdio mode stops here: no HTTP listener, scheduler engine or Discord
+    if mcp_stdio {
+        return mcp_stdio::serve(state).await;
+    }
+
     let router = app::build_router(state.clone());
 
     // Spawn the delivery router: routes fired scheduler jobs to Discord or WS.
@@ -153,6 +234,30 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    // Forward approval requests and decisions to every authenticated WS client.
+    let mut approval_rx = state.approvals.subscribe();
+    let state_for_approvals = Arc::clone(&state);
+    tokio::spawn(async move {
+        use skynet_agent::approval::ApprovalEvent;
+        use tokio::sync::broadcast::error::RecvError;
+        loop {
+            let (name, req) = match approval_rx.recv().await {
+                Ok(ApprovalEvent::Requested(req)) => ("approval.requested", req),
+                Ok(ApprovalEvent::Resolved(req)) => ("approval.resolved", req),
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!(skipped = n, "approval event forwarder lagged");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let ev = skynet_protocol::frames::EventFrame::new(name, &req)
+                .with_seq(state_for_approvals.next_seq());
+            if let Ok(payload) = serde_json::to_string(&ev) {
+                state_for_approvals.broadcaster.send(payload);
+            }
+        }
+    });
+
     // spawn Discord adapter if configured
     if let Some(ref discord_cfg) = state.config.channels.discord {
         let adapter = skynet_discord::DiscordAdapter::new(discord_cfg, Arc::clone(&state));
@@ -175,8 +280,10 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, router).await?;
 
-    // signal scheduler to stop
+    // signal scheduler to stop, drop MCP connections and the plugin watcher
     let _ = shutdown_tx.send(true);
+    state.mcp.shutdown();
+    state.plugins.shutdown();
     Ok(())
 }
 
@@ -185,6 +292,21 @@ async fn main() -> anyhow::Result<()> {
 fn build_provider(
     config: &skynet_core::config::SkynetConfig,
 ) -> Box<dyn skynet_agent::provider::LlmProvider> {
+    // an explicit failover chain takes precedence over single-provider config
+    if !config.providers.chain.is_empty() {
+        let slots: Vec<_> = config
+            .providers
+            .chain
+            .iter()
+            .filter_map(|entry| build_chain_slot(entry, &config.providers))
+            .collect();
+        if !slots.is_empty() {
+            info!(providers = slots.len(), "LLM provider: failover chain");
+            return Box::new(skynet_agent::router::ProviderRouter::new(slots));
+        }
+        tracing::warn!("providers.chain has no usable entries — falling back");
+    }
+
     // check configured providers
     if let Some(ref anthropic) = config.providers.anthropic {
         info!("LLM provider: Anthropic ({})", anthropic.base_url);
@@ -229,6 +351,83 @@ fn build_provider(
     Box::new(NullProvider)
 }
 
+/// Build one `ProviderSlot` from a `[[providers.chain]]` entry.
+/// Returns `None` (with a warning) when the entry cannot be used.
+fn build_chain_slot(
+    entry: &skynet_core::config::ProviderChainEntry,
+    providers: &skynet_core::config::ProvidersConfig,
+) -> Option<skynet_agent::router::ProviderSlot> {
+    let provider: Box<dyn skynet_agent::provider::LlmProvider> = match entry.provider.as_str() {
+        "anthropic" => {
+            let key = entry
+                .api_key
+                .clone()
+                .or_else(|| providers.anthropic.as_ref().map(|a| a.api_key.clone()))
+                .or_else(|| std::env::var("ANTHROPIC_OAUTH_TOKEN").ok())
+                .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok());
+            let base_url = entry
+                .base_url
+                .clone()
+                .or_else(|| providers.anthropic.as_ref().map(|a| a.base_url.clone()));
+            let Some(key) = key else {
+                tracing::warn!("providers.chain: anthropic entry has no api_key — skipped");
+                return None;
+            };
+            Box::new(skynet_agent::anthropic::AnthropicProvider::new(
+                key, base_url,
+            ))
+        }
+        "openai" => {
+            let key = entry
+                .api_key
+                .clone()
+                .or_else(|| providers.openai.as_ref().map(|o| o.api_key.clone()))
+                .or_else(|| std::env::var("OPENAI_API_KEY").ok());
+            let base_url = entry
+                .base_url
+                .clone()
+                .or_else(|| providers.openai.as_ref().map(|o| o.base_url.clone()));
+            let Some(key) = key else {
+                tracing::warn!("providers.chain: openai entry has no api_key — skipped");
+                return None;
+            };
+            Box::new(skynet_agent::openai::OpenAiProvider::new(key, base_url))
+        }
+        "ollama" => {
+            let base_url = entry
+                .base_url
+                .clone()
+                .or_else(|| providers.ollama.as_ref().map(|o| o.base_url.clone()));
+            Box::new(skynet_agent::ollama::OllamaProvider::new(base_url))
+        }
+        other => {
+            tracing::warn!(provider = %other, "providers.chain: unknown provider — skipped");
+            return None;
+        }
+    };
+
+    info!(
+        provider = %entry.provider,
+        max_retries = entry.max_retries,
+        timeout_secs = entry.timeout_secs,
+        "failover chain slot"
+    );
+
+    let mut slot = skynet_agent::router::ProviderSlot::new(provider, entry.max_retries)
+        .with_model_map(entry.models.clone())
+        .with_circuit(
+            entry.failure_threshold,
+            std::time::Duration::from_secs(entry.cooldown_secs),
+        );
+    if entry.timeout_secs > 0 {
+        slot = slot.with_timeout(std::time::Duration::from_secs(entry.timeout_secs));
+    }
+    if let Some(ref model) = entry.model {
+        slot = slot.with_default_model(model.clone());
+    }
+    Some(slot)
+}
+
 /// Ensure the parent directory for a file path exists.
 fn ensure_parent_dir(path: &str) {
     if let Some(parent) = std::path::Path::new(path).parent() {
@@ -0,0 +1,73 @@
+//! `skynet-gateway --mcp-stdio` — serve MCP over stdin/stdout.
+//!
+//! Lets a local MCP client (an editor, another agent) spawn Skynet as a
+//! subprocess. Messages are newline-delimited JSON-RPC; logs go to stderr.
+//! Each message is handled in its own task, so a `notifications/cancelled`
+//! can reach a tool call that is still running.
+//! The process shares the gateway's database, so knowledge, memory and
+//! reminders written here are seen by a running gateway, whose scheduler
+//! fires the reminders.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::info;
+
+use skynet_agent::mcp::server::{self, McpEndpoint, RpcError};
+
+use crate::app::AppState;
+
+/// Session key MCP tool calls over stdio are recorded under.
+const SESSION_KEY: &str = "mcp:stdio";
+
+/// Answer MCP messages until stdin closes.
+pub async fn serve(state: Arc<AppState>) -> anyhow::Result<()> {
+    // Nobody can approve `ask` tools from here, so they are not offered.
+    let serve = &state.config.mcp.serve;
+    let mut endpoint =
+        McpEndpoint::new(Arc::clone(&state), SESSION_KEY, &serve.tools).without_gated_tools();
+    if let Some(user_id) = &serve.user_id {
+        endpoint = endpoint.with_user(user_id);
+    }
+    let endpoint = Arc::new(endpoint);
+    info!("serving MCP on stdio");
+
+    // Replies are written by one task, in the order they complete.
+    let (reply_tx, mut reply_rx) = mpsc::channel::<Value>(64);
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(reply) = reply_rx.recv().await {
+            stdout.write_all(format!("{reply}\n").as_bytes()).await?;
+            stdout.flush().await?;
+        }
+        Ok::<_, std::io::Error>(())
+    });
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let msg = match serde_json::from_str::<Value>(&line) {
+            Ok(msg) => msg,
+            Err(e) => {
+                let err = RpcError::new(server::PARSE_ERROR, format!("parse error: {e}"));
+                let _ = reply_tx.send(server::response(Value::Null, Err(err))).await;
+                continue;
+            }
+        };
+        let endpoint = Arc::clone(&endpoint);
+        let reply_tx = reply_tx.clone();
+        tokio::spawn(async move {
+            if let Some(reply) = endpoint.handle(msg).await {
+                let _ = reply_tx.send(reply).await;
+            }
+        });
+    }
+    info!("stdin closed, MCP stdio server exiting");
+    drop(reply_tx);
+    writer.await??;
+    Ok(())
+}
@@ -78,6 +78,11 @@ async fn run_connection(socket: WebSocket, state: Arc<AppState>) {
             }
 
             event = broadcast_rx.recv() => {
+                // Broadcasts can carry tool inputs (approval requests) —
+                // only authenticated clients receive them.
+                if !matches!(conn_state, ConnState::Authenticated) {
+                    continue;
+                }
                 if let Ok(payload) = event {
                     let mut guard = shared_tx.lock().await;
                     if guard.send(Message::Text(payload.into())).await.is_err() {
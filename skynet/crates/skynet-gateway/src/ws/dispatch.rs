@@ -22,6 +22,7 @@ pub async fn route(
     method: &str,
     params: Option<&serde_json::Value>,
     req_id: &str,
+    conn_id: &str,
     app: &Arc<AppState>,
     tx: &mut WsSink,
 ) -> ResFrame {
@@ -41,6 +42,8 @@ pub async fn route(
             handle_chat_send_inline(params, req_id, app, tx).await
         }
 
+        "chat.abort" => handle_chat_abort(params, req_id, conn_id, app),
+
         "agent.status" => {
             let current_model = app.agent.get_model().await;
             ResFrame::ok(
@@ -49,7 +52,8 @@ pub async fn route(
                     "agents": [{
                         "id": "main",
                         "model": current_model,
-                        "status": "idle"
+                        "status": "idle",
+                        "providers": app.agent.provider().health(),
                     }]
                 }),
             )
@@ -73,6 +77,32 @@ pub async fn route(
 
         "memory.forget" => handlers::handle_memory_forget(params, req_id, app).await,
 
+        // ------------------------------------------------------------------
+        // Tool approvals
+        // ------------------------------------------------------------------
+        "approval.list" => handlers::handle_approval_list(req_id, app).await,
+
+        "approval.decide" => handlers::handle_approval_decide(params, req_id, conn_id, app).await,
+
+        // ------------------------------------------------------------------
+        // MCP servers
+        // ------------------------------------------------------------------
+        "mcp.status" => handlers::handle_mcp_status(req_id, app).await,
+
+        // ------------------------------------------------------------------
+        // Script plugins
+        // ------------------------------------------------------------------
+        "tools.list" => handlers::handle_tools_list(req_id, app).await,
+
+        "tools.reload" => handlers::handle_tools_reload(req_id, app).await,
+
+        // ------------------------------------------------------------------
+        // File checkpoints
+        // ------------------------------------------------------------------
+        "checkpoints.list" => handlers::handle_checkpoints_list(params, req_id, app).await,
+
+        "checkpoints.restore" => handlers::handle_checkpoints_restore(params, req_id, app).await,
+
         // ------------------------------------------------------------------
         // Scheduler / Cron
         // ------------------------------------------------------------------
@@ -128,33 +158,48 @@ pub async fn route(
 pub async fn handle_chat_send_task(
     params: Option<&serde_json::Value>,
     req_id: &str,
+    conn_id: &str,
     app: &Arc<AppState>,
     tx: &send::SharedSink,
 ) {
-    let res = handle_chat_send(params, req_id, app, tx).await;
+    let res = handle_chat_send(params, req_id, conn_id, app, tx).await;
     let _ = send::json_shared(tx, &res).await;
 }
 
 /// Handle `chat.send` — stream LLM response back as EVENT frames.
 ///
-/// Params: `{ "message": string, "stream"?: bool, "model"?: string, "channel"?: string, "sender_id"?: string }`
+/// Params: `{ "message": string, "attachments"?: [{ "media_type", "data" | "url" }], "stream"?: bool, "model"?: string, "channel"?: string, "sender_id"?: string }`
 async fn handle_chat_send(
     params: Option<&serde_json::Value>,
     req_id: &str,
+    conn_id: &str,
     app: &Arc<AppState>,
     tx: &send::SharedSink,
 ) -> ResFrame {
+    let attachments = match parse_attachments(params) {
+        Ok(a) => a,
+        Err(e) => return ResFrame::err(req_id, "INVALID_PARAMS", &e),
+    };
+    // The text may be empty when the user only sends attachments.
     let message = match params
         .and_then(|p| p.get("message"))
         .and_then(|v| v.as_str())
     {
-        Some(m) if !m.is_empty() => m,
+        Some(m) if !m.is_empty() || !attachments.is_empty() => m,
         Some(_) => return ResFrame::err(req_id, "INVALID_PARAMS", "message cannot be empty"),
         None => return ResFrame::err(req_id, "INVALID_PARAMS", "missing 'message' field"),
     };
 
+    let channel = params
+        .and_then(|p| p.get("channel"))
+        .and_then(|v| v.as_str());
+    let sender_id = params
+        .and_then(|p| p.get("sender_id"))
+        .and_then(|v| v.as_str());
+    let session_key = session_key_for(channel, sender_id);
+
     // Intercept slash commands before sending to the AI (zero context cost).
-    if let Some(response) = handle_slash_command(message, app).await {
+    if let Some(response) = handle_slash_command(message, &session_key, app).await {
         return ResFrame::ok(
             req_id,
             serde_json::json!({ "content": response, "model": "gateway", "usage": { "input_tokens": 0, "output_tokens": 0 }, "stop_reason": "command" }),
@@ -173,33 +218,28 @@ async fn handle_chat_send(
         .filter(|s| !s.is_empty());
 
     // Resolve user memory context (None = anonymous / no context).
-    let channel = params
-        .and_then(|p| p.get("channel"))
-        .and_then(|v| v.as_str());
-    let sender_id = params
-        .and_then(|p| p.get("sender_id"))
-        .and_then(|v| v.as_str());
     let user_context = resolve_user_context(app, channel, sender_id);
+    let sender = resolve_sender_info(app, channel, sender_id);
 
-    // Derive session key: "channel:sender_id" for channel messages, "web:default" for web UI.
-    let session_key = match (channel, sender_id) {
-        (Some(ch), Some(sid)) => format!("{}:{}", ch, sid),
-        _ => "web:default".to_string(),
-    };
     let channel_name = channel.unwrap_or("web").to_string();
 
     info!(
         method = "chat.send",
         msg_len = message.len(),
+        attachments = attachments.len(),
         stream = wants_stream,
         model_override = model_override,
         session = %session_key,
         "processing"
     );
 
+    // Cancellable via `chat.abort` until the turn finishes.
+    let run = app.runs.register(conn_id, req_id, &session_key);
+
     if wants_stream {
         handle_streaming(
             message,
+            &attachments,
             req_id,
             app,
             tx,
@@ -207,22 +247,109 @@ async fn handle_chat_send(
             model_override,
             &session_key,
             &channel_name,
+            sender.as_ref(),
+            run.token(),
         )
         .await
     } else {
         handle_non_streaming(
             message,
+            &attachments,
             req_id,
             app,
             user_context.as_deref(),
             model_override,
             &session_key,
             &channel_name,
+            sender.as_ref(),
+            run.token(),
         )
         .await
     }
 }
 
+/// Derive the session key: "channel:sender_id" for channel messages,
+/// "web:default" for the web UI.
+pub(super) fn session_key_for(channel: Option<&str>, sender_id: Option<&str>) -> String {
+    match (channel, sender_id) {
+        (Some(ch), Some(sid)) => format!("{}:{}", ch, sid),
+        _ => "web:default".to_string(),
+    }
+}
+
+/// Handle `chat.abort` — cancel in-flight `chat.send` turns.
+///
+/// Params: `{ "req_id"?: string }` cancels the turn started by that request
+/// on this connection; otherwise the turns in the session given by
+/// `"session_key"` (or derived from `"channel"` / `"sender_id"` like
+/// `chat.send`) are cancelled. Only this connection's turns are touched.
+/// Returns `{ "aborted": <count> }`. The aborted turns still send their own
+/// final RES with `stop_reason: "aborted"`.
+fn handle_chat_abort(
+    params: Option<&serde_json::Value>,
+    req_id: &str,
+    conn_id: &str,
+    app: &AppState,
+) -> ResFrame {
+    let param = |key: &str| params.and_then(|p| p.get(key)).and_then(|v| v.as_str());
+
+    let aborted = match param("req_id") {
+        Some(target) => app.runs.cancel_request(conn_id, target),
+        None => {
+            let session_key = match param("session_key") {
+                Some(key) => key.to_string(),
+                None => session_key_for(param("channel"), param("sender_id")),
+            };
+            app.runs.cancel_session(&session_key, conn_id)
+        }
+    };
+
+    info!(method = "chat.abort", aborted, "processing");
+    ResFrame::ok(req_id, serde_json::json!({ "aborted": aborted }))
+}
+
+/// Parse the optional `attachments` param of `chat.send` into content blocks.
+///
+/// Each entry is `{ "media_type", "data" }` (base64) or `{ "media_type", "url" }`;
+/// `media_type` must be a supported image type or `application/pdf`.
+fn parse_attachments(
+    params: Option<&serde_json::Value>,
+) -> Result<Vec<skynet_agent::provider::ContentBlock>, String> {
+    use skynet_agent::provider::{ContentBlock, MediaSource};
+
+    let Some(list) = params.and_then(|p| p.get("attachments")) else {
+        return Ok(Vec::new());
+    };
+    let list = list
+        .as_array()
+        .ok_or_else(|| "'attachments' must be an array".to_string())?;
+
+    list.iter()
+        .enumerate()
+        .map(|(i, a)| {
+            let media_type = a
+                .get("media_type")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| format!("attachments[{i}]: missing 'media_type'"))?;
+            let source = match (
+                a.get("data").and_then(|v| v.as_str()),
+                a.get("url").and_then(|v| v.as_str()),
+            ) {
+                (Some(data), _) => MediaSource::Base64 {
+                    media_type: media_type.to_string(),
+                    data: data.to_string(),
+                },
+                (None, Some(url)) => MediaSource::Url {
+                    url: url.to_string(),
+                },
+                (None, None) => return Err(format!("attachments[{i}]: needs 'data' or 'url'")),
+            };
+            ContentBlock::attachment(media_type, source)
+                .ok_or_else(|| format!("attachments[{i}]: unsupported media_type '{media_type}'"))
+        })
+        .collect()
+}
+
 /// Inline fallback for `chat.send` if it arrives through `route()`.
 ///
 /// This should not happen in normal operation — `chat.send` is spawned
@@ -242,7 +369,8 @@ async fn handle_chat_send_inline(
         None => return ResFrame::err(req_id, "INVALID_PARAMS", "missing 'message' field"),
     };
 
-    if let Some(response) = handle_slash_command(message, app).await {
+    // The inline path always runs in the web session (see handle_streaming_inline).
+    if let Some(response) = handle_slash_command(message, "web:default", app).await {
         return ResFrame::ok(
             req_id,
             serde_json::json!({ "content": response, "model": "gateway", "usage": { "input_tokens": 0, "output_tokens": 0 }, "stop_reason": "command" }),
@@ -299,6 +427,16 @@ fn resolve_user_context(
     }
 }
 
+/// Resolve the sender's role and remaining daily token budget for model routing.
+/// Returns `None` for anonymous senders or when resolution fails.
+fn resolve_sender_info(
+    app: &AppState,
+    channel: Option<&str>,
+    sender_id: Option<&str>,
+) -> Option<skynet_agent::routing::SenderInfo> {
+    skynet_agent::routing::SenderInfo::resolve(&app.users, channel?, sender_id?)
+}
+
 /// Streaming path (shared sink) — pushes `chat.delta` EVENT frames, returns final RES.
 ///
 /// Uses `send::json_shared` for all writes so that the connection loop and
@@ -306,6 +444,7 @@ fn resolve_user_context(
 #[allow(clippy::too_many_arguments)]
 async fn handle_streaming(
     message: &str,
+    attachments: &[skynet_agent::provider::ContentBlock],
     req_id: &str,
     app: &Arc<AppState>,
     tx: &send::SharedSink,
@@ -313,14 +452,30 @@ async fn handle_streaming(
     model_override: Option<&str>,
     session_key: &str,
     channel_name: &str,
+    sender: Option<&skynet_agent::routing::SenderInfo>,
+    cancel: &skynet_agent::cancel::CancellationToken,
 ) -> ResFrame {
-    use skynet_agent::provider::ChatRequest;
+    use skynet_agent::cancel::{mark_aborted, ABORTED_STOP_REASON};
+    use skynet_agent::pipeline::stored_user_content;
+    use skynet_agent::pricing::TokenUsage;
+    use skynet_agent::provider::{ChatRequest, MessageContent, ToolCall};
+    use skynet_agent::routing::RoutingInput;
     use skynet_agent::stream::StreamEvent;
+    use skynet_agent::tools::loop_guard::{self, LoopGuard, LoopVerdict, LOOP_STOP_REASON};
+    use skynet_agent::tools::tool_loop::execute_tool_calls;
+    use skynet_agent::tools::ToolResult;
     use skynet_memory::types::ConversationMessage;
 
     // Build tools once for the entire turn.
     // WS has no single Discord channel_id — reminders are broadcast to all WS clients.
-    let tools = crate::tools::build_tools(Arc::clone(app), channel_name, None);
+    let tools = crate::tools::build_tools(
+        Arc::clone(app),
+        channel_name,
+        None,
+        session_key,
+        sender,
+        cancel,
+    );
     let tool_defs = crate::tools::tool_definitions(&tools);
 
     // Acquire the system prompt then immediately release the RwLock so we
@@ -331,10 +486,21 @@ async fn handle_streaming(
     };
     let plain = system_prompt.to_plain_text();
 
-    let model = match model_override {
-        Some(m) => m.to_string(),
-        None => app.agent.get_model().await,
+    let sender = sender.cloned().unwrap_or_default();
+    let routing_input = RoutingInput {
+        message,
+        channel: channel_name,
+        user_role: sender.role,
+        tools_used_last_turn: app
+            .memory
+            .last_turn_used_tools(session_key)
+            .unwrap_or(false),
+        budget_remaining_tokens: sender.budget_remaining_tokens,
     };
+    let (model, routing_rule) = app
+        .agent
+        .resolve_model(model_override, &routing_input)
+        .await;
 
     // Load conversation history from SQLite (last 40 turns = 20 exchanges).
     let history = app.memory.get_history(session_key, 40).unwrap_or_default();
@@ -342,21 +508,32 @@ async fn handle_streaming(
     // Build rolling message list: prior turns + current user message.
     let mut raw_messages: Vec<serde_json::Value> = history
         .iter()
-        .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
+        .map(|m| serde_json::json!({ "role": m.api_role(), "content": m.content }))
         .collect();
-    raw_messages.push(serde_json::json!({ "role": "user", "content": message }));
+    raw_messages.push(serde_json::json!({
+        "role": "user",
+        "content": MessageContent::with_attachments(message, attachments),
+    }));
 
     let mut accumulated = String::new();
     let mut final_model = String::new();
-    let mut final_tokens_in: u32 = 0;
-    let mut final_tokens_out: u32 = 0;
+    // Summed over every LLM call in the tool loop.
+    let mut usage = TokenUsage::default();
     let mut final_stop = String::new();
+    // Set when `chat.abort` cancels the turn.
+    let mut aborted = false;
+    let mut guard = LoopGuard::new();
 
     // Cap tool-loop iterations to prevent runaway agents.
     const MAX_ITERS: usize = 10;
 
     for _iter in 0..MAX_ITERS {
-        let req = ChatRequest {
+        if cancel.is_cancelled() {
+            aborted = true;
+            break;
+        }
+
+        let mut req = ChatRequest {
             model: model.clone(),
             system: plain.clone(),
             system_prompt: Some(system_prompt.clone()),
@@ -367,6 +544,12 @@ async fn handle_streaming(
             tools: tool_defs.clone(),
             raw_messages: Some(raw_messages.clone()),
         };
+        // The router may remap the model, so size the window after routing.
+        let window = app
+            .agent
+            .context_windows()
+            .get(&app.agent.provider().resolve_model(&model));
+        skynet_agent::context_budget::fit_request(&mut req, window);
 
         let (stream_tx, mut stream_rx) = tokio::sync::mpsc::channel::<StreamEvent>(64);
         let send_fut = app.agent.provider().send_stream(&req, stream_tx);
@@ -378,6 +561,11 @@ async fn handle_streaming(
 
         loop {
             tokio::select! {
+                // Dropping `send_fut` closes the provider stream.
+                _ = cancel.cancelled() => {
+                    aborted = true;
+                    break;
+                }
                 event = stream_rx.recv() => {
                     match event {
                         Some(StreamEvent::TextDelta { text }) => {
@@ -392,10 +580,22 @@ async fn handle_streaming(
                         Some(StreamEvent::ToolUse { id, name, input }) => {
                             iter_tools.push((id, name, input));
                         }
-                        Some(StreamEvent::Done { model: m, tokens_in, tokens_out, stop_reason }) => {
+                        Some(StreamEvent::Done {
+                            model: m,
+                            tokens_in,
+                            tokens_out,
+                            cache_write_tokens,
+                            cache_read_tokens,
+                            stop_reason,
+                        }) => {
                             final_model = m;
-                            final_tokens_in = tokens_in;
-                            final_tokens_out = tokens_out;
+                            usage.add(&TokenUsage {
+                                tokens_in,
+                                tokens_out,
+                                cache_write_tokens,
+                                cache_read_tokens,
+                                ..Default::default()
+                            });
                             final_stop = stop_reason;
                         }
                         Some(StreamEvent::Error { message }) => {
@@ -426,10 +626,22 @@ async fn handle_streaming(
                             StreamEvent::ToolUse { id, name, input } => {
                                 iter_tools.push((id, name, input));
                             }
-                            StreamEvent::Done { model: m, tokens_in, tokens_out, stop_reason } => {
+                            StreamEvent::Done {
+                                model: m,
+                                tokens_in,
+                                tokens_out,
+                                cache_write_tokens,
+                                cache_read_tokens,
+                                stop_reason,
+                            } => {
                                 final_model = m;
-                                final_tokens_in = tokens_in;
-                                final_tokens_out = tokens_out;
+                                usage.add(&TokenUsage {
+                                    tokens_in,
+                                    tokens_out,
+                                    cache_write_tokens,
+                                    cache_read_tokens,
+                                    ..Default::default()
+                                });
                                 final_stop = stop_reason;
                             }
                             _ => {}
@@ -441,7 +653,7 @@ async fn handle_streaming(
         }
 
         // No tool calls -> streaming is complete.
-        if iter_tools.is_empty() || final_stop != "tool_use" {
+        if aborted || iter_tools.is_empty() || final_stop != "tool_use" {
             break;
         }
 
@@ -457,13 +669,28 @@ async fn handle_streaming(
         }
         raw_messages.push(serde_json::json!({ "role": "assistant", "content": asst }));
 
-        // Execute each tool — tool output is sent as a separate `chat.tool` event
+        // Execute the tools — output is sent as separate `chat.tool` events
         // (not inline in the chat bubble) so the UI can render it independently.
-        let mut tool_results: Vec<serde_json::Value> = Vec::new();
-        for (id, name, input) in iter_tools {
+        // Concurrency-safe calls run in parallel; `id` pairs their events.
+        let calls: Vec<ToolCall> = iter_tools
+            .into_iter()
+            .map(|(id, name, input)| ToolCall { id, name, input })
+            .collect();
+        let tools = &tools;
+        let results = execute_tool_calls(tools, &calls, |call| async move {
+            // Tools not yet started are skipped once the turn is aborted.
+            if cancel.is_cancelled() {
+                return ToolResult::error("turn aborted before this tool ran");
+            }
+
             // Human-readable label for the tool call.
+            let name = &call.name;
             let label = if name == "execute_command" || name == "bash" {
-                let cmd = input.get("command").and_then(|v| v.as_str()).unwrap_or("?");
+                let cmd = call
+                    .input
+                    .get("command")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("?");
                 format!("$ {}", cmd.chars().take(80).collect::<String>())
             } else {
                 name.clone()
@@ -476,6 +703,7 @@ async fn handle_streaming(
                     "chat.tool",
                     serde_json::json!({
                         "req_id": req_id,
+                        "id": call.id,
                         "name": name,
                         "label": label,
                         "status": "running",
@@ -487,10 +715,12 @@ async fn handle_streaming(
             let result = match tools.iter().find(|t| t.name() == name) {
                 Some(tool) => {
                     info!(tool = %name, "executing tool");
-                    tool.execute(input).await
+                    tool.execute(call.input.clone()).await
                 }
-                None => skynet_agent::tools::ToolResult::error(format!("unknown tool: {name}")),
+                None => ToolResult::error(format!("unknown tool: {name}")),
             };
+            // Same frequency tracking as the non-streaming pipeline.
+            let _ = app.memory.log_tool_call(name, session_key);
 
             // Notify client that the tool finished (truncated output for the UI).
             let output_preview: String = result.content.chars().take(500).collect();
@@ -505,6 +735,7 @@ async fn handle_streaming(
                     "chat.tool",
                     serde_json::json!({
                         "req_id": req_id,
+                        "id": call.id,
                         "name": name,
                         "label": label,
                         "status": "done",
@@ -515,60 +746,130 @@ async fn handle_streaming(
             )
             .await;
 
-            tool_results.push(serde_json::json!({
-                "type": "tool_result",
-                "tool_use_id": id,
-                "content": result.content,
-                "is_error": result.is_error,
-            }));
+            result
+        })
+        .await;
+        // Subagents report the tokens they spent; bill them to this turn.
+        for u in results.iter().filter_map(|r| r.usage.as_ref()) {
+            usage.add(u);
+        }
+
+        let verdict = guard.observe(&calls, &results);
+
+        let mut tool_results: Vec<serde_json::Value> = calls
+            .iter()
+            .zip(results)
+            .map(|(call, result)| {
+                serde_json::json!({
+                    "type": "tool_result",
+                    "tool_use_id": call.id,
+                    "content": result.content,
+                    "is_error": result.is_error,
+                })
+            })
+            .collect();
+
+        match verdict {
+            LoopVerdict::Continue => {}
+            LoopVerdict::Nudge(message) => {
+                warn!(%message, session = %session_key, "agent looks stuck, nudging");
+                tool_results.push(serde_json::json!({ "type": "text", "text": message }));
+            }
+            LoopVerdict::Stop(reason) => {
+                warn!(%reason, session = %session_key, "agent stuck, ending turn");
+                let note = if accumulated.is_empty() {
+                    loop_guard::stop_note(&reason)
+                } else {
+                    format!("\n\n{}", loop_guard::stop_note(&reason))
+                };
+                accumulated.push_str(&note);
+                let ev = EventFrame::new(
+                    "chat.delta",
+                    serde_json::json!({ "text": note, "req_id": req_id }),
+                );
+                let _ = send::json_shared(tx, &ev).await;
+                final_stop = LOOP_STOP_REASON.to_string();
+                break;
+            }
         }
 
         raw_messages.push(serde_json::json!({ "role": "user", "content": tool_results }));
     }
 
+    if aborted {
+        final_stop = ABORTED_STOP_REASON.to_string();
+        if final_model.is_empty() {
+            final_model = model.clone();
+        }
+        info!(session = %session_key, "chat.send aborted");
+    }
+
+    let cost_usd = app.agent.pricing().cost_usd(&final_model, &usage);
+
     info!(
-        tokens_in = final_tokens_in,
-        tokens_out = final_tokens_out,
+        tokens_in = usage.tokens_in,
+        tokens_out = usage.tokens_out,
+        cache_read = usage.cache_read_tokens,
+        cost_usd,
         model = %final_model,
         session = %session_key,
         "streaming chat complete"
     );
 
+    // Bill every turn, including tool-only turns that end without text.
+    skynet_agent::pipeline::record_session_usage(
+        app.as_ref(),
+        session_key,
+        sender.user_id.as_deref(),
+        &usage,
+        cost_usd,
+        &final_model,
+    );
+
     // Persist this turn to SQLite so future messages have conversation history.
-    if !accumulated.is_empty() {
+    // An aborted turn keeps its partial reply, marked as aborted.
+    if !accumulated.is_empty() || aborted {
         let now = chrono::Utc::now().to_rfc3339();
         let _ = app.memory.save_message(&ConversationMessage {
             id: 0,
-            user_id: None,
+            user_id: sender.user_id.clone(),
             session_key: session_key.to_string(),
             channel: channel_name.to_string(),
             role: "user".to_string(),
-            content: message.to_string(),
+            content: stored_user_content(message, attachments),
             model_used: None,
             tokens_in: 0,
             tokens_out: 0,
             cost_usd: 0.0,
             created_at: now.clone(),
+            routing_rule: None,
+            condense_parent: None,
         });
         let _ = app.memory.save_message(&ConversationMessage {
             id: 0,
-            user_id: None,
+            user_id: sender.user_id.clone(),
             session_key: session_key.to_string(),
             channel: channel_name.to_string(),
             role: "assistant".to_string(),
-            content: accumulated.clone(),
+            content: if aborted {
+                mark_aborted(&accumulated)
+            } else {
+                accumulated.clone()
+            },
             model_used: Some(final_model.clone()),
-            tokens_in: final_tokens_in,
-            tokens_out: final_tokens_out,
-            cost_usd: 0.0,
+            tokens_in: usage.tokens_in,
+            tokens_out: usage.tokens_out,
+            cost_usd,
             created_at: now,
+            routing_rule: routing_rule.clone(),
+            condense_parent: None,
         });
 
-        // Fire-and-forget: compact if the session has grown too long.
+        // Fire-and-forget: condense if the history is over its token budget.
         let app_clone = Arc::clone(app);
         let sk = session_key.to_string();
         tokio::spawn(async move {
-            compact_session_if_needed(app_clone, sk).await;
+            condense_session_if_needed(app_clone, sk, model).await;
         });
     }
 
@@ -578,10 +879,14 @@ async fn handle_streaming(
             "content": accumulated,
             "model": final_model,
             "usage": {
-                "input_tokens": final_tokens_in,
-                "output_tokens": final_tokens_out,
+                "input_tokens": usage.tokens_in,
+                "output_tokens": usage.tokens_out,
+                "cache_read_tokens": usage.cache_read_tokens,
+                "cache_write_tokens": usage.cache_write_tokens,
+                "cost_usd": cost_usd,
             },
             "stop_reason": final_stop,
+            "routing_rule": routing_rule,
         }),
     )
 }
@@ -600,7 +905,14 @@ async fn handle_streaming_inline(
     use skynet_agent::provider::ChatRequest;
     use skynet_agent::stream::StreamEvent;
 
-    let tools = crate::tools::build_tools(Arc::clone(app), "ws", None);
+    let tools = crate::tools::build_tools(
+        Arc::clone(app),
+        "ws",
+        None,
+        "web:default",
+        None,
+        &skynet_agent::cancel::CancellationToken::new(),
+    );
     let tool_defs = crate::tools::tool_definitions(&tools);
 
     let system_prompt = {
@@ -626,7 +938,7 @@ async fn handle_streaming_inline(
     const MAX_ITERS: usize = 10;
 
     for _iter in 0..MAX_ITERS {
-        let req = ChatRequest {
+        let mut req = ChatRequest {
             model: model.clone(),
             system: plain.clone(),
             system_prompt: Some(system_prompt.clone()),
@@ -637,6 +949,12 @@ async fn handle_streaming_inline(
             tools: tool_defs.clone(),
             raw_messages: Some(raw_messages.clone()),
         };
+        // The router may remap the model, so size the window after routing.
+        let window = app
+            .agent
+            .context_windows()
+            .get(&app.agent.provider().resolve_model(&model));
+        skynet_agent::context_budget::fit_request(&mut req, window);
 
         let (stream_tx, mut stream_rx) = tokio::sync::mpsc::channel::<StreamEvent>(64);
         let send_fut = app.agent.provider().send_stream(&req, stream_tx);
@@ -661,7 +979,7 @@ async fn handle_streaming_inline(
                         Some(StreamEvent::ToolUse { id, name, input }) => {
                             iter_tools.push((id, name, input));
                         }
-                        Some(StreamEvent::Done { model: m, tokens_in, tokens_out, stop_reason }) => {
+                        Some(StreamEvent::Done { model: m, tokens_in, tokens_out, stop_reason, .. }) => {
                             final_model = m;
                             final_tokens_in = tokens_in;
                             final_tokens_out = tokens_out;
@@ -694,7 +1012,7 @@ async fn handle_streaming_inline(
                             StreamEvent::ToolUse { id, name, input } => {
                                 iter_tools.push((id, name, input));
                             }
-                            StreamEvent::Done { model: m, tokens_in, tokens_out, stop_reason } => {
+                            StreamEvent::Done { model: m, tokens_in, tokens_out, stop_reason, .. } => {
                                 final_model = m;
                                 final_tokens_in = tokens_in;
                                 final_tokens_out = tokens_out;
@@ -805,16 +1123,20 @@ async fn handle_streaming_inline(
 /// Non-streaming path — delegates to the shared pipeline in skynet-agent.
 ///
 /// All pipeline logic (history load, prompt build, tool loop, memory save,
-/// session compact) lives in `skynet_agent::pipeline::process_message_non_streaming`.
+/// session condensation) lives in `skynet_agent::pipeline::process_message_non_streaming`.
 /// This function only adds the gateway-specific WS frame formatting.
+#[allow(clippy::too_many_arguments)]
 async fn handle_non_streaming(
     message: &str,
+    attachments: &[skynet_agent::provider::ContentBlock],
     req_id: &str,
     app: &Arc<AppState>,
     user_context: Option<&str>,
     model_override: Option<&str>,
     session_key: &str,
     channel_name: &str,
+    sender: Option<&skynet_agent::routing::SenderInfo>,
+    cancel: &skynet_agent::cancel::CancellationToken,
 ) -> ResFrame {
     use skynet_agent::pipeline::process_message_non_streaming;
 
@@ -823,9 +1145,12 @@ async fn handle_non_streaming(
         session_key,
         channel_name,
         message,
+        attachments,
         user_context,
         model_override,
         None, // WS: no Discord channel_id; reminder delivery is broadcast to ws_clients
+        sender,
+        cancel,
     )
     .await
     {
@@ -845,8 +1170,12 @@ async fn handle_non_streaming(
                     "usage": {
                         "input_tokens": r.tokens_in,
                         "output_tokens": r.tokens_out,
+                        "cache_read_tokens": r.cache_read_tokens,
+                        "cache_write_tokens": r.cache_write_tokens,
+                        "cost_usd": r.cost_usd,
                     },
                     "stop_reason": r.stop_reason,
+                    "routing_rule": r.routing_rule,
                 }),
             )
         }
@@ -858,14 +1187,15 @@ async fn handle_non_streaming(
 }
 
 // ---------------------------------------------------------------------------
-// Session compaction — re-exported from the shared pipeline in skynet-agent
+// Session condensation — re-exported from the shared pipeline in skynet-agent
 // ---------------------------------------------------------------------------
 
-/// Compact a session's conversation history when it exceeds the turn threshold.
+/// Condense a session's oldest turns into a summary when its history exceeds
+/// the token budget.
 ///
 /// This is the canonical implementation from `skynet_agent::pipeline`.
 /// Re-aliased here for use by the streaming path which still calls it directly.
-use skynet_agent::pipeline::compact_session_if_needed;
+use skynet_agent::pipeline::condense_session_if_needed;
 
 // ---------------------------------------------------------------------------
 // agent.model — get/set the runtime default LLM model
@@ -934,7 +1264,9 @@ fn resolve_model_alias(input: &str) -> Option<&'static str> {
 ///   /model sonnet    -- switch to claude-sonnet-4-6
 ///   /model haiku     -- switch to claude-haiku-4-5
 ///   /config          -- show runtime configuration summary
-async fn handle_slash_command(message: &str, app: &AppState) -> Option<String> {
+///   /undo            -- roll back the files changed in the session's last turn
+///   /undo <turn_id>  -- roll back that turn and every later one
+async fn handle_slash_command(message: &str, session_key: &str, app: &AppState) -> Option<String> {
     let trimmed = message.trim();
 
     // /model [name]
@@ -977,6 +1309,11 @@ async fn handle_slash_command(message: &str, app: &AppState) -> Option<String> {
         ));
     }
 
+    // /undo [turn_id]
+    if let Some(turn_id) = skynet_agent::pipeline::parse_undo(trimmed) {
+        return Some(skynet_agent::pipeline::undo(app, session_key, turn_id));
+    }
+
     // Not a slash command -- forward to AI.
     None
 }
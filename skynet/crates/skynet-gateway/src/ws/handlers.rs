@@ -9,10 +9,13 @@ use skynet_memory::types::{MemoryCategory, MemorySource};
 use skynet_protocol::frames::ResFrame;
 use skynet_scheduler::Schedule;
 use skynet_sessions::types::SessionKey;
+use skynet_sessions::SessionError;
 use tracing::warn;
 
 use crate::app::AppState;
 
+use super::dispatch::session_key_for;
+
 // ---------------------------------------------------------------------------
 // sessions.list
 // ---------------------------------------------------------------------------
@@ -259,6 +262,75 @@ pub async fn handle_memory_forget(
     }
 }
 
+// ---------------------------------------------------------------------------
+// approval.list
+// ---------------------------------------------------------------------------
+
+/// Handler for `approval.list`. Returns tool calls waiting for a decision,
+/// oldest first.
+pub async fn handle_approval_list(req_id: &str, app: &AppState) -> ResFrame {
+    match app.approvals.pending() {
+        Ok(requests) => ResFrame::ok(req_id, serde_json::json!({ "requests": requests })),
+        Err(e) => {
+            warn!(error = %e, "approval.list failed");
+            ResFrame::err(req_id, "INTERNAL_ERROR", &e.to_string())
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// approval.decide
+// ---------------------------------------------------------------------------
+
+/// Handler for `approval.decide`.
+///
+/// Params: `{ "id": string, "approve": bool, "reason"?: string }`
+///
+/// Resumes (approve) or rejects the parked tool call. A WS connection is
+/// authenticated only by the shared gateway credentials, so every connected
+/// client is the operator and may decide; the decision is recorded as
+/// `ws:{conn_id}` in `decided_by`.
+pub async fn handle_approval_decide(
+    params: Option<&serde_json::Value>,
+    req_id: &str,
+    conn_id: &str,
+    app: &AppState,
+) -> ResFrame {
+    let p = match params {
+        Some(p) => p,
+        None => return ResFrame::err(req_id, "INVALID_PARAMS", "params object required"),
+    };
+
+    let id = match p.get("id").and_then(|v| v.as_str()) {
+        Some(s) if !s.is_empty() => s,
+        _ => return ResFrame::err(req_id, "INVALID_PARAMS", "missing or empty 'id' field"),
+    };
+
+    let approve = match p.get("approve").and_then(|v| v.as_bool()) {
+        Some(b) => b,
+        None => return ResFrame::err(req_id, "INVALID_PARAMS", "missing 'approve' field"),
+    };
+
+    let reason = p
+        .get("reason")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty());
+
+    let decided_by = format!("ws:{conn_id}");
+    match app.approvals.decide(id, approve, &decided_by, reason) {
+        Ok(Some(request)) => ResFrame::ok(req_id, serde_json::json!({ "request": request })),
+        Ok(None) => ResFrame::err(
+            req_id,
+            "NOT_FOUND",
+            &format!("no pending approval request: {id}"),
+        ),
+        Err(e) => {
+            warn!(error = %e, "approval.decide failed");
+            ResFrame::err(req_id, "INTERNAL_ERROR", &e.to_string())
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // cron.list
 // ---------------------------------------------------------------------------
@@ -371,6 +443,7 @@ fn map_terminal_error(req_id: &str, e: skynet_terminal::TerminalError) -> ResFra
         TerminalError::Timeout { ms } => {
             ResFrame::err(req_id, "TIMEOUT", &format!("timed out after {ms}ms"))
         }
+        TerminalError::Cancelled => ResFrame::err(req_id, "CANCELLED", "operation cancelled"),
         TerminalError::PtySpawn(msg) => ResFrame::err(req_id, "SPAWN_ERROR", &msg),
         TerminalError::IoError(e) => ResFrame::err(req_id, "IO_ERROR", &e.to_string()),
     }
@@ -419,6 +492,7 @@ pub async fn handle_terminal_exec(
         max_output_chars,
         // Safety filter always enabled via WS API; callers cannot bypass it.
         skip_safety: false,
+        cancel: None,
     };
 
     match app.terminal.lock().await.exec(command, opts).await {
@@ -684,3 +758,115 @@ pub async fn handle_terminal_job_kill(
         }
     }
 }
+
+// ---------------------------------------------------------------------------
+// mcp.status
+// ---------------------------------------------------------------------------
+
+/// Handler for `mcp.status`. Returns every configured MCP server with its
+/// connection state, exposed tools and last error.
+pub async fn handle_mcp_status(req_id: &str, app: &AppState) -> ResFrame {
+    ResFrame::ok(req_id, serde_json::json!({ "servers": app.mcp.status() }))
+}
+
+// ---------------------------------------------------------------------------
+// tools.list / tools.reload
+// ---------------------------------------------------------------------------
+
+/// Handler for `tools.list`. Returns the loaded script plugins and the
+/// plugin directories that failed to load.
+pub async fn handle_tools_list(req_id: &str, app: &AppState) -> ResFrame {
+    ResFrame::ok(req_id, serde_json::json!(app.plugins.status()))
+}
+
+/// Handler for `tools.reload`. Re-scans the plugin directory now and returns
+/// the same payload as `tools.list`.
+pub async fn handle_tools_reload(req_id: &str, app: &AppState) -> ResFrame {
+    let plugins = app.plugins.clone();
+    match tokio::task::spawn_blocking(move || plugins.reload()).await {
+        Ok(status) => ResFrame::ok(req_id, serde_json::json!(status)),
+        Err(e) => ResFrame::err(req_id, "INTERNAL_ERROR", &e.to_string()),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// checkpoints.list / checkpoints.restore
+// ---------------------------------------------------------------------------
+
+/// Session addressed by a `checkpoints.*` call: `"session_key"`, or derived
+/// from `"channel"` / `"sender_id"` like `chat.send`. Every authenticated WS
+/// client is the operator, so any session may be named.
+fn checkpoint_session(params: Option<&serde_json::Value>) -> String {
+    let param = |key: &str| params.and_then(|p| p.get(key)).and_then(|v| v.as_str());
+    match param("session_key") {
+        Some(key) => key.to_string(),
+        None => session_key_for(param("channel"), param("sender_id")),
+    }
+}
+
+/// Handler for `checkpoints.list`.
+///
+/// Params: `{ "session_key"?: string, "channel"?: string, "sender_id"?: string,
+/// "limit"?: number }`
+///
+/// Returns the session's most recent turns that changed files, newest first.
+pub async fn handle_checkpoints_list(
+    params: Option<&serde_json::Value>,
+    req_id: &str,
+    app: &AppState,
+) -> ResFrame {
+    const DEFAULT_LIMIT: usize = 20;
+    const MAX_LIMIT: usize = 100;
+
+    let Some(ref store) = app.checkpoints else {
+        return ResFrame::err(req_id, "NOT_FOUND", "file checkpoints are disabled");
+    };
+    let session_key = checkpoint_session(params);
+    let limit = params
+        .and_then(|p| p.get("limit"))
+        .and_then(|v| v.as_u64())
+        .map(|n| (n as usize).min(MAX_LIMIT))
+        .unwrap_or(DEFAULT_LIMIT);
+
+    match store.list(&session_key, limit) {
+        Ok(turns) => ResFrame::ok(
+            req_id,
+            serde_json::json!({ "session_key": session_key, "turns": turns }),
+        ),
+        Err(e) => {
+            warn!(error = %e, "checkpoints.list failed");
+            ResFrame::err(req_id, "INTERNAL_ERROR", &e.to_string())
+        }
+    }
+}
+
+/// Handler for `checkpoints.restore`.
+///
+/// Params: `{ "turn_id"?: string }` plus the session params of
+/// `checkpoints.list`. Rolls back `turn_id` and every later turn of the
+/// session, or only the latest turn when `turn_id` is absent.
+pub async fn handle_checkpoints_restore(
+    params: Option<&serde_json::Value>,
+    req_id: &str,
+    app: &AppState,
+) -> ResFrame {
+    let Some(ref store) = app.checkpoints else {
+        return ResFrame::err(req_id, "NOT_FOUND", "file checkpoints are disabled");
+    };
+    let session_key = checkpoint_session(params);
+    let turn_id = params
+        .and_then(|p| p.get("turn_id"))
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty());
+
+    match store.restore(&session_key, turn_id) {
+        Ok(report) => ResFrame::ok(req_id, serde_json::json!(report)),
+        Err(e @ SessionError::NoCheckpoint(_)) => {
+            ResFrame::err(req_id, "NOT_FOUND", &e.to_string())
+        }
+        Err(e) => {
+            warn!(error = %e, "checkpoints.restore failed");
+            ResFrame::err(req_id, "INTERNAL_ERROR", &e.to_string())
+        }
+    }
+}
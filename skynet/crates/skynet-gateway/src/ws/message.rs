@@ -28,7 +28,7 @@ pub async fn handle(
 
     match state {
         ConnState::AwaitingConnect { .. } => handle_auth(conn_id, frame, tx, app).await,
-        ConnState::Authenticated => handle_method(frame, tx, app).await,
+        ConnState::Authenticated => handle_method(conn_id, frame, tx, app).await,
         ConnState::Closing => ConnState::Closing,
     }
 }
@@ -86,6 +86,7 @@ async fn handle_auth(
 /// loop can keep reading new messages. All other methods run inline (they are
 /// fast, non-blocking operations).
 async fn handle_method(
+    conn_id: &str,
     frame: InboundFrame,
     tx: &send::SharedSink,
     app: &Arc<AppState>,
@@ -99,16 +100,31 @@ async fn handle_method(
         let tx2 = Arc::clone(tx);
         let params_owned = req.params;
         let req_id_owned = req.id.clone();
+        let conn_id_owned = conn_id.to_string();
         tokio::spawn(async move {
-            dispatch::handle_chat_send_task(params_owned.as_ref(), &req_id_owned, &app2, &tx2)
-                .await;
+            dispatch::handle_chat_send_task(
+                params_owned.as_ref(),
+                &req_id_owned,
+                &conn_id_owned,
+                &app2,
+                &tx2,
+            )
+            .await;
         });
         return ConnState::Authenticated;
     }
 
     // All other methods: lock the sink, run inline, send response.
     let mut guard = tx.lock().await;
-    let res = dispatch::route(&req.method, req.params.as_ref(), &req.id, app, &mut guard).await;
+    let res = dispatch::route(
+        &req.method,
+        req.params.as_ref(),
+        &req.id,
+        conn_id,
+        app,
+        &mut guard,
+    )
+    .await;
     let _ = send::json(&mut guard, &res).await;
 
     ConnState::Authenticated
@@ -1,5 +1,7 @@
 use rusqlite::{Connection, Result};
 
+use skynet_core::db::add_column_if_missing;
+
 /// Initialise memory tables. Safe to call on every startup (idempotent).
 pub fn init_db(conn: &Connection) -> Result<()> {
     create_user_memory_table(conn)?;
@@ -91,11 +93,16 @@ fn create_conversations_table(conn: &Connection) -> Result<()> {
             tokens_in   INTEGER NOT NULL DEFAULT 0,
             tokens_out  INTEGER NOT NULL DEFAULT 0,
             cost_usd    REAL NOT NULL DEFAULT 0,
-            created_at  TEXT NOT NULL
+            created_at  TEXT NOT NULL,
+            routing_rule TEXT,
+            condense_parent INTEGER
         );
         CREATE INDEX IF NOT EXISTS idx_conv_user
             ON conversations(user_id, created_at DESC);
         CREATE INDEX IF NOT EXISTS idx_conv_session
             ON conversations(session_key, created_at);",
-    )
+    )?;
+    // Columns added after the first release — older databases need ALTERs.
+    add_column_if_missing(conn, "conversations", "routing_rule", "TEXT")?;
+    add_column_if_missing(conn, "conversations", "condense_parent", "INTEGER")
 }
@@ -60,7 +60,7 @@ impl MemoryManager {
             .ok();
 
         match existing {
-            Some((id, old_conf)) if confidence < old_conf => {
+            Some((_, old_conf)) if confidence < old_conf => {
                 debug!(
                     user_id,
                     key,
@@ -224,24 +224,7 @@ impl MemoryManager {
     /// Store a conversation message for history and cost tracking.
     pub fn save_message(&self, msg: &ConversationMessage) -> Result<(), MemoryError> {
         let db = self.db.lock().unwrap();
-        db.execute(
-            "INSERT INTO conversations
-             (user_id, session_key, channel, role, content, model_used,
-              tokens_in, tokens_out, cost_usd, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-            rusqlite::params![
-                msg.user_id,
-                msg.session_key,
-                msg.channel,
-                msg.role,
-                msg.content,
-                msg.model_used,
-                msg.tokens_in,
-                msg.tokens_out,
-                msg.cost_usd,
-                msg.created_at,
-            ],
-        )?;
+        insert_message(&db, msg)?;
         Ok(())
     }
 
@@ -256,87 +239,104 @@ impl MemoryManager {
         Ok(count)
     }
 
-    /// Retrieve the oldest N conversation turns for a session (ascending order).
-    pub fn get_oldest_turns(
+    /// All turns of a session that have not been condensed (ascending order),
+    /// including the current summary row if there is one.
+    pub fn get_active_turns(
         &self,
         session_key: &str,
-        limit: usize,
     ) -> Result<Vec<ConversationMessage>, MemoryError> {
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare(
-            "SELECT id, user_id, session_key, channel, role, content,
-                    model_used, tokens_in, tokens_out, cost_usd, created_at
+        let mut stmt = db.prepare(&format!(
+            "SELECT {CONVERSATION_COLUMNS}
              FROM conversations
-             WHERE session_key = ?1
-             ORDER BY created_at ASC
-             LIMIT ?2",
-        )?;
-        let rows = stmt.query_map(rusqlite::params![session_key, limit], |row| {
-            Ok(ConversationMessage {
-                id: row.get(0)?,
-                user_id: row.get(1)?,
-                session_key: row.get(2)?,
-                channel: row.get(3)?,
-                role: row.get(4)?,
-                content: row.get(5)?,
-                model_used: row.get(6)?,
-                tokens_in: row.get(7)?,
-                tokens_out: row.get(8)?,
-                cost_usd: row.get(9)?,
-                created_at: row.get(10)?,
-            })
-        })?;
+             WHERE session_key = ?1 AND condense_parent IS NULL
+             ORDER BY created_at ASC, id ASC"
+        ))?;
+        let rows = stmt.query_map(rusqlite::params![session_key], row_to_message)?;
         Ok(rows.filter_map(|r| r.ok()).collect())
     }
 
-    /// Delete specific conversation turns by their row IDs.
-    /// Returns the number of rows deleted.
-    pub fn delete_turns(&self, ids: &[i64]) -> Result<usize, MemoryError> {
+    /// Replace `ids` in the active history with `summary`.
+    ///
+    /// Inserts the summary row and tags every condensed row with its id in
+    /// `condense_parent` — nothing is deleted. Runs in one transaction and
+    /// returns `None` without changes if any row was already condensed
+    /// (e.g. by a concurrent condensation of the same session).
+    pub fn condense_turns(
+        &self,
+        ids: &[i64],
+        summary: &ConversationMessage,
+    ) -> Result<Option<i64>, MemoryError> {
         if ids.is_empty() {
-            return Ok(0);
+            return Ok(None);
         }
-        let db = self.db.lock().unwrap();
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        insert_message(&tx, summary)?;
+        let summary_id = tx.last_insert_rowid();
+
         let placeholders: String = std::iter::repeat_n("?", ids.len())
             .collect::<Vec<_>>()
             .join(", ");
-        let sql = format!("DELETE FROM conversations WHERE id IN ({})", placeholders);
-        let deleted = db.execute(&sql, rusqlite::params_from_iter(ids.iter()))?;
-        Ok(deleted)
+        let sql = format!(
+            "UPDATE conversations SET condense_parent = ?
+             WHERE condense_parent IS NULL AND id IN ({placeholders})"
+        );
+        let params = std::iter::once(summary_id).chain(ids.iter().copied());
+        let tagged = tx.execute(&sql, rusqlite::params_from_iter(params))?;
+        if tagged != ids.len() {
+            // Dropping `tx` rolls back the summary insert.
+            debug!(
+                tagged,
+                expected = ids.len(),
+                "condense: rows already condensed"
+            );
+            return Ok(None);
+        }
+        tx.commit()?;
+        Ok(Some(summary_id))
     }
 
     /// Retrieve recent conversation history for a session.
+    ///
+    /// Only active (non-condensed) rows are returned. When the session has a
+    /// summary that falls outside the `limit` newest rows, it is prepended so
+    /// the condensed narrative is never lost.
     pub fn get_history(
         &self,
         session_key: &str,
         limit: usize,
     ) -> Result<Vec<ConversationMessage>, MemoryError> {
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare(
-            "SELECT id, user_id, session_key, channel, role, content,
-                    model_used, tokens_in, tokens_out, cost_usd, created_at
+        let mut stmt = db.prepare(&format!(
+            "SELECT {CONVERSATION_COLUMNS}
              FROM conversations
-             WHERE session_key = ?1
-             ORDER BY created_at DESC
-             LIMIT ?2",
-        )?;
-        let rows = stmt.query_map(rusqlite::params![session_key, limit], |row| {
-            Ok(ConversationMessage {
-                id: row.get(0)?,
-                user_id: row.get(1)?,
-                session_key: row.get(2)?,
-                channel: row.get(3)?,
-                role: row.get(4)?,
-                content: row.get(5)?,
-                model_used: row.get(6)?,
-                tokens_in: row.get(7)?,
-                tokens_out: row.get(8)?,
-                cost_usd: row.get(9)?,
-                created_at: row.get(10)?,
-            })
-        })?;
+             WHERE session_key = ?1 AND condense_parent IS NULL
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?2"
+        ))?;
+        let rows = stmt.query_map(rusqlite::params![session_key, limit], row_to_message)?;
         // Reverse so oldest first
         let mut msgs: Vec<_> = rows.filter_map(|r| r.ok()).collect();
         msgs.reverse();
+
+        if msgs.len() == limit && !msgs.iter().any(ConversationMessage::is_summary) {
+            let summary = db.query_row(
+                &format!(
+                    "SELECT {CONVERSATION_COLUMNS}
+                     FROM conversations
+                     WHERE session_key = ?1 AND role = 'summary' AND condense_parent IS NULL
+                     ORDER BY id DESC LIMIT 1"
+                ),
+                rusqlite::params![session_key],
+                row_to_message,
+            );
+            match summary {
+                Ok(s) => msgs.insert(0, s),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
         Ok(msgs)
     }
 
@@ -428,6 +428,31 @@ impl MemoryManager {
         Ok(rows.filter_map(|r| r.ok()).collect())
     }
 
+    /// Most recently updated knowledge entries, newest first.
+    pub fn knowledge_list(&self, limit: usize) -> Result<Vec<KnowledgeEntry>, MemoryError> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT id, topic, content, tags, created_at, updated_at
+             FROM knowledge
+             ORDER BY updated_at DESC
+             LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![limit], row_to_knowledge)?;
+        Ok(rows.filter_map(|r| r.ok()).collect())
+    }
+
+    /// Knowledge entry with exactly this `topic`, if any.
+    pub fn knowledge_get(&self, topic: &str) -> Result<Option<KnowledgeEntry>, MemoryError> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT id, topic, content, tags, created_at, updated_at
+             FROM knowledge
+             WHERE topic = ?1",
+        )?;
+        let mut rows = stmt.query_map(rusqlite::params![topic], row_to_knowledge)?;
+        Ok(rows.next().transpose()?)
+    }
+
     // -----------------------------------------------------------------------
     // Tool call tracking
     // -----------------------------------------------------------------------
@@ -444,6 +469,33 @@ impl MemoryManager {
         Ok(())
     }
 
+    /// Whether the most recent completed turn in a session called any tools.
+    ///
+    /// Tool calls are logged during the turn and both conversation rows are
+    /// saved afterwards, so the last turn's calls are those logged after the
+    /// previous assistant row and no later than the latest one.
+    pub fn last_turn_used_tools(&self, session_key: &str) -> Result<bool, MemoryError> {
+        let db = self.db.lock().unwrap();
+        let used: bool = db.query_row(
+            "WITH turns AS (
+                 SELECT created_at FROM conversations
+                 WHERE session_key = ?1 AND role = 'assistant'
+                 ORDER BY id DESC LIMIT 2
+             )
+             SELECT EXISTS(
+                 SELECT 1 FROM tool_calls
+                 WHERE session_key = ?1
+                   AND called_at <= (SELECT MAX(created_at) FROM turns)
+                   AND called_at > COALESCE(
+                       (SELECT MIN(created_at) FROM turns WHERE (SELECT COUNT(*) FROM turns) = 2),
+                       '')
+             )",
+            rusqlite::params![session_key],
+            |row| row.get(0),
+        )?;
+        Ok(used)
+    }
+
     /// Return the top `limit` most-called tool names in the last `days` days.
     pub fn get_top_tools(&self, days: i64, limit: usize) -> Result<Vec<String>, MemoryError> {
         let db = self.db.lock().unwrap();
@@ -507,7 +559,7 @@ impl MemoryManager {
             .filter(|(score, _)| *score > 0)
             .collect();
 
-        scored.sort_by(|a, b| b.0.cmp(&a.0));
+        scored.sort_by_key(|s| std::cmp::Reverse(s.0));
         Ok(scored.into_iter().take(limit).map(|(_, e)| e).collect())
     }
 
@@ -579,3 +631,60 @@ fn row_to_memory(row: &rusqlite::Row<'_>) -> rusqlite::Result<UserMemory> {
         updated_at: row.get(9)?,
     })
 }
+
+fn row_to_knowledge(row: &rusqlite::Row<'_>) -> rusqlite::Result<KnowledgeEntry> {
+    Ok(KnowledgeEntry {
+        id: row.get(0)?,
+        topic: row.get(1)?,
+        content: row.get(2)?,
+        tags: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+/// Column list matching `row_to_message`.
+const CONVERSATION_COLUMNS: &str = "id, user_id, session_key, channel, role, content,
+                    model_used, tokens_in, tokens_out, cost_usd, created_at,
+                    routing_rule, condense_parent";
+
+fn insert_message(conn: &Connection, msg: &ConversationMessage) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT INTO conversations
+         (user_id, session_key, channel, role, content, model_used,
+          tokens_in, tokens_out, cost_usd, created_at, routing_rule, condense_parent)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        rusqlite::params![
+            msg.user_id,
+            msg.session_key,
+            msg.channel,
+            msg.role,
+            msg.content,
+            msg.model_used,
+            msg.tokens_in,
+            msg.tokens_out,
+            msg.cost_usd,
+            msg.created_at,
+            msg.routing_rule,
+            msg.condense_parent,
+        ],
+    )
+}
+
+fn row_to_message(row: &rusqlite::Row<'_>) -> rusqlite::Result<ConversationMessage> {
+    Ok(ConversationMessage {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        session_key: row.get(2)?,
+        channel: row.get(3)?,
+        role: row.get(4)?,
+        content: row.get(5)?,
+        model_used: row.get(6)?,
+        tokens_in: row.get(7)?,
+        tokens_out: row.get(8)?,
+        cost_usd: row.get(9)?,
+        created_at: row.get(10)?,
+        routing_rule: row.get(11)?,
+        condense_parent: row.get(12)?,
+    })
+}
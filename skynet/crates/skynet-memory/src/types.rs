@@ -99,6 +99,31 @@ pub struct ConversationMessage {
     pub tokens_out: u32,
     pub cost_usd: f64,
     pub created_at: String,
+    /// Name of the `[agent.routing]` rule that picked `model_used`, if any.
+    #[serde(default)]
+    pub routing_rule: Option<String>,
+    /// Id of the summary row that condensed this one. `None` = still part of
+    /// the active history.
+    #[serde(default)]
+    pub condense_parent: Option<i64>,
+}
+
+impl ConversationMessage {
+    /// Role value of condensation summary rows.
+    pub const SUMMARY_ROLE: &'static str = "summary";
+
+    pub fn is_summary(&self) -> bool {
+        self.role == Self::SUMMARY_ROLE
+    }
+
+    /// Role to send to the LLM API. Summaries are replayed as user turns.
+    pub fn api_role(&self) -> &'static str {
+        if self.role == "assistant" {
+            "assistant"
+        } else {
+            "user"
+        }
+    }
 }
 
 /// A knowledge base entry — operator or bot-authored fact stored with FTS5 index.
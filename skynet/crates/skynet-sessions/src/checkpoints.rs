@@ -0,0 +1,557 @@
+//! File checkpoints for rolling back agent edits.
+//!
+//! Before a file tool modifies a file, the agent snapshots it here: the old
+//! content goes into a content-addressed blob under the checkpoint directory
+//! and a `file_checkpoints` row records `(session, turn, path, blob)`. Only
+//! the first snapshot of a path per turn is kept, since that is the state the
+//! turn started from. A file that did not exist is recorded with a NULL blob,
+//! so restoring it deletes the file again.
+//!
+//! After each write the tool also records the SHA-256 of what it wrote
+//! (`after_hash`). A restore only touches files that still hold the agent's
+//! last write (or already hold the content being restored), so edits made
+//! by someone else since are never overwritten.
+
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{debug, instrument, warn};
+
+use crate::error::{Result, SessionError};
+
+/// Files one turn modified, newest turn first in [`CheckpointStore::list`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckpointTurn {
+    pub turn_id: String,
+    /// When the turn's first file was snapshotted (RFC 3339).
+    pub created_at: String,
+    /// Paths in the order the turn first touched them.
+    pub files: Vec<String>,
+}
+
+/// What [`CheckpointStore::restore`] rolled back.
+///
+/// When `conflicts` is not empty nothing was changed. When `failed` is not
+/// empty the files in `restored` / `deleted` were rolled back but the
+/// checkpoints are kept, so the same restore can be retried.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreReport {
+    /// Turns undone, oldest first. Empty if the restore was refused.
+    pub turns: Vec<String>,
+    /// Files written back to their earlier content.
+    pub restored: Vec<String>,
+    /// Files removed because the turns created them.
+    pub deleted: Vec<String>,
+    /// Files changed by someone else after the agent last wrote them. Their
+    /// presence refuses the whole restore.
+    pub conflicts: Vec<String>,
+    /// Files that could not be restored.
+    pub failed: Vec<FailedFile>,
+}
+
+/// A file [`CheckpointStore::restore`] could not write back or delete.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedFile {
+    pub path: String,
+    pub error: String,
+}
+
+/// How one path is rolled back: the content to go back to and the content
+/// the agent left it with.
+struct PathRestore<'a> {
+    path: &'a str,
+    /// Blob of the earliest snapshot; `None` when the file was created.
+    target: Option<&'a str>,
+    /// Hash of the agent's last write; `None` when it was not recorded.
+    expected: Option<&'a str>,
+}
+
+/// Checkpoint index in SQLite plus a blob directory on disk.
+///
+/// Cheap to clone; clones share the connection.
+#[derive(Clone)]
+pub struct CheckpointStore {
+    db: Arc<Mutex<Connection>>,
+    blob_dir: PathBuf,
+    /// Turns kept per session; older ones are pruned as new turns start.
+    keep_turns: usize,
+}
+
+impl CheckpointStore {
+    /// Wrap an already-open (and `init_db`-initialised) connection.
+    pub fn new(conn: Connection, blob_dir: impl Into<PathBuf>, keep_turns: usize) -> Self {
+        Self {
+            db: Arc::new(Mutex::new(conn)),
+            blob_dir: blob_dir.into(),
+            keep_turns: keep_turns.max(1),
+        }
+    }
+
+    /// Record the current content of `path` as the state `turn_id` started
+    /// from. Later snapshots of the same path in the same turn are no-ops.
+    #[instrument(skip(self), fields(path = %path.display()))]
+    pub fn snapshot(&self, session_key: &str, turn_id: &str, path: &Path) -> Result<()> {
+        let path_str = path.to_string_lossy();
+        let db = self.db.lock().unwrap();
+        let exists: bool = db.query_row(
+            "SELECT EXISTS(SELECT 1 FROM file_checkpoints WHERE turn_id = ?1 AND path = ?2)",
+            rusqlite::params![turn_id, path_str],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Ok(());
+        }
+
+        let blob = match std::fs::read(path) {
+            Ok(content) => Some(self.write_blob(&content)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let new_turn: bool = db.query_row(
+            "SELECT NOT EXISTS(SELECT 1 FROM file_checkpoints WHERE turn_id = ?1)",
+            rusqlite::params![turn_id],
+            |row| row.get(0),
+        )?;
+        db.execute(
+            "INSERT INTO file_checkpoints (session_key, turn_id, path, blob, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                session_key,
+                turn_id,
+                path_str,
+                blob,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        debug!(turn_id, "file checkpointed");
+
+        if new_turn {
+            self.prune(&db, session_key)?;
+        }
+        Ok(())
+    }
+
+    /// Record what `turn_id` wrote to `path`, so a later restore can tell
+    /// whether anyone changed the file since.
+    pub fn record_write(&self, turn_id: &str, path: &Path, content: &[u8]) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "UPDATE file_checkpoints SET after_hash = ?1 WHERE turn_id = ?2 AND path = ?3",
+            rusqlite::params![
+                hex::encode(Sha256::digest(content)),
+                turn_id,
+                path.to_string_lossy()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The most recent `limit` turns of a session that modified files.
+    pub fn list(&self, session_key: &str, limit: usize) -> Result<Vec<CheckpointTurn>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT turn_id, MIN(created_at) FROM file_checkpoints
+             WHERE session_key = ?1
+             GROUP BY turn_id ORDER BY MIN(id) DESC LIMIT ?2",
+        )?;
+        let turns = stmt
+            .query_map(rusqlite::params![session_key, limit as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut files_stmt =
+            db.prepare("SELECT path FROM file_checkpoints WHERE turn_id = ?1 ORDER BY id")?;
+        turns
+            .into_iter()
+            .map(|(turn_id, created_at)| {
+                let files = files_stmt
+                    .query_map(rusqlite::params![turn_id], |row| row.get(0))?
+                    .collect::<std::result::Result<Vec<String>, _>>()?;
+                Ok(CheckpointTurn {
+                    turn_id,
+                    created_at,
+                    files,
+                })
+            })
+            .collect()
+    }
+
+    /// Roll back `turn_id` (default: the latest turn) and every later turn of
+    /// the session: each file goes back to its content before the earliest of
+    /// those turns touched it. The rolled-back checkpoints are then dropped,
+    /// so the next undo goes one turn further back.
+    ///
+    /// Nothing is changed if any file no longer holds what the agent last
+    /// wrote to it; those files are listed in `conflicts`. Files that fail
+    /// to restore are listed in `failed` and the checkpoints are kept.
+    #[instrument(skip(self))]
+    pub fn restore(&self, session_key: &str, turn_id: Option<&str>) -> Result<RestoreReport> {
+        let db = self.db.lock().unwrap();
+        let first_id: Option<i64> = match turn_id {
+            Some(turn_id) => db.query_row(
+                "SELECT MIN(id) FROM file_checkpoints WHERE session_key = ?1 AND turn_id = ?2",
+                rusqlite::params![session_key, turn_id],
+                |row| row.get(0),
+            )?,
+            None => db
+                .query_row(
+                    "SELECT MIN(id) FROM file_checkpoints WHERE session_key = ?1
+                     GROUP BY turn_id ORDER BY MIN(id) DESC LIMIT 1",
+                    rusqlite::params![session_key],
+                    |row| row.get(0),
+                )
+                .optional()?,
+        };
+        let Some(first_id) = first_id else {
+            return Err(SessionError::NoCheckpoint(match turn_id {
+                Some(turn_id) => format!("turn {turn_id}"),
+                None => format!("session {session_key}"),
+            }));
+        };
+
+        // Every row of the turns that started at or after `first_id`.
+        let mut stmt = db.prepare(
+            "SELECT turn_id, path, blob, after_hash FROM file_checkpoints
+             WHERE session_key = ?1 AND turn_id IN (
+                 SELECT turn_id FROM file_checkpoints WHERE session_key = ?1
+                 GROUP BY turn_id HAVING MIN(id) >= ?2)
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(rusqlite::params![session_key, first_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        drop(stmt);
+
+        // The earliest snapshot of a path is the state to go back to; the
+        // latest write is the state it should be in now.
+        let mut turns: Vec<String> = Vec::new();
+        let mut paths: Vec<PathRestore> = Vec::new();
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for (turn, path, blob, after_hash) in &rows {
+            if !turns.contains(turn) {
+                turns.push(turn.clone());
+            }
+            match index.get(path.as_str()) {
+                Some(&i) => paths[i].expected = after_hash.as_deref(),
+                None => {
+                    index.insert(path, paths.len());
+                    paths.push(PathRestore {
+                        path,
+                        target: blob.as_deref(),
+                        expected: after_hash.as_deref(),
+                    });
+                }
+            }
+        }
+
+        let mut report = RestoreReport::default();
+        for p in &paths {
+            if !self.is_restorable(p)? {
+                report.conflicts.push(p.path.to_string());
+            }
+        }
+        if !report.conflicts.is_empty() {
+            debug!(conflicts = report.conflicts.len(), "restore refused");
+            return Ok(report);
+        }
+
+        report.turns = turns;
+        for p in &paths {
+            match self.restore_path(p) {
+                Ok(Some(true)) => report.restored.push(p.path.to_string()),
+                Ok(Some(false)) => report.deleted.push(p.path.to_string()),
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(error = %e, path = p.path, "file restore failed");
+                    report.failed.push(FailedFile {
+                        path: p.path.to_string(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        if !report.failed.is_empty() {
+            // Keep the checkpoints so the restore can be retried; files
+            // already rolled back then match their target and pass.
+            return Ok(report);
+        }
+
+        let blobs: Vec<&str> = rows
+            .iter()
+            .filter_map(|(_, _, b, _)| b.as_deref())
+            .collect();
+        for turn in &report.turns {
+            db.execute(
+                "DELETE FROM file_checkpoints WHERE turn_id = ?1",
+                rusqlite::params![turn],
+            )?;
+        }
+        self.remove_unused_blobs(&db, blobs)?;
+        debug!(turns = report.turns.len(), "checkpoints restored");
+        Ok(report)
+    }
+
+    /// Whether `p` holds the agent's last write, or already holds the
+    /// content it would be restored to.
+    fn is_restorable(&self, p: &PathRestore) -> Result<bool> {
+        let current = match std::fs::read(p.path) {
+            Ok(content) => Some(hex::encode(Sha256::digest(content))),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(current.as_deref() == p.target
+            || (p.expected.is_some() && current.as_deref() == p.expected))
+    }
+
+    /// Roll `p` back. `Some(true)` if it was written, `Some(false)` if it
+    /// was deleted, `None` if it already matched.
+    fn restore_path(&self, p: &PathRestore) -> Result<Option<bool>> {
+        match p.target {
+            Some(hash) => {
+                let content = std::fs::read(self.blob_path(hash))?;
+                if std::fs::read(p.path).is_ok_and(|current| current == content) {
+                    return Ok(None);
+                }
+                if let Some(parent) = Path::new(p.path).parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(p.path, content)?;
+                Ok(Some(true))
+            }
+            None => match std::fs::remove_file(p.path) {
+                Ok(()) => Ok(Some(false)),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            },
+        }
+    }
+
+    /// Drop the checkpoints of all but the newest `keep_turns` turns.
+    fn prune(&self, db: &Connection, session_key: &str) -> Result<()> {
+        let mut stmt = db.prepare(
+            "SELECT turn_id FROM file_checkpoints WHERE session_key = ?1
+             GROUP BY turn_id ORDER BY MIN(id) DESC LIMIT -1 OFFSET ?2",
+        )?;
+        let old_turns = stmt
+            .query_map(
+                rusqlite::params![session_key, self.keep_turns as i64],
+                |row| row.get::<_, String>(0),
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut blobs = HashSet::new();
+        for turn in &old_turns {
+            let mut blob_stmt = db.prepare(
+                "SELECT blob FROM file_checkpoints WHERE turn_id = ?1 AND blob IS NOT NULL",
+            )?;
+            for blob in blob_stmt.query_map(rusqlite::params![turn], |row| row.get(0))? {
+                blobs.insert(blob?);
+            }
+            db.execute(
+                "DELETE FROM file_checkpoints WHERE turn_id = ?1",
+                rusqlite::params![turn],
+            )?;
+        }
+        self.remove_unused_blobs(db, blobs.iter().map(String::as_str).collect())
+    }
+
+    /// Delete the blob files in `hashes` that no checkpoint refers to any more.
+    fn remove_unused_blobs(&self, db: &Connection, hashes: Vec<&str>) -> Result<()> {
+        for hash in hashes {
+            let used: bool = db.query_row(
+                "SELECT EXISTS(SELECT 1 FROM file_checkpoints WHERE blob = ?1)",
+                rusqlite::params![hash],
+                |row| row.get(0),
+            )?;
+            if used {
+                continue;
+            }
+            if let Err(e) = std::fs::remove_file(self.blob_path(hash)) {
+                if e.kind() != ErrorKind::NotFound {
+                    warn!(error = %e, hash, "failed to remove checkpoint blob");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Store `content` under its SHA-256 and return the hex digest.
+    fn write_blob(&self, content: &[u8]) -> Result<String> {
+        let hash = hex::encode(Sha256::digest(content));
+        let path = self.blob_path(&hash);
+        if !path.exists() {
+            let dir = path.parent().expect("blob path has a parent");
+            std::fs::create_dir_all(dir)?;
+            // Write then rename, so a crash never leaves a truncated blob.
+            let tmp = dir.join(format!("{hash}.tmp"));
+            std::fs::write(&tmp, content)?;
+            std::fs::rename(&tmp, &path)?;
+        }
+        Ok(hash)
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.blob_dir.join(&hash[..2]).join(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `content` to `path` the way the file tools do.
+    fn write(store: &CheckpointStore, turn: &str, path: &Path, content: &str) {
+        store.snapshot("s", turn, path).unwrap();
+        std::fs::write(path, content).unwrap();
+        store.record_write(turn, path, content.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn restores_turns_in_reverse() {
+        let dir = std::env::temp_dir().join(format!("skynet-ckpt-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        let store = CheckpointStore::new(conn, dir.join("blobs"), 50);
+        let notes = dir.join("notes.md");
+        let new = dir.join("new.md");
+        std::fs::write(&notes, "v1").unwrap();
+
+        // Turn 1 edits notes twice; only the first snapshot counts.
+        write(&store, "t1", &notes, "v2");
+        write(&store, "t1", &notes, "v3");
+        // Turn 2 edits notes again and creates a new file.
+        write(&store, "t2", &notes, "v4");
+        write(&store, "t2", &new, "fresh");
+
+        let turns = store.list("s", 10).unwrap();
+        let ids: Vec<&str> = turns.iter().map(|t| t.turn_id.as_str()).collect();
+        assert_eq!(ids, ["t2", "t1"]);
+        assert_eq!(turns[0].files.len(), 2);
+        assert!(store.list("other", 10).unwrap().is_empty());
+
+        // Undo the latest turn.
+        let report = store.restore("s", None).unwrap();
+        assert_eq!(report.turns, ["t2"]);
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "v3");
+        assert!(!new.exists());
+        assert_eq!(report.deleted, [new.to_string_lossy()]);
+
+        // Undo the rest; the blob store is emptied.
+        store.restore("s", Some("t1")).unwrap();
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "v1");
+        assert!(matches!(
+            store.restore("s", None),
+            Err(SessionError::NoCheckpoint(_))
+        ));
+        let blobs = std::fs::read_dir(dir.join("blobs")).unwrap();
+        assert!(blobs
+            .flat_map(|d| std::fs::read_dir(d.unwrap().path()).unwrap())
+            .next()
+            .is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prunes_old_turns() {
+        let dir = std::env::temp_dir().join(format!("skynet-ckpt-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        let store = CheckpointStore::new(conn, dir.join("blobs"), 2);
+        let file = dir.join("f.txt");
+        for turn in ["t1", "t2", "t3"] {
+            std::fs::write(&file, turn).unwrap();
+            store.snapshot("s", turn, &file).unwrap();
+        }
+        let ids: Vec<String> = store
+            .list("s", 10)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.turn_id)
+            .collect();
+        assert_eq!(ids, ["t3", "t2"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_overwrite_later_edits() {
+        let dir = std::env::temp_dir().join(format!("skynet-ckpt-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        let store = CheckpointStore::new(conn, dir.join("blobs"), 50);
+        let notes = dir.join("notes.md");
+        let other = dir.join("other.md");
+        std::fs::write(&notes, "v1").unwrap();
+        std::fs::write(&other, "o1").unwrap();
+        write(&store, "t1", &notes, "v2");
+        write(&store, "t1", &other, "o2");
+
+        // Someone edits notes after the agent: nothing is restored.
+        std::fs::write(&notes, "human").unwrap();
+        let report = store.restore("s", None).unwrap();
+        assert_eq!(report.conflicts, [notes.to_string_lossy()]);
+        assert!(report.turns.is_empty() && report.restored.is_empty());
+        assert_eq!(std::fs::read_to_string(&other).unwrap(), "o2");
+
+        // Once notes is back to the agent's version the undo goes through.
+        std::fs::write(&notes, "v2").unwrap();
+        let report = store.restore("s", None).unwrap();
+        assert_eq!(report.turns, ["t1"]);
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "v1");
+        assert_eq!(std::fs::read_to_string(&other).unwrap(), "o1");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_files_keep_the_checkpoints() {
+        let dir = std::env::temp_dir().join(format!("skynet-ckpt-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        let store = CheckpointStore::new(conn, dir.join("blobs"), 50);
+        let a = dir.join("a.txt");
+        let b = dir.join("b.txt");
+        std::fs::write(&a, "a1").unwrap();
+        std::fs::write(&b, "b1").unwrap();
+        write(&store, "t1", &a, "a2");
+        write(&store, "t1", &b, "b2");
+
+        // b's blob goes missing: a is restored, b is reported, rows stay.
+        let b_blob = store.blob_path(&hex::encode(Sha256::digest(b"b1")));
+        let saved = std::fs::read(&b_blob).unwrap();
+        std::fs::remove_file(&b_blob).unwrap();
+        let report = store.restore("s", None).unwrap();
+        assert_eq!(report.restored, [a.to_string_lossy()]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].path, b.to_string_lossy());
+        assert_eq!(store.list("s", 10).unwrap().len(), 1);
+
+        // A retry skips the file that is already back and finishes.
+        std::fs::write(&b_blob, saved).unwrap();
+        let report = store.restore("s", None).unwrap();
+        assert_eq!(report.restored, [b.to_string_lossy()]);
+        assert!(report.failed.is_empty());
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "a1");
+        assert_eq!(std::fs::read_to_string(&b).unwrap(), "b1");
+        assert!(store.list("s", 10).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
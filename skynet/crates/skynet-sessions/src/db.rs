@@ -1,8 +1,9 @@
 use rusqlite::Connection;
+use skynet_core::db::add_column_if_missing;
 
 use crate::error::Result;
 
-/// Initialise the sessions table and its index.
+/// Initialise the sessions and file checkpoint tables and their indexes.
 ///
 /// Safe to call on every startup — uses `IF NOT EXISTS` throughout.
 pub fn init_db(conn: &Connection) -> Result<()> {
@@ -16,12 +17,34 @@ pub fn init_db(conn: &Connection) -> Result<()> {
             title         TEXT,
             message_count INTEGER NOT NULL DEFAULT 0,
             total_tokens  INTEGER NOT NULL DEFAULT 0,
+            total_cost_usd REAL NOT NULL DEFAULT 0,
             last_model    TEXT,
             created_at    TEXT NOT NULL,
             updated_at    TEXT NOT NULL
         );
         CREATE INDEX IF NOT EXISTS idx_sessions_user
-            ON sessions(user_id, updated_at DESC);",
+            ON sessions(user_id, updated_at DESC);
+
+        CREATE TABLE IF NOT EXISTS file_checkpoints (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_key TEXT NOT NULL,
+            turn_id     TEXT NOT NULL,
+            path        TEXT NOT NULL,
+            blob        TEXT,
+            after_hash  TEXT,
+            created_at  TEXT NOT NULL,
+            UNIQUE(turn_id, path)
+        );
+        CREATE INDEX IF NOT EXISTS idx_file_checkpoints_session
+            ON file_checkpoints(session_key, id);",
+    )?;
+    // Columns added after the first release — older databases need ALTERs.
+    add_column_if_missing(
+        conn,
+        "sessions",
+        "total_cost_usd",
+        "REAL NOT NULL DEFAULT 0",
     )?;
+    add_column_if_missing(conn, "file_checkpoints", "after_hash", "TEXT")?;
     Ok(())
 }
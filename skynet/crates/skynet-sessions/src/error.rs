@@ -20,6 +20,14 @@ pub enum SessionError {
     /// The user has reached the maximum allowed number of sessions.
     #[error("session limit exceeded for user {user_id}: max {limit}")]
     LimitExceeded { user_id: String, limit: usize },
+
+    /// Reading or writing a checkpointed file or blob failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// There is no checkpoint to restore for the given turn or session.
+    #[error("no checkpoint found for {0}")]
+    NoCheckpoint(String),
 }
 
 pub type Result<T> = std::result::Result<T, SessionError>;
@@ -1,8 +1,10 @@
+pub mod checkpoints;
 pub mod db;
 pub mod error;
 pub mod manager;
 pub mod types;
 
+pub use checkpoints::{CheckpointStore, CheckpointTurn, FailedFile, RestoreReport};
 pub use error::SessionError;
 pub use manager::SessionManager;
 pub use types::{Session, SessionKey};
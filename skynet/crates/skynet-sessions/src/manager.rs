@@ -52,7 +52,8 @@ impl SessionManager {
         // Read back — handles the race where two threads insert simultaneously
         let session = db.query_row(
             "SELECT id, session_key, user_id, agent_id, name, title,
-                    message_count, total_tokens, last_model, created_at, updated_at
+                    message_count, total_tokens, last_model, created_at, updated_at,
+                    total_cost_usd
              FROM sessions WHERE session_key = ?1",
             rusqlite::params![key_str],
             row_to_session,
@@ -68,7 +69,8 @@ impl SessionManager {
         let db = self.db.lock().unwrap();
         match db.query_row(
             "SELECT id, session_key, user_id, agent_id, name, title,
-                    message_count, total_tokens, last_model, created_at, updated_at
+                    message_count, total_tokens, last_model, created_at, updated_at,
+                    total_cost_usd
              FROM sessions WHERE session_key = ?1",
             rusqlite::params![key_str],
             row_to_session,
@@ -85,7 +87,8 @@ impl SessionManager {
         let db = self.db.lock().unwrap();
         let mut stmt = db.prepare(
             "SELECT id, session_key, user_id, agent_id, name, title,
-                    message_count, total_tokens, last_model, created_at, updated_at
+                    message_count, total_tokens, last_model, created_at, updated_at,
+                    total_cost_usd
              FROM sessions
              WHERE user_id = ?1
              ORDER BY updated_at DESC
@@ -95,12 +98,18 @@ impl SessionManager {
         Ok(rows.filter_map(|r| r.ok()).collect())
     }
 
-    /// Increment `message_count` by 1, add `tokens` to `total_tokens`,
-    /// and record the model that was used.
+    /// Increment `message_count` by 1, add `tokens` to `total_tokens` and
+    /// `cost_usd` to `total_cost_usd`, and record the model that was used.
     ///
     /// Also bumps `updated_at` so `list_for_user` ordering stays current.
-    #[instrument(skip(self), fields(key = %key, tokens, model))]
-    pub fn update_stats(&self, key: &SessionKey, tokens: u64, model: &str) -> Result<()> {
+    #[instrument(skip(self), fields(key = %key, tokens, cost_usd, model))]
+    pub fn update_stats(
+        &self,
+        key: &SessionKey,
+        tokens: u64,
+        cost_usd: f64,
+        model: &str,
+    ) -> Result<()> {
         let key_str = key.format();
         let now = chrono::Utc::now().to_rfc3339();
         let db = self.db.lock().unwrap();
@@ -108,10 +117,11 @@ impl SessionManager {
             "UPDATE sessions
              SET message_count = message_count + 1,
                  total_tokens  = total_tokens  + ?1,
-                 last_model    = ?2,
-                 updated_at    = ?3
-             WHERE session_key = ?4",
-            rusqlite::params![tokens as i64, model, now, key_str],
+                 total_cost_usd = total_cost_usd + ?2,
+                 last_model    = ?3,
+                 updated_at    = ?4
+             WHERE session_key = ?5",
+            rusqlite::params![tokens as i64, cost_usd, model, now, key_str],
         )?;
         if rows_changed == 0 {
             return Err(SessionError::NotFound { key: key_str });
@@ -158,5 +168,6 @@ fn row_to_session(row: &rusqlite::Row<'_>) -> rusqlite::Result<Session> {
         last_model: row.get(8)?,
         created_at: row.get(9)?,
         updated_at: row.get(10)?,
+        total_cost_usd: row.get(11)?,
     })
 }
@@ -109,6 +109,8 @@ pub struct Session {
     pub message_count: u32,
     /// Cumulative token usage across all messages.
     pub total_tokens: u64,
+    /// Cumulative LLM cost in USD across all messages.
+    pub total_cost_usd: f64,
     /// The model used for the most recent message (may change over time).
     pub last_model: Option<String>,
     /// RFC3339 creation timestamp.
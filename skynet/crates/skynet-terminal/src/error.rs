@@ -21,6 +21,10 @@ pub enum TerminalError {
     #[error("Operation timed out after {ms}ms")]
     Timeout { ms: u64 },
 
+    /// The caller cancelled the operation; the child was killed.
+    #[error("Operation cancelled")]
+    Cancelled,
+
     /// Command was rejected by the safety checker.
     #[error("Command blocked: {reason}")]
     CommandBlocked { reason: String },
@@ -123,6 +123,7 @@ impl TerminalManager {
     ///
     /// - `CommandBlocked` — command was rejected by the safety checker.
     /// - `Timeout`        — child exceeded `options.timeout_secs`.
+    /// - `Cancelled`      — `options.cancel` fired before the child exited.
     /// - `PtySpawn`       — child could not be spawned.
     /// - `IoError`        — underlying I/O failure.
     pub async fn exec(&self, command: &str, options: ExecOptions) -> Result<ExecResult> {
@@ -137,18 +138,22 @@ impl TerminalManager {
         let timeout_secs = options.effective_timeout_secs();
         let timeout_duration = std::time::Duration::from_secs(timeout_secs);
 
-        // Spawn the child process.
-        let child = AsyncCommand::new("sh")
-            .arg("-c")
+        // Spawn the child process in its own process group so a kill reaches
+        // everything `sh -c` started, not just the shell.
+        let mut cmd = AsyncCommand::new("sh");
+        cmd.arg("-c")
             .arg(command)
             .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        #[cfg(unix)]
+        cmd.process_group(0);
+        let child = cmd
             .spawn()
             .map_err(|e| TerminalError::PtySpawn(format!("spawn failed: {e}")))?;
 
         // `wait_with_output` takes `self` by value, so we drive it on a spawned
         // task and communicate back via a oneshot channel.  We capture the PID
-        // first so we can issue a SIGKILL on the timeout path.
+        // first so we can issue a SIGKILL on the timeout and cancel paths.
         let pid = child.id();
         let (tx, rx) = tokio::sync::oneshot::channel();
 
@@ -156,7 +161,16 @@ impl TerminalManager {
             let _ = tx.send(child.wait_with_output().await);
         });
 
-        match tokio::time::timeout(timeout_duration, rx).await {
+        let cancel = options.cancel.clone().unwrap_or_default();
+        let outcome = tokio::select! {
+            outcome = tokio::time::timeout(timeout_duration, rx) => outcome,
+            _ = cancel.cancelled() => {
+                kill_process_group(pid);
+                return Err(TerminalError::Cancelled);
+            }
+        };
+
+        match outcome {
             // The task completed within the deadline and sent a result.
             Ok(Ok(Ok(output))) => {
                 let exit_code = output.status.code().unwrap_or(-1);
@@ -185,22 +199,7 @@ impl TerminalManager {
 
             // Deadline expired — kill the child via its PID.
             Err(_elapsed) => {
-                // POSIX kill(2) with SIGKILL is the most reliable way to
-                // terminate the child when we no longer own the Child handle.
-                if let Some(raw_pid) = pid {
-                    // Safety: raw_pid is our direct child, still running.
-                    #[cfg(unix)]
-                    unsafe {
-                        libc::kill(raw_pid as libc::pid_t, libc::SIGKILL);
-                    }
-                    #[cfg(not(unix))]
-                    {
-                        // On non-Unix platforms best effort via taskkill or noop.
-                        let _ = std::process::Command::new("taskkill")
-                            .args(["/F", "/PID", &raw_pid.to_string()])
-                            .output();
-                    }
-                }
+                kill_process_group(pid);
                 Err(TerminalError::Timeout {
                     ms: timeout_secs * 1_000,
                 })
@@ -380,6 +379,29 @@ impl Default for TerminalManager {
     }
 }
 
+/// SIGKILL the process group led by `pid` (the `sh -c` child of `exec`).
+///
+/// POSIX kill(2) is the most reliable way to terminate the child when we no
+/// longer own the `Child` handle.
+fn kill_process_group(pid: Option<u32>) {
+    let Some(raw_pid) = pid else {
+        return;
+    };
+    // Safety: raw_pid leads the process group created at spawn; a negative
+    // PID addresses the whole group.
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(raw_pid as libc::pid_t), libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    {
+        // On non-Unix platforms best effort via taskkill.
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &raw_pid.to_string()])
+            .output();
+    }
+}
+
 /// Strip ANSI escape codes and convert bytes to a UTF-8 string.
 fn strip_text(raw: &[u8]) -> String {
     let clean = strip_ansi_escapes::strip(raw);
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 // ---------------------------------------------------------------------------
@@ -164,6 +165,11 @@ pub struct ExecOptions {
     /// Only set this for admin-level callers that have already validated the
     /// command through a separate policy layer.
     pub skip_safety: bool,
+
+    /// Kills the child (and its process group) when cancelled, e.g. by
+    /// `chat.abort`.
+    #[serde(skip)]
+    pub cancel: Option<CancellationToken>,
 }
 
 impl Default for ExecOptions {
@@ -172,6 +178,7 @@ impl Default for ExecOptions {
             timeout_secs: 30,
             max_output_chars: 30_000,
             skip_safety: false,
+            cancel: None,
         }
     }
 }
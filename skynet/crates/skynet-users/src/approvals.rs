@@ -0,0 +1,287 @@
+use std::sync::Mutex;
+
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::error::{Result, UserError};
+use crate::types::{ApprovalRequest, ApprovalStatus};
+
+/// `requested_by` references `users(id)`, so requests from unresolved senders
+/// are recorded against this placeholder user, created on first use.
+pub const ANONYMOUS_REQUESTER: &str = "anonymous";
+
+const APPROVAL_SELECT_SQL: &str = "SELECT id, requested_by, action_type, action_details, context,
+        status, decided_by, decided_at, reason, expires_at, created_at
+ FROM approval_queue";
+
+/// Persistent queue of actions awaiting admin sign-off (`approval_queue`).
+///
+/// Only stores and transitions rows — waking whoever is waiting on a decision
+/// is the caller's job (see `skynet-agent::approval`).
+pub struct ApprovalQueue {
+    db: Mutex<Connection>,
+}
+
+impl ApprovalQueue {
+    /// Wrap an already-open (and `init_db`-initialised) connection.
+    pub fn new(conn: Connection) -> Self {
+        Self {
+            db: Mutex::new(conn),
+        }
+    }
+
+    /// Insert a new pending request. `requested_by` is a user id, or `None`
+    /// for an unresolved sender.
+    pub fn enqueue(
+        &self,
+        requested_by: Option<&str>,
+        action_type: &str,
+        action_details: serde_json::Value,
+        context: serde_json::Value,
+        expires_at: Option<String>,
+    ) -> Result<ApprovalRequest> {
+        let now = Utc::now().to_rfc3339();
+        let db = self.db.lock().unwrap();
+        if requested_by.is_none() {
+            db.execute(
+                "INSERT OR IGNORE INTO users
+                 (id, display_name, first_seen_at, last_seen_at, created_at, updated_at)
+                 VALUES (?1, 'Anonymous', ?2, ?2, ?2, ?2)",
+                params![ANONYMOUS_REQUESTER, now],
+            )?;
+        }
+        let req = ApprovalRequest {
+            id: Uuid::now_v7().to_string(),
+            requested_by: requested_by.unwrap_or(ANONYMOUS_REQUESTER).to_string(),
+            action_type: action_type.to_string(),
+            action_details,
+            context,
+            status: ApprovalStatus::Pending,
+            decided_by: None,
+            decided_at: None,
+            reason: None,
+            expires_at,
+            created_at: now,
+        };
+        db.execute(
+            "INSERT INTO approval_queue
+             (id, requested_by, action_type, action_details, context, status, expires_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                req.id,
+                req.requested_by,
+                req.action_type,
+                req.action_details.to_string(),
+                req.context.to_string(),
+                req.status.to_string(),
+                req.expires_at,
+                req.created_at,
+            ],
+        )?;
+        Ok(req)
+    }
+
+    /// Load a request by id. Returns None when absent.
+    pub fn get(&self, id: &str) -> Result<Option<ApprovalRequest>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!("{APPROVAL_SELECT_SQL} WHERE id = ?1"))?;
+        match stmt.query_row(params![id], row_to_request) {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(UserError::DatabaseError(e)),
+        }
+    }
+
+    /// All pending requests, oldest first. Stale rows are expired first so
+    /// they never show up here.
+    pub fn list_pending(&self) -> Result<Vec<ApprovalRequest>> {
+        self.expire_stale()?;
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "{APPROVAL_SELECT_SQL} WHERE status = 'pending' ORDER BY created_at"
+        ))?;
+        let rows = stmt.query_map([], row_to_request)?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Move a pending request to `status` (approved, rejected, cancelled…).
+    ///
+    /// Returns None if the request does not exist or was already resolved —
+    /// the first decision wins. A request past `expires_at` is expired
+    /// instead and returned with that status.
+    pub fn resolve(
+        &self,
+        id: &str,
+        status: ApprovalStatus,
+        decided_by: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<Option<ApprovalRequest>> {
+        self.expire_stale()?;
+        let changed = self.db.lock().unwrap().execute(
+            "UPDATE approval_queue SET status = ?2, decided_by = ?3, decided_at = ?4, reason = ?5
+             WHERE id = ?1 AND status = 'pending'",
+            params![
+                id,
+                status.to_string(),
+                decided_by,
+                Utc::now().to_rfc3339(),
+                reason
+            ],
+        )?;
+        if changed == 0 {
+            return match self.get(id)? {
+                Some(r) if r.status == ApprovalStatus::Expired => Ok(Some(r)),
+                _ => Ok(None),
+            };
+        }
+        self.get(id)
+    }
+
+    /// Expire every pending request whose `expires_at` has passed. Returns
+    /// the number of rows expired.
+    pub fn expire_stale(&self) -> Result<usize> {
+        let now = Utc::now().to_rfc3339();
+        Ok(self.db.lock().unwrap().execute(
+            "UPDATE approval_queue SET status = 'expired', decided_at = ?1
+             WHERE status = 'pending' AND expires_at IS NOT NULL AND expires_at <= ?1",
+            params![now],
+        )?)
+    }
+
+    /// Expire every pending request regardless of `expires_at`. Used at
+    /// startup: nothing from a previous process is still waiting for them.
+    pub fn expire_all_pending(&self, reason: &str) -> Result<usize> {
+        Ok(self.db.lock().unwrap().execute(
+            "UPDATE approval_queue SET status = 'expired', decided_at = ?1, reason = ?2
+             WHERE status = 'pending'",
+            params![Utc::now().to_rfc3339(), reason],
+        )?)
+    }
+}
+
+fn row_to_request(row: &rusqlite::Row<'_>) -> rusqlite::Result<ApprovalRequest> {
+    use std::str::FromStr;
+    let json = |s: String| serde_json::from_str(&s).unwrap_or(serde_json::Value::Null);
+    Ok(ApprovalRequest {
+        id: row.get(0)?,
+        requested_by: row.get(1)?,
+        action_type: row.get(2)?,
+        action_details: json(row.get(3)?),
+        context: json(row.get(4)?),
+        status: ApprovalStatus::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+        decided_by: row.get(6)?,
+        decided_at: row.get(7)?,
+        reason: row.get(8)?,
+        expires_at: row.get(9)?,
+        created_at: row.get(10)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue() -> ApprovalQueue {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        ApprovalQueue::new(conn)
+    }
+
+    fn enqueue(queue: &ApprovalQueue, expires_at: Option<String>) -> ApprovalRequest {
+        queue
+            .enqueue(
+                None,
+                "execute_command",
+                serde_json::json!({ "command": "ls" }),
+                serde_json::json!({ "session_key": "web:default" }),
+                expires_at,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn enqueues_and_lists_pending_oldest_first() {
+        let queue = queue();
+        let first = enqueue(&queue, None);
+        let second = enqueue(&queue, None);
+
+        let stored = queue.get(&first.id).unwrap().unwrap();
+        assert_eq!(stored.requested_by, ANONYMOUS_REQUESTER);
+        assert_eq!(stored.status, ApprovalStatus::Pending);
+        assert_eq!(stored.action_details["command"], "ls");
+        assert!(queue.get("missing").unwrap().is_none());
+
+        let ids: Vec<String> = queue
+            .list_pending()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(ids, [first.id.clone(), second.id.clone()]);
+
+        queue
+            .resolve(&first.id, ApprovalStatus::Approved, Some("admin"), None)
+            .unwrap();
+        let pending = queue.list_pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, second.id);
+    }
+
+    #[test]
+    fn first_decision_wins() {
+        let queue = queue();
+        let request = enqueue(&queue, None);
+
+        let decided = queue
+            .resolve(
+                &request.id,
+                ApprovalStatus::Rejected,
+                Some("admin"),
+                Some("too risky"),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(decided.status, ApprovalStatus::Rejected);
+        assert_eq!(decided.decided_by.as_deref(), Some("admin"));
+        assert_eq!(decided.reason.as_deref(), Some("too risky"));
+        assert!(decided.decided_at.is_some());
+
+        assert!(queue
+            .resolve(&request.id, ApprovalStatus::Approved, Some("other"), None)
+            .unwrap()
+            .is_none());
+        assert!(queue
+            .resolve("missing", ApprovalStatus::Approved, None, None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn expires_stale_and_leftover_requests() {
+        let queue = queue();
+        let past = (Utc::now() - chrono::Duration::seconds(1)).to_rfc3339();
+        let future = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        let stale = enqueue(&queue, Some(past));
+        let fresh = enqueue(&queue, Some(future));
+
+        // Listing expires the stale request first.
+        let pending = queue.list_pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, fresh.id);
+
+        // A late decision reports the expiry instead.
+        let late = queue
+            .resolve(&stale.id, ApprovalStatus::Approved, Some("admin"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(late.status, ApprovalStatus::Expired);
+        assert_eq!(late.decided_by, None);
+
+        assert_eq!(queue.expire_all_pending("restart").unwrap(), 1);
+        let leftover = queue.get(&fresh.id).unwrap().unwrap();
+        assert_eq!(leftover.status, ApprovalStatus::Expired);
+        assert_eq!(leftover.reason.as_deref(), Some("restart"));
+        assert_eq!(queue.expire_stale().unwrap(), 0);
+    }
+}
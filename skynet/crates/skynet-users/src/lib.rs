@@ -1,3 +1,4 @@
+pub mod approvals;
 pub mod db;
 pub mod error;
 pub mod identity;
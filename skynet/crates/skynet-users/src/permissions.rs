@@ -21,6 +21,43 @@ pub enum Permission {
     ViewCostReports,
 }
 
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Permission::InstallSoftware => "install_software",
+            Permission::ExecuteCommands => "execute_commands",
+            Permission::UseBrowser => "use_browser",
+            Permission::SendMessages => "send_messages",
+            Permission::AccessMemory => "access_memory",
+            Permission::AccessAllMemory => "access_all_memory",
+            Permission::ManageUsers => "manage_users",
+            Permission::ApproveRequests => "approve_requests",
+            Permission::ViewCostReports => "view_cost_reports",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parses the snake_case names printed by `Display`, e.g. in plugin manifests.
+impl std::str::FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "install_software" => Ok(Permission::InstallSoftware),
+            "execute_commands" => Ok(Permission::ExecuteCommands),
+            "use_browser" => Ok(Permission::UseBrowser),
+            "send_messages" => Ok(Permission::SendMessages),
+            "access_memory" => Ok(Permission::AccessMemory),
+            "access_all_memory" => Ok(Permission::AccessAllMemory),
+            "manage_users" => Ok(Permission::ManageUsers),
+            "approve_requests" => Ok(Permission::ApproveRequests),
+            "view_cost_reports" => Ok(Permission::ViewCostReports),
+            other => Err(format!("unknown permission: {}", other)),
+        }
+    }
+}
+
 /// Result of a permission check. Callers pattern-match this rather than
 /// catching errors so they can distinguish "hard no" from "ask admin".
 #[derive(Debug, Clone)]
@@ -7,6 +7,7 @@ use tracing::{debug, info};
 
 use crate::error::{Result, UserError};
 use crate::identity::{add_identity, create_user, find_user_by_identity};
+use crate::permissions::{PermissionCheck, PermissionChecker};
 use crate::types::User;
 
 /// Maximum number of (channel, identifier) → user_id pairs kept in the
@@ -134,6 +135,13 @@ impl UserResolver {
         Ok(())
     }
 
+    /// Charge `tokens` against the user's daily budget.
+    /// Returns `BudgetExceeded` once the day's total passes the user's cap.
+    pub fn record_token_usage(&self, user_id: &str, tokens: u64) -> Result<PermissionCheck> {
+        let conn = self.db.lock().unwrap();
+        PermissionChecker::record_token_usage(&conn, user_id, tokens)
+    }
+
     /// Drop all cache entries that belong to `user_id`.
     /// Call this after updating a user's role or capabilities.
     pub fn invalidate_user(&self, user_id: &str) {
@@ -99,3 +99,65 @@ pub struct UserIdentity {
     pub linked_at: String,
     pub created_at: String,
 }
+
+/// Lifecycle of an `approval_queue` row. Only `Pending` rows can be decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalStatus {
+    #[default]
+    Pending,
+    Approved,
+    Rejected,
+    /// Nobody decided before `expires_at`.
+    Expired,
+    /// The turn that asked was aborted or ended before a decision.
+    Cancelled,
+}
+
+impl std::fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApprovalStatus::Pending => write!(f, "pending"),
+            ApprovalStatus::Approved => write!(f, "approved"),
+            ApprovalStatus::Rejected => write!(f, "rejected"),
+            ApprovalStatus::Expired => write!(f, "expired"),
+            ApprovalStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::str::FromStr for ApprovalStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ApprovalStatus::Pending),
+            "approved" => Ok(ApprovalStatus::Approved),
+            "rejected" => Ok(ApprovalStatus::Rejected),
+            "expired" => Ok(ApprovalStatus::Expired),
+            "cancelled" => Ok(ApprovalStatus::Cancelled),
+            other => Err(format!("unknown approval status: {}", other)),
+        }
+    }
+}
+
+/// An action waiting for (or past) admin sign-off — one `approval_queue` row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub id: String,
+    /// User id of the requester (`ANONYMOUS_REQUESTER` for unresolved senders).
+    pub requested_by: String,
+    /// What is being approved, e.g. "tool:execute_command".
+    pub action_type: String,
+    /// Action-specific details (for tools: name and input).
+    pub action_details: serde_json::Value,
+    /// Where the request came from (session, channel).
+    pub context: serde_json::Value,
+    pub status: ApprovalStatus,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<String>,
+    pub reason: Option<String>,
+    /// ISO-8601; a pending request past this time is expired.
+    pub expires_at: Option<String>,
+    pub created_at: String,
+}